@@ -0,0 +1,109 @@
+//! Derive macro of the [centraldogma](https://docs.rs/centraldogma) crate,
+//! re-exported by it with the `derive` feature.
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, DeriveInput, LitStr};
+
+/// Binds a struct to a JSON file of a repository by implementing `DogmaConfig`.
+///
+/// ```ignore
+/// #[derive(serde::Deserialize, centraldogma::DogmaConfig)]
+/// #[dogma(path = "/service/foo.json")]
+/// struct Foo {
+///     timeout_ms: u64,
+/// }
+/// ```
+#[proc_macro_derive(DogmaConfig, attributes(dogma))]
+pub fn derive_dogma_config(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let mut path: Option<LitStr> = None;
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("dogma")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("path") {
+                path = Some(meta.value()?.parse()?);
+                Ok(())
+            } else {
+                Err(meta.error("unsupported dogma attribute, expected `path`"))
+            }
+        })?;
+    }
+
+    let path = path.ok_or_else(|| {
+        syn::Error::new_spanned(
+            &input.ident,
+            "missing `#[dogma(path = \"/...\")]` attribute",
+        )
+    })?;
+    let value = path.value();
+    if !value.starts_with('/') || value.ends_with('/') {
+        return Err(syn::Error::new_spanned(
+            &path,
+            "path must be the absolute path of a file, e.g. \"/service/foo.json\"",
+        ));
+    }
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::centraldogma::DogmaConfig for #name #ty_generics #where_clause {
+            const PATH: &'static str = #path;
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use syn::parse_quote;
+
+    use super::*;
+
+    #[test]
+    fn test_expand() {
+        let output = expand(parse_quote! {
+            #[dogma(path = "/service/foo.json")]
+            struct Foo {
+                a: i32,
+            }
+        })
+        .unwrap();
+
+        assert_eq!(
+            output.to_string(),
+            quote! {
+                impl ::centraldogma::DogmaConfig for Foo {
+                    const PATH: &'static str = "/service/foo.json";
+                }
+            }
+            .to_string()
+        );
+    }
+
+    #[test]
+    fn test_expand_errors() {
+        let missing = expand(parse_quote! {
+            struct Foo;
+        });
+        assert!(missing.unwrap_err().to_string().contains("missing"));
+
+        let relative = expand(parse_quote! {
+            #[dogma(path = "foo.json")]
+            struct Foo;
+        });
+        assert!(relative.unwrap_err().to_string().contains("absolute"));
+
+        let unknown = expand(parse_quote! {
+            #[dogma(file = "/foo.json")]
+            struct Foo;
+        });
+        assert!(unknown.unwrap_err().to_string().contains("unsupported"));
+    }
+}
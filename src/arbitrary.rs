@@ -0,0 +1,377 @@
+//! [`proptest::arbitrary::Arbitrary`] implementations for the data models,
+//! enabled by the `proptest` feature.
+use proptest::{
+    arbitrary::{any, Arbitrary},
+    collection::{hash_map, vec},
+    option,
+    prelude::{BoxedStrategy, Just, Strategy},
+    prop_oneof,
+};
+use serde_json::Value;
+
+use crate::model::{
+    Author, Change, ChangeContent, Commit, CommitDetail, CommitMessage, Entry, EntryContent,
+    EntryType, ListEntry, Project, PushResult, Query, QueryType, Repository, Revision,
+    WatchFileResult, WatchRepoChanges, WatchRepoResult,
+};
+
+/// JSON values which survive a serialization round trip.
+/// Floating point numbers are left out as their textual form is lossy.
+fn json_value() -> BoxedStrategy<Value> {
+    let leaf = prop_oneof![
+        Just(Value::Null),
+        any::<bool>().prop_map(Value::Bool),
+        any::<i64>().prop_map(Value::from),
+        any::<String>().prop_map(Value::String),
+    ];
+
+    leaf.prop_recursive(3, 32, 4, |inner| {
+        prop_oneof![
+            vec(inner.clone(), 0..4).prop_map(Value::Array),
+            hash_map("[a-zA-Z0-9_]{1,8}", inner, 0..4)
+                .prop_map(|m| Value::Object(m.into_iter().collect())),
+        ]
+    })
+    .boxed()
+}
+
+fn file_path() -> BoxedStrategy<String> {
+    "(/[a-zA-Z0-9_-]{1,8}){1,3}(\\.json|\\.txt)?".boxed()
+}
+
+fn timestamp() -> BoxedStrategy<Option<String>> {
+    option::of("20[0-9]{2}-(0[1-9]|1[0-2])-[0-2][0-9]T[0-1][0-9]:[0-5][0-9]:[0-5][0-9]Z").boxed()
+}
+
+impl Arbitrary for Revision {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        prop_oneof![
+            1 => Just(Revision::DEFAULT),
+            9 => any::<i64>()
+                .prop_filter("revision 0 is invalid", |n| *n != 0)
+                .prop_map(Revision::from),
+        ]
+        .boxed()
+    }
+}
+
+impl Arbitrary for Author {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        ("[a-z]{1,10}", "[a-z]{1,10}@[a-z]{1,10}\\.[a-z]{2,3}")
+            .prop_map(|(name, email)| Author { name, email })
+            .boxed()
+    }
+}
+
+impl Arbitrary for Project {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        (
+            "[a-zA-Z][a-zA-Z0-9_-]{0,15}",
+            any::<Author>(),
+            option::of("/api/v1/projects/[a-z]{1,10}"),
+            timestamp(),
+        )
+            .prop_map(|(name, creator, url, created_at)| Project {
+                name,
+                creator,
+                url,
+                created_at,
+            })
+            .boxed()
+    }
+}
+
+impl Arbitrary for Repository {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        (
+            "[a-zA-Z][a-zA-Z0-9_-]{0,15}",
+            any::<Author>(),
+            any::<Revision>(),
+            option::of("/api/v1/projects/[a-z]{1,10}/repos/[a-z]{1,10}"),
+            timestamp(),
+        )
+            .prop_map(
+                |(name, creator, head_revision, url, created_at)| Repository {
+                    name,
+                    creator,
+                    head_revision,
+                    url,
+                    created_at,
+                },
+            )
+            .boxed()
+    }
+}
+
+impl Arbitrary for EntryContent {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        prop_oneof![
+            json_value().prop_map(EntryContent::Json),
+            any::<String>().prop_map(EntryContent::Text),
+            Just(EntryContent::Directory),
+        ]
+        .boxed()
+    }
+}
+
+impl Arbitrary for Entry {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        (
+            file_path(),
+            any::<EntryContent>(),
+            any::<Revision>(),
+            timestamp(),
+        )
+            .prop_map(|(path, content, revision, modified_at)| Entry {
+                url: format!("/api/v1/projects/foo/repos/bar/contents{}", path),
+                path,
+                content,
+                revision,
+                modified_at,
+            })
+            .boxed()
+    }
+}
+
+impl Arbitrary for EntryType {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        prop_oneof![
+            Just(EntryType::Json),
+            Just(EntryType::Text),
+            Just(EntryType::Directory),
+        ]
+        .boxed()
+    }
+}
+
+impl Arbitrary for ListEntry {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        (file_path(), any::<EntryType>())
+            .prop_map(|(path, r#type)| ListEntry { path, r#type })
+            .boxed()
+    }
+}
+
+impl Arbitrary for Query {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        let r#type = prop_oneof![
+            Just(QueryType::Identity),
+            Just(QueryType::IdentityJson),
+            Just(QueryType::IdentityText),
+            vec("\\$(\\.[a-z]{1,8}){1,3}", 1..3).prop_map(QueryType::JsonPath),
+        ];
+
+        (file_path(), r#type)
+            .prop_map(|(path, r#type)| Query { path, r#type })
+            .boxed()
+    }
+}
+
+impl Arbitrary for CommitDetail {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        prop_oneof![
+            any::<String>().prop_map(CommitDetail::Markdown),
+            any::<String>().prop_map(CommitDetail::Plaintext),
+        ]
+        .boxed()
+    }
+}
+
+impl Arbitrary for CommitMessage {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        ("[^\\n]{1,32}", option::of(any::<CommitDetail>()))
+            .prop_map(|(summary, detail)| CommitMessage { summary, detail })
+            .boxed()
+    }
+}
+
+impl Arbitrary for PushResult {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        (any::<Revision>(), timestamp())
+            .prop_map(|(revision, pushed_at)| PushResult {
+                revision,
+                pushed_at,
+            })
+            .boxed()
+    }
+}
+
+impl Arbitrary for Commit {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        (
+            any::<Revision>(),
+            any::<Author>(),
+            any::<CommitMessage>(),
+            timestamp(),
+        )
+            .prop_map(|(revision, author, commit_message, pushed_at)| Commit {
+                revision,
+                author,
+                commit_message,
+                pushed_at,
+            })
+            .boxed()
+    }
+}
+
+impl Arbitrary for ChangeContent {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        prop_oneof![
+            json_value().prop_map(ChangeContent::UpsertJson),
+            any::<String>().prop_map(ChangeContent::UpsertText),
+            Just(ChangeContent::Remove),
+            file_path().prop_map(ChangeContent::Rename),
+            json_value().prop_map(ChangeContent::ApplyJsonPatch),
+            any::<String>().prop_map(ChangeContent::ApplyTextPatch),
+        ]
+        .boxed()
+    }
+}
+
+impl Arbitrary for Change {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        (file_path(), any::<ChangeContent>())
+            .prop_map(|(path, content)| Change { path, content })
+            .boxed()
+    }
+}
+
+impl Arbitrary for WatchFileResult {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        any::<Entry>()
+            .prop_map(|entry| WatchFileResult {
+                revision: entry.revision,
+                entry,
+            })
+            .boxed()
+    }
+}
+
+impl Arbitrary for WatchRepoResult {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        any::<Revision>()
+            .prop_map(|revision| WatchRepoResult { revision })
+            .boxed()
+    }
+}
+
+impl Arbitrary for WatchRepoChanges {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        (any::<Revision>(), vec(any::<Change>(), 0..4))
+            .prop_map(|(revision, changes)| WatchRepoChanges { revision, changes })
+            .boxed()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use proptest::proptest;
+    use serde::{de::DeserializeOwned, Serialize};
+
+    fn round_trip<T: Serialize + DeserializeOwned>(value: &T) -> T {
+        let json = serde_json::to_string(value).unwrap();
+        serde_json::from_str(&json).unwrap()
+    }
+
+    proptest! {
+        #[test]
+        fn test_revision_round_trip(rev in any::<Revision>()) {
+            assert_eq!(round_trip(&rev), rev);
+        }
+
+        #[test]
+        fn test_project_round_trip(project in any::<Project>()) {
+            assert_eq!(round_trip(&project), project);
+        }
+
+        #[test]
+        fn test_repository_round_trip(repo in any::<Repository>()) {
+            assert_eq!(round_trip(&repo), repo);
+        }
+
+        #[test]
+        fn test_entry_round_trip(entry in any::<Entry>()) {
+            assert_eq!(round_trip(&entry), entry);
+        }
+
+        #[test]
+        fn test_list_entry_round_trip(entry in any::<ListEntry>()) {
+            assert_eq!(round_trip(&entry), entry);
+        }
+
+        #[test]
+        fn test_commit_round_trip(commit in any::<Commit>()) {
+            assert_eq!(round_trip(&commit), commit);
+        }
+
+        #[test]
+        fn test_change_round_trip(change in any::<Change>()) {
+            assert_eq!(round_trip(&change), change);
+        }
+
+        #[test]
+        fn test_push_result_round_trip(result in any::<PushResult>()) {
+            assert_eq!(round_trip(&result), result);
+        }
+
+        #[test]
+        fn test_watch_file_result_round_trip(result in any::<WatchFileResult>()) {
+            assert_eq!(round_trip(&result), result);
+        }
+    }
+}
@@ -0,0 +1,412 @@
+//! Backups of the repositories into a tar archive, and their restoration, enabled by the
+//! `backup` feature.
+//!
+//! An archive holds the files of every repository at its head revision when the backup
+//! started, under `projects/<project>/<repo>/`, and a `manifest.json` describing the
+//! projects and repositories, including their revision.
+//!
+//! ```no_run
+//! use centraldogma::{backup::RestoreOptions, Client};
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), centraldogma::Error> {
+//! let client = Client::new("http://localhost:36462", None).await?;
+//!
+//! let file = std::fs::File::create("/var/backups/dogma.tar").unwrap();
+//! let manifest = client.backup(|project| project != "scratch", file).await?;
+//!
+//! let file = std::fs::File::open("/var/backups/dogma.tar").unwrap();
+//! client.restore(file, RestoreOptions::new()).await?;
+//! # Ok(())
+//! # }
+//! ```
+use std::{
+    collections::HashMap,
+    io::{Read, Write},
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    local::{file_content, io},
+    meta::INTERNAL_REPOS,
+    model::{Change, ChangeContent, CommitMessage, Project, Repository, Revision},
+    push::FILES_PER_COMMIT,
+    Client, ContentService, Error, ErrorCode, ProjectService, RepoService,
+};
+
+const MANIFEST: &str = "manifest.json";
+const PROJECTS_DIR: &str = "projects";
+
+/// The description of the content of an archive.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackupManifest {
+    /// The projects in the archive
+    pub projects: Vec<ProjectBackup>,
+}
+
+/// A project in an archive.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProjectBackup {
+    /// The project, as listed when it was backed up
+    pub project: Project,
+    /// The repositories of the project in the archive
+    pub repos: Vec<RepoBackup>,
+}
+
+/// A repository in an archive.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RepoBackup {
+    /// The repository, whose head revision is the revision of the files in the archive
+    pub repo: Repository,
+    /// Number of files in the archive
+    pub files: usize,
+}
+
+type ProjectFilter = dyn Fn(&str) -> bool + Send + Sync;
+
+/// Options of [`Client::restore()`].
+pub struct RestoreOptions {
+    project_filter: Option<Box<ProjectFilter>>,
+    skip_existing: bool,
+}
+
+impl RestoreOptions {
+    /// Returns the default options, restoring every project of the archive and pushing the
+    /// files to the repositories which already exist.
+    pub fn new() -> Self {
+        RestoreOptions {
+            project_filter: None,
+            skip_existing: false,
+        }
+    }
+
+    /// Only restores the projects whose name is accepted by `filter`.
+    pub fn project_filter<F>(mut self, filter: F) -> Self
+    where
+        F: Fn(&str) -> bool + Send + Sync + 'static,
+    {
+        self.project_filter = Some(Box::new(filter));
+        self
+    }
+
+    /// Leaves the repositories which already exist as they are, instead of pushing
+    /// the files of the archive to them.
+    pub fn skip_existing(mut self, skip_existing: bool) -> Self {
+        self.skip_existing = skip_existing;
+        self
+    }
+}
+
+impl Default for RestoreOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Client {
+    /// Writes a tar archive of the repositories of the projects accepted by
+    /// `project_filter` to `writer`, returning the manifest of the archive.
+    ///
+    /// The files of a repository are read at its head revision when it is backed up,
+    /// except the ones of the internal `dogma` and `meta` repositories.
+    /// `writer` is written synchronously, so it should be buffered if slow.
+    pub async fn backup<F, W>(&self, project_filter: F, writer: W) -> Result<BackupManifest, Error>
+    where
+        F: Fn(&str) -> bool,
+        W: Write,
+    {
+        let mut archive = tar::Builder::new(writer);
+        let mut manifest = BackupManifest {
+            projects: Vec::new(),
+        };
+
+        for project in self.list_projects().await? {
+            if !project_filter(&project.name) {
+                continue;
+            }
+            let mut repos = Vec::new();
+            for repo in self.project(&project.name).list_repos().await? {
+                if INTERNAL_REPOS.contains(&repo.name.as_str()) {
+                    continue;
+                }
+                let entries = self
+                    .repo(&project.name, &repo.name)
+                    .get_files(repo.head_revision, "/**")
+                    .await?;
+
+                let mut files = 0;
+                for entry in &entries {
+                    let Some(content) = file_content(entry)? else {
+                        continue;
+                    };
+                    let path = format!(
+                        "{}/{}/{}{}",
+                        PROJECTS_DIR, project.name, repo.name, entry.path
+                    );
+                    append(&mut archive, &path, &content)?;
+                    files += 1;
+                }
+                repos.push(RepoBackup { repo, files });
+            }
+            manifest.projects.push(ProjectBackup { project, repos });
+        }
+
+        append(
+            &mut archive,
+            MANIFEST,
+            &serde_json::to_vec_pretty(&manifest)?,
+        )?;
+        archive
+            .into_inner()
+            .and_then(|mut w| w.flush())
+            .map_err(|e| io(Path::new(MANIFEST), e))?;
+
+        Ok(manifest)
+    }
+
+    /// Recreates the projects and repositories of a tar archive written by
+    /// [`backup()`](Self::backup), returning the manifest of the archive.
+    ///
+    /// The files of a repository are pushed in commits of at most 100 files, on top of
+    /// the files of the repository if it already exists. Fails with
+    /// [`Error::InvalidConfig`] if the archive has no valid manifest.
+    pub async fn restore<R: Read>(
+        &self,
+        archive: R,
+        options: RestoreOptions,
+    ) -> Result<BackupManifest, Error> {
+        let (manifest, mut changes) = read_archive(archive)?;
+
+        for backup in &manifest.projects {
+            let project = &backup.project.name;
+            if !options.project_filter.as_ref().is_none_or(|f| f(project)) {
+                continue;
+            }
+            match self.create_project(project).await {
+                Err(e) if e.code() != ErrorCode::ProjectExists => return Err(e),
+                _ => {}
+            }
+
+            for repo_backup in &backup.repos {
+                let repo = &repo_backup.repo.name;
+                match self.project(project).create_repo(repo).await {
+                    Err(e) if e.code() != ErrorCode::RepositoryExists => return Err(e),
+                    Err(_) if options.skip_existing => continue,
+                    _ => {}
+                }
+
+                let summary = format!(
+                    "Restore {}/{} at revision {}",
+                    project, repo, repo_backup.repo.head_revision
+                );
+                let files = changes
+                    .remove(&(project.clone(), repo.clone()))
+                    .unwrap_or_default();
+                for chunk in files.chunks(FILES_PER_COMMIT) {
+                    let pushed = self
+                        .repo(project, repo)
+                        .push(
+                            Revision::HEAD,
+                            CommitMessage::only_summary(&summary),
+                            chunk.to_vec(),
+                        )
+                        .await;
+                    match pushed {
+                        Err(Error::RedundantChange(_)) | Ok(_) => {}
+                        Err(e) => return Err(e),
+                    }
+                }
+            }
+        }
+
+        Ok(manifest)
+    }
+}
+
+fn append<W: Write>(
+    archive: &mut tar::Builder<W>,
+    path: &str,
+    content: &[u8],
+) -> Result<(), Error> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(content.len() as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+
+    archive
+        .append_data(&mut header, path, content)
+        .map_err(|e| io(Path::new(path), e))
+}
+
+type RepoChanges = HashMap<(String, String), Vec<Change>>;
+
+/// Reads the manifest and the files of an archive, as changes per repository.
+fn read_archive<R: Read>(archive: R) -> Result<(BackupManifest, RepoChanges), Error> {
+    let mut archive = tar::Archive::new(archive);
+    let mut manifest = None;
+    let mut changes: RepoChanges = HashMap::new();
+
+    for entry in archive.entries().map_err(|e| io(Path::new("archive"), e))? {
+        let mut entry = entry.map_err(|e| io(Path::new("archive"), e))?;
+        let path = entry
+            .path()
+            .map_err(|e| io(Path::new("archive"), e))?
+            .to_string_lossy()
+            .into_owned();
+        let mut content = String::new();
+        entry
+            .read_to_string(&mut content)
+            .map_err(|e| io(Path::new(&path), e))?;
+
+        if path == MANIFEST {
+            manifest = Some(serde_json::from_str(&content).map_err(|e| invalid(&path, e))?);
+            continue;
+        }
+        let mut parts = path.splitn(4, '/');
+        let (Some(PROJECTS_DIR), Some(project), Some(repo), Some(file)) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            continue;
+        };
+
+        let file = format!("/{}", file);
+        let content =
+            ChangeContent::upsert_of(&file, content).map_err(|message| Error::InvalidConfig {
+                path: path.clone(),
+                message,
+            })?;
+        changes
+            .entry((project.to_owned(), repo.to_owned()))
+            .or_default()
+            .push(Change {
+                path: file,
+                content,
+            });
+    }
+
+    let manifest = manifest.ok_or_else(|| Error::InvalidConfig {
+        path: MANIFEST.to_owned(),
+        message: "missing from the archive".to_owned(),
+    })?;
+
+    Ok((manifest, changes))
+}
+
+fn invalid(path: &str, e: serde_json::Error) -> Error {
+    Error::InvalidConfig {
+        path: path.to_owned(),
+        message: e.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+    use wiremock::{
+        matchers::{body_json, method, path, query_param},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+
+    fn author() -> serde_json::Value {
+        json!({"name": "minux", "email": "minux@m.x"})
+    }
+
+    #[tokio::test]
+    async fn test_backup_and_restore() {
+        let source = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+                {"name": "foo", "creator": author()},
+                {"name": "skipped", "creator": author()}
+            ])))
+            .mount(&source)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+                {"name": "meta", "creator": author(), "headRevision": 1},
+                {"name": "bar", "creator": author(), "headRevision": 3}
+            ])))
+            .mount(&source)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/contents/**"))
+            .and(query_param("revision", "3"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+                {"path": "/a.json", "type": "JSON", "content": {"a": 1}, "revision": 3, "url": "/a.json"},
+                {"path": "/b", "type": "DIRECTORY", "revision": 3, "url": "/b"},
+                {"path": "/b/c.txt", "type": "TEXT", "content": "c", "revision": 3, "url": "/b/c.txt"}
+            ])))
+            .mount(&source)
+            .await;
+
+        let client = Client::new(&source.uri(), None).await.unwrap();
+        let mut archive = Vec::new();
+        let manifest = client
+            .backup(|project| project != "skipped", &mut archive)
+            .await
+            .unwrap();
+        assert_eq!(manifest.projects.len(), 1);
+        assert_eq!(manifest.projects[0].repos.len(), 1);
+        assert_eq!(manifest.projects[0].repos[0].files, 2);
+
+        let target = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/v1/projects"))
+            .and(body_json(json!({"name": "foo"})))
+            .respond_with(
+                ResponseTemplate::new(201)
+                    .set_body_json(json!({"name": "foo", "creator": author()})),
+            )
+            .expect(1)
+            .mount(&target)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/api/v1/projects/foo/repos"))
+            .and(body_json(json!({"name": "bar"})))
+            .respond_with(
+                ResponseTemplate::new(201)
+                    .set_body_json(json!({"name": "bar", "creator": author(), "headRevision": 1})),
+            )
+            .expect(1)
+            .mount(&target)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/api/v1/projects/foo/repos/bar/contents"))
+            .and(body_json(json!({
+                "commitMessage": {"summary": "Restore foo/bar at revision 3"},
+                "changes": [
+                    {"path": "/a.json", "type": "UPSERT_JSON", "content": {"a": 1}},
+                    {"path": "/b/c.txt", "type": "UPSERT_TEXT", "content": "c"}
+                ]
+            })))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(json!({"revision": 2, "pushedAt": null})),
+            )
+            .expect(1)
+            .mount(&target)
+            .await;
+
+        let client = Client::new(&target.uri(), None).await.unwrap();
+        let restored = client
+            .restore(archive.as_slice(), RestoreOptions::new())
+            .await
+            .unwrap();
+        assert_eq!(restored, manifest);
+    }
+
+    #[test]
+    fn test_missing_manifest() {
+        let mut archive = tar::Builder::new(Vec::new());
+        append(&mut archive, "projects/foo/bar/a.txt", b"a").unwrap();
+        let archive = archive.into_inner().unwrap();
+
+        let err = read_archive(archive.as_slice()).err().unwrap();
+        assert_eq!(err.code(), ErrorCode::InvalidConfig);
+    }
+}
@@ -0,0 +1,327 @@
+//! A blocking client, enabled by the `blocking` feature, for programs which don't run an async
+//! runtime, such as command line tools and build scripts.
+//!
+//! It wraps the async [`Client`](crate::Client), driving its requests with an internal
+//! runtime. Like the blocking client of `reqwest`, it must not be used within an async
+//! runtime, where it panics.
+//!
+//! ```no_run
+//! use centraldogma::{blocking::Client, model::{Query, Revision}};
+//!
+//! # fn main() -> Result<(), centraldogma::Error> {
+//! let client = Client::new("http://localhost:36462", None)?;
+//! let entry = client
+//!     .repo("foo", "bar")
+//!     .get_file(Revision::HEAD, &Query::identity("/a.json").unwrap())?;
+//! # Ok(())
+//! # }
+//! ```
+use std::{future::Future, sync::Arc};
+
+use serde::de::DeserializeOwned;
+use tokio::runtime::Runtime;
+
+use crate::{
+    model::{
+        Change, Commit, CommitMessage, Entry, ListEntry, MergeQuery, MergedEntry, Project,
+        PushResult, Query, Repository, Revision, TypedEntry,
+    },
+    ContentService, Error, ProjectService, RepoService,
+};
+
+/// A blocking [`Client`](crate::Client).
+#[derive(Clone)]
+pub struct Client {
+    inner: crate::Client,
+    runtime: Arc<Runtime>,
+}
+
+impl Client {
+    /// Returns a new client from provided `base_url` and an optional
+    /// `token` string for authentication, see [`Client::new`](crate::Client::new).
+    pub fn new(base_url: &str, token: Option<&str>) -> Result<Self, Error> {
+        let runtime = new_runtime()?;
+        let inner = runtime.block_on(crate::Client::new(base_url, token))?;
+
+        Ok(Client {
+            inner,
+            runtime: Arc::new(runtime),
+        })
+    }
+
+    /// Returns a blocking client sending its requests with `client`, e.g. built with
+    /// [`Client::builder`](crate::Client::builder).
+    pub fn from_async(client: crate::Client) -> Result<Self, Error> {
+        Ok(Client {
+            inner: client,
+            runtime: Arc::new(new_runtime()?),
+        })
+    }
+
+    /// Returns the async client the requests are sent with.
+    pub fn as_async(&self) -> &crate::Client {
+        &self.inner
+    }
+
+    fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.runtime.block_on(future)
+    }
+
+    /// Creates a project.
+    pub fn create_project(&self, name: &str) -> Result<Project, Error> {
+        self.block_on(self.inner.create_project(name))
+    }
+
+    /// Removes a project. A removed project can be [unremoved](Self::unremove_project).
+    pub fn remove_project(&self, name: &str) -> Result<(), Error> {
+        self.block_on(self.inner.remove_project(name))
+    }
+
+    /// Purges a project that was removed before.
+    pub fn purge_project(&self, name: &str) -> Result<(), Error> {
+        self.block_on(self.inner.purge_project(name))
+    }
+
+    /// Unremoves a project.
+    pub fn unremove_project(&self, name: &str) -> Result<Project, Error> {
+        self.block_on(self.inner.unremove_project(name))
+    }
+
+    /// Retrieves the list of the projects.
+    pub fn list_projects(&self) -> Result<Vec<Project>, Error> {
+        self.block_on(self.inner.list_projects())
+    }
+
+    /// Retrieves the list of the removed projects.
+    pub fn list_removed_projects(&self) -> Result<Vec<String>, Error> {
+        self.block_on(self.inner.list_removed_projects())
+    }
+
+    /// Creates a temporary client within a context of the specified Project.
+    pub fn project<'a>(&'a self, project_name: &'a str) -> ProjectClient<'a> {
+        ProjectClient {
+            client: self,
+            project: project_name,
+        }
+    }
+
+    /// Creates a temporary client within a context of the specified Repository.
+    pub fn repo<'a>(&'a self, project_name: &'a str, repo_name: &'a str) -> RepoClient<'a> {
+        RepoClient {
+            client: self,
+            project: project_name,
+            repo: repo_name,
+        }
+    }
+}
+
+fn new_runtime() -> Result<Runtime, Error> {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(|e| Error::Io {
+            path: "<runtime>".to_owned(),
+            source: e,
+        })
+}
+
+/// A blocking [`ProjectClient`](crate::ProjectClient).
+pub struct ProjectClient<'a> {
+    client: &'a Client,
+    project: &'a str,
+}
+
+impl<'a> ProjectClient<'a> {
+    fn inner(&self) -> crate::ProjectClient<'_> {
+        self.client.inner.project(self.project)
+    }
+
+    /// Creates a repository.
+    pub fn create_repo(&self, repo_name: &str) -> Result<Repository, Error> {
+        self.client.block_on(self.inner().create_repo(repo_name))
+    }
+
+    /// Removes a repository. A removed repository can be [unremoved](Self::unremove_repo).
+    pub fn remove_repo(&self, repo_name: &str) -> Result<(), Error> {
+        self.client.block_on(self.inner().remove_repo(repo_name))
+    }
+
+    /// Purges a repository that was removed before.
+    pub fn purge_repo(&self, repo_name: &str) -> Result<(), Error> {
+        self.client.block_on(self.inner().purge_repo(repo_name))
+    }
+
+    /// Unremoves a repository.
+    pub fn unremove_repo(&self, repo_name: &str) -> Result<Repository, Error> {
+        self.client.block_on(self.inner().unremove_repo(repo_name))
+    }
+
+    /// Retrieves the list of the repositories.
+    pub fn list_repos(&self) -> Result<Vec<Repository>, Error> {
+        self.client.block_on(self.inner().list_repos())
+    }
+
+    /// Retrieves the list of the removed repositories.
+    pub fn list_removed_repos(&self) -> Result<Vec<String>, Error> {
+        self.client.block_on(self.inner().list_removed_repos())
+    }
+}
+
+/// A blocking [`RepoClient`](crate::RepoClient).
+///
+/// See [`ContentService`] for the details of the operations.
+pub struct RepoClient<'a> {
+    client: &'a Client,
+    project: &'a str,
+    repo: &'a str,
+}
+
+impl<'a> RepoClient<'a> {
+    fn inner(&self) -> crate::RepoClient<'_> {
+        self.client.inner.repo(self.project, self.repo)
+    }
+
+    /// Retrieves the list of the files at the specified [`Revision`] matched by the path
+    /// pattern.
+    pub fn list_files(
+        &self,
+        revision: Revision,
+        path_pattern: &str,
+    ) -> Result<Vec<ListEntry>, Error> {
+        self.client
+            .block_on(self.inner().list_files(revision, path_pattern))
+    }
+
+    /// Queries a file at the specified [`Revision`] and path with the specified [`Query`].
+    pub fn get_file(&self, revision: Revision, query: &Query) -> Result<Entry, Error> {
+        self.client.block_on(self.inner().get_file(revision, query))
+    }
+
+    /// Converts a relative [`Revision`] into the absolute revision it currently refers to.
+    pub fn normalize_revision(&self, revision: Revision) -> Result<Revision, Error> {
+        self.client
+            .block_on(self.inner().normalize_revision(revision))
+    }
+
+    /// Queries a file, and deserializes its content into `T`.
+    pub fn get_file_as<T>(&self, revision: Revision, query: &Query) -> Result<TypedEntry<T>, Error>
+    where
+        T: DeserializeOwned + Send,
+    {
+        self.client
+            .block_on(self.inner().get_file_as(revision, query))
+    }
+
+    /// Retrieves the files at the specified [`Revision`] matched by the path pattern.
+    pub fn get_files(&self, revision: Revision, path_pattern: &str) -> Result<Vec<Entry>, Error> {
+        self.client
+            .block_on(self.inner().get_files(revision, path_pattern))
+    }
+
+    /// Merges the JSON files of the [`MergeQuery`] at the specified [`Revision`] into one.
+    pub fn merge_files(
+        &self,
+        revision: Revision,
+        query: &MergeQuery,
+    ) -> Result<MergedEntry, Error> {
+        self.client
+            .block_on(self.inner().merge_files(revision, query))
+    }
+
+    /// Retrieves the history of the files matched by the path pattern between two
+    /// [`Revision`]s.
+    pub fn get_history(
+        &self,
+        from_rev: Revision,
+        to_rev: Revision,
+        path: &str,
+        max_commits: Option<u32>,
+    ) -> Result<Vec<Commit>, Error> {
+        self.client.block_on(
+            self.inner()
+                .get_history(from_rev, to_rev, path, max_commits),
+        )
+    }
+
+    /// Returns the diff of a file between two [`Revision`]s.
+    pub fn get_diff(
+        &self,
+        from_rev: Revision,
+        to_rev: Revision,
+        query: &Query,
+    ) -> Result<Change, Error> {
+        self.client
+            .block_on(self.inner().get_diff(from_rev, to_rev, query))
+    }
+
+    /// Retrieves the diffs of the files matched by the path pattern between two
+    /// [`Revision`]s.
+    pub fn get_diffs(
+        &self,
+        from_rev: Revision,
+        to_rev: Revision,
+        path_pattern: &str,
+    ) -> Result<Vec<Change>, Error> {
+        self.client
+            .block_on(self.inner().get_diffs(from_rev, to_rev, path_pattern))
+    }
+
+    /// Returns the changes the specified [`Change`]s would make if pushed, without pushing
+    /// them.
+    pub fn preview_diffs(
+        &self,
+        base_revision: Revision,
+        changes: Vec<Change>,
+    ) -> Result<Vec<Change>, Error> {
+        self.client
+            .block_on(self.inner().preview_diffs(base_revision, changes))
+    }
+
+    /// Pushes the specified [`Change`]s to the repository.
+    pub fn push(
+        &self,
+        base_revision: Revision,
+        cm: CommitMessage,
+        changes: Vec<Change>,
+    ) -> Result<PushResult, Error> {
+        self.client
+            .block_on(self.inner().push(base_revision, cm, changes))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+    use wiremock::{
+        matchers::{method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_blocking_client() {
+        let runtime = Runtime::new().unwrap();
+        let server = runtime.block_on(MockServer::start());
+        runtime.block_on(
+            Mock::given(method("GET"))
+                .and(path("/api/v1/projects/foo/repos/bar/contents/a.json"))
+                .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                    "path": "/a.json",
+                    "type": "JSON",
+                    "content": {"a": 1},
+                    "revision": 2,
+                    "url": "/a.json"
+                })))
+                .mount(&server),
+        );
+
+        let client = Client::new(&server.uri(), None).unwrap();
+        let entry = client
+            .repo("foo", "bar")
+            .get_file_as::<serde_json::Value>(Revision::HEAD, &Query::identity("/a.json").unwrap())
+            .unwrap();
+        assert_eq!(entry.value, json!({"a": 1}));
+        assert_eq!(entry.revision, Revision::from(2));
+    }
+}
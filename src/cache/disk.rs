@@ -0,0 +1,347 @@
+//! An on-disk cache of the responses, with an offline mode serving the last cached
+//! responses while the server is unreachable.
+use std::path::{Path, PathBuf};
+
+use reqwest::Method;
+
+use super::{is_immutable, key_of, Cached};
+use crate::Error;
+
+/// A directory of cached responses.
+///
+/// The responses are keyed by their path and the representation they accept, so a
+/// directory should only be used for one server, or for the replicas of one.
+///
+/// The responses at absolute revisions are stored, and, in the offline mode, the last
+/// responses of the other reads too. Nothing is ever removed, so the directory grows with
+/// the number of distinct reads and should be cleaned up externally if they are unbounded.
+///
+/// ```no_run
+/// use centraldogma::{cache::DiskCache, Client};
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), centraldogma::Error> {
+/// let client = Client::new("http://localhost:36462", None)
+///     .await?
+///     .with_disk_cache(DiskCache::new("/var/cache/dogma").offline(true));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct DiskCache {
+    dir: PathBuf,
+    offline: bool,
+}
+
+impl DiskCache {
+    /// Returns a cache storing the responses in `dir`, created if needed.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        DiskCache {
+            dir: dir.into(),
+            offline: false,
+        }
+    }
+
+    /// Serves the last cached response of a read which fails to reach the server,
+    /// e.g. so that an application can restart during an outage.
+    pub fn offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+
+    pub(crate) async fn execute(
+        &self,
+        http_client: &reqwest::Client,
+        req: reqwest::Request,
+        limit: Option<usize>,
+    ) -> Result<reqwest::Response, Error> {
+        if req.method() != Method::GET || req.headers().contains_key("if-none-match") {
+            return Ok(http_client.execute(req).await?);
+        }
+
+        let key = key_of(&req);
+        let path = self.path_of(&key);
+        let immutable = is_immutable(req.url());
+        if immutable {
+            if let Some(cached) = read(&path, &key).await {
+                return Ok(cached.response());
+            }
+        }
+
+        match http_client.execute(req).await {
+            Ok(resp) if resp.status().is_success() => {
+                let cached = Cached::read(resp, limit).await?;
+                // Only the offline mode reads back the responses at a relative revision
+                if immutable || self.offline {
+                    if let Err(e) = write(&path, &key, &cached).await {
+                        log::warn!("Failed to cache the response of {}: {}", key, e);
+                    }
+                }
+                Ok(cached.response())
+            }
+            Ok(resp) => Ok(resp),
+            Err(e) if self.offline && (e.is_connect() || e.is_timeout()) => {
+                match read(&path, &key).await {
+                    Some(cached) => {
+                        log::warn!("Serving {} from the cache: {}", key, e);
+                        Ok(cached.response())
+                    }
+                    None => Err(e.into()),
+                }
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn path_of(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{:016x}", fnv1a(key.as_bytes())))
+    }
+}
+
+/// A stable hash of the keys, naming the files.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, b| {
+        (hash ^ u64::from(*b)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Reads the response cached for `key`: the key, to tell the keys of the same hash apart,
+/// the status code and the content type, each followed by a newline, then the body.
+async fn read(path: &Path, key: &str) -> Option<Cached> {
+    let content = tokio::fs::read(path).await.ok()?;
+    let rest = content.strip_prefix(key.as_bytes())?.strip_prefix(b"\n")?;
+    let (status, rest) = split_line(rest)?;
+    let (content_type, body) = split_line(rest)?;
+
+    Some(Cached {
+        status: status.parse().ok()?,
+        content_type: (!content_type.is_empty()).then(|| content_type.to_owned()),
+        body: body.to_vec(),
+    })
+}
+
+/// Splits the first line of `bytes`, without its newline, from the rest.
+fn split_line(bytes: &[u8]) -> Option<(&str, &[u8])> {
+    let end = bytes.iter().position(|&b| b == b'\n')?;
+    let line = std::str::from_utf8(&bytes[..end]).ok()?;
+
+    Some((line, &bytes[end + 1..]))
+}
+
+async fn write(path: &Path, key: &str, cached: &Cached) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        tokio::fs::create_dir_all(dir).await?;
+    }
+    let status = cached.status.as_str();
+    let content_type = cached.content_type.as_deref().unwrap_or_default();
+    let mut content =
+        Vec::with_capacity(key.len() + status.len() + content_type.len() + 3 + cached.body.len());
+    content.extend_from_slice(key.as_bytes());
+    content.push(b'\n');
+    content.extend_from_slice(status.as_bytes());
+    content.push(b'\n');
+    content.extend_from_slice(content_type.as_bytes());
+    content.push(b'\n');
+    content.extend_from_slice(&cached.body);
+
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(format!(".tmp-{:x}", fastrand::u64(..)));
+    tokio::fs::write(&temp, content).await?;
+    tokio::fs::rename(&temp, path).await
+}
+
+#[cfg(test)]
+mod test {
+    use wiremock::{
+        matchers::{method, path, query_param},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+    use crate::{
+        model::{Query, Revision},
+        Client, ContentService, ErrorCode, RepoService,
+    };
+
+    const ENTRY: &str =
+        r#"{"path":"/a.json","type":"JSON","content":{"a":1},"revision":2,"url":"/a.json"}"#;
+
+    fn cache_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("centraldogma-cache-{}", fastrand::u64(..)))
+    }
+
+    #[tokio::test]
+    async fn test_absolute_revision_served_from_cache() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/contents/a.json"))
+            .and(query_param("revision", "2"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(ENTRY, "application/json"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let dir = cache_dir();
+        let client = Client::new(&server.uri(), None)
+            .await
+            .unwrap()
+            .with_disk_cache(DiskCache::new(&dir));
+        let query = Query::identity("/a.json").unwrap();
+        for _ in 0..2 {
+            let entry = client
+                .repo("foo", "bar")
+                .get_file(Revision::from(2), &query)
+                .await
+                .unwrap();
+            assert_eq!(entry.revision, Revision::from(2));
+        }
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_relative_revision_stored_only_offline() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(ENTRY, "application/json"))
+            .mount(&server)
+            .await;
+
+        let query = Query::identity("/a.json").unwrap();
+        for offline in [false, true] {
+            let dir = cache_dir();
+            let client = Client::new(&server.uri(), None)
+                .await
+                .unwrap()
+                .with_disk_cache(DiskCache::new(&dir).offline(offline));
+            client
+                .repo("foo", "bar")
+                .get_file(Revision::HEAD, &query)
+                .await
+                .unwrap();
+
+            assert_eq!(dir.exists(), offline);
+            if offline {
+                std::fs::remove_dir_all(&dir).unwrap();
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_content_type_replayed() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/contents/a.txt"))
+            .and(query_param("revision", "2"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw("{\"a\":1}", "text/plain"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let dir = cache_dir();
+        let client = Client::new(&server.uri(), None)
+            .await
+            .unwrap()
+            .with_disk_cache(DiskCache::new(&dir));
+        for _ in 0..2 {
+            let body = client
+                .repo("foo", "bar")
+                .get_file_raw(Revision::from(2), "/a.txt")
+                .await
+                .unwrap();
+            assert_eq!(body, "{\"a\":1}");
+        }
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_offline() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(ENTRY, "application/json"))
+            .mount(&server)
+            .await;
+        // Nothing listens on port 1
+        let unreachable = "http://127.0.0.1:1";
+
+        let dir = cache_dir();
+        let client = |uri: String, offline| {
+            let dir = dir.clone();
+            async move {
+                Client::new(&uri, None)
+                    .await
+                    .unwrap()
+                    .with_disk_cache(DiskCache::new(dir).offline(offline))
+            }
+        };
+        let query = Query::identity("/a.json").unwrap();
+
+        client(server.uri(), true)
+            .await
+            .repo("foo", "bar")
+            .get_file(Revision::HEAD, &query)
+            .await
+            .unwrap();
+
+        let err = client(unreachable.to_owned(), false)
+            .await
+            .repo("foo", "bar")
+            .get_file(Revision::HEAD, &query)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::Connect);
+
+        let entry = client(unreachable.to_owned(), true)
+            .await
+            .repo("foo", "bar")
+            .get_file(Revision::HEAD, &query)
+            .await
+            .unwrap();
+        assert_eq!(entry.revision, Revision::from(2));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_status_replayed() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos"))
+            .and(query_param("status", "removed"))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let dir = cache_dir();
+        let client = |uri: String| {
+            let dir = dir.clone();
+            async move {
+                Client::new(&uri, None)
+                    .await
+                    .unwrap()
+                    .with_disk_cache(DiskCache::new(dir).offline(true))
+            }
+        };
+
+        let removed = client(server.uri())
+            .await
+            .project("foo")
+            .list_removed_repos()
+            .await
+            .unwrap();
+        assert!(removed.is_empty());
+
+        // Nothing listens on port 1
+        let removed = client("http://127.0.0.1:1".to_owned())
+            .await
+            .project("foo")
+            .list_removed_repos()
+            .await
+            .unwrap();
+        assert!(removed.is_empty());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
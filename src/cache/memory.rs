@@ -0,0 +1,291 @@
+//! An in-memory cache of the responses at absolute revisions.
+use std::{collections::HashMap, sync::Mutex};
+
+use reqwest::Method;
+
+use super::{is_immutable, key_of, Cached};
+use crate::Error;
+
+/// The responses of the reads at absolute revisions, e.g. of
+/// [get_file](trait@crate::ContentService#tymethod.get_file) and
+/// [get_files](trait@crate::ContentService#tymethod.get_files), keyed by their path,
+/// which holds the project, the repository, the revision and the query, and by the
+/// representation they accept.
+///
+/// Holds at most `capacity` responses, evicting the least recently used ones.
+///
+/// ```no_run
+/// use centraldogma::{cache::MemoryCache, Client};
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), centraldogma::Error> {
+/// let client = Client::new("http://localhost:36462", None)
+///     .await?
+///     .with_memory_cache(MemoryCache::new(1000));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct MemoryCache {
+    capacity: usize,
+    entries: Mutex<Entries>,
+}
+
+#[derive(Debug, Default)]
+struct Entries {
+    /// The responses, and when they were last used
+    bodies: HashMap<String, (Cached, u64)>,
+    clock: u64,
+}
+
+impl MemoryCache {
+    /// Returns a cache holding at most `capacity` responses.
+    pub fn new(capacity: usize) -> Self {
+        MemoryCache {
+            capacity,
+            entries: Mutex::new(Entries::default()),
+        }
+    }
+
+    /// Returns the number of cached responses.
+    pub fn len(&self) -> usize {
+        self.lock().bodies.len()
+    }
+
+    /// Returns `true` if no response is cached.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Drops every cached response.
+    pub fn clear(&self) {
+        self.lock().bodies.clear();
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Entries> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Returns the key of the response of `req`, `None` if it can't be cached.
+    pub(crate) fn key_of(req: &reqwest::Request) -> Option<String> {
+        if req.method() != Method::GET
+            || req.headers().contains_key("if-none-match")
+            || !is_immutable(req.url())
+        {
+            return None;
+        }
+
+        Some(key_of(req))
+    }
+
+    pub(crate) fn get(&self, key: &str) -> Option<reqwest::Response> {
+        let mut entries = self.lock();
+        entries.clock += 1;
+        let clock = entries.clock;
+        let (cached, last_used) = entries.bodies.get_mut(key)?;
+        *last_used = clock;
+
+        Some(cached.response())
+    }
+
+    /// Caches the body of `resp` if it succeeded, and returns it. Fails if the body is larger
+    /// than `limit`.
+    pub(crate) async fn store(
+        &self,
+        key: String,
+        resp: reqwest::Response,
+        limit: Option<usize>,
+    ) -> Result<reqwest::Response, Error> {
+        if !resp.status().is_success() || self.capacity == 0 {
+            return Ok(resp);
+        }
+        let cached = Cached::read(resp, limit).await?;
+
+        let mut entries = self.lock();
+        if entries.bodies.len() >= self.capacity && !entries.bodies.contains_key(&key) {
+            let oldest = entries
+                .bodies
+                .iter()
+                .min_by_key(|(_, (_, last_used))| *last_used)
+                .map(|(k, _)| k.clone());
+            if let Some(oldest) = oldest {
+                entries.bodies.remove(&oldest);
+            }
+        }
+        entries.clock += 1;
+        let clock = entries.clock;
+        let resp = cached.response();
+        entries.bodies.insert(key, (cached, clock));
+
+        Ok(resp)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use wiremock::{
+        matchers::{headers, method, path, query_param},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+    use crate::{
+        model::{Query, Revision},
+        Client, ContentService,
+    };
+
+    fn entry(name: &str) -> String {
+        format!(
+            r#"{{"path":"/{}","type":"JSON","content":{{"a":1}},"revision":2,"url":"/{}"}}"#,
+            name, name
+        )
+    }
+
+    #[tokio::test]
+    async fn test_memory_cache() {
+        let server = MockServer::start().await;
+        for (name, expected) in [("a.json", 2), ("b.json", 1), ("c.json", 1)] {
+            Mock::given(method("GET"))
+                .and(path(format!(
+                    "/api/v1/projects/foo/repos/bar/contents/{}",
+                    name
+                )))
+                .and(query_param("revision", "2"))
+                .respond_with(
+                    ResponseTemplate::new(200).set_body_raw(entry(name), "application/json"),
+                )
+                .expect(expected)
+                .mount(&server)
+                .await;
+        }
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/contents/a.json"))
+            .and(query_param("revision", "-1"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_raw(entry("a.json"), "application/json"),
+            )
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let client = Client::new(&server.uri(), None)
+            .await
+            .unwrap()
+            .with_memory_cache(MemoryCache::new(2));
+        let repo = client.repo("foo", "bar");
+        // `a.json` is evicted by `c.json` as `b.json` was used after it, then `c.json` by `a.json`.
+        for (name, revision) in [
+            ("a.json", 2),
+            ("a.json", 2),
+            ("b.json", 2),
+            ("c.json", 2),
+            ("b.json", 2),
+            ("a.json", 2),
+            ("a.json", -1),
+            ("a.json", -1),
+        ] {
+            let query = Query::identity(name).unwrap();
+            let entry = repo
+                .get_file(Revision::from(revision), &query)
+                .await
+                .unwrap();
+            assert_eq!(entry.path, format!("/{}", name));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_content_type_replayed() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/contents/a.txt"))
+            .and(query_param("revision", "2"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw("{\"a\":1}", "text/plain"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = Client::new(&server.uri(), None)
+            .await
+            .unwrap()
+            .with_memory_cache(MemoryCache::new(2));
+        for _ in 0..2 {
+            let body = client
+                .repo("foo", "bar")
+                .get_file_raw(Revision::from(2), "/a.txt")
+                .await
+                .unwrap();
+            assert_eq!(body, "{\"a\":1}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_keyed_by_accept() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/contents/a.json"))
+            .and(query_param("revision", "2"))
+            .and(headers(
+                "accept",
+                vec![
+                    "text/plain",
+                    "application/octet-stream",
+                    "application/json;q=0.5",
+                ],
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_raw("{\"a\":1}", "text/plain"))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/contents/a.json"))
+            .and(query_param("revision", "2"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_raw(entry("a.json"), "application/json"),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = Client::new(&server.uri(), None)
+            .await
+            .unwrap()
+            .with_memory_cache(MemoryCache::new(2));
+        let repo = client.repo("foo", "bar");
+        let query = Query::identity("/a.json").unwrap();
+        for _ in 0..2 {
+            let entry = repo.get_file(Revision::from(2), &query).await.unwrap();
+            assert_eq!(entry.path, "/a.json");
+            let body = repo
+                .get_file_raw(Revision::from(2), "/a.json")
+                .await
+                .unwrap();
+            assert_eq!(body, "{\"a\":1}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_max_response_size() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/contents/a.json"))
+            .and(query_param("revision", "2"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_raw(entry("a.json"), "application/json"),
+            )
+            .mount(&server)
+            .await;
+
+        let cache = MemoryCache::new(2);
+        let client = Client::new(&server.uri(), None)
+            .await
+            .unwrap()
+            .with_memory_cache(cache)
+            .with_max_response_size(16);
+        let err = client
+            .repo("foo", "bar")
+            .get_file(Revision::from(2), &Query::identity("/a.json").unwrap())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::ResponseTooLarge { limit: 16 }));
+    }
+}
@@ -0,0 +1,86 @@
+//! Caches of the responses of a [`Client`](crate::Client): an on-disk cache enabled by the
+//! `disk-cache` feature, and an in-memory cache enabled by the `cache` feature.
+//!
+//! Only the reads are cached, i.e. the `GET` requests other than watches. The content
+//! at an absolute revision never changes, so a read at an absolute revision is served
+//! from the cache without reaching the server once cached.
+use reqwest::{
+    header::{ACCEPT, CONTENT_TYPE},
+    StatusCode,
+};
+
+use crate::services::read_limited;
+
+#[cfg(feature = "disk-cache")]
+mod disk;
+#[cfg(feature = "cache")]
+mod memory;
+
+#[cfg(feature = "disk-cache")]
+pub use disk::DiskCache;
+#[cfg(feature = "cache")]
+pub use memory::MemoryCache;
+
+/// Returns the key of the response of `req`: its path and query, and the representation
+/// it accepts if not the default one, e.g. the raw content of a file rather than an entry.
+fn key_of(req: &reqwest::Request) -> String {
+    let mut key = req.url().path().to_owned();
+    if let Some(query) = req.url().query() {
+        key.push('?');
+        key.push_str(query);
+    }
+    if let Some(accept) = req.headers().get(ACCEPT).and_then(|v| v.to_str().ok()) {
+        key.push_str(" accept=");
+        key.push_str(accept);
+    }
+    key
+}
+
+/// Returns whether the response of `url` is at an absolute revision.
+fn is_immutable(url: &url::Url) -> bool {
+    url.query_pairs()
+        .any(|(k, v)| k == "revision" && v.parse::<i64>().is_ok_and(|r| r > 0))
+}
+
+/// The status and the body of a successful response, and its content type, e.g. to tell
+/// the raw content of a file from an entry, or an empty list from a missing one.
+#[derive(Debug, Clone)]
+struct Cached {
+    status: StatusCode,
+    content_type: Option<String>,
+    body: Vec<u8>,
+}
+
+impl Cached {
+    /// Reads `resp`, failing if its body is larger than `limit`.
+    async fn read(resp: reqwest::Response, limit: Option<usize>) -> Result<Self, crate::Error> {
+        let status = resp.status();
+        let content_type = resp
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_owned);
+        let body = read_limited(resp, limit).await?.to_vec();
+
+        Ok(Cached {
+            status,
+            content_type,
+            body,
+        })
+    }
+
+    /// Returns a response replaying the cached one.
+    fn response(&self) -> reqwest::Response {
+        let mut builder = http::Response::builder().status(self.status);
+        if let Some(content_type) = &self.content_type {
+            builder = builder.header(CONTENT_TYPE.as_str(), content_type.as_str());
+        }
+        let resp = builder.body(self.body.clone()).unwrap_or_else(|_| {
+            let mut resp = http::Response::new(self.body.clone());
+            *resp.status_mut() = self.status;
+            resp
+        });
+
+        reqwest::Response::from(resp)
+    }
+}
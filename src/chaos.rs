@@ -0,0 +1,286 @@
+//! Injection of faults into the requests of a [`Client`], enabled by the
+//! `chaos` feature, to test how an application copes with a degraded server.
+//!
+//! ```no_run
+//! use std::time::Duration;
+//!
+//! use centraldogma::{chaos::FaultInjector, Client};
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let client = Client::new("http://localhost:36462", None)
+//!     .await
+//!     .unwrap()
+//!     .with_fault_injector(
+//!         FaultInjector::new()
+//!             .latency(0.5, Duration::from_millis(300))
+//!             .timeouts(0.1)
+//!             .server_errors(0.1, 503),
+//!     );
+//! # }
+//! ```
+use std::{sync::Mutex, time::Duration};
+
+use crate::{Client, Error};
+
+/// Decides which requests fail, and how.
+///
+/// For every request, the latency is added first with its rate. Then at most one fault
+/// is injected, with the rates of the faults, in place of sending the request.
+/// The failures are the same [`Error`]s as the real ones, e.g. [`Error::Timeout`].
+#[derive(Debug)]
+pub struct FaultInjector {
+    latency: Option<(f64, Duration)>,
+    timeout_rate: f64,
+    reset_rate: f64,
+    server_error: Option<(f64, u16)>,
+    rng: Mutex<fastrand::Rng>,
+}
+
+impl Default for FaultInjector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+enum Fault {
+    Timeout,
+    Reset,
+    ServerError(u16),
+}
+
+impl FaultInjector {
+    /// Returns an injector without any fault.
+    pub fn new() -> Self {
+        FaultInjector {
+            latency: None,
+            timeout_rate: 0.0,
+            reset_rate: 0.0,
+            server_error: None,
+            rng: Mutex::new(fastrand::Rng::new()),
+        }
+    }
+
+    /// Makes the random decisions reproducible.
+    pub fn seed(self, seed: u64) -> Self {
+        *self.rng.lock().unwrap() = fastrand::Rng::with_seed(seed);
+        self
+    }
+
+    /// Delays `rate` of the requests, between 0 and 1, by `delay`.
+    ///
+    /// The delay goes through the [`Clock`](crate::Clock) of the client.
+    pub fn latency(mut self, rate: f64, delay: Duration) -> Self {
+        self.latency = Some((clamp(rate), delay));
+        self
+    }
+
+    /// Fails `rate` of the requests, between 0 and 1, with [`Error::Timeout`]
+    /// right away.
+    pub fn timeouts(mut self, rate: f64) -> Self {
+        self.timeout_rate = clamp(rate);
+        self
+    }
+
+    /// Fails `rate` of the requests, between 0 and 1, as if the connection was closed
+    /// before the response.
+    pub fn resets(mut self, rate: f64) -> Self {
+        self.reset_rate = clamp(rate);
+        self
+    }
+
+    /// Answers `rate` of the requests, between 0 and 1, with `status`,
+    /// which should be a 5xx status.
+    pub fn server_errors(mut self, rate: f64, status: u16) -> Self {
+        self.server_error = Some((clamp(rate), status));
+        self
+    }
+
+    /// Returns the response or the error in place of the real one,
+    /// if a fault is injected.
+    pub(crate) async fn inject(&self, client: &Client) -> Option<Result<reqwest::Response, Error>> {
+        let (delay, fault) = self.decide();
+        if let Some(delay) = delay {
+            client.sleep(delay).await;
+        }
+
+        match fault? {
+            Fault::Timeout => timeout().await.map(Err),
+            Fault::Reset => reset().await.map(Err),
+            Fault::ServerError(status) => Some(Ok(server_error(status))),
+        }
+    }
+
+    fn decide(&self) -> (Option<Duration>, Option<Fault>) {
+        let rng = self.rng.lock().unwrap();
+        let delay = self
+            .latency
+            .filter(|(rate, _)| rng.f64() < *rate)
+            .map(|(_, delay)| delay);
+
+        let mut roll = rng.f64();
+        let mut faults = [
+            (self.timeout_rate, Fault::Timeout),
+            (self.reset_rate, Fault::Reset),
+        ]
+        .into_iter()
+        .chain(
+            self.server_error
+                .map(|(rate, status)| (rate, Fault::ServerError(status))),
+        );
+        let fault = faults.find_map(|(rate, fault)| {
+            if roll < rate {
+                Some(fault)
+            } else {
+                roll -= rate;
+                None
+            }
+        });
+
+        (delay, fault)
+    }
+}
+
+fn clamp(rate: f64) -> f64 {
+    rate.clamp(0.0, 1.0)
+}
+
+/// Returns a genuine timeout error, of a request to a local socket which never answers.
+async fn timeout() -> Option<Error> {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.ok()?;
+    let addr = listener.local_addr().ok()?;
+
+    let err = reqwest::Client::new()
+        .get(format!("http://{}", addr))
+        .timeout(Duration::from_millis(1))
+        .send()
+        .await
+        .err()?;
+
+    Some(Error::from(err))
+}
+
+/// Returns a genuine error of a connection closed before the response,
+/// by a local socket which closes every connection it accepts.
+async fn reset() -> Option<Error> {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.ok()?;
+    let addr = listener.local_addr().ok()?;
+    tokio::spawn(async move {
+        if let Ok((stream, _)) = listener.accept().await {
+            drop(stream);
+        }
+    });
+
+    let err = reqwest::Client::new()
+        .get(format!("http://{}", addr))
+        .send()
+        .await
+        .err()?;
+
+    Some(Error::from(err))
+}
+
+fn server_error(status: u16) -> reqwest::Response {
+    let body = r#"{"message":"Fault injected by the client"}"#;
+    let resp = http::Response::builder()
+        .status(status)
+        .header("content-type", "application/json")
+        .body(body)
+        .unwrap_or_else(|_| http::Response::new(body));
+
+    reqwest::Response::from(resp)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Clock, ErrorCode, ProjectService};
+    use std::{
+        future::Future,
+        pin::Pin,
+        sync::{Arc, Mutex},
+    };
+    use wiremock::{matchers::method, Mock, MockServer, ResponseTemplate};
+
+    async fn client(server: &MockServer, injector: FaultInjector) -> Client {
+        Client::new(&server.uri(), None)
+            .await
+            .unwrap()
+            .with_fault_injector(injector)
+    }
+
+    async fn server() -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw("[]", "application/json"))
+            .mount(&server)
+            .await;
+        server
+    }
+
+    #[tokio::test]
+    async fn test_inject_faults() {
+        let server = server().await;
+
+        let err = client(&server, FaultInjector::new().timeouts(1.0))
+            .await
+            .list_projects()
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::Timeout);
+
+        let err = client(&server, FaultInjector::new().resets(1.0))
+            .await
+            .list_projects()
+            .await
+            .unwrap_err();
+        assert!(err.is_retryable(), "{:?}", err);
+
+        let err = client(&server, FaultInjector::new().server_errors(1.0, 503))
+            .await
+            .list_projects()
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::ErrorResponse { status: 503, .. }));
+
+        // Nothing injected
+        let projects = client(&server, FaultInjector::new())
+            .await
+            .list_projects()
+            .await
+            .unwrap();
+        assert!(projects.is_empty());
+        assert_eq!(server.received_requests().await.unwrap().len(), 1);
+    }
+
+    #[derive(Clone, Default)]
+    struct RecordingClock(Arc<Mutex<Vec<Duration>>>);
+
+    impl Clock for RecordingClock {
+        fn sleep(&self, duration: Duration) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+            self.0.lock().unwrap().push(duration);
+            Box::pin(async {})
+        }
+    }
+
+    #[tokio::test]
+    async fn test_inject_latency_with_rate() {
+        let server = server().await;
+        let clock = RecordingClock::default();
+        let client = client(
+            &server,
+            FaultInjector::new()
+                .seed(7)
+                .latency(0.5, Duration::from_secs(1)),
+        )
+        .await
+        .with_clock(clock.clone());
+
+        for _ in 0..100 {
+            client.list_projects().await.unwrap();
+        }
+
+        let delayed = clock.0.lock().unwrap().len();
+        assert!((25..75).contains(&delayed), "{}", delayed);
+    }
+}
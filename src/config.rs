@@ -0,0 +1,373 @@
+//! A configuration kept up to date with a file.
+use std::{
+    marker::PhantomData,
+    sync::{
+        atomic::{AtomicI64, Ordering},
+        Arc,
+    },
+};
+
+use arc_swap::ArcSwap;
+use async_trait::async_trait;
+use futures::StreamExt;
+use serde::de::DeserializeOwned;
+use tokio::task::JoinHandle;
+
+use crate::{
+    model::{Entry, EntryContent, Query, Revision},
+    services::watch::watch_file_stream_since,
+    CentralDogmaRepository, ContentService, Error,
+};
+
+type Validator<T> = dyn Fn(&T) -> Result<(), String> + Send + Sync;
+
+/// A value deserialized from a JSON file, updated in the background whenever the file
+/// changes.
+///
+/// [`load()`](Self::load) never blocks nor locks, so it can be called on every request.
+/// A new content which fails to deserialize or validate is ignored, keeping the last
+/// valid value. The background watch stops when this is dropped.
+///
+/// ```no_run
+/// use centraldogma::{model::Query, CentralDogmaConfig, Client};
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Limits {
+///     max_connections: usize,
+/// }
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), centraldogma::Error> {
+/// let client = Client::new("http://localhost:36462", None).await?;
+/// let repo = client.for_repo("foo", "bar");
+/// let limits: CentralDogmaConfig<Limits> =
+///     CentralDogmaConfig::builder(Query::identity("/limits.json").unwrap())
+///         .validator(|l: &Limits| {
+///             if l.max_connections == 0 {
+///                 return Err("max_connections must be positive".to_owned());
+///             }
+///             Ok(())
+///         })
+///         .start(&repo)
+///         .await?;
+///
+/// let max_connections = limits.load().max_connections;
+/// # Ok(())
+/// # }
+/// ```
+pub struct CentralDogmaConfig<T> {
+    shared: Arc<Shared<T>>,
+    task: JoinHandle<()>,
+}
+
+struct Shared<T> {
+    value: ArcSwap<T>,
+    revision: AtomicI64,
+}
+
+impl<T> CentralDogmaConfig<T>
+where
+    T: DeserializeOwned + Send + Sync + 'static,
+{
+    /// Returns a builder of a configuration deserialized from the file of `query`.
+    pub fn builder(query: Query) -> CentralDogmaConfigBuilder<T> {
+        CentralDogmaConfigBuilder {
+            query,
+            validator: None,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T> CentralDogmaConfig<T> {
+    /// Returns the current value.
+    pub fn load(&self) -> Arc<T> {
+        self.shared.value.load_full()
+    }
+
+    /// Returns the revision of the file the current value was read from.
+    pub fn revision(&self) -> Revision {
+        Revision::from(self.shared.revision.load(Ordering::Acquire))
+    }
+}
+
+#[cfg(all(test, feature = "tower"))]
+impl<T> CentralDogmaConfig<T> {
+    /// Returns a configuration which never changes.
+    pub(crate) fn fixed(value: T) -> Self {
+        CentralDogmaConfig {
+            shared: Arc::new(Shared {
+                value: ArcSwap::from_pointee(value),
+                revision: AtomicI64::new(1),
+            }),
+            task: tokio::spawn(async {}),
+        }
+    }
+}
+
+impl<T> Drop for CentralDogmaConfig<T> {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Builder of a [`CentralDogmaConfig`], created by [`CentralDogmaConfig::builder()`].
+pub struct CentralDogmaConfigBuilder<T> {
+    query: Query,
+    validator: Option<Arc<Validator<T>>>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> CentralDogmaConfigBuilder<T>
+where
+    T: DeserializeOwned + Send + Sync + 'static,
+{
+    /// Rejects the values for which `validator` returns an error.
+    pub fn validator<F>(mut self, validator: F) -> Self
+    where
+        F: Fn(&T) -> Result<(), String> + Send + Sync + 'static,
+    {
+        self.validator = Some(Arc::new(validator));
+        self
+    }
+
+    /// Reads the file from `repo`, then keeps watching it.
+    ///
+    /// Fails with [`Error::InvalidConfig`] if the current content of the file
+    /// fails to deserialize or validate.
+    pub async fn start(
+        self,
+        repo: &CentralDogmaRepository,
+    ) -> Result<CentralDogmaConfig<T>, Error> {
+        let client = repo.client();
+        let entry = client.get_file(Revision::HEAD, &self.query).await?;
+        let initial = parse(&entry, self.validator.as_deref())?;
+
+        let shared = Arc::new(Shared {
+            value: ArcSwap::from_pointee(initial),
+            revision: AtomicI64::new(entry.revision.as_i64().unwrap_or_default()),
+        });
+
+        let stream = watch_file_stream_since(&client, &self.query, entry.revision);
+        let task_shared = shared.clone();
+        let validator = self.validator;
+        let task = tokio::spawn(async move {
+            futures::pin_mut!(stream);
+            while let Some(result) = stream.next().await {
+                match parse(&result.entry, validator.as_deref()) {
+                    Ok(value) => {
+                        task_shared.value.store(Arc::new(value));
+                        if let Some(revision) = result.revision.as_i64() {
+                            task_shared.revision.store(revision, Ordering::Release);
+                        }
+                    }
+                    Err(e) => log::warn!("Ignoring the new content: {}", e),
+                }
+            }
+        });
+
+        Ok(CentralDogmaConfig { shared, task })
+    }
+}
+
+/// A type bound to a JSON file of a repository,
+/// usually implemented with `#[derive(DogmaConfig)]` of the `derive` feature:
+///
+/// ```ignore
+/// use centraldogma::DogmaConfig;
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize, DogmaConfig)]
+/// #[dogma(path = "/limits.json")]
+/// struct Limits {
+///     max_connections: usize,
+/// }
+///
+/// let limits = Limits::load(&repo).await?;
+/// let watched = Limits::watch(&repo).await?;
+/// ```
+#[async_trait]
+pub trait DogmaConfig: DeserializeOwned + Send + Sync + 'static {
+    /// The absolute path of the file.
+    const PATH: &'static str;
+
+    /// Returns the query of the file.
+    fn query() -> Query {
+        Query::identity(Self::PATH).expect("the path of a config is not empty")
+    }
+
+    /// Reads the file from `repo`.
+    ///
+    /// Fails with [`Error::InvalidConfig`] if its content fails to deserialize.
+    async fn load(repo: &CentralDogmaRepository) -> Result<Self, Error> {
+        let entry = repo
+            .client()
+            .get_file(Revision::HEAD, &Self::query())
+            .await?;
+
+        parse(&entry, None)
+    }
+
+    /// Reads the file from `repo`, then keeps watching it.
+    ///
+    /// Use [`CentralDogmaConfig::builder()`] to validate the values.
+    async fn watch(repo: &CentralDogmaRepository) -> Result<CentralDogmaConfig<Self>, Error> {
+        CentralDogmaConfig::builder(Self::query()).start(repo).await
+    }
+}
+
+fn parse<T: DeserializeOwned>(entry: &Entry, validator: Option<&Validator<T>>) -> Result<T, Error> {
+    let invalid = |message: String| Error::InvalidConfig {
+        path: entry.path.clone(),
+        message,
+    };
+
+    let value = match &entry.content {
+        EntryContent::Json(json) => T::deserialize(json).map_err(|e| invalid(e.to_string()))?,
+        EntryContent::Text(text) => {
+            serde_json::from_str(text).map_err(|e| invalid(e.to_string()))?
+        }
+        #[cfg(feature = "yaml")]
+        EntryContent::Yaml(yaml) => {
+            serde_yaml::from_value(yaml.clone()).map_err(|e| invalid(e.to_string()))?
+        }
+        EntryContent::Directory => return Err(invalid("not a file".to_owned())),
+    };
+    if let Some(validator) = validator {
+        validator(&value).map_err(invalid)?;
+    }
+
+    Ok(value)
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use serde::Deserialize;
+    use wiremock::{
+        matchers::{header, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+    use crate::{Client, ErrorCode};
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Limits {
+        max: i64,
+    }
+
+    fn entry(revision: i64, max: i64) -> String {
+        format!(
+            r#"{{"path":"/a.json","type":"JSON","content":{{"max":{}}},"revision":{},"url":"/a.json"}}"#,
+            max, revision
+        )
+    }
+
+    async fn mount_watch(server: &MockServer, known: i64, revision: i64, max: i64) {
+        let body = format!(
+            r#"{{"revision":{},"entry":{}}}"#,
+            revision,
+            entry(revision, max)
+        );
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/contents/a.json"))
+            .and(header("if-none-match", known.to_string().as_str()))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(body, "application/json"))
+            .up_to_n_times(1)
+            .mount(server)
+            .await;
+    }
+
+    async fn wait_for_revision(config: &CentralDogmaConfig<Limits>, revision: i64) {
+        for _ in 0..50 {
+            if config.revision() == Revision::from(revision) {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        panic!("not updated to revision {}", revision);
+    }
+
+    #[tokio::test]
+    async fn test_config() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/contents/a.json"))
+            .and(wiremock::matchers::query_param("revision", "-1"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(entry(2, 1), "application/json"))
+            .mount(&server)
+            .await;
+        // Rejected by the validator, then valid
+        mount_watch(&server, 2, 3, -1).await;
+        mount_watch(&server, 3, 4, 5).await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(304).set_delay(Duration::from_millis(100)))
+            .mount(&server)
+            .await;
+
+        let client = Client::new(&server.uri(), None).await.unwrap();
+        let config = CentralDogmaConfig::builder(Query::identity("/a.json").unwrap())
+            .validator(|l: &Limits| {
+                if l.max < 0 {
+                    return Err("negative".to_owned());
+                }
+                Ok(())
+            })
+            .start(&client.for_repo("foo", "bar"))
+            .await
+            .unwrap();
+
+        assert_eq!(*config.load(), Limits { max: 1 });
+        assert_eq!(config.revision(), Revision::from(2));
+
+        wait_for_revision(&config, 4).await;
+        assert_eq!(*config.load(), Limits { max: 5 });
+    }
+
+    #[tokio::test]
+    async fn test_invalid_initial_config() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(
+                r#"{"path":"/a.json","type":"JSON","content":{"max":"a"},"revision":2,"url":"/a.json"}"#,
+                "application/json",
+            ))
+            .mount(&server)
+            .await;
+
+        let client = Client::new(&server.uri(), None).await.unwrap();
+        let result = CentralDogmaConfig::<Limits>::builder(Query::identity("/a.json").unwrap())
+            .start(&client.for_repo("foo", "bar"))
+            .await;
+
+        let err = result.err().unwrap();
+        assert_eq!(err.code(), ErrorCode::InvalidConfig);
+        assert!(matches!(err, Error::InvalidConfig { path, .. } if path == "/a.json"));
+    }
+
+    #[cfg(feature = "derive")]
+    #[tokio::test]
+    async fn test_derive() {
+        #[derive(Debug, Deserialize, PartialEq, crate::DogmaConfig)]
+        #[dogma(path = "/a.json")]
+        struct Derived {
+            max: i64,
+        }
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/contents/a.json"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(entry(2, 7), "application/json"))
+            .mount(&server)
+            .await;
+
+        let client = Client::new(&server.uri(), None).await.unwrap();
+        let repo = client.for_repo("foo", "bar");
+
+        assert_eq!(Derived::PATH, "/a.json");
+        assert_eq!(Derived::load(&repo).await.unwrap(), Derived { max: 7 });
+    }
+}
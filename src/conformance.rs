@@ -0,0 +1,607 @@
+//! Checks of a Central Dogma server against this crate, enabled by the `conformance`
+//! feature.
+//!
+//! The suite exercises every capability of the client in a project of its own,
+//! which is purged afterwards, so operators can validate a new server version
+//! before upgrading.
+//!
+//! ```no_run
+//! use centraldogma::{conformance, Client};
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let client = Client::new("http://localhost:36462", Some("token")).await.unwrap();
+//! let report = conformance::run(&client).await;
+//! println!("{}", report);
+//! assert!(report.is_compatible());
+//! # }
+//! ```
+use std::{fmt, time::Duration};
+
+use futures::StreamExt;
+use serde_json::json;
+
+use crate::{
+    json_path::JsonPath,
+    model::{
+        Change, ChangeContent, CommitMessage, EntryContent, MergeQuery, MergeSource,
+        PerRolePermissions, Permission, Query, Revision,
+    },
+    Client, ContentService, MetadataService, ProjectService, RepoService, WatchService,
+};
+
+const REPO: &str = "conformance";
+const WATCH_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// A group of APIs of the server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Capability {
+    /// Creating, listing, removing and restoring projects
+    Projects,
+    /// Creating, listing, removing and restoring repositories
+    Repositories,
+    /// Pushing and reading files, their history and diffs
+    Content,
+    /// Watching files and repositories
+    Watch,
+    /// Merging JSON files
+    Merge,
+    /// Project and repository metadata, e.g. members and tokens
+    Metadata,
+}
+
+impl fmt::Display for Capability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Capability::Projects => "projects",
+            Capability::Repositories => "repositories",
+            Capability::Content => "content",
+            Capability::Watch => "watch",
+            Capability::Merge => "merge",
+            Capability::Metadata => "metadata",
+        };
+        f.write_str(name)
+    }
+}
+
+/// The outcome of a check.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    /// The server behaved as this crate expects.
+    Passed,
+    /// The server did not behave as this crate expects, with the reason.
+    Failed(String),
+    /// The check was not run, with the reason.
+    Skipped(String),
+}
+
+/// The outcome of a check of a [`Capability`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Check {
+    /// The capability checked
+    pub capability: Capability,
+    /// What was checked, e.g. `create project`
+    pub name: &'static str,
+    /// The outcome of the check
+    pub outcome: Outcome,
+}
+
+/// The outcomes of all the checks of a run of the suite.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Report {
+    /// The checks in the order they ran
+    pub checks: Vec<Check>,
+}
+
+impl Report {
+    /// Returns whether no check failed.
+    pub fn is_compatible(&self) -> bool {
+        !self
+            .checks
+            .iter()
+            .any(|c| matches!(c.outcome, Outcome::Failed(_)))
+    }
+
+    /// Returns whether every check of `capability` passed.
+    /// A capability without a check that ran is not supported.
+    pub fn supports(&self, capability: Capability) -> bool {
+        let mut checks = self
+            .checks
+            .iter()
+            .filter(|c| c.capability == capability)
+            .peekable();
+
+        checks.peek().is_some() && checks.all(|c| c.outcome == Outcome::Passed)
+    }
+
+    /// Returns the checks which failed.
+    pub fn failures(&self) -> impl Iterator<Item = &Check> {
+        self.checks
+            .iter()
+            .filter(|c| matches!(c.outcome, Outcome::Failed(_)))
+    }
+
+    fn record(&mut self, capability: Capability, name: &'static str, result: Result<(), String>) {
+        let outcome = match result {
+            Ok(()) => Outcome::Passed,
+            Err(reason) => Outcome::Failed(reason),
+        };
+        self.checks.push(Check {
+            capability,
+            name,
+            outcome,
+        });
+    }
+
+    fn skip(&mut self, capability: Capability, name: &'static str, reason: &str) {
+        self.checks.push(Check {
+            capability,
+            name,
+            outcome: Outcome::Skipped(reason.to_owned()),
+        });
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for check in &self.checks {
+            write!(f, "[{}] {}: ", check.capability, check.name)?;
+            match &check.outcome {
+                Outcome::Passed => writeln!(f, "ok")?,
+                Outcome::Failed(reason) => writeln!(f, "FAILED ({})", reason)?,
+                Outcome::Skipped(reason) => writeln!(f, "skipped ({})", reason)?,
+            }
+        }
+        let failed = self.failures().count();
+        if failed == 0 {
+            write!(f, "compatible")
+        } else {
+            write!(f, "incompatible, {} check(s) failed", failed)
+        }
+    }
+}
+
+fn ensure(condition: bool, message: &str) -> Result<(), String> {
+    if condition {
+        Ok(())
+    } else {
+        Err(message.to_owned())
+    }
+}
+
+fn failed(action: &str) -> impl FnOnce(crate::Error) -> String + '_ {
+    move |e| format!("failed to {}: {}", action, e)
+}
+
+/// Runs the suite with `client`, which needs permission to create projects.
+///
+/// The checks run in a project named `conformance-<random suffix>`,
+/// which is removed and purged at the end.
+pub async fn run(client: &Client) -> Report {
+    let project = format!("conformance-{:08x}", fastrand::u32(..));
+    let mut report = Report::default();
+
+    let created = check_projects(client, &project, &mut report).await;
+    if created {
+        let repo_created = check_repositories(client, &project, &mut report).await;
+        if repo_created {
+            check_content(client, &project, &mut report).await;
+            check_watch(client, &project, &mut report).await;
+            check_merge(client, &project, &mut report).await;
+            check_metadata(client, &project, &mut report).await;
+        } else {
+            report.skip(Capability::Content, "content", "no repository");
+            report.skip(Capability::Watch, "watch", "no repository");
+            report.skip(Capability::Merge, "merge", "no repository");
+            report.skip(Capability::Metadata, "metadata", "no repository");
+        }
+    } else {
+        report.skip(Capability::Repositories, "repositories", "no project");
+        report.skip(Capability::Content, "content", "no project");
+        report.skip(Capability::Watch, "watch", "no project");
+        report.skip(Capability::Merge, "merge", "no project");
+        report.skip(Capability::Metadata, "metadata", "no project");
+    }
+
+    if created {
+        let cleanup = async {
+            client
+                .remove_project(&project)
+                .await
+                .map_err(failed("remove project"))?;
+            client
+                .purge_project(&project)
+                .await
+                .map_err(failed("purge project"))
+        };
+        report.record(Capability::Projects, "purge project", cleanup.await);
+    }
+
+    report
+}
+
+/// Returns whether the project was created, and is active.
+async fn check_projects(client: &Client, project: &str, report: &mut Report) -> bool {
+    let result = client
+        .create_project(project)
+        .await
+        .map_err(failed("create project"))
+        .and_then(|p| ensure(p.name == project, "created project has another name"));
+    let created = result.is_ok();
+    report.record(Capability::Projects, "create project", result);
+    if !created {
+        return false;
+    }
+
+    let result = async {
+        let projects = client
+            .list_projects()
+            .await
+            .map_err(failed("list projects"))?;
+        ensure(
+            projects.iter().any(|p| p.name == project),
+            "created project is not listed",
+        )
+    };
+    report.record(Capability::Projects, "list projects", result.await);
+
+    let result = async {
+        client
+            .remove_project(project)
+            .await
+            .map_err(failed("remove project"))?;
+        let removed = client
+            .list_removed_projects()
+            .await
+            .map_err(failed("list removed projects"))?;
+        ensure(
+            removed.iter().any(|p| p == project),
+            "removed project is not listed as removed",
+        )
+    };
+    report.record(Capability::Projects, "remove project", result.await);
+
+    let result = client
+        .unremove_project(project)
+        .await
+        .map_err(failed("unremove project"))
+        .and_then(|p| ensure(p.name == project, "unremoved project has another name"));
+    let active = result.is_ok();
+    report.record(Capability::Projects, "unremove project", result);
+
+    active
+}
+
+/// Returns whether the repository was created, and is active.
+async fn check_repositories(client: &Client, project: &str, report: &mut Report) -> bool {
+    let p = client.project(project);
+
+    let result = p
+        .create_repo(REPO)
+        .await
+        .map_err(failed("create repository"))
+        .and_then(|r| ensure(r.name == REPO, "created repository has another name"));
+    let created = result.is_ok();
+    report.record(Capability::Repositories, "create repository", result);
+    if !created {
+        return false;
+    }
+
+    let result = async {
+        let repos = p.list_repos().await.map_err(failed("list repositories"))?;
+        ensure(
+            repos.iter().any(|r| r.name == REPO),
+            "created repository is not listed",
+        )
+    };
+    report.record(Capability::Repositories, "list repositories", result.await);
+
+    let result = async {
+        p.remove_repo(REPO)
+            .await
+            .map_err(failed("remove repository"))?;
+        let removed = p
+            .list_removed_repos()
+            .await
+            .map_err(failed("list removed repositories"))?;
+        ensure(
+            removed.iter().any(|r| r == REPO),
+            "removed repository is not listed as removed",
+        )
+    };
+    report.record(Capability::Repositories, "remove repository", result.await);
+
+    let result = p
+        .unremove_repo(REPO)
+        .await
+        .map_err(failed("unremove repository"))
+        .and_then(|r| ensure(r.name == REPO, "unremoved repository has another name"));
+    let active = result.is_ok();
+    report.record(Capability::Repositories, "unremove repository", result);
+
+    active
+}
+
+async fn check_content(client: &Client, project: &str, report: &mut Report) {
+    let r = client.repo(project, REPO);
+
+    let result = async {
+        let pushed = r
+            .push(
+                Revision::HEAD,
+                CommitMessage::only_summary("Add files"),
+                vec![
+                    Change::from(("/a.json", json!({"a": {"b": 1}}))),
+                    Change {
+                        path: "/b.txt".to_owned(),
+                        content: ChangeContent::UpsertText("b\n".to_owned()),
+                    },
+                ],
+            )
+            .await
+            .map_err(failed("push"))?;
+        ensure(pushed.revision.as_i64().is_some(), "push has no revision")
+    };
+    report.record(Capability::Content, "push", result.await);
+
+    let result = async {
+        let entry = r
+            .get_file(Revision::HEAD, &Query::identity("/a.json").unwrap())
+            .await
+            .map_err(failed("get file"))?;
+        ensure(
+            entry.content == EntryContent::Json(json!({"a": {"b": 1}})),
+            "file has other content than pushed",
+        )
+    };
+    report.record(Capability::Content, "get file", result.await);
+
+    let result = async {
+        let query = Query::of_json_path("/a.json", vec!["$.a.b".to_owned()]).unwrap();
+        let entry = r
+            .get_file(Revision::HEAD, &query)
+            .await
+            .map_err(failed("query file"))?;
+        ensure(
+            entry.content == EntryContent::Json(json!(1)),
+            "JSON path query has another result",
+        )
+    };
+    report.record(Capability::Content, "JSON path query", result.await);
+
+    let result = async {
+        let files = r
+            .list_files(Revision::HEAD, "/**")
+            .await
+            .map_err(failed("list files"))?;
+        ensure(files.len() == 2, "not all pushed files are listed")?;
+        let entries = r
+            .get_files(Revision::HEAD, "/*.txt")
+            .await
+            .map_err(failed("get files"))?;
+        ensure(
+            entries.len() == 1 && entries[0].content == EntryContent::Text("b\n".to_owned()),
+            "path pattern matches other files",
+        )
+    };
+    report.record(Capability::Content, "list files", result.await);
+
+    let result = async {
+        r.push(
+            Revision::HEAD,
+            CommitMessage::only_summary("Edit a.json"),
+            vec![Change::from(("/a.json", json!({"a": {"b": 2}})))],
+        )
+        .await
+        .map_err(failed("push"))?;
+        let history = r
+            .get_history(Revision::INIT, Revision::HEAD, "/**", None)
+            .await
+            .map_err(failed("get history"))?;
+        ensure(history.len() >= 2, "history lacks the pushed commits")?;
+        let diff = r
+            .get_diff(
+                Revision::from(-2),
+                Revision::HEAD,
+                &Query::identity("/a.json").unwrap(),
+            )
+            .await
+            .map_err(failed("get diff"))?;
+        ensure(diff.path == "/a.json", "diff is of another file")
+    };
+    report.record(Capability::Content, "history and diff", result.await);
+}
+
+async fn check_watch(client: &Client, project: &str, report: &mut Report) {
+    let r = client.repo(project, REPO);
+
+    let result = async {
+        let stream = r
+            .watch_file_stream(&Query::identity("/w.json").unwrap())
+            .map_err(failed("watch file"))?;
+        let push = async {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            r.push(
+                Revision::HEAD,
+                CommitMessage::only_summary("Add w.json"),
+                vec![Change::from(("/w.json", json!({"w": 1})))],
+            )
+            .await
+        };
+        let stream = stream.take_until(tokio::time::sleep(WATCH_TIMEOUT));
+        tokio::pin!(stream);
+        let (result, pushed) = tokio::join!(stream.next(), push);
+        let pushed = pushed.map_err(failed("push"))?;
+        let result = result.ok_or_else(|| "no change was watched".to_owned())?;
+        ensure(
+            result.revision == pushed.revision,
+            "watched another revision than pushed",
+        )
+    };
+    report.record(Capability::Watch, "watch file", result.await);
+
+    let result = async {
+        let stream = r
+            .watch_repo_stream("/w.json")
+            .map_err(failed("watch repository"))?;
+        let push = async {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            r.push(
+                Revision::HEAD,
+                CommitMessage::only_summary("Edit w.json"),
+                vec![Change::from(("/w.json", json!({"w": 2})))],
+            )
+            .await
+        };
+        let stream = stream.take_until(tokio::time::sleep(WATCH_TIMEOUT));
+        tokio::pin!(stream);
+        let (result, pushed) = tokio::join!(stream.next(), push);
+        let pushed = pushed.map_err(failed("push"))?;
+        let result = result.ok_or_else(|| "no change was watched".to_owned())?;
+        ensure(
+            result.revision == pushed.revision,
+            "watched another revision than pushed",
+        )
+    };
+    report.record(Capability::Watch, "watch repository", result.await);
+}
+
+async fn check_merge(client: &Client, project: &str, report: &mut Report) {
+    let r = client.repo(project, REPO);
+
+    let result = async {
+        r.push(
+            Revision::HEAD,
+            CommitMessage::only_summary("Add m.json"),
+            vec![Change::from(("/m.json", json!({"a": {"c": 3}})))],
+        )
+        .await
+        .map_err(failed("push"))?;
+        let query = MergeQuery::of_json(vec![
+            MergeSource::required("/a.json").unwrap(),
+            MergeSource::required("/m.json").unwrap(),
+            MergeSource::optional("/missing.json").unwrap(),
+        ])
+        .unwrap();
+        let merged = r
+            .merge_files(Revision::HEAD, &query)
+            .await
+            .map_err(failed("merge files"))?;
+        ensure(
+            merged.content == json!({"a": {"b": 2, "c": 3}}),
+            "merged content is not the files merged in order",
+        )?;
+        ensure(
+            merged.paths == ["/a.json", "/m.json"],
+            "merged paths are not the existing files",
+        )
+    };
+    report.record(Capability::Merge, "merge files", result.await);
+
+    let result = async {
+        let query = MergeQuery::of_json_path(
+            vec![
+                MergeSource::required("/a.json").unwrap(),
+                MergeSource::required("/m.json").unwrap(),
+            ],
+            vec![JsonPath::root().field("a").field("c")],
+        )
+        .unwrap();
+        let merged = r
+            .merge_files(Revision::HEAD, &query)
+            .await
+            .map_err(failed("merge files"))?;
+        ensure(
+            merged.content == json!(3),
+            "JSON path merge query has another result",
+        )
+    };
+    report.record(Capability::Merge, "JSON path merge query", result.await);
+}
+
+async fn check_metadata(client: &Client, project: &str, report: &mut Report) {
+    let p = client.project(project);
+
+    let result = async {
+        let metadata = p.get_metadata().await.map_err(failed("get metadata"))?;
+        ensure(metadata.name == project, "metadata is of another project")?;
+        ensure(
+            metadata.repos.contains_key(REPO),
+            "created repository has no metadata",
+        )
+    };
+    report.record(Capability::Metadata, "get metadata", result.await);
+
+    let result = async {
+        let permissions = PerRolePermissions {
+            owner: vec![Permission::Read, Permission::Write],
+            member: vec![Permission::Read],
+            guest: Vec::new(),
+            anonymous: None,
+        };
+        p.update_repo_role_permissions(REPO, &permissions)
+            .await
+            .map_err(failed("update repository permissions"))?;
+        let metadata = p.get_metadata().await.map_err(failed("get metadata"))?;
+        let updated = metadata
+            .repos
+            .get(REPO)
+            .map(|r| &r.per_role_permissions)
+            .ok_or_else(|| "repository has no metadata".to_owned())?;
+        ensure(
+            updated.owner == permissions.owner
+                && updated.member == permissions.member
+                && updated.guest == permissions.guest,
+            "repository permissions are not updated",
+        )
+    };
+    report.record(
+        Capability::Metadata,
+        "update repository permissions",
+        result.await,
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn check(capability: Capability, outcome: Outcome) -> Check {
+        Check {
+            capability,
+            name: "check",
+            outcome,
+        }
+    }
+
+    #[test]
+    fn test_report() {
+        let report = Report {
+            checks: vec![
+                check(Capability::Projects, Outcome::Passed),
+                check(Capability::Content, Outcome::Passed),
+                check(Capability::Content, Outcome::Failed("wrong".to_owned())),
+                check(
+                    Capability::Merge,
+                    Outcome::Skipped("unsupported".to_owned()),
+                ),
+            ],
+        };
+
+        assert!(!report.is_compatible());
+        assert!(report.supports(Capability::Projects));
+        assert!(!report.supports(Capability::Content));
+        assert!(!report.supports(Capability::Merge));
+        assert!(!report.supports(Capability::Watch));
+        assert_eq!(report.failures().count(), 1);
+        assert_eq!(
+            report.to_string(),
+            "[projects] check: ok\n\
+             [content] check: ok\n\
+             [content] check: FAILED (wrong)\n\
+             [merge] check: skipped (unsupported)\n\
+             incompatible, 1 check(s) failed"
+        );
+    }
+}
@@ -0,0 +1,277 @@
+//! Pushing of the files edited in a local directory to a repository, enabled by the
+//! `dev-push` feature, to try configuration changes without committing them by hand.
+//!
+//! ```no_run
+//! use centraldogma::{dev::DevPusher, Client};
+//! use futures::StreamExt;
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), centraldogma::Error> {
+//! let client = Client::new("http://localhost:36462", None).await?;
+//! let mut pushes = DevPusher::new("./config", client.for_repo("foo", "dev"))
+//!     .target_dir("/service")
+//!     .start()?;
+//!
+//! while let Some(push) = pushes.next().await {
+//!     match push {
+//!         Ok(push) => println!("Pushed {} changes: {:?}", push.changes.len(), push.result),
+//!         Err(e) => eprintln!("Failed to push: {}", e),
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+    pin::Pin,
+    time::Duration,
+};
+
+use futures::Stream;
+use notify::{RecursiveMode, Watcher};
+use tokio::sync::mpsc;
+
+use crate::{
+    model::{Change, ChangeContent, CommitMessage, PushResult, Revision},
+    CentralDogmaRepository, ContentService, Error,
+};
+
+const DEFAULT_DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// The changes pushed after a burst of edits, output by [`DevPusher::start()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DevPush {
+    /// The changes of the edited files
+    pub changes: Vec<Change>,
+    /// The result of the push, `None` in a dry run
+    pub result: Option<PushResult>,
+}
+
+/// The stream returned by [`DevPusher::start()`].
+pub type DevPushStream = Pin<Box<dyn Stream<Item = Result<DevPush, Error>> + Send>>;
+
+/// Watches a local directory and pushes the edited files to a repository.
+///
+/// Files ending with `.json` are pushed as JSON and the other ones as text. Hidden files
+/// and editor backups, whose names start with `.` or end with `~`, are ignored.
+pub struct DevPusher {
+    dir: PathBuf,
+    repo: CentralDogmaRepository,
+    target_dir: String,
+    debounce: Duration,
+    dry_run: bool,
+}
+
+impl DevPusher {
+    /// Returns a pusher of the files of `dir` to `repo`.
+    pub fn new(dir: impl Into<PathBuf>, repo: CentralDogmaRepository) -> Self {
+        DevPusher {
+            dir: dir.into(),
+            repo,
+            target_dir: String::new(),
+            debounce: DEFAULT_DEBOUNCE,
+            dry_run: false,
+        }
+    }
+
+    /// Pushes the files under `path` of the repository instead of its root,
+    /// e.g. `a.json` to `/service/a.json` with `/service`.
+    pub fn target_dir(mut self, path: &str) -> Self {
+        self.target_dir = path.trim_end_matches('/').to_owned();
+        if !self.target_dir.is_empty() && !self.target_dir.starts_with('/') {
+            self.target_dir.insert(0, '/');
+        }
+        self
+    }
+
+    /// Waits for the edits to stop for `debounce` before pushing them, 500ms by default.
+    pub fn debounce(mut self, debounce: Duration) -> Self {
+        self.debounce = debounce;
+        self
+    }
+
+    /// Only outputs the changes instead of pushing them.
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Starts watching the directory, returning a stream which pushes the edits
+    /// and outputs their result. The directory is no longer watched when the stream
+    /// is dropped.
+    ///
+    /// Fails with [`Error::Io`] if the directory can't be watched.
+    pub fn start(self) -> Result<DevPushStream, Error> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let mut watcher = notify::recommended_watcher(move |event| {
+            let _ = tx.send(event);
+        })
+        .map_err(|e| watch_error(&self.dir, e))?;
+        watcher
+            .watch(&self.dir, RecursiveMode::Recursive)
+            .map_err(|e| watch_error(&self.dir, e))?;
+
+        let stream = futures::stream::unfold(
+            (self, rx, watcher),
+            |(pusher, mut rx, watcher)| async move {
+                loop {
+                    let paths = pusher.next_edits(&mut rx).await?;
+                    match pusher.push(&paths).await {
+                        Ok(None) => continue,
+                        Ok(Some(push)) => return Some((Ok(push), (pusher, rx, watcher))),
+                        Err(e) => return Some((Err(e), (pusher, rx, watcher))),
+                    }
+                }
+            },
+        );
+
+        Ok(Box::pin(stream))
+    }
+
+    /// Waits for a burst of edits, returning the edited paths,
+    /// or `None` when the watcher stopped.
+    async fn next_edits(
+        &self,
+        rx: &mut mpsc::UnboundedReceiver<notify::Result<notify::Event>>,
+    ) -> Option<BTreeSet<PathBuf>> {
+        let mut paths = BTreeSet::new();
+        let mut event = rx.recv().await?;
+        loop {
+            match event {
+                Ok(event) if !event.kind.is_access() => paths.extend(event.paths),
+                Ok(_) => {}
+                Err(e) => log::warn!("Failed to watch {}: {}", self.dir.display(), e),
+            }
+            event = match tokio::time::timeout(self.debounce, rx.recv()).await {
+                Ok(Some(event)) => event,
+                Ok(None) | Err(_) => return Some(paths),
+            };
+        }
+    }
+
+    /// Pushes the changes of `paths`, if any.
+    async fn push(&self, paths: &BTreeSet<PathBuf>) -> Result<Option<DevPush>, Error> {
+        let mut changes = Vec::new();
+        for path in paths {
+            if let Some(change) = self.change_of(path).await? {
+                changes.push(change);
+            }
+        }
+        if changes.is_empty() {
+            return Ok(None);
+        }
+
+        let result = if self.dry_run {
+            None
+        } else {
+            let summary = format!("Push {} edited files", changes.len());
+            let result = self
+                .repo
+                .client()
+                .push(
+                    Revision::HEAD,
+                    CommitMessage::only_summary(&summary),
+                    changes.clone(),
+                )
+                .await?;
+            Some(result)
+        };
+
+        Ok(Some(DevPush { changes, result }))
+    }
+
+    /// Returns the change of a local file, `None` for the ignored files.
+    async fn change_of(&self, path: &Path) -> Result<Option<Change>, Error> {
+        let Ok(relative) = path.strip_prefix(&self.dir) else {
+            return Ok(None);
+        };
+        let ignored = relative
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_none_or(|name| name.starts_with('.') || name.ends_with('~'));
+        if ignored || path.is_dir() {
+            return Ok(None);
+        }
+
+        let mut change = Change::try_from((relative, ""))?;
+        change.path.insert_str(0, &self.target_dir);
+        change.content = match tokio::fs::read_to_string(path).await {
+            Ok(text) => ChangeContent::upsert_of(&change.path, text).map_err(|message| {
+                Error::InvalidConfig {
+                    path: path.display().to_string(),
+                    message,
+                }
+            })?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => ChangeContent::Remove,
+            Err(e) => {
+                return Err(Error::Io {
+                    path: path.display().to_string(),
+                    source: e,
+                })
+            }
+        };
+
+        Ok(Some(change))
+    }
+}
+
+fn watch_error(dir: &Path, e: notify::Error) -> Error {
+    let source = match e.kind {
+        notify::ErrorKind::Io(e) => e,
+        kind => std::io::Error::other(format!("{:?}", kind)),
+    };
+
+    Error::Io {
+        path: dir.display().to_string(),
+        source,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use futures::StreamExt;
+    use serde_json::json;
+
+    use super::*;
+    use crate::Client;
+
+    #[tokio::test]
+    async fn test_dry_run() {
+        let dir = std::env::temp_dir().join(format!("centraldogma-dev-{}", fastrand::u64(..)));
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        std::fs::write(dir.join("old.txt"), "old").unwrap();
+
+        // Never reached in a dry run
+        let client = Client::new("http://127.0.0.1:1", None).await.unwrap();
+        let mut pushes = DevPusher::new(&dir, client.for_repo("foo", "bar"))
+            .target_dir("service")
+            .debounce(Duration::from_millis(200))
+            .dry_run(true)
+            .start()
+            .unwrap();
+
+        std::fs::write(dir.join("sub/a.json"), r#"{"a": 1}"#).unwrap();
+        std::fs::write(dir.join(".a.json.swp"), "ignored").unwrap();
+        std::fs::remove_file(dir.join("old.txt")).unwrap();
+
+        let push = tokio::time::timeout(Duration::from_secs(10), pushes.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        assert_eq!(push.result, None);
+        assert_eq!(
+            push.changes,
+            vec![
+                Change {
+                    path: "/service/old.txt".to_owned(),
+                    content: ChangeContent::Remove,
+                },
+                Change::from(("/service/sub/a.json", json!({"a": 1}))),
+            ]
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,198 @@
+//! The patches between an old and a new value held locally, enabled by the `diff` feature,
+//! to push only what changed without asking the server for the diff.
+//!
+//! ```no_run
+//! use centraldogma::{
+//!     diff,
+//!     model::{CommitMessage, Revision},
+//!     Client, ContentService,
+//! };
+//! use serde_json::json;
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), centraldogma::Error> {
+//! let client = Client::new("http://localhost:36462", None).await?;
+//! let old = json!({"port": 8080, "hosts": ["a", "b"]});
+//! let new = json!({"port": 9090, "hosts": ["a", "b", "c"]});
+//!
+//! if let Some(change) = diff::json_change("/config.json", &old, &new) {
+//!     client
+//!         .repo("foo", "bar")
+//!         .push(
+//!             Revision::from(3),
+//!             CommitMessage::only_summary("Move to port 9090"),
+//!             vec![change],
+//!         )
+//!         .await?;
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use serde_json::Value;
+use similar::TextDiff;
+
+use crate::{
+    json_patch::{pointer, JsonPatch},
+    model::{Change, ChangeContent},
+};
+
+/// Returns the [`JsonPatch`] turning `old` into `new`, empty if they are equal.
+///
+/// The members of objects are added, removed or patched one by one, and so are the
+/// elements of arrays by index, the extra elements being removed from the end or added.
+/// The other values which differ are replaced.
+pub fn json_patch(old: &Value, new: &Value) -> JsonPatch {
+    diff_json(JsonPatch::new(), &mut Vec::new(), old, new)
+}
+
+fn diff_json(patch: JsonPatch, tokens: &mut Vec<String>, old: &Value, new: &Value) -> JsonPatch {
+    match (old, new) {
+        _ if old == new => patch,
+        (Value::Object(old), Value::Object(new)) => {
+            let mut patch = patch;
+            for (key, old_value) in old {
+                tokens.push(key.clone());
+                patch = match new.get(key) {
+                    Some(new_value) => diff_json(patch, tokens, old_value, new_value),
+                    None => patch.remove(&pointer(tokens.iter())),
+                };
+                tokens.pop();
+            }
+            for (key, new_value) in new {
+                if !old.contains_key(key) {
+                    tokens.push(key.clone());
+                    patch = patch.add(&pointer(tokens.iter()), new_value.clone());
+                    tokens.pop();
+                }
+            }
+            patch
+        }
+        (Value::Array(old), Value::Array(new)) => {
+            let mut patch = patch;
+            for (i, (old_value, new_value)) in old.iter().zip(new).enumerate() {
+                tokens.push(i.to_string());
+                patch = diff_json(patch, tokens, old_value, new_value);
+                tokens.pop();
+            }
+            for i in (new.len()..old.len()).rev() {
+                tokens.push(i.to_string());
+                patch = patch.remove(&pointer(tokens.iter()));
+                tokens.pop();
+            }
+            for (i, new_value) in new.iter().enumerate().skip(old.len()) {
+                tokens.push(i.to_string());
+                patch = patch.add(&pointer(tokens.iter()), new_value.clone());
+                tokens.pop();
+            }
+            patch
+        }
+        _ => patch.replace(&pointer(tokens.iter()), new.clone()),
+    }
+}
+
+/// Returns the unified diff of the lines of the text file at `path` turning `old` into
+/// `new`, with three lines of context, empty if they are equal.
+pub fn text_patch(path: &str, old: &str, new: &str) -> String {
+    TextDiff::from_lines(old, new)
+        .unified_diff()
+        .context_radius(3)
+        .header(path, path)
+        .to_string()
+}
+
+/// Returns the [`ChangeContent::ApplyJsonPatch`] change of the JSON file at `path` turning
+/// `old` into `new`, `None` if they are equal.
+pub fn json_change(path: &str, old: &Value, new: &Value) -> Option<Change> {
+    let patch = json_patch(old, new);
+    if patch.operations().is_empty() {
+        return None;
+    }
+
+    Some(patch.into_change(path))
+}
+
+/// Returns the [`ChangeContent::ApplyTextPatch`] change of the text file at `path` turning
+/// `old` into `new`, `None` if they are equal.
+pub fn text_change(path: &str, old: &str, new: &str) -> Option<Change> {
+    if old == new {
+        return None;
+    }
+
+    Some(Change {
+        path: path.to_owned(),
+        content: ChangeContent::ApplyTextPatch(text_patch(path, old, new)),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_json_patch() {
+        let old = json!({
+            "port": 8080,
+            "removed": true,
+            "hosts": ["a", "b", "c"],
+            "nested": {"a/b": 1, "same": [1, 2]},
+            "type": {"x": 1}
+        });
+        let new = json!({
+            "port": 9090,
+            "hosts": ["a", "d"],
+            "nested": {"a/b": 2, "same": [1, 2], "added": null},
+            "type": [1]
+        });
+        let patch = json_patch(&old, &new);
+
+        assert_eq!(
+            serde_json::to_value(&patch).unwrap(),
+            json!([
+                {"op": "replace", "path": "/hosts/1", "value": "d"},
+                {"op": "remove", "path": "/hosts/2"},
+                {"op": "replace", "path": "/nested/a~1b", "value": 2},
+                {"op": "add", "path": "/nested/added", "value": null},
+                {"op": "replace", "path": "/port", "value": 9090},
+                {"op": "remove", "path": "/removed"},
+                {"op": "replace", "path": "/type", "value": [1]},
+            ])
+        );
+
+        let patch = json_patch(&json!([1]), &json!([1, 2, 3]));
+        assert_eq!(
+            serde_json::to_value(&patch).unwrap(),
+            json!([
+                {"op": "add", "path": "/1", "value": 2},
+                {"op": "add", "path": "/2", "value": 3},
+            ])
+        );
+        let patch = json_patch(&json!(1), &json!("1"));
+        assert_eq!(
+            serde_json::to_value(&patch).unwrap(),
+            json!([{"op": "replace", "path": "", "value": "1"}])
+        );
+
+        assert!(json_change("/a.json", &old, &old).is_none());
+        let change = json_change("/a.json", &old, &new).unwrap();
+        assert_eq!(change.path, "/a.json");
+        assert!(matches!(change.content, ChangeContent::ApplyJsonPatch(_)));
+    }
+
+    #[test]
+    fn test_text_patch() {
+        let old = "a\nb\nc\n";
+        let new = "a\nB\nc\nd\n";
+
+        assert_eq!(
+            text_patch("/a.txt", old, new),
+            "--- /a.txt\n+++ /a.txt\n@@ -1,3 +1,4 @@\n a\n-b\n+B\n c\n+d\n"
+        );
+        assert!(text_change("/a.txt", old, old).is_none());
+        assert_eq!(
+            text_change("/a.txt", old, new).unwrap().content,
+            ChangeContent::ApplyTextPatch(text_patch("/a.txt", old, new))
+        );
+    }
+}
@@ -0,0 +1,281 @@
+//! Exports of the files of a repository into a local directory, and imports of a local
+//! directory into a repository.
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    local::{file_content, io, relative_path},
+    model::{Change, ChangeContent, CommitMessage, EntryType, PushResult, Revision},
+    ContentService, Error, RepoClient,
+};
+
+impl<'a> RepoClient<'a> {
+    /// Writes the files matched by `path_pattern` at the specified [`Revision`] under
+    /// `dest`, keeping their directory structure, and returns the paths of the files written.
+    ///
+    /// JSON files are pretty-printed, text files are written as is, and YAML files, with the
+    /// `yaml` feature, as YAML documents. Existing files are
+    /// overwritten, and the other files under `dest` are left untouched.
+    ///
+    /// ```no_run
+    /// use std::path::Path;
+    ///
+    /// use centraldogma::{model::Revision, Client};
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), centraldogma::Error> {
+    /// let client = Client::new("http://localhost:36462", None).await?;
+    /// let files = client
+    ///     .repo("foo", "bar")
+    ///     .export_to_dir(Revision::HEAD, "/**", Path::new("/tmp/bar"))
+    ///     .await?;
+    /// println!("exported {} files", files.len());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn export_to_dir(
+        &self,
+        revision: Revision,
+        path_pattern: &str,
+        dest: &Path,
+    ) -> Result<Vec<PathBuf>, Error> {
+        let entries = self.get_files(revision, path_pattern).await?;
+
+        let mut written = Vec::new();
+        for entry in entries {
+            let path = dest.join(relative_path(&entry.path)?);
+            let Some(content) = file_content(&entry)? else {
+                tokio::fs::create_dir_all(&path)
+                    .await
+                    .map_err(|e| io(&path, e))?;
+                continue;
+            };
+            if let Some(parent) = path.parent() {
+                tokio::fs::create_dir_all(parent)
+                    .await
+                    .map_err(|e| io(parent, e))?;
+            }
+            tokio::fs::write(&path, content)
+                .await
+                .map_err(|e| io(&path, e))?;
+            written.push(path);
+        }
+
+        Ok(written)
+    }
+
+    /// Pushes the files under the local directory `src` to the directory `prefix` of the
+    /// repository in one commit, removing the files under `prefix` which are not in `src`,
+    /// the counterpart of [`export_to_dir()`](Self::export_to_dir).
+    ///
+    /// The `.json` files are pushed as JSON, failing with [`Error::InvalidConfig`] if they
+    /// don't parse, likewise the `.yaml` and `.yml` files as YAML with the `yaml` feature,
+    /// and the other files as text. Hidden files and backup files ending with
+    /// `~` are ignored.
+    ///
+    /// ```no_run
+    /// use std::path::Path;
+    ///
+    /// use centraldogma::{
+    ///     model::{CommitMessage, Revision},
+    ///     Client,
+    /// };
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), centraldogma::Error> {
+    /// let client = Client::new("http://localhost:36462", None).await?;
+    /// let result = client
+    ///     .repo("foo", "bar")
+    ///     .import_dir(
+    ///         Revision::HEAD,
+    ///         Path::new("./config"),
+    ///         "/config",
+    ///         CommitMessage::only_summary("Deploy the config"),
+    ///     )
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn import_dir(
+        &self,
+        base_revision: Revision,
+        src: &Path,
+        prefix: &str,
+        commit_message: CommitMessage,
+    ) -> Result<PushResult, Error> {
+        let prefix = match prefix.trim_matches('/') {
+            "" => String::new(),
+            p => format!("/{}", p),
+        };
+
+        let mut changes = Vec::new();
+        for file in local_files(src).await? {
+            let Ok(relative) = file.strip_prefix(src) else {
+                continue;
+            };
+            let mut change = Change::try_from((relative, ""))?;
+            change.path.insert_str(0, &prefix);
+            let text = tokio::fs::read_to_string(&file)
+                .await
+                .map_err(|e| io(&file, e))?;
+            change.content = ChangeContent::upsert_of(&change.path, text).map_err(|message| {
+                Error::InvalidConfig {
+                    path: file.display().to_string(),
+                    message,
+                }
+            })?;
+            changes.push(change);
+        }
+
+        let local: HashSet<_> = changes.iter().map(|c| c.path.clone()).collect();
+        let remote = match self
+            .list_files(base_revision, &format!("{}/**", prefix))
+            .await
+        {
+            Ok(entries) => entries,
+            Err(Error::EntryNotFound(_)) => Vec::new(),
+            Err(e) => return Err(e),
+        };
+        for entry in remote {
+            if entry.r#type != EntryType::Directory && !local.contains(&entry.path) {
+                changes.push(Change {
+                    path: entry.path,
+                    content: ChangeContent::Remove,
+                });
+            }
+        }
+        changes.sort_by(|a, b| a.path.cmp(&b.path));
+
+        self.push(base_revision, commit_message, changes).await
+    }
+}
+
+/// Returns the files under `dir`, skipping the hidden and backup files and directories.
+async fn local_files(dir: &Path) -> Result<Vec<PathBuf>, Error> {
+    let mut files = Vec::new();
+    let mut dirs = vec![dir.to_owned()];
+    while let Some(dir) = dirs.pop() {
+        let mut entries = tokio::fs::read_dir(&dir).await.map_err(|e| io(&dir, e))?;
+        while let Some(entry) = entries.next_entry().await.map_err(|e| io(&dir, e))? {
+            let path = entry.path();
+            let ignored = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_none_or(|name| name.starts_with('.') || name.ends_with('~'));
+            if ignored {
+                continue;
+            }
+            let file_type = entry.file_type().await.map_err(|e| io(&path, e))?;
+            if file_type.is_dir() {
+                dirs.push(path);
+            } else {
+                files.push(path);
+            }
+        }
+    }
+
+    Ok(files)
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+    use wiremock::{
+        matchers::{body_json, method, path, query_param},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+    use crate::Client;
+
+    #[tokio::test]
+    async fn test_export_to_dir() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/contents/**"))
+            .and(query_param("revision", "2"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+                {"path": "/a.json", "type": "JSON", "content": {"a": 1}, "revision": 2, "url": "/a.json"},
+                {"path": "/dir", "type": "DIRECTORY", "revision": 2, "url": "/dir"},
+                {"path": "/dir/sub/b.txt", "type": "TEXT", "content": "b\n", "revision": 2, "url": "/dir/sub/b.txt"},
+                {"path": "/empty", "type": "DIRECTORY", "revision": 2, "url": "/empty"}
+            ])))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let dest = std::env::temp_dir().join(format!("centraldogma-export-{}", fastrand::u64(..)));
+        let client = Client::new(&server.uri(), None).await.unwrap();
+        let files = client
+            .repo("foo", "bar")
+            .export_to_dir(Revision::from(2), "/**", &dest)
+            .await
+            .unwrap();
+
+        assert_eq!(files, [dest.join("a.json"), dest.join("dir/sub/b.txt")]);
+        assert_eq!(
+            std::fs::read_to_string(dest.join("a.json")).unwrap(),
+            "{\n  \"a\": 1\n}\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(dest.join("dir/sub/b.txt")).unwrap(),
+            "b\n"
+        );
+        assert!(dest.join("empty").is_dir());
+        std::fs::remove_dir_all(&dest).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_import_dir() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/list/conf/**"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+                {"path": "/conf/a.json", "type": "JSON"},
+                {"path": "/conf/old", "type": "DIRECTORY"},
+                {"path": "/conf/old/c.txt", "type": "TEXT"}
+            ])))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/api/v1/projects/foo/repos/bar/contents"))
+            .and(body_json(json!({
+                "commitMessage": {"summary": "Import"},
+                "changes": [
+                    {"path": "/conf/a.json", "type": "UPSERT_JSON", "content": {"a": 1}},
+                    {"path": "/conf/old/c.txt", "type": "REMOVE"},
+                    {"path": "/conf/sub/b.txt", "type": "UPSERT_TEXT", "content": "b"}
+                ]
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(
+                r#"{"revision":3,"pushedAt":"2017-05-22T00:00:00Z"}"#,
+                "application/json",
+            ))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let src = std::env::temp_dir().join(format!("centraldogma-import-{}", fastrand::u64(..)));
+        std::fs::create_dir_all(src.join("sub")).unwrap();
+        std::fs::write(src.join("a.json"), r#"{"a": 1}"#).unwrap();
+        std::fs::write(src.join("sub/b.txt"), "b").unwrap();
+        std::fs::write(src.join(".a.json.swp"), "ignored").unwrap();
+
+        let client = Client::new(&server.uri(), None).await.unwrap();
+        let result = client
+            .repo("foo", "bar")
+            .import_dir(
+                Revision::HEAD,
+                &src,
+                "conf/",
+                CommitMessage::only_summary("Import"),
+            )
+            .await;
+        std::fs::remove_dir_all(&src).unwrap();
+
+        assert_eq!(result.unwrap().revision, Revision::from(3));
+    }
+}
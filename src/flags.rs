@@ -0,0 +1,260 @@
+//! Feature flags defined in a JSON file, evaluated against the context of a request.
+//!
+//! The file maps the names of the flags to their definition:
+//!
+//! ```json
+//! {
+//!   "new-checkout": {
+//!     "enabled": true,
+//!     "rollout": 25,
+//!     "rules": [{ "attribute": "country", "values": ["KR", "JP"] }]
+//!   }
+//! }
+//! ```
+//!
+//! A flag is enabled for a context if it is `enabled`, every rule matches an attribute of the
+//! context, and the key of the context falls in the `rollout` percentage, 100 by default.
+//! The rollout is stable: a key stays enabled when the percentage increases.
+//!
+//! ```no_run
+//! use centraldogma::{flags::{FeatureFlags, FlagContext}, Client};
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), centraldogma::Error> {
+//! let client = Client::new("http://localhost:36462", None).await?;
+//! let flags = FeatureFlags::watch(&client.for_repo("foo", "bar"), "/flags.json").await?;
+//!
+//! let context = FlagContext::new("user-42").attribute("country", "KR");
+//! if flags.is_enabled("new-checkout", &context) {
+//!     // ...
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{model::Query, CentralDogmaConfig, CentralDogmaRepository, Error};
+
+/// The context a flag is evaluated against, e.g. the user of a request.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FlagContext {
+    key: String,
+    attributes: HashMap<String, String>,
+}
+
+impl FlagContext {
+    /// Returns a context without attributes, whose `key` decides the rollout,
+    /// e.g. the identifier of a user.
+    pub fn new(key: &str) -> Self {
+        FlagContext {
+            key: key.to_owned(),
+            attributes: HashMap::new(),
+        }
+    }
+
+    /// Sets the value of an attribute matched by the rules.
+    pub fn attribute(mut self, name: &str, value: &str) -> Self {
+        self.attributes.insert(name.to_owned(), value.to_owned());
+        self
+    }
+}
+
+/// A rule matching the contexts whose attribute is one of `values`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Rule {
+    /// Name of the attribute
+    pub attribute: String,
+    /// The matching values of the attribute
+    pub values: Vec<String>,
+}
+
+/// The definition of a flag.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Flag {
+    /// Whether the flag can be enabled at all
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+    /// Percentage of the keys the flag is enabled for, from 0 to 100
+    #[serde(default = "full_rollout")]
+    pub rollout: f64,
+    /// Rules which must all match the context
+    #[serde(default)]
+    pub rules: Vec<Rule>,
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+fn full_rollout() -> f64 {
+    100.0
+}
+
+impl Flag {
+    /// Returns whether the flag named `name` is enabled for `context`.
+    pub fn is_enabled(&self, name: &str, context: &FlagContext) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        let matched = self.rules.iter().all(|rule| {
+            context
+                .attributes
+                .get(&rule.attribute)
+                .is_some_and(|value| rule.values.contains(value))
+        });
+
+        matched && bucket(name, &context.key) < self.rollout * 100.0
+    }
+}
+
+/// Returns the bucket of `key` for the flag `name`, from 0 to 9999.
+///
+/// The name is hashed with the key so that a key isn't in the first percents
+/// of every flag.
+fn bucket(name: &str, key: &str) -> f64 {
+    let hash = name
+        .bytes()
+        .chain([0])
+        .chain(key.bytes())
+        .fold(0xcbf2_9ce4_8422_2325_u64, |hash, b| {
+            (hash ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+        });
+
+    (hash % 10_000) as f64
+}
+
+/// The flags of a file, kept up to date by a [`CentralDogmaConfig`].
+pub struct FeatureFlags {
+    config: CentralDogmaConfig<BTreeMap<String, Flag>>,
+}
+
+impl FeatureFlags {
+    /// Reads the flags of the file at `path` of `repo`, then keeps watching it.
+    ///
+    /// Fails with [`Error::InvalidConfig`] if the file is not a valid definition of flags,
+    /// e.g. with a rollout which is not between 0 and 100. Later invalid contents are
+    /// ignored, keeping the last valid flags.
+    pub async fn watch(repo: &CentralDogmaRepository, path: &str) -> Result<Self, Error> {
+        let query = Query::identity(path).ok_or(Error::InvalidParams("Invalid flags path"))?;
+        let config = CentralDogmaConfig::builder(query)
+            .validator(|flags: &BTreeMap<String, Flag>| {
+                match flags
+                    .iter()
+                    .find(|(_, flag)| !(0.0..=100.0).contains(&flag.rollout))
+                {
+                    Some((name, _)) => {
+                        Err(format!("rollout of `{}` is not between 0 and 100", name))
+                    }
+                    None => Ok(()),
+                }
+            })
+            .start(repo)
+            .await?;
+
+        Ok(FeatureFlags { config })
+    }
+
+    /// Returns whether the flag named `name` is enabled for `context`,
+    /// `false` for an unknown flag.
+    pub fn is_enabled(&self, name: &str, context: &FlagContext) -> bool {
+        self.config
+            .load()
+            .get(name)
+            .is_some_and(|flag| flag.is_enabled(name, context))
+    }
+
+    /// Returns the current definitions of the flags.
+    pub fn flags(&self) -> Arc<BTreeMap<String, Flag>> {
+        self.config.load()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+    use wiremock::{
+        matchers::{method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+    use crate::{Client, ErrorCode};
+
+    fn flag(value: serde_json::Value) -> Flag {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_flag() {
+        let kr = FlagContext::new("a").attribute("country", "KR");
+        let us = FlagContext::new("a").attribute("country", "US");
+
+        assert!(flag(json!({})).is_enabled("f", &kr));
+        assert!(!flag(json!({"enabled": false})).is_enabled("f", &kr));
+
+        let by_country = flag(json!({"rules": [{"attribute": "country", "values": ["KR"]}]}));
+        assert!(by_country.is_enabled("f", &kr));
+        assert!(!by_country.is_enabled("f", &us));
+        assert!(!by_country.is_enabled("f", &FlagContext::new("a")));
+
+        let half = flag(json!({"rollout": 50}));
+        let quarter = flag(json!({"rollout": 25}));
+        let enabled = |flag: &Flag| {
+            (0..10_000)
+                .filter(|i| flag.is_enabled("f", &FlagContext::new(&i.to_string())))
+                .collect::<Vec<_>>()
+        };
+        let half_enabled = enabled(&half);
+        let quarter_enabled = enabled(&quarter);
+        assert!((4_500..5_500).contains(&half_enabled.len()));
+        assert!((2_000..3_000).contains(&quarter_enabled.len()));
+        assert!(quarter_enabled.iter().all(|i| half_enabled.contains(i)));
+        assert!(enabled(&flag(json!({"rollout": 0}))).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_feature_flags() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/contents/flags.json"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "path": "/flags.json",
+                "type": "JSON",
+                "content": {"on": {}, "off": {"enabled": false}},
+                "revision": 2,
+                "url": "/flags.json"
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/contents/invalid.json"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "path": "/invalid.json",
+                "type": "JSON",
+                "content": {"on": {"rollout": 120}},
+                "revision": 2,
+                "url": "/invalid.json"
+            })))
+            .mount(&server)
+            .await;
+
+        let client = Client::new(&server.uri(), None).await.unwrap();
+        let repo = client.for_repo("foo", "bar");
+        let flags = FeatureFlags::watch(&repo, "/flags.json").await.unwrap();
+        let context = FlagContext::new("a");
+        assert!(flags.is_enabled("on", &context));
+        assert!(!flags.is_enabled("off", &context));
+        assert!(!flags.is_enabled("unknown", &context));
+
+        let err = FeatureFlags::watch(&repo, "/invalid.json")
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(err.code(), ErrorCode::InvalidConfig);
+    }
+}
@@ -0,0 +1,383 @@
+//! A fluent API of a repository, created by [`Client::for_repo()`].
+//!
+//! ```no_run
+//! use centraldogma::{
+//!     model::{Change, CommitMessage, Query, Revision},
+//!     Client,
+//! };
+//! use futures::StreamExt;
+//! use serde_json::json;
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), centraldogma::Error> {
+//! let client = Client::new("http://localhost:36462", None).await?;
+//! let repo = client.for_repo("foo", "bar");
+//!
+//! let query = Query::identity("/a.json").unwrap();
+//! let entry = repo.file(query.clone()).get().await?;
+//!
+//! repo.commit(
+//!     CommitMessage::only_summary("Update a.json"),
+//!     vec![Change::from(("/a.json", json!({"a": 1})))],
+//! )
+//! .base_revision(entry.revision)
+//! .push()
+//! .await?;
+//!
+//! let diff = repo.diff(entry.revision, Revision::HEAD).file(&query).await?;
+//! let mut changes = repo.file(query).watch()?;
+//! while let Some(result) = changes.next().await {
+//!     println!("{:?}", result.entry);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use std::pin::Pin;
+
+use futures::Stream;
+use serde::de::DeserializeOwned;
+
+use crate::{
+    model::{
+        Change, Commit, CommitMessage, Entry, ListEntry, PushResult, Query, Revision,
+        WatchFileResult, WatchRepoResult,
+    },
+    push::PushBuilder,
+    watcher::FileWatcher,
+    Client, ContentService, Error, RepoClient, WatchService,
+};
+
+/// A repository, with a fluent API of its files and commits.
+///
+/// Unlike [`RepoClient`], it owns the names of the project and the repository,
+/// so it can be stored.
+#[derive(Clone)]
+pub struct CentralDogmaRepository {
+    client: Client,
+    project: String,
+    repo: String,
+}
+
+impl CentralDogmaRepository {
+    pub(crate) fn new(client: Client, project: &str, repo: &str) -> Self {
+        CentralDogmaRepository {
+            client,
+            project: project.to_owned(),
+            repo: repo.to_owned(),
+        }
+    }
+
+    /// Returns the name of the project.
+    pub fn project_name(&self) -> &str {
+        &self.project
+    }
+
+    /// Returns the name of the repository.
+    pub fn repo_name(&self) -> &str {
+        &self.repo
+    }
+
+    /// Returns a [`RepoClient`] of the repository, to use the service traits directly.
+    pub fn client(&self) -> RepoClient<'_> {
+        self.client.repo(&self.project, &self.repo)
+    }
+
+    /// Starts a request of the file of `query`.
+    pub fn file(&self, query: Query) -> FileRequest<'_> {
+        FileRequest {
+            repo: self,
+            query,
+            revision: Revision::HEAD,
+        }
+    }
+
+    /// Starts a request of the files matched by `path_pattern`.
+    ///
+    /// See [get_files](trait@crate::ContentService#tymethod.get_files)
+    /// for the syntax of the path pattern.
+    pub fn files(&self, path_pattern: &str) -> FilesRequest<'_> {
+        FilesRequest {
+            repo: self,
+            path_pattern: path_pattern.to_owned(),
+            revision: Revision::HEAD,
+        }
+    }
+
+    /// Starts a commit of `changes` with `commit_message`.
+    pub fn commit(&self, commit_message: CommitMessage, changes: Vec<Change>) -> CommitRequest<'_> {
+        CommitRequest {
+            repo: self,
+            commit_message,
+            changes,
+            base_revision: Revision::HEAD,
+        }
+    }
+
+    /// Starts a commit of changes added one by one, see [`PushBuilder`].
+    pub fn prepare_push(&self) -> PushBuilder<'_> {
+        self.client().prepare_push()
+    }
+
+    /// Starts a request of the diffs between two [`Revision`]s.
+    pub fn diff(&self, from: Revision, to: Revision) -> DiffRequest<'_> {
+        DiffRequest {
+            repo: self,
+            from,
+            to,
+        }
+    }
+
+    /// Starts a request of the commits which changed the files matched by `path_pattern`.
+    pub fn history(&self, path_pattern: &str) -> HistoryRequest<'_> {
+        HistoryRequest {
+            repo: self,
+            path_pattern: path_pattern.to_owned(),
+            from: Revision::HEAD,
+            to: Revision::INIT,
+            max_commits: None,
+        }
+    }
+}
+
+/// A request of a file, created by [`CentralDogmaRepository::file()`].
+pub struct FileRequest<'a> {
+    repo: &'a CentralDogmaRepository,
+    query: Query,
+    revision: Revision,
+}
+
+impl<'a> FileRequest<'a> {
+    /// Requests the file at `revision` instead of [`Revision::HEAD`].
+    pub fn revision(mut self, revision: Revision) -> Self {
+        self.revision = revision;
+        self
+    }
+
+    /// Retrieves the file.
+    pub async fn get(self) -> Result<Entry, Error> {
+        self.repo
+            .client()
+            .get_file(self.revision, &self.query)
+            .await
+    }
+
+    /// Returns a stream which outputs the file whenever it changes.
+    pub fn watch(self) -> Result<Pin<Box<dyn Stream<Item = WatchFileResult> + Send>>, Error> {
+        self.repo.client().watch_file_stream(&self.query)
+    }
+
+    /// Returns a [`FileWatcher`] keeping the latest value of the file, deserialized into `T`.
+    pub fn watcher<T>(self) -> Result<FileWatcher<T>, Error>
+    where
+        T: DeserializeOwned + Send + Sync + 'static,
+    {
+        Ok(FileWatcher::start(self.watch()?))
+    }
+}
+
+/// A request of the files matched by a path pattern,
+/// created by [`CentralDogmaRepository::files()`].
+pub struct FilesRequest<'a> {
+    repo: &'a CentralDogmaRepository,
+    path_pattern: String,
+    revision: Revision,
+}
+
+impl<'a> FilesRequest<'a> {
+    /// Requests the files at `revision` instead of [`Revision::HEAD`].
+    pub fn revision(mut self, revision: Revision) -> Self {
+        self.revision = revision;
+        self
+    }
+
+    /// Retrieves the files with their content.
+    pub async fn get(self) -> Result<Vec<Entry>, Error> {
+        self.repo
+            .client()
+            .get_files(self.revision, &self.path_pattern)
+            .await
+    }
+
+    /// Retrieves the paths and the types of the files.
+    pub async fn list(self) -> Result<Vec<ListEntry>, Error> {
+        self.repo
+            .client()
+            .list_files(self.revision, &self.path_pattern)
+            .await
+    }
+
+    /// Returns a stream which outputs the revision of every commit changing the files.
+    pub fn watch(self) -> Result<Pin<Box<dyn Stream<Item = WatchRepoResult> + Send>>, Error> {
+        self.repo.client().watch_repo_stream(&self.path_pattern)
+    }
+}
+
+/// A commit, created by [`CentralDogmaRepository::commit()`].
+pub struct CommitRequest<'a> {
+    repo: &'a CentralDogmaRepository,
+    commit_message: CommitMessage,
+    changes: Vec<Change>,
+    base_revision: Revision,
+}
+
+impl<'a> CommitRequest<'a> {
+    /// Commits on top of `base_revision` instead of [`Revision::HEAD`],
+    /// failing with [`Error::Conflict`] if the files changed since.
+    pub fn base_revision(mut self, base_revision: Revision) -> Self {
+        self.base_revision = base_revision;
+        self
+    }
+
+    /// Pushes the commit.
+    pub async fn push(self) -> Result<PushResult, Error> {
+        self.repo
+            .client()
+            .push(self.base_revision, self.commit_message, self.changes)
+            .await
+    }
+}
+
+/// A request of diffs, created by [`CentralDogmaRepository::diff()`].
+pub struct DiffRequest<'a> {
+    repo: &'a CentralDogmaRepository,
+    from: Revision,
+    to: Revision,
+}
+
+impl<'a> DiffRequest<'a> {
+    /// Retrieves the diff of the file of `query`.
+    pub async fn file(self, query: &Query) -> Result<Change, Error> {
+        self.repo.client().get_diff(self.from, self.to, query).await
+    }
+
+    /// Retrieves the diffs of the files matched by `path_pattern`.
+    pub async fn files(self, path_pattern: &str) -> Result<Vec<Change>, Error> {
+        self.repo
+            .client()
+            .get_diffs(self.from, self.to, path_pattern)
+            .await
+    }
+}
+
+/// A request of the history, created by [`CentralDogmaRepository::history()`].
+pub struct HistoryRequest<'a> {
+    repo: &'a CentralDogmaRepository,
+    path_pattern: String,
+    from: Revision,
+    to: Revision,
+    max_commits: Option<u32>,
+}
+
+impl<'a> HistoryRequest<'a> {
+    /// Requests the commits between two [`Revision`]s instead of all of them,
+    /// newest first.
+    pub fn range(mut self, from: Revision, to: Revision) -> Self {
+        self.from = from;
+        self.to = to;
+        self
+    }
+
+    /// Requests at most `max_commits` commits.
+    pub fn max_commits(mut self, max_commits: u32) -> Self {
+        self.max_commits = Some(max_commits);
+        self
+    }
+
+    /// Retrieves the commits.
+    pub async fn get(self) -> Result<Vec<Commit>, Error> {
+        self.repo
+            .client()
+            .get_history(self.from, self.to, &self.path_pattern, self.max_commits)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+    use wiremock::{
+        matchers::{body_json, method, path, query_param},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    #[tokio::test]
+    async fn test_fluent_file_and_commit() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/contents/a.json"))
+            .and(query_param("revision", "2"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(
+                r#"{"path":"/a.json","type":"JSON","content":{"a":1},"revision":2,
+                    "url":"/api/v1/projects/foo/repos/bar/contents/a.json"}"#,
+                "application/json",
+            ))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/api/v1/projects/foo/repos/bar/contents"))
+            .and(query_param("revision", "2"))
+            .and(body_json(json!({
+                "commitMessage": {"summary": "Edit a.json"},
+                "changes": [{"path": "/a.json", "type": "UPSERT_JSON", "content": {"a": 2}}],
+            })))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_raw(r#"{"revision":3,"pushedAt":"a"}"#, "application/json"),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = Client::new(&server.uri(), None).await.unwrap();
+        let repo = client.for_repo("foo", "bar");
+        assert_eq!(repo.project_name(), "foo");
+        assert_eq!(repo.repo_name(), "bar");
+
+        let entry = repo
+            .file(Query::identity("/a.json").unwrap())
+            .revision(Revision::from(2))
+            .get()
+            .await
+            .unwrap();
+        let result = repo
+            .commit(
+                CommitMessage::only_summary("Edit a.json"),
+                vec![Change::from(("/a.json", json!({"a": 2})))],
+            )
+            .base_revision(entry.revision)
+            .push()
+            .await
+            .unwrap();
+
+        assert_eq!(result.revision, Revision::from(3));
+    }
+
+    #[tokio::test]
+    async fn test_fluent_diff() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/compare"))
+            .and(query_param("from", "1"))
+            .and(query_param("to", "-1"))
+            .and(query_param("path", "/a.json"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(
+                r#"{"path":"/a.json","type":"APPLY_JSON_PATCH","content":[]}"#,
+                "application/json",
+            ))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = Client::new(&server.uri(), None).await.unwrap();
+        let diff = client
+            .for_repo("foo", "bar")
+            .diff(Revision::INIT, Revision::HEAD)
+            .file(&Query::identity("/a.json").unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(diff.path, "/a.json");
+    }
+}
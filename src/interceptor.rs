@@ -0,0 +1,146 @@
+//! Interceptors of the requests of a [`Client`](crate::Client), modifying them before
+//! they are sent and observing their responses, e.g. to add custom authentication
+//! headers, to sign the requests or to keep an audit log.
+//!
+//! ```no_run
+//! use async_trait::async_trait;
+//! use centraldogma::{interceptor::RequestInterceptor, Client, Error};
+//! use reqwest::header::HeaderValue;
+//!
+//! struct Tenant(&'static str);
+//!
+//! #[async_trait]
+//! impl RequestInterceptor for Tenant {
+//!     async fn before_request(&self, req: &mut reqwest::Request) -> Result<(), Error> {
+//!         req.headers_mut()
+//!             .insert("x-tenant", HeaderValue::from_static(self.0));
+//!         Ok(())
+//!     }
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), centraldogma::Error> {
+//! let client = Client::new("http://localhost:36462", None)
+//!     .await?
+//!     .with_interceptor(Tenant("shopping"));
+//! # Ok(())
+//! # }
+//! ```
+use async_trait::async_trait;
+use reqwest::Method;
+use url::Url;
+
+use crate::Error;
+
+/// Intercepts every attempt of the requests of a client, installed with
+/// [`Client::with_interceptor()`](crate::Client::with_interceptor).
+///
+/// The requests go through the interceptors in the order they were installed, and the
+/// responses in the reverse order. A retried request is intercepted again, so that
+/// e.g. a signature covering the time of the request is renewed.
+#[async_trait]
+pub trait RequestInterceptor: Send + Sync {
+    /// Modifies a request before it's sent. An error fails the request without sending it.
+    /// Does nothing by default.
+    async fn before_request(&self, _req: &mut reqwest::Request) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Observes the response of the request sent with `method` to `url`, or its failure.
+    /// Does nothing by default.
+    async fn after_response(
+        &self,
+        _method: &Method,
+        _url: &Url,
+        _result: &Result<reqwest::Response, Error>,
+    ) {
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{Arc, Mutex};
+
+    use reqwest::header::HeaderValue;
+    use wiremock::{
+        matchers::{headers, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+    use crate::{Client, ProjectService};
+
+    /// Adds a header to the requests, and records the statuses of the responses.
+    #[derive(Clone)]
+    struct Audit {
+        name: &'static str,
+        log: Arc<Mutex<Vec<String>>>,
+    }
+
+    #[async_trait]
+    impl RequestInterceptor for Audit {
+        async fn before_request(&self, req: &mut reqwest::Request) -> Result<(), Error> {
+            if req.url().path().ends_with("/forbidden") {
+                return Err(Error::InvalidParams("forbidden project"));
+            }
+            req.headers_mut()
+                .append("x-audit", HeaderValue::from_static(self.name));
+            self.log
+                .lock()
+                .unwrap()
+                .push(format!("{} {}", self.name, req.url().path()));
+            Ok(())
+        }
+
+        async fn after_response(
+            &self,
+            method: &Method,
+            _url: &Url,
+            result: &Result<reqwest::Response, Error>,
+        ) {
+            let status = result.as_ref().map(|resp| resp.status().as_u16());
+            self.log
+                .lock()
+                .unwrap()
+                .push(format!("{} {} {:?}", self.name, method, status.ok()));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_interceptors() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects"))
+            .and(headers("x-audit", vec!["first", "second"]))
+            .respond_with(ResponseTemplate::new(200).set_body_raw("[]", "application/json"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let client = Client::new(&server.uri(), None)
+            .await
+            .unwrap()
+            .with_interceptor(Audit {
+                name: "first",
+                log: log.clone(),
+            })
+            .with_interceptor(Audit {
+                name: "second",
+                log: log.clone(),
+            });
+        client.list_projects().await.unwrap();
+        assert_eq!(
+            *log.lock().unwrap(),
+            [
+                "first /api/v1/projects",
+                "second /api/v1/projects",
+                "second GET Some(200)",
+                "first GET Some(200)",
+            ]
+        );
+
+        let err = client.remove_project("forbidden").await.unwrap_err();
+        assert!(matches!(err, Error::InvalidParams("forbidden project")));
+    }
+}
@@ -0,0 +1,248 @@
+//! Builder for [JSON patches](https://tools.ietf.org/html/rfc6902) pushed as
+//! [`ChangeContent::ApplyJsonPatch`] changes.
+//!
+//! The paths of the operations are [JSON pointers](https://tools.ietf.org/html/rfc6901),
+//! which [`pointer()`] builds from unescaped member names and indexes.
+//!
+//! ```
+//! use centraldogma::json_patch::{pointer, JsonPatch};
+//! use serde_json::json;
+//!
+//! let patch = JsonPatch::new()
+//!     .test("/version", 2)
+//!     .safe_replace("/port", 8080, 9090)
+//!     .add(&pointer(["hosts", "-"]), "c.example.com")
+//!     .remove(&pointer(["a/b"]));
+//! assert_eq!(
+//!     serde_json::to_value(&patch).unwrap(),
+//!     json!([
+//!         {"op": "test", "path": "/version", "value": 2},
+//!         {"op": "safeReplace", "path": "/port", "oldValue": 8080, "value": 9090},
+//!         {"op": "add", "path": "/hosts/-", "value": "c.example.com"},
+//!         {"op": "remove", "path": "/a~1b"},
+//!     ])
+//! );
+//!
+//! let change = patch.into_change("/config.json");
+//! ```
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::model::{Change, ChangeContent};
+
+/// An operation of a [`JsonPatch`].
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "op", rename_all = "camelCase", rename_all_fields = "camelCase")]
+pub enum PatchOperation {
+    /// Adds `value` at `path`, inserting it into an array or replacing a member
+    Add {
+        /// Where to add the value
+        path: String,
+        /// The added value
+        value: Value,
+    },
+    /// Removes the value at `path`
+    Remove {
+        /// The removed value
+        path: String,
+    },
+    /// Replaces the value at `path` with `value`
+    Replace {
+        /// The replaced value
+        path: String,
+        /// The new value
+        value: Value,
+    },
+    /// Replaces the value at `path` with `value` if it is `old_value`, a Central Dogma
+    /// extension failing the patch otherwise
+    SafeReplace {
+        /// The replaced value
+        path: String,
+        /// The expected current value
+        old_value: Value,
+        /// The new value
+        value: Value,
+    },
+    /// Moves the value at `from` to `path`
+    Move {
+        /// The moved value
+        from: String,
+        /// Where to move the value
+        path: String,
+    },
+    /// Copies the value at `from` to `path`
+    Copy {
+        /// The copied value
+        from: String,
+        /// Where to copy the value
+        path: String,
+    },
+    /// Fails the patch unless the value at `path` is `value`
+    Test {
+        /// The tested value
+        path: String,
+        /// The expected value
+        value: Value,
+    },
+}
+
+/// A JSON patch, the operations applied in order to a JSON file.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(transparent)]
+pub struct JsonPatch {
+    operations: Vec<PatchOperation>,
+}
+
+impl JsonPatch {
+    /// Returns an empty patch.
+    pub fn new() -> Self {
+        JsonPatch::default()
+    }
+
+    /// Appends an [`Add`](PatchOperation::Add) operation.
+    pub fn add(self, path: &str, value: impl Into<Value>) -> Self {
+        self.push(PatchOperation::Add {
+            path: path.to_owned(),
+            value: value.into(),
+        })
+    }
+
+    /// Appends a [`Remove`](PatchOperation::Remove) operation.
+    pub fn remove(self, path: &str) -> Self {
+        self.push(PatchOperation::Remove {
+            path: path.to_owned(),
+        })
+    }
+
+    /// Appends a [`Replace`](PatchOperation::Replace) operation.
+    pub fn replace(self, path: &str, value: impl Into<Value>) -> Self {
+        self.push(PatchOperation::Replace {
+            path: path.to_owned(),
+            value: value.into(),
+        })
+    }
+
+    /// Appends a [`SafeReplace`](PatchOperation::SafeReplace) operation.
+    pub fn safe_replace(
+        self,
+        path: &str,
+        old_value: impl Into<Value>,
+        value: impl Into<Value>,
+    ) -> Self {
+        self.push(PatchOperation::SafeReplace {
+            path: path.to_owned(),
+            old_value: old_value.into(),
+            value: value.into(),
+        })
+    }
+
+    /// Appends a [`Move`](PatchOperation::Move) operation.
+    pub fn move_to(self, from: &str, path: &str) -> Self {
+        self.push(PatchOperation::Move {
+            from: from.to_owned(),
+            path: path.to_owned(),
+        })
+    }
+
+    /// Appends a [`Copy`](PatchOperation::Copy) operation.
+    pub fn copy_to(self, from: &str, path: &str) -> Self {
+        self.push(PatchOperation::Copy {
+            from: from.to_owned(),
+            path: path.to_owned(),
+        })
+    }
+
+    /// Appends a [`Test`](PatchOperation::Test) operation.
+    pub fn test(self, path: &str, value: impl Into<Value>) -> Self {
+        self.push(PatchOperation::Test {
+            path: path.to_owned(),
+            value: value.into(),
+        })
+    }
+
+    /// Appends an operation.
+    pub fn push(mut self, operation: PatchOperation) -> Self {
+        self.operations.push(operation);
+        self
+    }
+
+    /// Returns the operations of this patch.
+    pub fn operations(&self) -> &[PatchOperation] {
+        &self.operations
+    }
+
+    /// Returns a change applying this patch to the JSON file at `path`.
+    pub fn into_change(self, path: &str) -> Change {
+        Change {
+            path: path.to_owned(),
+            content: self.into(),
+        }
+    }
+}
+
+/// Creates a [`ChangeContent::ApplyJsonPatch`] change content from a patch.
+impl From<JsonPatch> for ChangeContent {
+    fn from(patch: JsonPatch) -> Self {
+        let patch = serde_json::to_value(patch).expect("JSON patches are serializable");
+
+        ChangeContent::ApplyJsonPatch(patch)
+    }
+}
+
+/// Returns the JSON pointer of the value reached through `tokens`, the member names and
+/// array indexes, escaping `~` and `/`, e.g. `["a/b", "0"]` becomes `/a~1b/0`.
+pub fn pointer<I>(tokens: I) -> String
+where
+    I: IntoIterator,
+    I::Item: AsRef<str>,
+{
+    let mut pointer = String::new();
+    for token in tokens {
+        pointer.push('/');
+        pointer.push_str(&token.as_ref().replace('~', "~0").replace('/', "~1"));
+    }
+    pointer
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_operations() {
+        let patch = JsonPatch::new()
+            .add("/a", json!({"b": 1}))
+            .remove("/b")
+            .replace("/c", "d")
+            .safe_replace("/e", 1, 2)
+            .move_to("/f", "/g")
+            .copy_to("/h", "/i")
+            .test("/j", Value::Null);
+        let expected = json!([
+            {"op": "add", "path": "/a", "value": {"b": 1}},
+            {"op": "remove", "path": "/b"},
+            {"op": "replace", "path": "/c", "value": "d"},
+            {"op": "safeReplace", "path": "/e", "oldValue": 1, "value": 2},
+            {"op": "move", "from": "/f", "path": "/g"},
+            {"op": "copy", "from": "/h", "path": "/i"},
+            {"op": "test", "path": "/j", "value": null},
+        ]);
+
+        assert_eq!(serde_json::to_value(&patch).unwrap(), expected);
+        let parsed: JsonPatch = serde_json::from_value(expected.clone()).unwrap();
+        assert_eq!(parsed, patch);
+
+        let change = patch.into_change("/a.json");
+        assert_eq!(change.path, "/a.json");
+        assert_eq!(change.content, ChangeContent::ApplyJsonPatch(expected));
+    }
+
+    #[test]
+    fn test_pointer() {
+        assert_eq!(pointer(Vec::<&str>::new()), "");
+        assert_eq!(pointer(["a", "0"]), "/a/0");
+        assert_eq!(pointer(["a/b", "m~n", ""]), "/a~1b/m~0n/");
+    }
+}
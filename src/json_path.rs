@@ -0,0 +1,278 @@
+//! Builder for [JSON path expressions](https://github.com/json-path/JsonPath/blob/master/README.md)
+//! used by [`Query::of_json_path_exprs`](crate::model::Query::of_json_path_exprs).
+//!
+//! ```
+//! use centraldogma::json_path::JsonPath;
+//!
+//! let expr = JsonPath::root().field("servers").wildcard().field("host name");
+//! assert_eq!(expr.as_str(), "$.servers[*]['host name']");
+//!
+//! assert!(JsonPath::parse("$.servers[0].host").is_ok());
+//! assert!(JsonPath::parse("$.servers[0").is_err());
+//! ```
+use std::fmt;
+
+use crate::Error;
+
+/// A syntactically valid JSON path expression.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct JsonPath {
+    expr: String,
+}
+
+impl JsonPath {
+    /// Returns an expression which refers to the root element, `$`.
+    pub fn root() -> Self {
+        JsonPath {
+            expr: "$".to_owned(),
+        }
+    }
+
+    /// Appends a member access by name.
+    /// Names which are not plain identifiers are escaped using the bracket notation.
+    pub fn field(mut self, name: &str) -> Self {
+        if is_identifier(name) {
+            self.expr.push('.');
+            self.expr.push_str(name);
+        } else {
+            self.push_quoted(name);
+        }
+        self
+    }
+
+    /// Appends an array index access. A negative index counts from the end of the array.
+    pub fn index(mut self, index: i64) -> Self {
+        self.expr.push_str(&format!("[{}]", index));
+        self
+    }
+
+    /// Appends an access to all members of an object or all elements of an array.
+    pub fn wildcard(mut self) -> Self {
+        self.expr.push_str("[*]");
+        self
+    }
+
+    /// Appends a deep scan for the members with the specified name.
+    pub fn descendant(mut self, name: &str) -> Self {
+        self.expr.push_str("..");
+        if is_identifier(name) {
+            self.expr.push_str(name);
+        } else {
+            self.push_quoted(name);
+        }
+        self
+    }
+
+    fn push_quoted(&mut self, name: &str) {
+        self.expr.push_str("['");
+        for c in name.chars() {
+            if c == '\'' || c == '\\' {
+                self.expr.push('\\');
+            }
+            self.expr.push(c);
+        }
+        self.expr.push_str("']");
+    }
+
+    /// Validates a raw JSON path expression.
+    pub fn parse(expr: &str) -> Result<Self, Error> {
+        validate(expr)?;
+
+        Ok(JsonPath {
+            expr: expr.to_owned(),
+        })
+    }
+
+    /// Returns the expression as a string slice.
+    pub fn as_str(&self) -> &str {
+        &self.expr
+    }
+}
+
+impl fmt::Display for JsonPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.expr)
+    }
+}
+
+impl From<JsonPath> for String {
+    fn from(path: JsonPath) -> Self {
+        path.expr
+    }
+}
+
+impl std::str::FromStr for JsonPath {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        JsonPath::parse(s)
+    }
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {}
+        _ => return false,
+    }
+    chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn validate(expr: &str) -> Result<(), Error> {
+    let chars: Vec<char> = expr.chars().collect();
+    match chars.first() {
+        Some('$') | Some('@') => {}
+        _ => {
+            return Err(Error::InvalidParams(
+                "JSON path expression must start with '$' or '@'",
+            ))
+        }
+    }
+
+    let mut i = 1;
+    while i < chars.len() {
+        match chars[i] {
+            '.' => {
+                i += 1;
+                if chars.get(i) == Some(&'.') {
+                    i += 1;
+                }
+                match chars.get(i) {
+                    Some('*') => i += 1,
+                    Some('[') => {}
+                    Some(_) => {
+                        let start = i;
+                        while i < chars.len() && !matches!(chars[i], '.' | '[') {
+                            i += 1;
+                        }
+                        if chars[start..i].iter().any(|c| c.is_whitespace()) {
+                            return Err(Error::InvalidParams(
+                                "JSON path member name cannot contain whitespace, use ['...']",
+                            ));
+                        }
+                    }
+                    None => {
+                        return Err(Error::InvalidParams(
+                            "JSON path expression cannot end with '.'",
+                        ))
+                    }
+                }
+            }
+            '[' => i = validate_bracket(&chars, i)?,
+            _ => {
+                return Err(Error::InvalidParams(
+                    "unexpected character in JSON path expression",
+                ))
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Validates a bracket segment starting at `start` and returns the index after it.
+fn validate_bracket(chars: &[char], start: usize) -> Result<usize, Error> {
+    let mut i = start + 1;
+    let mut depth = 0;
+    let mut quote: Option<char> = None;
+    while i < chars.len() {
+        let c = chars[i];
+        match quote {
+            Some(q) => {
+                if c == '\\' {
+                    i += 1;
+                } else if c == q {
+                    quote = None;
+                }
+            }
+            None => match c {
+                '\'' | '"' => quote = Some(c),
+                '(' => depth += 1,
+                ')' if depth == 0 => {
+                    return Err(Error::InvalidParams(
+                        "unbalanced parentheses in JSON path expression",
+                    ))
+                }
+                ')' => depth -= 1,
+                ']' if depth == 0 => {
+                    if i == start + 1 {
+                        return Err(Error::InvalidParams(
+                            "empty brackets in JSON path expression",
+                        ));
+                    }
+                    return Ok(i + 1);
+                }
+                _ => {}
+            },
+        }
+        i += 1;
+    }
+
+    if quote.is_some() {
+        Err(Error::InvalidParams(
+            "unterminated string in JSON path expression",
+        ))
+    } else {
+        Err(Error::InvalidParams(
+            "unbalanced brackets in JSON path expression",
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_build() {
+        let expr = JsonPath::root().field("a").index(0).field("b-c");
+        assert_eq!(expr.as_str(), "$.a[0]['b-c']");
+
+        let expr = JsonPath::root().wildcard().field("it's").index(-1);
+        assert_eq!(expr.as_str(), "$[*]['it\\'s'][-1]");
+
+        let expr = JsonPath::root().descendant("price");
+        assert_eq!(expr.as_str(), "$..price");
+
+        let expr = JsonPath::root().descendant("a b");
+        assert_eq!(expr.as_str(), "$..['a b']");
+    }
+
+    #[test]
+    fn test_built_expressions_are_valid() {
+        let exprs = [
+            JsonPath::root().field("a").index(0).field("b-c"),
+            JsonPath::root().wildcard().field("it's").index(-1),
+            JsonPath::root().descendant("a b").field("]"),
+        ];
+        for expr in exprs.iter() {
+            assert!(JsonPath::parse(expr.as_str()).is_ok(), "{}", expr);
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        let valid = [
+            "$",
+            "$.a",
+            "$.a.b",
+            "$..a",
+            "$.*",
+            "$['a']",
+            "$[0,1]",
+            "$[1:3]",
+            "$.a[?(@.b > 1)]",
+            "$.a[?(@.b == ']')]",
+        ];
+        for expr in valid.iter() {
+            assert!(JsonPath::parse(expr).is_ok(), "{}", expr);
+        }
+
+        let invalid = [
+            "", "a", "$.", "$.a b", "$[", "$[]", "$['a]", "$[?(@.a]", "$a",
+        ];
+        for expr in invalid.iter() {
+            assert!(JsonPath::parse(expr).is_err(), "{}", expr);
+        }
+    }
+}
@@ -0,0 +1,169 @@
+//! Compatibility with the servers which only expose the legacy v0 API, enabled by the
+//! `legacy-v0` feature.
+//!
+//! With [`Client::with_legacy_v0_api()`](crate::Client::with_legacy_v0_api), the requests
+//! of the operations which the v0 API supports are sent to their v0 endpoints, and the
+//! other operations fail with [`Error::Unsupported`] without reaching the server. Watches
+//! are unsupported, so their streams end immediately.
+//!
+//! | Operation | v0 endpoint |
+//! |-----------|-------------|
+//! | `list_projects` | `GET /api/v0/projects` |
+//! | `list_repos` | `GET /api/v0/projects/{project}/repositories` |
+//! | `get_file` of an identity query | `GET /api/v0/projects/{project}/repositories/{repo}/files/revisions/{revision}{path}` |
+//! | `get_history` without a maximum number of commits | `GET /api/v0/projects/{project}/repositories/{repo}/history/{revision}` |
+//!
+//! The responses are decoded as the ones of the v1 API.
+use std::borrow::Cow;
+
+use reqwest::Method;
+
+use crate::Error;
+
+const V1_PROJECTS: &str = "/api/v1/projects";
+const V0_PROJECTS: &str = "/api/v0/projects";
+
+/// Returns the v0 request path of the v1 request `path`,
+/// failing with [`Error::Unsupported`] if the v0 API has no equivalent.
+pub(crate) fn translate(method: &Method, path: &str) -> Result<String, Error> {
+    let (path, query) = path.split_once('?').unwrap_or((path, ""));
+    let rest = path
+        .strip_prefix(V1_PROJECTS)
+        .ok_or_else(|| unsupported(method, path))?;
+    if method != Method::GET {
+        return Err(unsupported(method, path));
+    }
+
+    let segments: Vec<_> = rest.splitn(6, '/').skip(1).collect();
+    let pairs: Vec<(Cow<'_, str>, Cow<'_, str>)> =
+        form_urlencoded::parse(query.as_bytes()).collect();
+    let param = |name: &str| {
+        pairs
+            .iter()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.as_ref())
+    };
+
+    match segments.as_slice() {
+        [] if query.is_empty() => Ok(V0_PROJECTS.to_owned()),
+        [project, "repos"] if query.is_empty() => {
+            Ok(format!("{}/{}/repositories", V0_PROJECTS, project))
+        }
+        [project, "repos", repo, "contents", file]
+            if !file.contains('*') && pairs.iter().all(|(k, _)| k == "revision") =>
+        {
+            Ok(format!(
+                "{}/{}/repositories/{}/files/revisions/{}/{}",
+                V0_PROJECTS,
+                project,
+                repo,
+                param("revision").unwrap_or("-1"),
+                file
+            ))
+        }
+        [project, "repos", repo, "commits", revision]
+            if !revision.contains('/') && pairs.iter().all(|(k, _)| k == "path" || k == "to") =>
+        {
+            let url = format!(
+                "{}/{}/repositories/{}/history/{}?",
+                V0_PROJECTS, project, repo, revision
+            );
+            let len = url.len();
+            let mut s = form_urlencoded::Serializer::for_suffix(url, len);
+            for (k, v) in &pairs {
+                s.append_pair(k, v);
+            }
+            Ok(s.finish().trim_end_matches('?').to_owned())
+        }
+        _ => Err(unsupported(method, path)),
+    }
+}
+
+fn unsupported(method: &Method, path: &str) -> Error {
+    Error::Unsupported(format!("{} {} has no v0 API equivalent", method, path))
+}
+
+#[cfg(test)]
+mod test {
+    use wiremock::{
+        matchers::{method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+    use crate::{
+        model::{Change, CommitMessage, Query, Revision},
+        Client, ContentService, ErrorCode, ProjectService, WatchService,
+    };
+
+    #[test]
+    fn test_translate() {
+        let get = |path| translate(&Method::GET, path);
+
+        assert_eq!(get("/api/v1/projects").unwrap(), "/api/v0/projects");
+        assert_eq!(
+            get("/api/v1/projects/foo/repos").unwrap(),
+            "/api/v0/projects/foo/repositories"
+        );
+        assert_eq!(
+            get("/api/v1/projects/foo/repos/bar/contents/a/b.json?revision=3").unwrap(),
+            "/api/v0/projects/foo/repositories/bar/files/revisions/3/a/b.json"
+        );
+        assert_eq!(
+            get("/api/v1/projects/foo/repos/bar/commits/-1?path=%2F**&to=1").unwrap(),
+            "/api/v0/projects/foo/repositories/bar/history/-1?path=%2F**&to=1"
+        );
+
+        assert!(get("/api/v1/projects?status=removed").is_err());
+        assert!(get("/api/v1/projects/foo/repos/bar/contents/**").is_err());
+        assert!(get("/api/v1/projects/foo/repos/bar/contents/a.json?jsonpath=%24.a").is_err());
+        assert!(get("/api/v1/projects/foo/repos/bar/compare?path=%2Fa.json").is_err());
+        assert!(get("/api/v1/projects/foo/repos/bar/commits/-1?to=1&maxCommits=5").is_err());
+        assert!(translate(&Method::POST, "/api/v1/projects").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_legacy_v0_api() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path(
+                "/api/v0/projects/foo/repositories/bar/files/revisions/-1/a.json",
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(
+                r#"{"path":"/a.json","type":"JSON","content":{"a":1},"revision":2,"url":"/a.json"}"#,
+                "application/json",
+            ))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = Client::new(&server.uri(), None)
+            .await
+            .unwrap()
+            .with_legacy_v0_api();
+        let query = Query::identity("/a.json").unwrap();
+        let repo = client.repo("foo", "bar");
+
+        let entry = repo.get_file(Revision::HEAD, &query).await.unwrap();
+        assert_eq!(entry.revision, Revision::from(2));
+
+        let err = client.create_project("foo").await.unwrap_err();
+        assert_eq!(err.code(), ErrorCode::Unsupported);
+        let err = repo
+            .push(
+                Revision::HEAD,
+                CommitMessage::only_summary("summary"),
+                vec![Change::from(("/a.json", serde_json::json!({"a": 2})))],
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::Unsupported);
+        let err = repo
+            .get_history(Revision::HEAD, Revision::INIT, "/**", Some(5))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::Unsupported);
+        let mut watch = repo.watch_file_stream(&query).unwrap();
+        assert!(futures::StreamExt::next(&mut watch).await.is_none());
+    }
+}
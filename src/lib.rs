@@ -1,9 +1,79 @@
 #![doc = include_str!("../README.md")]
+// The derive macro refers to this crate by its name.
+#[cfg(all(test, feature = "derive"))]
+extern crate self as centraldogma;
+#[cfg(feature = "proptest")]
+mod arbitrary;
+#[cfg(feature = "backup")]
+pub mod backup;
+#[cfg(feature = "blocking")]
+pub mod blocking;
+#[cfg(any(feature = "cache", feature = "disk-cache"))]
+pub mod cache;
+#[cfg(feature = "chaos")]
+pub mod chaos;
 mod client;
+mod config;
+#[cfg(feature = "conformance")]
+pub mod conformance;
+#[cfg(feature = "dev-push")]
+pub mod dev;
+#[cfg(feature = "diff")]
+pub mod diff;
+mod export;
+pub mod flags;
+pub mod fluent;
+pub mod interceptor;
+pub mod json_patch;
+pub mod json_path;
+#[cfg(feature = "legacy-v0")]
+pub mod legacy;
+mod local;
+pub mod manifest;
+pub mod meta;
+#[cfg(feature = "prometheus")]
+pub mod metrics;
+pub mod migrate;
 pub mod model;
+#[cfg(feature = "otel")]
+pub mod otel;
+pub mod overlay;
+pub mod policy;
+#[cfg(feature = "proxy")]
+pub mod proxy;
+pub mod push;
+pub mod recorder;
+mod replica;
+pub mod report;
+pub mod retry;
+pub mod search;
+pub mod select;
 mod services;
+pub mod sync;
+#[cfg(any(test, feature = "test-util"))]
+pub mod test_util;
+#[cfg(feature = "tower")]
+pub mod tower;
+#[cfg(feature = "tracing")]
+pub mod trace;
+pub mod transform;
+pub mod validation;
+pub mod warmup;
+pub mod watcher;
 
-pub use client::{Client, Error, ProjectClient, RepoClient};
+#[cfg(feature = "derive")]
+pub use centraldogma_derive::DogmaConfig;
+pub use client::{
+    Client, ClientBuilder, Clock, Error, ErrorCode, ErrorContext, ProjectClient, RepoClient,
+};
+pub use config::{CentralDogmaConfig, CentralDogmaConfigBuilder, DogmaConfig};
+pub use fluent::CentralDogmaRepository;
 pub use services::{
-    content::ContentService, project::ProjectService, repository::RepoService, watch::WatchService,
+    content::ContentService,
+    metadata::MetadataService,
+    project::ProjectService,
+    repository::RepoService,
+    watch::{
+        TryWatchStream, TypedWatch, WatchFilesStream, WatchHandle, WatchOptions, WatchService,
+    },
 };
@@ -0,0 +1,87 @@
+//! The files of a repository written to a local directory, by the exports and the syncs.
+use std::path::{Component, Path, PathBuf};
+
+use crate::{
+    model::{Entry, EntryContent},
+    Error,
+};
+
+/// Returns the content of the local file of `entry`, `None` for a directory.
+///
+/// JSON files are pretty-printed, text files are written as is, and YAML files, with the
+/// `yaml` feature, as YAML documents.
+pub(crate) fn file_content(entry: &Entry) -> Result<Option<Vec<u8>>, Error> {
+    let content = match &entry.content {
+        EntryContent::Json(json) => {
+            let mut content = serde_json::to_vec_pretty(json)?;
+            content.push(b'\n');
+            content
+        }
+        EntryContent::Text(text) => text.clone().into_bytes(),
+        #[cfg(feature = "yaml")]
+        EntryContent::Yaml(yaml) => serde_yaml::to_string(yaml)
+            .map_err(|_| Error::InvalidParams("YAML content cannot be serialized"))?
+            .into_bytes(),
+        EntryContent::Directory => return Ok(None),
+    };
+
+    Ok(Some(content))
+}
+
+/// Returns the path of the file at `path` relative to the local directory of its
+/// repository, refusing the paths which would escape it.
+pub(crate) fn relative_path(path: &str) -> Result<PathBuf, Error> {
+    let relative = Path::new(path.trim_start_matches('/'));
+    if relative
+        .components()
+        .all(|c| matches!(c, Component::Normal(_)))
+    {
+        Ok(relative.to_owned())
+    } else {
+        Err(Error::InvalidParams(
+            "entry path must stay in the local directory",
+        ))
+    }
+}
+
+pub(crate) fn io(path: &Path, source: std::io::Error) -> Error {
+    Error::Io {
+        path: path.display().to_string(),
+        source,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+    use crate::model::Revision;
+
+    #[test]
+    fn test_file_content() {
+        let entry = |content| Entry {
+            path: "/a".to_owned(),
+            content,
+            revision: Revision::from(1),
+            url: "/a".to_owned(),
+            modified_at: None,
+        };
+
+        let content = file_content(&entry(EntryContent::Json(json!({"a": 1})))).unwrap();
+        assert_eq!(content.unwrap(), b"{\n  \"a\": 1\n}\n");
+        let content = file_content(&entry(EntryContent::Text("b\n".to_owned()))).unwrap();
+        assert_eq!(content.unwrap(), b"b\n");
+        assert!(file_content(&entry(EntryContent::Directory))
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_relative_path() {
+        assert_eq!(relative_path("/a/b.json").unwrap(), Path::new("a/b.json"));
+        assert!(relative_path("/a/./b.json").is_ok());
+        assert!(relative_path("/../a.json").is_err());
+        assert!(relative_path("/a/../../b.json").is_err());
+    }
+}
@@ -0,0 +1,236 @@
+//! Lookup of configuration files by a logical name, resolved by a manifest to a file of
+//! a repository, so that applications don't depend on the layout of the repositories.
+//!
+//! A manifest maps the names to the files, e.g. in JSON:
+//!
+//! ```json
+//! {
+//!   "service-a/flags": { "project": "platform", "repo": "service-a", "path": "/flags.json" },
+//!   "service-a/limits": { "project": "platform", "repo": "shared", "path": "/limits/a.json" }
+//! }
+//! ```
+//!
+//! ```no_run
+//! use centraldogma::{manifest::{ConfigRouter, Manifest}, Client};
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), centraldogma::Error> {
+//! let client = Client::new("http://localhost:36462", None).await?;
+//! let manifest = Manifest::load(&client.for_repo("platform", "manifests"), "/routes.json").await?;
+//! let router = ConfigRouter::new(client, manifest);
+//!
+//! let flags = router.get("service-a/flags").await?;
+//! # Ok(())
+//! # }
+//! ```
+use std::{collections::BTreeMap, pin::Pin};
+
+use futures::Stream;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    model::{Entry, EntryContent, Query, Revision, WatchFileResult},
+    CentralDogmaConfig, CentralDogmaRepository, Client, ContentService, Error, WatchService,
+};
+
+/// The file a logical name resolves to.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Route {
+    /// Name of the project
+    pub project: String,
+    /// Name of the repository
+    pub repo: String,
+    /// Path of the file
+    pub path: String,
+}
+
+/// Routes of logical names to files.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Manifest {
+    routes: BTreeMap<String, Route>,
+}
+
+impl Manifest {
+    /// Returns an empty manifest.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Routes `name` to the file at `path` of a repository, replacing any previous route.
+    pub fn route(mut self, name: &str, project: &str, repo: &str, path: &str) -> Self {
+        self.routes.insert(
+            name.to_owned(),
+            Route {
+                project: project.to_owned(),
+                repo: repo.to_owned(),
+                path: path.to_owned(),
+            },
+        );
+        self
+    }
+
+    /// Reads a manifest from the JSON file at `path` of `repo`.
+    ///
+    /// Fails with [`Error::InvalidConfig`] if the file is not a valid manifest.
+    pub async fn load(repo: &CentralDogmaRepository, path: &str) -> Result<Self, Error> {
+        let query = Query::identity(path).ok_or(Error::InvalidParams("Invalid manifest path"))?;
+        let entry = repo.client().get_file(Revision::HEAD, &query).await?;
+        let invalid = |message: String| Error::InvalidConfig {
+            path: entry.path.clone(),
+            message,
+        };
+
+        match &entry.content {
+            EntryContent::Json(json) => {
+                serde_json::from_value(json.clone()).map_err(|e| invalid(e.to_string()))
+            }
+            EntryContent::Text(text) => {
+                serde_json::from_str(text).map_err(|e| invalid(e.to_string()))
+            }
+            #[cfg(feature = "yaml")]
+            EntryContent::Yaml(yaml) => {
+                serde_yaml::from_value(yaml.clone()).map_err(|e| invalid(e.to_string()))
+            }
+            EntryContent::Directory => Err(invalid("not a file".to_owned())),
+        }
+    }
+
+    /// Returns the route of `name`, if any.
+    pub fn resolve(&self, name: &str) -> Option<&Route> {
+        self.routes.get(name)
+    }
+
+    /// Returns the names and their routes, sorted by name.
+    pub fn routes(&self) -> impl Iterator<Item = (&str, &Route)> {
+        self.routes
+            .iter()
+            .map(|(name, route)| (name.as_str(), route))
+    }
+}
+
+/// Reads and watches the files of a [`Manifest`] by their logical name.
+///
+/// The operations fail with [`Error::EntryNotFound`] for a name which the manifest
+/// doesn't route.
+#[derive(Clone)]
+pub struct ConfigRouter {
+    client: Client,
+    manifest: Manifest,
+}
+
+impl ConfigRouter {
+    /// Returns a router of the names of `manifest`.
+    pub fn new(client: Client, manifest: Manifest) -> Self {
+        ConfigRouter { client, manifest }
+    }
+
+    /// Returns the manifest.
+    pub fn manifest(&self) -> &Manifest {
+        &self.manifest
+    }
+
+    /// Retrieves the current file of `name`.
+    pub async fn get(&self, name: &str) -> Result<Entry, Error> {
+        let (repo, query) = self.resolve(name)?;
+
+        repo.client().get_file(Revision::HEAD, &query).await
+    }
+
+    /// Returns a stream which outputs the file of `name` whenever it changes.
+    pub fn watch(
+        &self,
+        name: &str,
+    ) -> Result<Pin<Box<dyn Stream<Item = WatchFileResult> + Send>>, Error> {
+        let (repo, query) = self.resolve(name)?;
+
+        repo.client().watch_file_stream(&query)
+    }
+
+    /// Returns the file of `name` deserialized into `T`, kept up to date in the background.
+    ///
+    /// Fails with [`Error::InvalidConfig`] if the current content fails to deserialize.
+    pub async fn config<T>(&self, name: &str) -> Result<CentralDogmaConfig<T>, Error>
+    where
+        T: DeserializeOwned + Send + Sync + 'static,
+    {
+        let (repo, query) = self.resolve(name)?;
+
+        CentralDogmaConfig::builder(query).start(&repo).await
+    }
+
+    fn resolve(&self, name: &str) -> Result<(CentralDogmaRepository, Query), Error> {
+        let route = self
+            .manifest
+            .resolve(name)
+            .ok_or_else(|| Error::EntryNotFound(name.to_owned()))?;
+        let query = Query::identity(&route.path)
+            .ok_or(Error::InvalidParams("Invalid path in the manifest"))?;
+
+        Ok((self.client.for_repo(&route.project, &route.repo), query))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+    use wiremock::{
+        matchers::{method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+    use crate::ErrorCode;
+
+    #[tokio::test]
+    async fn test_router() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/platform/repos/manifests/contents/routes.json"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "path": "/routes.json",
+                "type": "JSON",
+                "content": {
+                    "service-a/flags": {"project": "platform", "repo": "service-a", "path": "/flags.json"}
+                },
+                "revision": 2,
+                "url": "/routes.json"
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path(
+                "/api/v1/projects/platform/repos/service-a/contents/flags.json",
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "path": "/flags.json",
+                "type": "JSON",
+                "content": {"a": true},
+                "revision": 3,
+                "url": "/flags.json"
+            })))
+            .mount(&server)
+            .await;
+
+        let client = Client::new(&server.uri(), None).await.unwrap();
+        let manifest = Manifest::load(&client.for_repo("platform", "manifests"), "/routes.json")
+            .await
+            .unwrap();
+        assert_eq!(
+            manifest,
+            Manifest::new().route("service-a/flags", "platform", "service-a", "/flags.json")
+        );
+
+        let router = ConfigRouter::new(client, manifest);
+        let entry = router.get("service-a/flags").await.unwrap();
+        assert_eq!(entry.content, EntryContent::Json(json!({"a": true})));
+
+        let flags: CentralDogmaConfig<BTreeMap<String, bool>> =
+            router.config("service-a/flags").await.unwrap();
+        assert!(flags.load()["a"]);
+
+        let err = router.get("service-b/flags").await.unwrap_err();
+        assert_eq!(err.code(), ErrorCode::EntryNotFound);
+        assert!(router.watch("service-b/flags").is_err());
+    }
+}
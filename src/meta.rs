@@ -0,0 +1,281 @@
+//! The internal repositories of a project and their well-known files, e.g. the mirroring
+//! configuration in the `meta` repository.
+//!
+//! Every project has a `dogma` repository, accessible by the administrators, and a `meta`
+//! repository, accessible by the project owners, which hold the configuration of the
+//! project rather than application files.
+//!
+//! ```no_run
+//! use centraldogma::{model::Revision, Client};
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), centraldogma::Error> {
+//! let client = Client::new("http://localhost:36462", None).await?;
+//! let project = client.project("foo");
+//!
+//! for mirror in project.mirrors(Revision::HEAD).await? {
+//!     println!("{} <- {}", mirror.local_repo, mirror.remote_uri);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    model::{Query, Revision},
+    ContentService, Error, ProjectClient, RepoClient,
+};
+
+/// Name of the repository holding the configuration of a project accessible by the
+/// administrators
+pub const DOGMA_REPO: &str = "dogma";
+/// Name of the repository holding the configuration of a project accessible by the
+/// project owners
+pub const META_REPO: &str = "meta";
+/// The internal repositories of every project, which are not backed up nor migrated
+pub(crate) const INTERNAL_REPOS: [&str; 2] = [DOGMA_REPO, META_REPO];
+/// Path of the [`MirrorConfig`]s in the `meta` repository
+pub const MIRRORS_PATH: &str = "/mirrors.json";
+/// Path of the [`Credential`]s in the `meta` repository
+pub const CREDENTIALS_PATH: &str = "/credentials.json";
+
+/// The mirroring of a Git repository into a repository of the project, or the other way
+/// around, an element of [`MIRRORS_PATH`].
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct MirrorConfig {
+    /// Type of the mirror, `single` by default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub r#type: Option<String>,
+    /// Whether the mirror runs, `true` by default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    /// Direction of the mirroring
+    pub direction: MirrorDirection,
+    /// Name of the repository of the project
+    pub local_repo: String,
+    /// Directory of the repository of the project which is mirrored, `/` by default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub local_path: Option<String>,
+    /// URI of the Git repository, e.g. `git+ssh://github.com/foo/bar.git/path#main`
+    pub remote_uri: String,
+    /// Quartz cron expression of when the mirror runs, every minute by default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schedule: Option<String>,
+    /// ID of the [`Credential`] used to access the Git repository, chosen by the host name
+    /// of the URI by default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub credential_id: Option<String>,
+}
+
+/// The direction of a [`MirrorConfig`]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum MirrorDirection {
+    /// From the Git repository into the repository of the project
+    RemoteToLocal,
+    /// From the repository of the project into the Git repository
+    LocalToRemote,
+}
+
+/// The credential accessing a Git repository, an element of [`CREDENTIALS_PATH`].
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Credential {
+    /// ID referred to by [`MirrorConfig::credential_id`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    /// Patterns of the host names the credential is used for when a mirror has no
+    /// credential ID
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hostname_patterns: Vec<String>,
+    /// Whether the credential is used, `true` by default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    /// The secrets of the credential
+    #[serde(flatten)]
+    pub kind: CredentialKind,
+}
+
+/// The secrets of a [`Credential`], hidden from its `Debug` output.
+#[derive(Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(
+    tag = "type",
+    rename_all = "snake_case",
+    rename_all_fields = "camelCase"
+)]
+pub enum CredentialKind {
+    /// A user name and a password
+    Password {
+        /// The user name
+        username: String,
+        /// The password
+        password: String,
+    },
+    /// An SSH key pair
+    PublicKey {
+        /// The user name
+        username: String,
+        /// The public key
+        public_key: String,
+        /// The private key
+        private_key: String,
+        /// The passphrase of the private key
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        passphrase: Option<String>,
+    },
+    /// An access token
+    AccessToken {
+        /// The access token
+        access_token: String,
+    },
+    /// No credential, for public repositories
+    None,
+}
+
+impl fmt::Debug for CredentialKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CredentialKind::Password { username, .. } => f
+                .debug_struct("Password")
+                .field("username", username)
+                .finish_non_exhaustive(),
+            CredentialKind::PublicKey {
+                username,
+                public_key,
+                ..
+            } => f
+                .debug_struct("PublicKey")
+                .field("username", username)
+                .field("public_key", public_key)
+                .finish_non_exhaustive(),
+            CredentialKind::AccessToken { .. } => {
+                f.debug_struct("AccessToken").finish_non_exhaustive()
+            }
+            CredentialKind::None => f.write_str("None"),
+        }
+    }
+}
+
+impl<'a> ProjectClient<'a> {
+    /// Returns a client of the `meta` repository of the project.
+    pub fn meta_repo(&self) -> RepoClient<'a> {
+        self.client.repo(self.project, META_REPO)
+    }
+
+    /// Returns a client of the `dogma` repository of the project.
+    pub fn dogma_repo(&self) -> RepoClient<'a> {
+        self.client.repo(self.project, DOGMA_REPO)
+    }
+
+    /// Retrieves the [`MirrorConfig`]s of the project at the specified [`Revision`] of its
+    /// `meta` repository, none if it has no [`MIRRORS_PATH`] file.
+    pub async fn mirrors(&self, revision: Revision) -> Result<Vec<MirrorConfig>, Error> {
+        self.meta_file(revision, MIRRORS_PATH).await
+    }
+
+    /// Retrieves the [`Credential`]s of the project at the specified [`Revision`] of its
+    /// `meta` repository, none if it has no [`CREDENTIALS_PATH`] file.
+    pub async fn credentials(&self, revision: Revision) -> Result<Vec<Credential>, Error> {
+        self.meta_file(revision, CREDENTIALS_PATH).await
+    }
+
+    async fn meta_file<T>(&self, revision: Revision, path: &str) -> Result<Vec<T>, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let query = Query::identity(path).ok_or(Error::InvalidParams("path cannot be empty"))?;
+        match self.meta_repo().get_file_as(revision, &query).await {
+            Ok(entry) => Ok(entry.value),
+            Err(Error::EntryNotFound(_)) => Ok(Vec::new()),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use wiremock::{
+        matchers::{method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+    use crate::Client;
+
+    #[tokio::test]
+    async fn test_mirrors_and_credentials() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/meta/contents/mirrors.json"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(
+                r#"{"path":"/mirrors.json","type":"JSON","revision":3,"url":"/mirrors.json","content":[{
+                    "enabled":true,
+                    "direction":"REMOTE_TO_LOCAL",
+                    "localRepo":"bar",
+                    "remoteUri":"git+ssh://github.com/foo/bar.git#main",
+                    "credentialId":"github"
+                }]}"#,
+                "application/json",
+            ))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/meta/contents/credentials.json"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(
+                r#"{"path":"/credentials.json","type":"JSON","revision":3,"url":"/credentials.json","content":[{
+                    "type":"access_token",
+                    "id":"github",
+                    "hostnamePatterns":["^github\\.com$"],
+                    "accessToken":"secret"
+                }]}"#,
+                "application/json",
+            ))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/qux/repos/meta/contents/mirrors.json"))
+            .respond_with(ResponseTemplate::new(404).set_body_raw(
+                r#"{"exception":"com.linecorp.centraldogma.common.EntryNotFoundException","message":"/mirrors.json"}"#,
+                "application/json",
+            ))
+            .mount(&server)
+            .await;
+
+        let client = Client::new(&server.uri(), None).await.unwrap();
+        let project = client.project("foo");
+        assert_eq!(project.meta_repo().repo, "meta");
+        assert_eq!(project.dogma_repo().repo, "dogma");
+
+        let mirrors = project.mirrors(Revision::HEAD).await.unwrap();
+        assert_eq!(
+            mirrors,
+            [MirrorConfig {
+                r#type: None,
+                enabled: Some(true),
+                direction: MirrorDirection::RemoteToLocal,
+                local_repo: "bar".to_owned(),
+                local_path: None,
+                remote_uri: "git+ssh://github.com/foo/bar.git#main".to_owned(),
+                schedule: None,
+                credential_id: Some("github".to_owned()),
+            }]
+        );
+
+        let credentials = project.credentials(Revision::HEAD).await.unwrap();
+        assert_eq!(credentials[0].id.as_deref(), Some("github"));
+        assert_eq!(credentials[0].hostname_patterns, ["^github\\.com$"]);
+        assert_eq!(
+            credentials[0].kind,
+            CredentialKind::AccessToken {
+                access_token: "secret".to_owned()
+            }
+        );
+        assert!(!format!("{:?}", credentials[0]).contains("secret"));
+
+        let mirrors = client.project("qux").mirrors(Revision::HEAD).await.unwrap();
+        assert!(mirrors.is_empty());
+    }
+}
@@ -0,0 +1,255 @@
+//! Prometheus metrics of the requests of a [`Client`](crate::Client),
+//! enabled by the `prometheus` feature.
+//!
+//! | Metric                                         | Type      | Labels                         |
+//! |------------------------------------------------|-----------|--------------------------------|
+//! | `centraldogma_client_requests_total`           | Counter   | `method`, `endpoint`, `status` |
+//! | `centraldogma_client_request_duration_seconds` | Histogram | `method`, `endpoint`           |
+//! | `centraldogma_watch_responses_total`           | Counter   | `endpoint`, `result`           |
+//! | `centraldogma_watch_reconnects_total`          | Counter   | `endpoint`                     |
+//!
+//! `endpoint` is the route template of the request path,
+//! e.g. `/api/v1/projects/{project}/repos/{repo}/contents/{path}`, so that the names of
+//! the projects, repositories and files don't multiply the time series. `status` is the
+//! HTTP status of the response, or the [`ErrorCode`](crate::ErrorCode) of the failure
+//! when there is no response.
+//!
+//! A watch answers `not_modified` when the revision known by the client is still the
+//! latest one, and `modified` otherwise. The not-modified ratio of the watches is
+//!
+//! ```text
+//! sum(rate(centraldogma_watch_responses_total{result="not_modified"}[5m]))
+//!   / sum(rate(centraldogma_watch_responses_total[5m]))
+//! ```
+//!
+//! A watch reconnects after a failed request, unless the failure ends it.
+//!
+//! The collectors are a [`MetricsRecorder`], installed with
+//! [`Client::with_metrics()`](crate::Client::with_metrics).
+//!
+//! ```no_run
+//! use centraldogma::{metrics::Metrics, Client};
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let registry = prometheus::Registry::new();
+//! let client = Client::new("http://localhost:36462", None)
+//!     .await
+//!     .unwrap()
+//!     .with_metrics(Metrics::register(&registry).unwrap());
+//! # }
+//! ```
+use prometheus::{HistogramOpts, HistogramVec, IntCounterVec, Opts, Registry};
+
+use crate::{
+    recorder::{MetricsRecorder, RequestEvent, WatchBackoffEvent},
+    services::path::parse_route,
+};
+
+/// The collectors of a client, registered into a [`Registry`].
+///
+/// Cloning shares the collectors, so several clients can report to the same ones.
+#[derive(Clone, Debug)]
+pub struct Metrics {
+    requests: IntCounterVec,
+    request_duration: HistogramVec,
+    watch_responses: IntCounterVec,
+    watch_reconnects: IntCounterVec,
+}
+
+impl Metrics {
+    /// Creates the collectors and registers them into `registry`.
+    ///
+    /// Fails if collectors of the same names are already registered.
+    pub fn register(registry: &Registry) -> Result<Self, prometheus::Error> {
+        let metrics = Metrics {
+            requests: IntCounterVec::new(
+                Opts::new(
+                    "centraldogma_client_requests_total",
+                    "Requests sent to Central Dogma",
+                ),
+                &["method", "endpoint", "status"],
+            )?,
+            request_duration: HistogramVec::new(
+                HistogramOpts::new(
+                    "centraldogma_client_request_duration_seconds",
+                    "Duration of the requests to Central Dogma",
+                ),
+                &["method", "endpoint"],
+            )?,
+            watch_responses: IntCounterVec::new(
+                Opts::new(
+                    "centraldogma_watch_responses_total",
+                    "Successful responses to watch requests",
+                ),
+                &["endpoint", "result"],
+            )?,
+            watch_reconnects: IntCounterVec::new(
+                Opts::new(
+                    "centraldogma_watch_reconnects_total",
+                    "Watch requests sent again after a failure",
+                ),
+                &["endpoint"],
+            )?,
+        };
+
+        registry.register(Box::new(metrics.requests.clone()))?;
+        registry.register(Box::new(metrics.request_duration.clone()))?;
+        registry.register(Box::new(metrics.watch_responses.clone()))?;
+        registry.register(Box::new(metrics.watch_reconnects.clone()))?;
+
+        Ok(metrics)
+    }
+}
+
+impl MetricsRecorder for Metrics {
+    fn on_request(&self, event: &RequestEvent) {
+        let status = match (event.status, event.error) {
+            (Some(status), _) => status.to_string(),
+            (None, Some(error)) => error.as_str().to_owned(),
+            (None, None) => String::new(),
+        };
+        self.requests
+            .with_label_values(&[event.method.as_str(), &event.route, &status])
+            .inc();
+        self.request_duration
+            .with_label_values(&[event.method.as_str(), &event.route])
+            .observe(event.duration.as_secs_f64());
+    }
+
+    fn on_watch_response(&self, path: &str, modified: bool) {
+        let result = if modified { "modified" } else { "not_modified" };
+        self.watch_responses
+            .with_label_values(&[&parse_route(path).template, result])
+            .inc();
+    }
+
+    fn on_watch_backoff(&self, event: &WatchBackoffEvent) {
+        self.watch_reconnects
+            .with_label_values(&[&event.route])
+            .inc();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use futures::StreamExt;
+    use wiremock::{
+        matchers::{header, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+    use crate::{model::Query, Client, ProjectService, RepoService, WatchService};
+
+    fn value(registry: &Registry, name: &str, labels: &[(&str, &str)]) -> f64 {
+        registry
+            .gather()
+            .iter()
+            .filter(|family| family.name() == name)
+            .flat_map(|family| family.get_metric())
+            .filter(|metric| {
+                labels.iter().all(|(name, value)| {
+                    metric
+                        .get_label()
+                        .iter()
+                        .any(|l| l.name() == *name && l.value() == *value)
+                })
+            })
+            .map(|metric| metric.get_counter().get_value())
+            .sum()
+    }
+
+    #[tokio::test]
+    async fn test_request_metrics() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw("[]", "application/json"))
+            .mount(&server)
+            .await;
+
+        let registry = Registry::new();
+        let client = Client::new(&server.uri(), None)
+            .await
+            .unwrap()
+            .with_metrics(Metrics::register(&registry).unwrap());
+        client.list_projects().await.unwrap();
+        client.project("foo").list_repos().await.unwrap_err();
+
+        let requests = "centraldogma_client_requests_total";
+        assert_eq!(
+            value(
+                &registry,
+                requests,
+                &[("endpoint", "/api/v1/projects"), ("status", "200")]
+            ),
+            1.0
+        );
+        assert_eq!(
+            value(
+                &registry,
+                requests,
+                &[
+                    ("endpoint", "/api/v1/projects/{project}/repos"),
+                    ("status", "404")
+                ]
+            ),
+            1.0
+        );
+        assert!(Metrics::register(&registry).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_watch_metrics() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/contents/a.json"))
+            .and(header("if-none-match", "-1"))
+            .respond_with(ResponseTemplate::new(500))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/contents/a.json"))
+            .and(header("if-none-match", "-1"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(
+                r#"{"revision":3,"entry":{"path":"/a.json","type":"JSON","content":{},"revision":3,"url":"/a.json"}}"#,
+                "application/json",
+            ))
+            .mount(&server)
+            .await;
+
+        let registry = Registry::new();
+        let client = Client::new(&server.uri(), None)
+            .await
+            .unwrap()
+            .with_metrics(Metrics::register(&registry).unwrap());
+        let mut stream = client
+            .repo("foo", "bar")
+            .watch_file_stream(&Query::identity("/a.json").unwrap())
+            .unwrap();
+        stream.next().await.unwrap();
+
+        let endpoint = (
+            "endpoint",
+            "/api/v1/projects/{project}/repos/{repo}/contents/{path}",
+        );
+        assert_eq!(
+            value(
+                &registry,
+                "centraldogma_watch_responses_total",
+                &[endpoint, ("result", "modified")]
+            ),
+            1.0
+        );
+        assert_eq!(
+            value(
+                &registry,
+                "centraldogma_watch_reconnects_total",
+                &[endpoint]
+            ),
+            1.0
+        );
+    }
+}
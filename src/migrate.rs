@@ -0,0 +1,534 @@
+//! Copying of many repositories, between clusters or within one, without overloading the
+//! servers.
+//!
+//! ```no_run
+//! use centraldogma::{migrate::Migration, Client};
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), centraldogma::Error> {
+//! let source = Client::new("http://old-cluster:36462", None).await?;
+//! let target = Client::new("http://new-cluster:36462", None).await?;
+//! let report = Migration::new(source, target)
+//!     .project("foo", "foo")
+//!     .repo("bar", "baz", "qux", "baz")
+//!     .concurrency(4)
+//!     .rate_limit(20.0)
+//!     .checkpoint("/var/lib/migration/checkpoint")
+//!     .on_progress(|p| println!("{}/{} {:?}", p.completed, p.total, p.job))
+//!     .run()
+//!     .await?;
+//!
+//! for (job, e) in &report.failed {
+//!     eprintln!("Failed to migrate {:?}: {}", job, e);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use std::{
+    collections::HashSet,
+    path::PathBuf,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{Duration, Instant},
+};
+
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use tokio::{io::AsyncWriteExt, sync::Mutex};
+
+use crate::{
+    meta::INTERNAL_REPOS,
+    model::{Change, ChangeContent, CommitMessage, EntryContent, Revision},
+    push::FILES_PER_COMMIT,
+    Client, ContentService, Error, ErrorCode, ProjectService, RepoService,
+};
+
+type ProgressCallback = dyn Fn(&MigrationProgress) + Send + Sync;
+
+/// A repository to copy.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct MigrationJob {
+    /// Project of the source repository
+    pub source_project: String,
+    /// Name of the source repository
+    pub source_repo: String,
+    /// Project of the target repository, created if needed
+    pub target_project: String,
+    /// Name of the target repository, created if needed
+    pub target_repo: String,
+}
+
+/// The progress of a [`Migration`], reported when a job completes.
+#[derive(Debug)]
+pub struct MigrationProgress<'a> {
+    /// The completed job
+    pub job: &'a MigrationJob,
+    /// Number of jobs completed so far, including the skipped ones
+    pub completed: usize,
+    /// Number of jobs
+    pub total: usize,
+    /// Number of files copied by the job
+    pub files: usize,
+    /// The failure of the job, if it failed
+    pub error: Option<&'a Error>,
+}
+
+/// The outcome of a [`Migration`].
+#[derive(Debug, Default)]
+pub struct MigrationReport {
+    /// The jobs which were copied
+    pub migrated: Vec<MigrationJob>,
+    /// The jobs which were already completed according to the checkpoint
+    pub skipped: Vec<MigrationJob>,
+    /// The jobs which failed
+    pub failed: Vec<(MigrationJob, Error)>,
+}
+
+enum Source {
+    Project { source: String, target: String },
+    Repo(MigrationJob),
+}
+
+/// Copies the files at the head revision of repositories to other repositories.
+///
+/// The files of a repository are pushed in commits of at most 100 files. A repository
+/// whose files are already in the target is copied without any commit, so a failed
+/// migration can be run again.
+pub struct Migration {
+    source: Client,
+    target: Client,
+    sources: Vec<Source>,
+    concurrency: usize,
+    requests_per_second: Option<f64>,
+    checkpoint: Option<PathBuf>,
+    on_progress: Option<Box<ProgressCallback>>,
+}
+
+impl Migration {
+    /// Returns a migration from the repositories of `source` to the ones of `target`,
+    /// which can be the same client.
+    pub fn new(source: Client, target: Client) -> Self {
+        Migration {
+            source,
+            target,
+            sources: Vec::new(),
+            concurrency: 4,
+            requests_per_second: None,
+            checkpoint: None,
+            on_progress: None,
+        }
+    }
+
+    /// Copies every repository of the project `source` to the project `target`,
+    /// except the internal `dogma` and `meta` repositories.
+    pub fn project(mut self, source: &str, target: &str) -> Self {
+        self.sources.push(Source::Project {
+            source: source.to_owned(),
+            target: target.to_owned(),
+        });
+        self
+    }
+
+    /// Copies a repository.
+    pub fn repo(
+        mut self,
+        source_project: &str,
+        source_repo: &str,
+        target_project: &str,
+        target_repo: &str,
+    ) -> Self {
+        self.sources.push(Source::Repo(MigrationJob {
+            source_project: source_project.to_owned(),
+            source_repo: source_repo.to_owned(),
+            target_project: target_project.to_owned(),
+            target_repo: target_repo.to_owned(),
+        }));
+        self
+    }
+
+    /// Sets how many repositories are copied at the same time, 4 by default.
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Limits the requests sent to both clients, unlimited by default. Only the first request
+    /// is sent if the rate is so low that the interval between two requests overflows.
+    pub fn rate_limit(mut self, requests_per_second: f64) -> Self {
+        self.requests_per_second = Some(requests_per_second).filter(|r| *r > 0.0);
+        self
+    }
+
+    /// Records the completed jobs in the file at `path`, skipping the jobs it already
+    /// records, so that an interrupted migration resumes where it stopped.
+    pub fn checkpoint(mut self, path: impl Into<PathBuf>) -> Self {
+        self.checkpoint = Some(path.into());
+        self
+    }
+
+    /// Calls `callback` whenever a job completes.
+    pub fn on_progress<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&MigrationProgress) + Send + Sync + 'static,
+    {
+        self.on_progress = Some(Box::new(callback));
+        self
+    }
+
+    /// Copies the repositories.
+    ///
+    /// Fails if the repositories of a project can't be listed or the checkpoint can't be
+    /// read. The failures of the jobs are reported by [`MigrationReport::failed`].
+    pub async fn run(self) -> Result<MigrationReport, Error> {
+        let limiter = RateLimiter::new(self.requests_per_second);
+        let jobs = self.jobs(&limiter).await?;
+        let done = match &self.checkpoint {
+            Some(path) => read_checkpoint(path).await?,
+            None => HashSet::new(),
+        };
+
+        let mut report = MigrationReport::default();
+        let (skipped, pending): (Vec<_>, Vec<_>) =
+            jobs.into_iter().partition(|job| done.contains(job));
+        let total = skipped.len() + pending.len();
+        let completed = AtomicUsize::new(skipped.len());
+        report.skipped = skipped;
+
+        let checkpoint = Mutex::new(());
+        let this = &self;
+        let results: Vec<_> = futures::stream::iter(pending)
+            .map(|job| {
+                let (limiter, completed, checkpoint) = (&limiter, &completed, &checkpoint);
+                async move {
+                    let mut result = this.copy(&job, limiter).await;
+                    if let (Ok(_), Some(path)) = (&result, &this.checkpoint) {
+                        let _guard = checkpoint.lock().await;
+                        if let Err(e) = append_checkpoint(path, &job).await {
+                            result = Err(e);
+                        }
+                    }
+
+                    let completed = completed.fetch_add(1, Ordering::Relaxed) + 1;
+                    if let Some(callback) = &this.on_progress {
+                        callback(&MigrationProgress {
+                            job: &job,
+                            completed,
+                            total,
+                            files: *result.as_ref().unwrap_or(&0),
+                            error: result.as_ref().err(),
+                        });
+                    }
+                    (job, result)
+                }
+            })
+            .buffer_unordered(self.concurrency)
+            .collect()
+            .await;
+
+        for (job, result) in results {
+            match result {
+                Ok(_) => report.migrated.push(job),
+                Err(e) => report.failed.push((job, e)),
+            }
+        }
+
+        Ok(report)
+    }
+
+    async fn jobs(&self, limiter: &RateLimiter) -> Result<Vec<MigrationJob>, Error> {
+        let mut jobs = Vec::new();
+        for source in &self.sources {
+            match source {
+                Source::Repo(job) => jobs.push(job.clone()),
+                Source::Project { source, target } => {
+                    limiter.acquire(&self.source).await;
+                    let repos = self.source.project(source).list_repos().await?;
+                    jobs.extend(
+                        repos
+                            .into_iter()
+                            .filter(|r| !INTERNAL_REPOS.contains(&r.name.as_str()))
+                            .map(|r| MigrationJob {
+                                source_project: source.clone(),
+                                source_repo: r.name.clone(),
+                                target_project: target.clone(),
+                                target_repo: r.name,
+                            }),
+                    );
+                }
+            }
+        }
+
+        Ok(jobs)
+    }
+
+    /// Copies the files of a job, returning how many were copied.
+    async fn copy(&self, job: &MigrationJob, limiter: &RateLimiter) -> Result<usize, Error> {
+        limiter.acquire(&self.source).await;
+        let source = self.source.repo(&job.source_project, &job.source_repo);
+        let entries = source.get_files(Revision::HEAD, "/**").await?;
+        let revision = entries.iter().filter_map(|e| e.revision.as_i64()).max();
+
+        limiter.acquire(&self.target).await;
+        match self.target.create_project(&job.target_project).await {
+            Err(e) if e.code() != ErrorCode::ProjectExists => return Err(e),
+            _ => {}
+        }
+        limiter.acquire(&self.target).await;
+        match self
+            .target
+            .project(&job.target_project)
+            .create_repo(&job.target_repo)
+            .await
+        {
+            Err(e) if e.code() != ErrorCode::RepositoryExists => return Err(e),
+            _ => {}
+        }
+
+        let changes: Vec<_> = entries
+            .into_iter()
+            .filter_map(|entry| {
+                let content = match entry.content {
+                    EntryContent::Json(json) => ChangeContent::UpsertJson(json),
+                    EntryContent::Text(text) => ChangeContent::UpsertText(text),
+                    #[cfg(feature = "yaml")]
+                    EntryContent::Yaml(yaml) => ChangeContent::UpsertYaml(yaml),
+                    EntryContent::Directory => return None,
+                };
+                Some(Change {
+                    path: entry.path,
+                    content,
+                })
+            })
+            .collect();
+        let files = changes.len();
+
+        let target = self.target.repo(&job.target_project, &job.target_repo);
+        let summary = format!(
+            "Migrate {}/{} at revision {}",
+            job.source_project,
+            job.source_repo,
+            revision.unwrap_or(1)
+        );
+        for chunk in changes.chunks(FILES_PER_COMMIT) {
+            limiter.acquire(&self.target).await;
+            let pushed = target
+                .push(
+                    Revision::HEAD,
+                    CommitMessage::only_summary(&summary),
+                    chunk.to_vec(),
+                )
+                .await;
+            match pushed {
+                Err(Error::RedundantChange(_)) | Ok(_) => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(files)
+    }
+}
+
+/// Spaces out the requests evenly.
+struct RateLimiter {
+    interval: Option<Duration>,
+    /// When the next request can be sent, `None` if never, i.e. if the interval overflows
+    next: Mutex<Option<Instant>>,
+}
+
+impl RateLimiter {
+    fn new(requests_per_second: Option<f64>) -> Self {
+        RateLimiter {
+            interval: requests_per_second
+                .map(|r| Duration::try_from_secs_f64(1.0 / r).unwrap_or(Duration::MAX)),
+            next: Mutex::new(Some(Instant::now())),
+        }
+    }
+
+    /// Waits until a request can be sent.
+    async fn acquire(&self, client: &Client) {
+        let Some(interval) = self.interval else {
+            return;
+        };
+        let wait = {
+            let mut next = self.next.lock().await;
+            let now = Instant::now();
+            let Some(slot) = next.map(|n| n.max(now)) else {
+                drop(next);
+                return futures::future::pending().await;
+            };
+            *next = slot.checked_add(interval);
+            slot - now
+        };
+        if !wait.is_zero() {
+            client.sleep(wait).await;
+        }
+    }
+}
+
+async fn read_checkpoint(path: &PathBuf) -> Result<HashSet<MigrationJob>, Error> {
+    let content = match tokio::fs::read_to_string(path).await {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(HashSet::new()),
+        Err(source) => {
+            return Err(Error::Io {
+                path: path.display().to_string(),
+                source,
+            })
+        }
+    };
+
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            serde_json::from_str(line).map_err(|e| Error::InvalidConfig {
+                path: path.display().to_string(),
+                message: e.to_string(),
+            })
+        })
+        .collect()
+}
+
+async fn append_checkpoint(path: &PathBuf, job: &MigrationJob) -> Result<(), Error> {
+    let io = |source| Error::Io {
+        path: path.display().to_string(),
+        source,
+    };
+    let mut line = serde_json::to_string(job)?;
+    line.push('\n');
+
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .await
+        .map_err(io)?;
+    file.write_all(line.as_bytes()).await.map_err(io)?;
+    file.flush().await.map_err(io)
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{Arc, Mutex as StdMutex};
+
+    use serde_json::json;
+    use wiremock::{
+        matchers::{body_partial_json, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+
+    fn repo(name: &str) -> serde_json::Value {
+        json!({
+            "name": name,
+            "creator": {"name": "minux", "email": "minux@m.x"},
+            "headRevision": 2,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_tiny_rate_limit() {
+        let client = Client::new("http://localhost:36462", None).await.unwrap();
+        let limiter = RateLimiter::new(Some(1e-300));
+
+        limiter.acquire(&client).await;
+        let second = tokio::time::timeout(Duration::from_millis(100), limiter.acquire(&client));
+        assert!(second.await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_migration() {
+        let source = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+                repo("dogma"),
+                repo("a"),
+                repo("b")
+            ])))
+            .mount(&source)
+            .await;
+        for name in ["a", "b"] {
+            Mock::given(method("GET"))
+                .and(path(format!("/api/v1/projects/foo/repos/{}/contents/**", name)))
+                .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+                    {"path": "/x.json", "type": "JSON", "content": {"a": 1}, "revision": 2, "url": "/x.json"},
+                    {"path": "/d", "type": "DIRECTORY", "revision": 2, "url": "/d"},
+                    {"path": "/d/y.txt", "type": "TEXT", "content": "y", "revision": 2, "url": "/d/y.txt"}
+                ])))
+                .mount(&source)
+                .await;
+        }
+
+        let target = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/v1/projects"))
+            .respond_with(ResponseTemplate::new(409).set_body_json(json!({
+                "exception": "com.linecorp.centraldogma.common.ProjectExistsException",
+                "message": "exists"
+            })))
+            .mount(&target)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/api/v1/projects/bar/repos"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(repo("a")))
+            .mount(&target)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/api/v1/projects/bar/repos/a/contents"))
+            .and(body_partial_json(json!({
+                "commitMessage": {"summary": "Migrate foo/a at revision 2"},
+                "changes": [
+                    {"path": "/x.json", "type": "UPSERT_JSON", "content": {"a": 1}},
+                    {"path": "/d/y.txt", "type": "UPSERT_TEXT", "content": "y"}
+                ]
+            })))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(json!({"revision": 2, "pushedAt": null})),
+            )
+            .expect(1)
+            .mount(&target)
+            .await;
+
+        let checkpoint =
+            std::env::temp_dir().join(format!("centraldogma-migration-{}", fastrand::u64(..)));
+        let done = MigrationJob {
+            source_project: "foo".to_owned(),
+            source_repo: "b".to_owned(),
+            target_project: "bar".to_owned(),
+            target_repo: "b".to_owned(),
+        };
+        append_checkpoint(&checkpoint, &done).await.unwrap();
+
+        let progress = Arc::new(StdMutex::new(Vec::new()));
+        let recorded = progress.clone();
+        let started = Instant::now();
+        let report = Migration::new(
+            Client::new(&source.uri(), None).await.unwrap(),
+            Client::new(&target.uri(), None).await.unwrap(),
+        )
+        .project("foo", "bar")
+        .rate_limit(50.0)
+        .checkpoint(&checkpoint)
+        .on_progress(move |p| {
+            recorded
+                .lock()
+                .unwrap()
+                .push((p.completed, p.total, p.files))
+        })
+        .run()
+        .await
+        .unwrap();
+
+        assert_eq!(report.skipped, [done]);
+        assert_eq!(report.migrated.len(), 1);
+        assert_eq!(report.migrated[0].source_repo, "a");
+        assert!(report.failed.is_empty());
+        assert_eq!(*progress.lock().unwrap(), [(2, 2, 2)]);
+        // 5 requests spaced by 20ms
+        assert!(started.elapsed() >= Duration::from_millis(80));
+        assert_eq!(read_checkpoint(&checkpoint).await.unwrap().len(), 2);
+
+        std::fs::remove_file(&checkpoint).unwrap();
+    }
+}
@@ -1,5 +1,15 @@
 //! Data models of CentralDogma
-use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    borrow::Cow,
+    collections::BTreeMap,
+    path::{Component, Path},
+};
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize};
+use serde_json::value::RawValue;
+
+use crate::{json_path::JsonPath, ContentService, Error, RepoClient};
 
 /// A revision number of a [`Commit`].
 ///
@@ -16,7 +26,7 @@ use serde::{de::DeserializeOwned, Deserialize, Serialize};
 ///
 /// A revision with a negative integer is called 'relative revision'.
 /// By contrast, a revision with a positive integer is called 'absolute revision'.
-#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Revision(Option<i64>);
 
 impl Revision {
@@ -41,6 +51,7 @@ impl AsRef<Option<i64>> for Revision {
 }
 
 /// Create a new instance with the specified revision number.
+/// The value is not validated, use [`Revision::try_from_i64`] for untrusted input.
 impl From<i64> for Revision {
     fn from(value: i64) -> Self {
         Self(Some(value))
@@ -54,10 +65,117 @@ impl Revision {
     pub const INIT: Revision = Revision(Some(1));
     /// Omitted revision, behavior is decided on server side, usually [`Revision::HEAD`]
     pub const DEFAULT: Revision = Revision(None);
+
+    /// Creates a new instance with the specified revision number.
+    /// Returns an error if `value` is `0`, which is neither an absolute nor a relative revision.
+    pub fn try_from_i64(value: i64) -> Result<Self, Error> {
+        if value == 0 {
+            return Err(Error::InvalidParams("revision 0 is invalid"));
+        }
+
+        Ok(Revision(Some(value)))
+    }
+
+    /// Returns the relative revision `n` commits before [`Revision::HEAD`].
+    /// e.g. `head_minus(0)` is `-1` and `head_minus(1)` is `-2`.
+    pub const fn head_minus(n: u32) -> Self {
+        Revision(Some(-1 - n as i64))
+    }
+
+    /// Returns `true` if this is a relative revision, e.g. [`Revision::HEAD`].
+    pub fn is_relative(&self) -> bool {
+        matches!(self.0, Some(n) if n < 0)
+    }
+
+    /// Returns `true` if this is an absolute revision, e.g. [`Revision::INIT`].
+    pub fn is_absolute(&self) -> bool {
+        matches!(self.0, Some(n) if n > 0)
+    }
+
+    /// Returns the revision `n` commits before this one, of the same kind.
+    /// An absolute revision stops at [`Revision::INIT`], and [`Revision::DEFAULT`] is
+    /// taken as [`Revision::HEAD`].
+    pub fn backward(self, n: u32) -> Self {
+        match self.0 {
+            Some(r) if r > 0 => Revision(Some((r - n as i64).max(1))),
+            Some(r) => Revision(Some(r.saturating_sub(n as i64))),
+            None if n == 0 => self,
+            None => Revision::head_minus(n),
+        }
+    }
+
+    /// Returns the revision `n` commits after this one, of the same kind.
+    /// A relative revision stops at [`Revision::HEAD`], which [`Revision::DEFAULT`] is
+    /// taken as.
+    pub fn forward(self, n: u32) -> Self {
+        match self.0 {
+            Some(r) if r > 0 => Revision(Some(r.saturating_add(n as i64))),
+            Some(r) => Revision(Some((r + n as i64).min(-1))),
+            None if n == 0 => self,
+            None => Revision::HEAD,
+        }
+    }
+}
+
+/// Parses a revision number, or `head` in any case for [`Revision::HEAD`].
+/// Fails if the number is `0`, like [`Revision::try_from_i64`].
+impl std::str::FromStr for Revision {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.eq_ignore_ascii_case("head") {
+            return Ok(Revision::HEAD);
+        }
+        let value = s.parse().map_err(|_| {
+            Error::InvalidParams("revision must be `head` or a non-zero integer, e.g. -1 or 42")
+        })?;
+
+        Revision::try_from_i64(value)
+    }
+}
+
+/// Parses a revision, see the [`FromStr`](std::str::FromStr) implementation.
+impl TryFrom<&str> for Revision {
+    type Error = Error;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+/// [`Revision::forward`] by a number of commits.
+impl std::ops::Add<u32> for Revision {
+    type Output = Revision;
+
+    fn add(self, n: u32) -> Revision {
+        self.forward(n)
+    }
+}
+
+/// [`Revision::backward`] by a number of commits.
+impl std::ops::Sub<u32> for Revision {
+    type Output = Revision;
+
+    fn sub(self, n: u32) -> Revision {
+        self.backward(n)
+    }
+}
+
+/// Orders the revisions of the same kind from the oldest to the newest.
+/// An absolute and a relative revision can't be compared without the head revision.
+impl PartialOrd for Revision {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        match (self.0, other.0) {
+            (None, None) => Some(std::cmp::Ordering::Equal),
+            (Some(a), Some(b)) if (a > 0) == (b > 0) => Some(a.cmp(&b)),
+            _ => None,
+        }
+    }
 }
 
 /// Creator of a project or repository or commit
-#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 #[serde(rename_all = "camelCase")]
 pub struct Author {
     /// Name of this author.
@@ -69,7 +187,7 @@ pub struct Author {
 /// A top-level element in Central Dogma storage model.
 /// A project has "dogma" and "meta" repositories by default which contain project configuration
 /// files accessible by administrators and project owners respectively.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 #[serde(rename_all = "camelCase")]
 pub struct Project {
     /// Name of this project.
@@ -83,7 +201,7 @@ pub struct Project {
 }
 
 /// Repository information
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 #[serde(rename_all = "camelCase")]
 pub struct Repository {
     /// Name of this repository.
@@ -98,21 +216,180 @@ pub struct Repository {
     pub created_at: Option<String>,
 }
 
+impl Project {
+    /// Returns [`created_at`](#structfield.created_at) parsed as an RFC 3339 timestamp.
+    /// Returns `None` if it is absent or malformed.
+    #[cfg(feature = "time")]
+    pub fn created_at_time(&self) -> Option<time::OffsetDateTime> {
+        parse_timestamp(self.created_at.as_deref()?)
+    }
+}
+
+impl Repository {
+    /// Returns [`created_at`](#structfield.created_at) parsed as an RFC 3339 timestamp.
+    /// Returns `None` if it is absent or malformed.
+    #[cfg(feature = "time")]
+    pub fn created_at_time(&self) -> Option<time::OffsetDateTime> {
+        parse_timestamp(self.created_at.as_deref()?)
+    }
+}
+
+/// A role of a member or a token in a project.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ProjectRole {
+    /// Administrates the project and its repositories
+    Owner,
+    /// Reads and writes the repositories, as permitted per repository
+    Member,
+    /// Reads the repositories, as permitted per repository
+    Guest,
+}
+
+/// A permission on a repository.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Permission {
+    /// Reads the files and the history
+    Read,
+    /// Pushes changes
+    Write,
+}
+
+/// Who did something in the metadata of a project, and when.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub struct UserAndTimestamp {
+    /// Login of the user
+    pub user: String,
+    /// When, e.g. `2023-01-31T09:00:00Z`
+    pub timestamp: String,
+}
+
+/// The permissions on a repository of the members of a project, by their role.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub struct PerRolePermissions {
+    /// Permissions of the owners
+    pub owner: Vec<Permission>,
+    /// Permissions of the members
+    pub member: Vec<Permission>,
+    /// Permissions of the guests
+    pub guest: Vec<Permission>,
+    /// Permissions of the anonymous users, if the server allows them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub anonymous: Option<Vec<Permission>>,
+}
+
+/// The permissions on a repository of a project.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct RepositoryMetadata {
+    /// Name of the repository
+    pub name: String,
+    /// Permissions by role
+    pub per_role_permissions: PerRolePermissions,
+    /// Permissions granted to members, by login
+    #[serde(default)]
+    pub per_user_permissions: BTreeMap<String, Vec<Permission>>,
+    /// Permissions granted to tokens, by application ID
+    #[serde(default)]
+    pub per_token_permissions: BTreeMap<String, Vec<Permission>>,
+    /// Who created the repository
+    pub creation: UserAndTimestamp,
+    /// Who removed the repository, if it is removed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub removal: Option<UserAndTimestamp>,
+}
+
+/// A member of a project.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub struct Member {
+    /// Login of the member
+    pub login: String,
+    /// Role of the member
+    pub role: ProjectRole,
+    /// Who added the member
+    pub creation: UserAndTimestamp,
+}
+
+/// An application token registered to a project.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenRegistration {
+    /// Application ID of the token
+    pub app_id: String,
+    /// Role of the token
+    pub role: ProjectRole,
+    /// Who registered the token
+    pub creation: UserAndTimestamp,
+}
+
+/// The members, tokens and permissions of a project.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectMetadata {
+    /// Name of the project
+    pub name: String,
+    /// Permissions on the repositories, by name
+    #[serde(default)]
+    pub repos: BTreeMap<String, RepositoryMetadata>,
+    /// Members, by login
+    #[serde(default)]
+    pub members: BTreeMap<String, Member>,
+    /// Registered tokens, by application ID
+    #[serde(default)]
+    pub tokens: BTreeMap<String, TokenRegistration>,
+    /// Who created the project
+    pub creation: UserAndTimestamp,
+    /// Who removed the project, if it is removed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub removal: Option<UserAndTimestamp>,
+}
+
 /// The content of an [`Entry`]
-#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+///
+/// With the `redact-debug` feature, [`Debug`](std::fmt::Debug) prints the length and a hash
+/// of the content instead of the content itself.
+///
+/// The `Yaml` variant only exists with the `yaml` feature, so matches need a wildcard arm.
+#[derive(Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[cfg_attr(not(feature = "redact-debug"), derive(Debug))]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 #[serde(tag = "type", content = "content")]
+#[non_exhaustive]
 pub enum EntryContent {
     /// Content as a JSON Value.
     Json(serde_json::Value),
     /// Content as a String.
     Text(String),
+    /// Content as a YAML value.
+    #[cfg(feature = "yaml")]
+    Yaml(serde_yaml::Value),
     /// This Entry is a directory.
     Directory,
 }
 
+#[cfg(feature = "yaml")]
+impl EntryContent {
+    /// Returns the content as a YAML value: JSON and YAML content as is, and text content
+    /// parsed as YAML. Fails with [`Error::InvalidParams`] for a directory or a text which
+    /// isn't YAML.
+    pub fn to_yaml(&self) -> Result<serde_yaml::Value, Error> {
+        match self {
+            EntryContent::Json(json) => serde_yaml::to_value(json)
+                .map_err(|_| Error::InvalidParams("JSON content cannot be converted to YAML")),
+            EntryContent::Text(text) => serde_yaml::from_str(text)
+                .map_err(|_| Error::InvalidParams("text content is not valid YAML")),
+            EntryContent::Yaml(yaml) => Ok(yaml.clone()),
+            EntryContent::Directory => Err(Error::InvalidParams("a directory has no content")),
+        }
+    }
+}
+
 /// A file or a directory in a repository.
-#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 #[serde(rename_all = "camelCase")]
 pub struct Entry {
     /// Path of this entry.
@@ -128,55 +405,457 @@ pub struct Entry {
     pub modified_at: Option<String>,
 }
 
+/// Maximum size in bytes of binary data stored with [`Change::upsert_binary`].
+pub const MAX_BINARY_CONTENT_SIZE: usize = 1024 * 1024;
+
 impl Entry {
+    /// Returns [`modified_at`](#structfield.modified_at) parsed as an RFC 3339 timestamp.
+    /// Returns `None` if it is absent or malformed.
+    #[cfg(feature = "time")]
+    pub fn modified_at_time(&self) -> Option<time::OffsetDateTime> {
+        parse_timestamp(self.modified_at.as_deref()?)
+    }
+
     pub fn entry_type(&self) -> EntryType {
         match self.content {
             EntryContent::Json(_) => EntryType::Json,
             EntryContent::Text(_) => EntryType::Text,
+            #[cfg(feature = "yaml")]
+            EntryContent::Yaml(_) => EntryType::Yaml,
             EntryContent::Directory => EntryType::Directory,
         }
     }
+
+    /// Returns a compact, single line representation of this entry intended for logs,
+    /// e.g. `/a.json (JSON, 9 bytes, revision 3): {"a":"b"}`.
+    pub fn summary(&self) -> Summary<'_, Self> {
+        Summary(self)
+    }
+
+    /// Decodes binary data stored in a text entry with [`Change::upsert_binary`].
+    /// Whitespace, such as the trailing newline added to text files by the server, is ignored.
+    pub fn binary_content(&self) -> Result<Vec<u8>, Error> {
+        let text = match &self.content {
+            EntryContent::Text(t) => t,
+            _ => return Err(Error::InvalidParams("binary content must be a text entry")),
+        };
+        let encoded: Vec<u8> = text.bytes().filter(|b| !b.is_ascii_whitespace()).collect();
+        if encoded.len() > base64::encoded_len(MAX_BINARY_CONTENT_SIZE, true).unwrap_or(usize::MAX)
+        {
+            return Err(Error::InvalidParams("binary content is too large"));
+        }
+
+        BASE64
+            .decode(encoded)
+            .map_err(|_| Error::InvalidParams("binary content is not valid base64"))
+    }
+
+    /// Deserializes the content of this entry into `T`, keeping its metadata.
+    ///
+    /// The content of a text entry is parsed as JSON.
+    /// With the `yaml` feature, the content of a YAML entry is deserialized too.
+    /// Fails with [`Error::InvalidConfig`] if the content fails to deserialize.
+    pub fn into_typed<T: DeserializeOwned>(self) -> Result<TypedEntry<T>, Error> {
+        let invalid = |message: String| Error::InvalidConfig {
+            path: self.path.clone(),
+            message,
+        };
+        let value = match &self.content {
+            EntryContent::Json(json) => T::deserialize(json).map_err(|e| invalid(e.to_string())),
+            EntryContent::Text(text) => {
+                serde_json::from_str(text).map_err(|e| invalid(e.to_string()))
+            }
+            #[cfg(feature = "yaml")]
+            EntryContent::Yaml(yaml) => {
+                serde_yaml::from_value(yaml.clone()).map_err(|e| invalid(e.to_string()))
+            }
+            EntryContent::Directory => Err(invalid("not a file".to_owned())),
+        }?;
+
+        Ok(TypedEntry {
+            path: self.path,
+            value,
+            revision: self.revision,
+            url: self.url,
+            modified_at: self.modified_at,
+        })
+    }
+}
+
+/// A file whose content is deserialized into `T`, returned by
+/// [get_file_as](trait@crate::ContentService#method.get_file_as).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TypedEntry<T> {
+    /// Path of this entry.
+    pub path: String,
+    /// Content of this entry.
+    pub value: T,
+    /// Revision of this entry.
+    pub revision: Revision,
+    /// Url of this entry.
+    pub url: String,
+    /// When this entry was last modified.
+    pub modified_at: Option<String>,
+}
+
+/// A borrowed view of an [`Entry`], deserialized without copying the content out of the
+/// response body when possible.
+///
+/// JSON content is kept as the raw JSON text and text content only allocates if it contains
+/// escape sequences. Use [`RawEntries`] to obtain entries from
+/// [`ContentService::get_files_raw`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntryRef<'a> {
+    /// Path of this entry.
+    pub path: Cow<'a, str>,
+    /// Content of this entry.
+    pub content: EntryContentRef<'a>,
+    /// Revision of this entry.
+    pub revision: Revision,
+    /// Url of this entry.
+    pub url: Cow<'a, str>,
+    /// When this entry was last modified.
+    pub modified_at: Option<Cow<'a, str>>,
+}
+
+/// The borrowed content of an [`EntryRef`].
+///
+/// With the `redact-debug` feature, [`Debug`](std::fmt::Debug) prints the length and a hash
+/// of the content instead of the content itself.
+///
+/// The `Yaml` variant only exists with the `yaml` feature, so matches need a wildcard arm.
+#[derive(Clone, PartialEq, Eq)]
+#[cfg_attr(not(feature = "redact-debug"), derive(Debug))]
+#[non_exhaustive]
+pub enum EntryContentRef<'a> {
+    /// Content as raw JSON text.
+    Json(&'a str),
+    /// Content as a string.
+    Text(Cow<'a, str>),
+    /// Content of a YAML file, as the raw JSON text the server sends it as.
+    #[cfg(feature = "yaml")]
+    Yaml(&'a str),
+    /// This entry is a directory.
+    Directory,
+}
+
+impl<'a> EntryRef<'a> {
+    pub fn entry_type(&self) -> EntryType {
+        match self.content {
+            EntryContentRef::Json(_) => EntryType::Json,
+            EntryContentRef::Text(_) => EntryType::Text,
+            #[cfg(feature = "yaml")]
+            EntryContentRef::Yaml(_) => EntryType::Yaml,
+            EntryContentRef::Directory => EntryType::Directory,
+        }
+    }
+
+    /// Returns the raw JSON text if this is a JSON entry.
+    pub fn json_str(&self) -> Option<&'a str> {
+        match self.content {
+            EntryContentRef::Json(json) => Some(json),
+            _ => None,
+        }
+    }
+
+    /// Returns the text if this is a text entry.
+    pub fn text(&self) -> Option<&str> {
+        match &self.content {
+            EntryContentRef::Text(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    /// Deserializes the content of a JSON entry into `T`, borrowing from the response body
+    /// where `T` allows it.
+    pub fn json<T: Deserialize<'a>>(&self) -> Result<T, Error> {
+        match self.content {
+            EntryContentRef::Json(json) => Ok(serde_json::from_str(json)?),
+            _ => Err(Error::InvalidParams("entry is not a JSON entry")),
+        }
+    }
+
+    /// Copies this view into an owned [`Entry`].
+    pub fn to_entry(&self) -> Result<Entry, Error> {
+        let content = match &self.content {
+            EntryContentRef::Json(json) => EntryContent::Json(serde_json::from_str(json)?),
+            EntryContentRef::Text(text) => EntryContent::Text(text.to_string()),
+            #[cfg(feature = "yaml")]
+            EntryContentRef::Yaml(yaml) => EntryContent::Yaml(serde_json::from_str(yaml)?),
+            EntryContentRef::Directory => EntryContent::Directory,
+        };
+
+        Ok(Entry {
+            path: self.path.to_string(),
+            content,
+            revision: self.revision,
+            url: self.url.to_string(),
+            modified_at: self.modified_at.as_ref().map(|m| m.to_string()),
+        })
+    }
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for EntryRef<'a> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Raw<'a> {
+            #[serde(borrow)]
+            path: Cow<'a, str>,
+            r#type: EntryType,
+            #[serde(borrow)]
+            content: Option<&'a RawValue>,
+            revision: Revision,
+            #[serde(borrow)]
+            url: Cow<'a, str>,
+            #[serde(borrow, default, deserialize_with = "borrow_opt_str")]
+            modified_at: Option<Cow<'a, str>>,
+        }
+
+        #[derive(Deserialize)]
+        struct Text<'a>(#[serde(borrow)] Cow<'a, str>);
+
+        let raw = Raw::deserialize(deserializer)?;
+        let content = match (raw.r#type, raw.content) {
+            (EntryType::Json, Some(json)) => EntryContentRef::Json(json.get()),
+            (EntryType::Text, Some(text)) => {
+                let Text(text) =
+                    serde_json::from_str(text.get()).map_err(serde::de::Error::custom)?;
+                EntryContentRef::Text(text)
+            }
+            #[cfg(feature = "yaml")]
+            (EntryType::Yaml, Some(yaml)) => EntryContentRef::Yaml(yaml.get()),
+            (EntryType::Directory, _) => EntryContentRef::Directory,
+            (_, None) => return Err(serde::de::Error::missing_field("content")),
+        };
+
+        Ok(EntryRef {
+            path: raw.path,
+            content,
+            revision: raw.revision,
+            url: raw.url,
+            modified_at: raw.modified_at,
+        })
+    }
+}
+
+fn borrow_opt_str<'de: 'a, 'a, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Cow<'a, str>>, D::Error> {
+    #[derive(Deserialize)]
+    struct Borrowed<'a>(#[serde(borrow)] Cow<'a, str>);
+
+    Ok(Option::<Borrowed<'a>>::deserialize(deserializer)?.map(|b| b.0))
+}
+
+/// An undecoded response body holding a list of entries,
+/// returned by [`ContentService::get_files_raw`].
+///
+/// [`entries`](RawEntries::entries) borrows the entries from the body,
+/// which avoids allocating a copy of every path and content when scanning many files.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawEntries {
+    body: bytes::Bytes,
+}
+
+impl RawEntries {
+    pub(crate) fn new(body: bytes::Bytes) -> Self {
+        RawEntries { body }
+    }
+
+    /// Returns the undecoded response body.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.body
+    }
+
+    /// Decodes the entries, borrowing from the response body.
+    pub fn entries(&self) -> Result<Vec<EntryRef<'_>>, Error> {
+        if self.body.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        Ok(serde_json::from_slice(&self.body)?)
+    }
+}
+
+/// A directory in a repository with its files and sub-directories grouped,
+/// built from a flat list of [`Entry`]s.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Directory {
+    /// Path of this directory.
+    pub path: String,
+    /// Files directly under this directory.
+    pub files: Vec<Entry>,
+    /// Sub-directories directly under this directory.
+    pub directories: Vec<Directory>,
+}
+
+impl Directory {
+    fn new(path: String) -> Self {
+        Directory {
+            path,
+            files: Vec::new(),
+            directories: Vec::new(),
+        }
+    }
+
+    /// Groups a flat list of [`Entry`]s by their paths into a tree rooted at `/`.
+    /// Parent directories which are not in the list are created implicitly.
+    pub fn from_entries(entries: Vec<Entry>) -> Self {
+        let mut root = Directory::new("/".to_owned());
+        for entry in entries {
+            let path = entry.path.clone();
+            let components: Vec<&str> = path.split('/').filter(|c| !c.is_empty()).collect();
+            if !components.is_empty() {
+                root.insert(&components, entry);
+            }
+        }
+
+        root
+    }
+
+    fn insert(&mut self, components: &[&str], entry: Entry) {
+        let child_path = if self.path == "/" {
+            format!("/{}", components[0])
+        } else {
+            format!("{}/{}", self.path, components[0])
+        };
+
+        if components.len() == 1 && entry.entry_type() != EntryType::Directory {
+            self.files.push(entry);
+            return;
+        }
+
+        let child = match self.directories.iter().position(|d| d.path == child_path) {
+            Some(i) => &mut self.directories[i],
+            None => {
+                self.directories.push(Directory::new(child_path));
+                self.directories.last_mut().unwrap()
+            }
+        };
+        if components.len() > 1 {
+            child.insert(&components[1..], entry);
+        }
+    }
+
+    /// Returns the sub-directory at the specified path, searching recursively.
+    pub fn directory(&self, path: &str) -> Option<&Directory> {
+        if self.path == path {
+            return Some(self);
+        }
+        self.directories.iter().find_map(|d| d.directory(path))
+    }
+
+    /// Returns all files under this directory recursively, depth first.
+    pub fn all_files(&self) -> Vec<&Entry> {
+        let mut files: Vec<&Entry> = self.files.iter().collect();
+        for d in self.directories.iter() {
+            files.extend(d.all_files());
+        }
+
+        files
+    }
 }
 
 /// The type of a [`ListEntry`]
-#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+///
+/// The `Yaml` variant only exists with the `yaml` feature, so matches need a wildcard arm.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+#[non_exhaustive]
 pub enum EntryType {
     /// A UTF-8 encoded JSON file.
     Json,
     /// A UTF-8 encoded text file.
     Text,
+    /// A UTF-8 encoded YAML file.
+    #[cfg(feature = "yaml")]
+    Yaml,
     /// A directory.
     Directory,
 }
 
+/// The type of a file or a directory in a repository and its last modification, returned by
+/// [get_entry_meta](trait@crate::ContentService#method.get_entry_meta).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub struct EntryMeta {
+    /// Path of this entry.
+    pub path: String,
+    /// Type of this entry.
+    pub r#type: EntryType,
+    /// Revision of the last commit changing this entry.
+    pub revision: Revision,
+    /// When this entry was last modified.
+    pub modified_at: Option<String>,
+}
+
+impl EntryMeta {
+    /// Returns [`modified_at`](#structfield.modified_at) parsed as an RFC 3339 timestamp.
+    /// Returns `None` if it is absent or malformed.
+    #[cfg(feature = "time")]
+    pub fn modified_at_time(&self) -> Option<time::OffsetDateTime> {
+        parse_timestamp(self.modified_at.as_deref()?)
+    }
+}
+
 /// A metadata of a file or a directory in a repository.
 /// ListEntry has no content.
-#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 #[serde(rename_all = "camelCase")]
 pub struct ListEntry {
     pub path: String,
     pub r#type: EntryType,
 }
 
+impl ListEntry {
+    /// Returns a [`Query`] that retrieves this entry as it is,
+    /// based on the type of this entry.
+    /// Returns `None` if path is empty
+    pub fn query(&self) -> Option<Query> {
+        match self.r#type {
+            EntryType::Json => Query::of_json(&self.path),
+            EntryType::Text => Query::of_text(&self.path),
+            #[cfg(feature = "yaml")]
+            EntryType::Yaml => Query::of_yaml(&self.path),
+            EntryType::Directory => Query::identity(&self.path),
+        }
+    }
+
+    /// Retrieves the full [`Entry`] of this listed entry at the specified [`Revision`].
+    pub async fn fetch(&self, repo: &RepoClient<'_>, revision: Revision) -> Result<Entry, Error> {
+        let query = self
+            .query()
+            .ok_or(Error::InvalidParams("path of list entry cannot be empty"))?;
+
+        repo.get_file(revision, &query).await
+    }
+}
+
 /// Type of a [`Query`]
-#[derive(Debug, PartialEq, Eq)]
+///
+/// The `IdentityYaml` variant only exists with the `yaml` feature, so matches need a
+/// wildcard arm.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
 pub enum QueryType {
     Identity,
     IdentityJson,
     IdentityText,
+    #[cfg(feature = "yaml")]
+    IdentityYaml,
     JsonPath(Vec<String>),
 }
 
 /// A Query on a file
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Query {
     pub(crate) path: String,
     pub(crate) r#type: QueryType,
 }
 
 impl Query {
-    fn normalize_path(path: &str) -> String {
+    pub(crate) fn normalize_path(path: &str) -> String {
         if path.starts_with('/') {
             path.to_owned()
         } else {
@@ -220,6 +899,19 @@ impl Query {
         })
     }
 
+    /// Returns a newly-created [`Query`] that retrieves the YAML content as it is.
+    /// Returns `None` if path is empty
+    #[cfg(feature = "yaml")]
+    pub fn of_yaml(path: &str) -> Option<Self> {
+        if path.is_empty() {
+            return None;
+        }
+        Some(Query {
+            path: Self::normalize_path(path),
+            r#type: QueryType::IdentityYaml,
+        })
+    }
+
     /// Returns a newly-created [`Query`] that applies a series of
     /// [JSON path expressions](https://github.com/json-path/JsonPath/blob/master/README.md)
     /// to the content.
@@ -237,41 +929,208 @@ impl Query {
             r#type: QueryType::JsonPath(exprs),
         })
     }
-}
 
-/// Typed content of a [`CommitMessage`]
-#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
-#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
-#[serde(tag = "markup", content = "detail")]
-pub enum CommitDetail {
-    /// Commit details as markdown
-    Markdown(String),
-    /// Commit details as plaintext
-    Plaintext(String),
+    /// Returns a newly-created [`Query`] that applies a series of [`JsonPath`] expressions
+    /// to the content.
+    /// Returns `None` if path is empty or does not end with `.json`.
+    pub fn of_json_path_exprs(path: &str, exprs: Vec<JsonPath>) -> Option<Self> {
+        Self::of_json_path(path, exprs.into_iter().map(String::from).collect())
+    }
 }
 
-/// Description of a [`Commit`]
-#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
-#[serde(rename_all = "camelCase")]
-pub struct CommitMessage {
-    /// Summary of this commit message
-    pub summary: String,
-    #[serde(flatten, skip_serializing_if = "Option::is_none")]
-    /// Detailed description of this commit message
-    pub detail: Option<CommitDetail>,
+/// A JSON file merged by a [`MergeQuery`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MergeSource {
+    pub(crate) path: String,
+    pub(crate) optional: bool,
 }
 
-impl CommitMessage {
-    pub fn only_summary(summary: &str) -> Self {
-        CommitMessage {
-            summary: summary.to_owned(),
-            detail: None,
+impl MergeSource {
+    /// Returns a source which must exist.
+    /// Returns `None` if path is empty
+    pub fn required(path: &str) -> Option<Self> {
+        if path.is_empty() {
+            return None;
+        }
+        Some(MergeSource {
+            path: Query::normalize_path(path),
+            optional: false,
+        })
+    }
+
+    /// Returns a source which is skipped if it doesn't exist.
+    /// Returns `None` if path is empty
+    pub fn optional(path: &str) -> Option<Self> {
+        Self::required(path).map(|s| MergeSource {
+            optional: true,
+            ..s
+        })
+    }
+
+    /// Returns the path of the file.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Returns whether the file is skipped if it doesn't exist.
+    pub fn is_optional(&self) -> bool {
+        self.optional
+    }
+}
+
+/// A query merging JSON files into one, each one overriding the ones before.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MergeQuery {
+    pub(crate) sources: Vec<MergeSource>,
+    pub(crate) json_paths: Vec<String>,
+}
+
+impl MergeQuery {
+    /// Returns a newly-created [`MergeQuery`] that merges the JSON files of `sources`.
+    /// Returns `None` if there are no sources.
+    pub fn of_json(sources: Vec<MergeSource>) -> Option<Self> {
+        Self::of_json_path(sources, Vec::new())
+    }
+
+    /// Returns a newly-created [`MergeQuery`] that merges the JSON files of `sources`, then
+    /// applies a series of [`JsonPath`] expressions to the merged content.
+    /// Returns `None` if there are no sources.
+    pub fn of_json_path(sources: Vec<MergeSource>, exprs: Vec<JsonPath>) -> Option<Self> {
+        if sources.is_empty() {
+            return None;
+        }
+        Some(MergeQuery {
+            sources,
+            json_paths: exprs.into_iter().map(String::from).collect(),
+        })
+    }
+
+    /// Returns the sources, in the order they are merged.
+    pub fn sources(&self) -> &[MergeSource] {
+        &self.sources
+    }
+}
+
+/// The result of a [`MergeQuery`].
+///
+/// With the `redact-debug` feature, [`Debug`](std::fmt::Debug) prints the length and a hash
+/// of the merged content instead of the content itself.
+#[derive(Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(not(feature = "redact-debug"), derive(Debug))]
+#[serde(rename_all = "camelCase")]
+pub struct MergedEntry {
+    /// Revision the files were merged at.
+    pub revision: Revision,
+    /// Type of the merged content, always [`EntryType::Json`].
+    pub r#type: EntryType,
+    /// The merged content.
+    pub content: serde_json::Value,
+    /// Paths of the merged files, the missing optional ones excluded.
+    pub paths: Vec<String>,
+}
+
+/// Typed content of a [`CommitMessage`]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+#[serde(tag = "markup", content = "detail")]
+pub enum CommitDetail {
+    /// Commit details as markdown
+    Markdown(String),
+    /// Commit details as plaintext
+    Plaintext(String),
+}
+
+impl CommitDetail {
+    /// Returns commit details written in markdown.
+    pub fn markdown(detail: &str) -> Self {
+        CommitDetail::Markdown(detail.to_owned())
+    }
+
+    /// Returns commit details written in plaintext.
+    pub fn plaintext(detail: &str) -> Self {
+        CommitDetail::Plaintext(detail.to_owned())
+    }
+
+    /// Returns the detail text as it is, regardless of its markup.
+    pub fn text(&self) -> &str {
+        match self {
+            CommitDetail::Markdown(t) | CommitDetail::Plaintext(t) => t,
+        }
+    }
+
+    /// Returns the detail text with markdown formatting removed,
+    /// so it can be displayed uniformly. Plaintext details are returned as they are.
+    #[cfg(feature = "markdown")]
+    pub fn render_plaintext(&self) -> String {
+        use pulldown_cmark::{Event, Parser, Tag, TagEnd};
+
+        let markdown = match self {
+            CommitDetail::Markdown(t) => t,
+            CommitDetail::Plaintext(t) => return t.clone(),
+        };
+
+        let mut out = String::new();
+        let mut link_urls = Vec::new();
+        for event in Parser::new(markdown) {
+            match event {
+                Event::Text(t) | Event::Code(t) | Event::Html(t) | Event::InlineHtml(t) => {
+                    out.push_str(&t)
+                }
+                Event::SoftBreak => out.push(' '),
+                Event::HardBreak | Event::Rule => out.push('\n'),
+                Event::Start(Tag::Item) => out.push_str("- "),
+                Event::Start(Tag::Link { dest_url, .. }) => link_urls.push(dest_url),
+                Event::End(TagEnd::Link) => {
+                    if let Some(url) = link_urls.pop() {
+                        out.push_str(&format!(" ({})", url));
+                    }
+                }
+                Event::End(TagEnd::Paragraph)
+                | Event::End(TagEnd::Heading(_))
+                | Event::End(TagEnd::Item)
+                | Event::End(TagEnd::CodeBlock)
+                    if !out.ends_with('\n') =>
+                {
+                    out.push('\n');
+                }
+                _ => {}
+            }
+        }
+
+        out.trim_end().to_owned()
+    }
+}
+
+/// Description of a [`Commit`]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub struct CommitMessage {
+    /// Summary of this commit message
+    pub summary: String,
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    /// Detailed description of this commit message
+    pub detail: Option<CommitDetail>,
+}
+
+impl CommitMessage {
+    pub fn only_summary(summary: &str) -> Self {
+        CommitMessage {
+            summary: summary.to_owned(),
+            detail: None,
+        }
+    }
+
+    /// Returns a commit message with the specified summary and [`CommitDetail`].
+    pub fn with_detail(summary: &str, detail: CommitDetail) -> Self {
+        CommitMessage {
+            summary: summary.to_owned(),
+            detail: Some(detail),
         }
     }
 }
 
 /// Result of a [push](trait@crate::ContentService#tymethod.push) operation.
-#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 #[serde(rename_all = "camelCase")]
 pub struct PushResult {
     /// Revision of this commit.
@@ -280,8 +1139,60 @@ pub struct PushResult {
     pub pushed_at: Option<String>,
 }
 
+impl PushResult {
+    /// Returns [`pushed_at`](#structfield.pushed_at) parsed as an RFC 3339 timestamp.
+    /// Returns `None` if it is absent or malformed.
+    #[cfg(feature = "time")]
+    pub fn pushed_at_time(&self) -> Option<time::OffsetDateTime> {
+        parse_timestamp(self.pushed_at.as_deref()?)
+    }
+
+    /// Returns how long ago this commit was pushed.
+    /// Returns `None` if the push time is unknown.
+    #[cfg(feature = "time")]
+    pub fn age(&self) -> Option<std::time::Duration> {
+        let elapsed = time::OffsetDateTime::now_utc() - self.pushed_at_time()?;
+
+        Some(std::time::Duration::try_from(elapsed).unwrap_or_default())
+    }
+}
+
+/// Formats as `pushed revision 532 at 2017-05-22T00:00:00Z`,
+/// followed by the age of the push such as `(3s ago)` when the `time` feature is enabled.
+impl std::fmt::Display for PushResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "pushed revision {}", self.revision)?;
+        if let Some(pushed_at) = &self.pushed_at {
+            write!(f, " at {}", pushed_at)?;
+        }
+        #[cfg(feature = "time")]
+        if let Some(age) = self.age() {
+            write!(f, " ({} ago)", format_age(age))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Parses a timestamp of the server, in the RFC 3339 format.
+#[cfg(feature = "time")]
+fn parse_timestamp(timestamp: &str) -> Option<time::OffsetDateTime> {
+    time::OffsetDateTime::parse(timestamp, &time::format_description::well_known::Rfc3339).ok()
+}
+
+#[cfg(feature = "time")]
+fn format_age(age: std::time::Duration) -> String {
+    let secs = age.as_secs();
+    match secs {
+        0..=59 => format!("{}s", secs),
+        60..=3599 => format!("{}m {}s", secs / 60, secs % 60),
+        3600..=86399 => format!("{}h {}m", secs / 3600, secs % 3600 / 60),
+        _ => format!("{}d {}h", secs / 86400, secs % 86400 / 3600),
+    }
+}
+
 /// A set of Changes and its metadata.
-#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 #[serde(rename_all = "camelCase")]
 pub struct Commit {
     /// Revision of this commit.
@@ -294,10 +1205,58 @@ pub struct Commit {
     pub pushed_at: Option<String>,
 }
 
+impl Commit {
+    /// Returns [`pushed_at`](#structfield.pushed_at) parsed as an RFC 3339 timestamp.
+    /// Returns `None` if it is absent or malformed.
+    #[cfg(feature = "time")]
+    pub fn pushed_at_time(&self) -> Option<time::OffsetDateTime> {
+        parse_timestamp(self.pushed_at.as_deref()?)
+    }
+
+    /// Returns a compact, single line representation of this commit intended for logs,
+    /// e.g. `revision 3 by minux <minux@m.x>: Add a.json`.
+    pub fn summary(&self) -> Summary<'_, Self> {
+        Summary(self)
+    }
+
+    /// Retrieves the [`Change`]s introduced by this commit from the given repository.
+    /// Returns an empty list for the initial commit, which has no changes.
+    pub async fn changes(&self, repo: &RepoClient<'_>) -> Result<Vec<Change>, Error> {
+        let revision = match self.revision.as_i64() {
+            Some(n) if n > 0 => n,
+            _ => {
+                return Err(Error::InvalidParams(
+                    "commit revision must be an absolute revision",
+                ))
+            }
+        };
+        if revision == 1 {
+            return Ok(Vec::new());
+        }
+
+        repo.get_diffs(Revision::from(revision - 1), self.revision, "/**")
+            .await
+    }
+
+    /// Retrieves the paths of the files changed by this commit from the given repository.
+    pub async fn changed_paths(&self, repo: &RepoClient<'_>) -> Result<Vec<String>, Error> {
+        let changes = self.changes(repo).await?;
+
+        Ok(changes.into_iter().map(|c| c.path).collect())
+    }
+}
+
 /// Typed content of a [`Change`].
-#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+///
+/// With the `redact-debug` feature, [`Debug`](std::fmt::Debug) prints the length and a hash
+/// of the content instead of the content itself.
+///
+/// The `UpsertYaml` variant only exists with the `yaml` feature, so matches need a wildcard arm.
+#[derive(Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[cfg_attr(not(feature = "redact-debug"), derive(Debug))]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 #[serde(tag = "type", content = "content")]
+#[non_exhaustive]
 pub enum ChangeContent {
     /// Adds a new JSON file or replaces an existing file with the provided json.
     UpsertJson(serde_json::Value),
@@ -305,6 +1264,10 @@ pub enum ChangeContent {
     /// Adds a new text file or replaces an existing file with the provided content.
     UpsertText(String),
 
+    /// Adds a new YAML file or replaces an existing file with the provided YAML value.
+    #[cfg(feature = "yaml")]
+    UpsertYaml(serde_yaml::Value),
+
     /// Removes an existing file.
     Remove,
 
@@ -320,8 +1283,29 @@ pub enum ChangeContent {
     ApplyTextPatch(String),
 }
 
+impl ChangeContent {
+    /// Returns the upsert of a file named `path` holding `text`: JSON for the `.json` files,
+    /// YAML for the `.yaml` and `.yml` files with the `yaml` feature, and text otherwise.
+    /// Fails with the parse error if the content isn't valid.
+    pub(crate) fn upsert_of(path: &str, text: String) -> Result<Self, String> {
+        if path.ends_with(".json") {
+            return serde_json::from_str(&text)
+                .map(ChangeContent::UpsertJson)
+                .map_err(|e| e.to_string());
+        }
+        #[cfg(feature = "yaml")]
+        if path.ends_with(".yaml") || path.ends_with(".yml") {
+            return serde_yaml::from_str(&text)
+                .map(ChangeContent::UpsertYaml)
+                .map_err(|e| e.to_string());
+        }
+
+        Ok(ChangeContent::UpsertText(text))
+    }
+}
+
 /// A modification of an individual [`Entry`]
-#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 #[serde(rename_all = "camelCase")]
 pub struct Change {
     /// Path of the file change.
@@ -331,9 +1315,98 @@ pub struct Change {
     pub content: ChangeContent,
 }
 
+impl Change {
+    /// Returns a compact, single line representation of this change intended for logs,
+    /// e.g. `/a.json UPSERT_JSON (9 bytes): {"a":"b"}`.
+    pub fn summary(&self) -> Summary<'_, Self> {
+        Summary(self)
+    }
+
+    /// Returns a change which adds or replaces a text file with the specified binary data,
+    /// encoded as base64. Use [`Entry::binary_content`] to decode it.
+    /// Fails if the data is larger than [`MAX_BINARY_CONTENT_SIZE`].
+    pub fn upsert_binary(path: &str, data: &[u8]) -> Result<Self, Error> {
+        if data.len() > MAX_BINARY_CONTENT_SIZE {
+            return Err(Error::InvalidParams("binary content is too large"));
+        }
+
+        Ok(Change {
+            path: path.to_owned(),
+            content: ChangeContent::UpsertText(BASE64.encode(data)),
+        })
+    }
+
+    /// Returns a change which adds or replaces a YAML file with `value` serialized.
+    #[cfg(feature = "yaml")]
+    pub fn upsert_yaml<T: Serialize + ?Sized>(path: &str, value: &T) -> Result<Self, Error> {
+        let yaml = serde_yaml::to_value(value)
+            .map_err(|_| Error::InvalidParams("value cannot be serialized into YAML"))?;
+
+        Ok(Change {
+            path: path.to_owned(),
+            content: ChangeContent::UpsertYaml(yaml),
+        })
+    }
+}
+
+/// Creates a [`ChangeContent::UpsertJson`] change from a `(path, json)` pair.
+impl From<(String, serde_json::Value)> for Change {
+    fn from((path, json): (String, serde_json::Value)) -> Self {
+        Change {
+            path,
+            content: ChangeContent::UpsertJson(json),
+        }
+    }
+}
+
+/// Creates a [`ChangeContent::UpsertJson`] change from a `(path, json)` pair.
+impl From<(&str, serde_json::Value)> for Change {
+    fn from((path, json): (&str, serde_json::Value)) -> Self {
+        Change::from((path.to_owned(), json))
+    }
+}
+
+/// Creates a [`ChangeContent::UpsertText`] change from a `(path, text)` pair.
+///
+/// The local path is converted into a repository path with `/` separators,
+/// e.g. `foo/bar.txt` becomes `/foo/bar.txt`.
+/// Fails if the path is empty, is not valid UTF-8 or contains `..` or a prefix.
+impl TryFrom<(&Path, &str)> for Change {
+    type Error = Error;
+
+    fn try_from((path, text): (&Path, &str)) -> Result<Self, Self::Error> {
+        let mut repo_path = String::new();
+        for component in path.components() {
+            match component {
+                Component::Normal(c) => {
+                    let c = c
+                        .to_str()
+                        .ok_or(Error::InvalidParams("path is not valid UTF-8"))?;
+                    repo_path.push('/');
+                    repo_path.push_str(c);
+                }
+                Component::RootDir | Component::CurDir => {}
+                Component::ParentDir | Component::Prefix(_) => {
+                    return Err(Error::InvalidParams(
+                        "path cannot contain a prefix or a parent directory",
+                    ));
+                }
+            }
+        }
+        if repo_path.is_empty() {
+            return Err(Error::InvalidParams("path cannot be empty"));
+        }
+
+        Ok(Change {
+            path: repo_path,
+            content: ChangeContent::UpsertText(text.to_owned()),
+        })
+    }
+}
+
 /// A change result from a
 /// [watch_file](trait@crate::WatchService#tymethod.watch_file_stream) operation.
-#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 #[serde(rename_all = "camelCase")]
 pub struct WatchFileResult {
     /// Revision of the change.
@@ -342,15 +1415,253 @@ pub struct WatchFileResult {
     pub entry: Entry,
 }
 
+impl WatchFileResult {
+    /// Path of the watched entry.
+    pub fn path(&self) -> &str {
+        &self.entry.path
+    }
+
+    /// Type of the watched entry.
+    pub fn entry_type(&self) -> EntryType {
+        self.entry.entry_type()
+    }
+
+    /// When the watched entry was last modified.
+    pub fn modified_at(&self) -> Option<&str> {
+        self.entry.modified_at.as_deref()
+    }
+
+    /// Returns `true` if both results have the same path and content,
+    /// regardless of their revisions.
+    /// Useful to drop notifications which did not change the watched value,
+    /// e.g. when a JSON path query result stays the same across commits.
+    pub fn has_same_content(&self, other: &WatchFileResult) -> bool {
+        self.entry.path == other.entry.path && self.entry.content == other.entry.content
+    }
+}
+
 /// A change result from a
 /// [watch_repo](trait@crate::WatchService#tymethod.watch_repo_stream) operation.
-#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 #[serde(rename_all = "camelCase")]
 pub struct WatchRepoResult {
     /// Revision of the change.
     pub revision: Revision,
 }
 
+/// A commit reported by
+/// [watch_repo_changes_stream](crate::RepoClient::watch_repo_changes_stream),
+/// with the changes of the watched files since the previous one.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchRepoChanges {
+    /// Revision of the change.
+    pub revision: Revision,
+    /// Changes of the files matched by the watched path pattern.
+    pub changes: Vec<Change>,
+}
+
+impl WatchRepoChanges {
+    /// Returns the paths of the changed files.
+    pub fn paths(&self) -> impl Iterator<Item = &str> {
+        self.changes.iter().map(|c| c.path.as_str())
+    }
+}
+
+/// Debug representation of redacted content, its length and FNV-1a hash.
+#[cfg(feature = "redact-debug")]
+struct Redacted<'a>(&'a str);
+
+#[cfg(feature = "redact-debug")]
+impl std::fmt::Debug for Redacted<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let hash = self.0.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |h, b| {
+            (h ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+        });
+        write!(f, "<redacted {} bytes, fnv1a {:016x}>", self.0.len(), hash)
+    }
+}
+
+#[cfg(feature = "redact-debug")]
+impl std::fmt::Debug for EntryContent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EntryContent::Json(json) => f
+                .debug_tuple("Json")
+                .field(&Redacted(&json.to_string()))
+                .finish(),
+            EntryContent::Text(text) => f.debug_tuple("Text").field(&Redacted(text)).finish(),
+            #[cfg(feature = "yaml")]
+            EntryContent::Yaml(yaml) => f
+                .debug_tuple("Yaml")
+                .field(&Redacted(&yaml_string(yaml)))
+                .finish(),
+            EntryContent::Directory => f.write_str("Directory"),
+        }
+    }
+}
+
+#[cfg(feature = "redact-debug")]
+impl std::fmt::Debug for ChangeContent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChangeContent::UpsertJson(json) => f
+                .debug_tuple("UpsertJson")
+                .field(&Redacted(&json.to_string()))
+                .finish(),
+            ChangeContent::UpsertText(text) => {
+                f.debug_tuple("UpsertText").field(&Redacted(text)).finish()
+            }
+            #[cfg(feature = "yaml")]
+            ChangeContent::UpsertYaml(yaml) => f
+                .debug_tuple("UpsertYaml")
+                .field(&Redacted(&yaml_string(yaml)))
+                .finish(),
+            ChangeContent::Remove => f.write_str("Remove"),
+            ChangeContent::Rename(to) => f.debug_tuple("Rename").field(to).finish(),
+            ChangeContent::ApplyJsonPatch(patch) => f
+                .debug_tuple("ApplyJsonPatch")
+                .field(&Redacted(&patch.to_string()))
+                .finish(),
+            ChangeContent::ApplyTextPatch(patch) => f
+                .debug_tuple("ApplyTextPatch")
+                .field(&Redacted(patch))
+                .finish(),
+        }
+    }
+}
+
+#[cfg(feature = "redact-debug")]
+impl std::fmt::Debug for EntryContentRef<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EntryContentRef::Json(json) => f.debug_tuple("Json").field(&Redacted(json)).finish(),
+            EntryContentRef::Text(text) => f.debug_tuple("Text").field(&Redacted(text)).finish(),
+            #[cfg(feature = "yaml")]
+            EntryContentRef::Yaml(yaml) => f.debug_tuple("Yaml").field(&Redacted(yaml)).finish(),
+            EntryContentRef::Directory => f.write_str("Directory"),
+        }
+    }
+}
+
+#[cfg(feature = "redact-debug")]
+impl std::fmt::Debug for MergedEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MergedEntry")
+            .field("revision", &self.revision)
+            .field("type", &self.r#type)
+            .field("content", &Redacted(&self.content.to_string()))
+            .field("paths", &self.paths)
+            .finish()
+    }
+}
+
+/// Maximum number of characters of content included in a [`Summary`].
+const SUMMARY_PREVIEW_LEN: usize = 32;
+
+/// A compact, single line [`Display`](std::fmt::Display) of a model intended for logs,
+/// with the content size and a truncated preview instead of the full content.
+/// Created by [`Entry::summary`], [`Change::summary`] and [`Commit::summary`].
+#[derive(Debug, Clone, Copy)]
+pub struct Summary<'a, T>(&'a T);
+
+/// Writes the first characters of `content` on a single line.
+fn write_preview(f: &mut std::fmt::Formatter<'_>, content: &str) -> std::fmt::Result {
+    let mut chars = content.chars();
+    for c in chars.by_ref().take(SUMMARY_PREVIEW_LEN) {
+        if c.is_control() {
+            write!(f, "{}", c.escape_default())?;
+        } else {
+            write!(f, "{}", c)?;
+        }
+    }
+    if chars.next().is_some() {
+        write!(f, "...")?;
+    }
+
+    Ok(())
+}
+
+/// Returns the YAML document of `yaml`, empty if it can't be serialized.
+#[cfg(feature = "yaml")]
+fn yaml_string(yaml: &serde_yaml::Value) -> String {
+    serde_yaml::to_string(yaml).unwrap_or_default()
+}
+
+fn write_content(f: &mut std::fmt::Formatter<'_>, content: &str) -> std::fmt::Result {
+    write!(f, "{} bytes): ", content.len())?;
+    write_preview(f, content)
+}
+
+impl std::fmt::Display for Summary<'_, Entry> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let entry = self.0;
+        let r#type = match entry.entry_type() {
+            EntryType::Json => "JSON",
+            EntryType::Text => "TEXT",
+            #[cfg(feature = "yaml")]
+            EntryType::Yaml => "YAML",
+            EntryType::Directory => "DIRECTORY",
+        };
+        write!(f, "{} ({}, ", entry.path, r#type)?;
+        if entry.revision.as_i64().is_some() {
+            write!(f, "revision {}, ", entry.revision)?;
+        }
+        match &entry.content {
+            EntryContent::Json(json) => write_content(f, &json.to_string()),
+            EntryContent::Text(text) => write_content(f, text),
+            #[cfg(feature = "yaml")]
+            EntryContent::Yaml(yaml) => write_content(f, &yaml_string(yaml)),
+            EntryContent::Directory => write!(f, "0 bytes)"),
+        }
+    }
+}
+
+impl std::fmt::Display for Summary<'_, Change> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let change = self.0;
+        write!(f, "{} ", change.path)?;
+        match &change.content {
+            ChangeContent::UpsertJson(json) => {
+                write!(f, "UPSERT_JSON (")?;
+                write_content(f, &json.to_string())
+            }
+            ChangeContent::UpsertText(text) => {
+                write!(f, "UPSERT_TEXT (")?;
+                write_content(f, text)
+            }
+            #[cfg(feature = "yaml")]
+            ChangeContent::UpsertYaml(yaml) => {
+                write!(f, "UPSERT_YAML (")?;
+                write_content(f, &yaml_string(yaml))
+            }
+            ChangeContent::Remove => write!(f, "REMOVE"),
+            ChangeContent::Rename(to) => write!(f, "RENAME -> {}", to),
+            ChangeContent::ApplyJsonPatch(patch) => {
+                write!(f, "APPLY_JSON_PATCH (")?;
+                write_content(f, &patch.to_string())
+            }
+            ChangeContent::ApplyTextPatch(patch) => {
+                write!(f, "APPLY_TEXT_PATCH (")?;
+                write_content(f, patch)
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for Summary<'_, Commit> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let commit = self.0;
+        write!(
+            f,
+            "revision {} by {} <{}>: ",
+            commit.revision, commit.author.name, commit.author.email
+        )?;
+        let summary = commit.commit_message.summary.lines().next().unwrap_or("");
+        write_preview(f, summary)
+    }
+}
+
 /// A resource that is watchable
 /// Currently supported [`WatchFileResult`] and [`WatchRepoResult`]
 pub(crate) trait Watchable: DeserializeOwned + Send {
@@ -372,6 +1683,337 @@ impl Watchable for WatchRepoResult {
 #[cfg(test)]
 mod test {
     use super::*;
+    use std::collections::HashMap;
+    use wiremock::{
+        matchers::{method, path, query_param},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    #[test]
+    fn test_revision_try_from_i64() {
+        assert_eq!(Revision::try_from_i64(3).unwrap(), Revision::from(3));
+        assert_eq!(Revision::try_from_i64(-1).unwrap(), Revision::HEAD);
+        assert!(Revision::try_from_i64(0).is_err());
+    }
+
+    #[test]
+    fn test_revision_head_minus() {
+        assert_eq!(Revision::head_minus(0), Revision::HEAD);
+        assert_eq!(Revision::head_minus(2), Revision::from(-3));
+    }
+
+    #[test]
+    fn test_revision_from_str() {
+        assert_eq!("head".parse::<Revision>().unwrap(), Revision::HEAD);
+        assert_eq!("HEAD".parse::<Revision>().unwrap(), Revision::HEAD);
+        assert_eq!("-1".parse::<Revision>().unwrap(), Revision::HEAD);
+        assert_eq!(Revision::try_from(" 42 ").unwrap(), Revision::from(42));
+
+        let err = "0".parse::<Revision>().unwrap_err();
+        assert_eq!(err.to_string(), "Invalid params: revision 0 is invalid");
+        assert!(Revision::try_from("").is_err());
+        assert!(Revision::try_from("1.5").is_err());
+        assert!(Revision::try_from("tail").is_err());
+    }
+
+    #[test]
+    fn test_revision_arithmetic() {
+        assert!(Revision::HEAD.is_relative() && !Revision::HEAD.is_absolute());
+        assert!(Revision::INIT.is_absolute() && !Revision::INIT.is_relative());
+        assert!(!Revision::DEFAULT.is_relative() && !Revision::DEFAULT.is_absolute());
+
+        assert_eq!(Revision::HEAD - 10, Revision::from(-11));
+        assert_eq!(Revision::from(-11) + 3, Revision::from(-8));
+        assert_eq!(Revision::from(-3).forward(5), Revision::HEAD);
+        assert_eq!(Revision::from(12) - 10, Revision::from(2));
+        assert_eq!(Revision::from(12).backward(20), Revision::INIT);
+        assert_eq!(Revision::INIT + 4, Revision::from(5));
+        assert_eq!(Revision::DEFAULT - 1, Revision::from(-2));
+        assert_eq!(Revision::DEFAULT - 0, Revision::DEFAULT);
+
+        assert!(Revision::from(2) < Revision::from(5));
+        assert!(Revision::from(-3) < Revision::HEAD);
+        assert_eq!(Revision::from(2).partial_cmp(&Revision::HEAD), None);
+        assert_eq!(Revision::DEFAULT.partial_cmp(&Revision::INIT), None);
+    }
+
+    #[test]
+    fn test_push_result_display() {
+        let result = PushResult {
+            revision: Revision::from(532),
+            pushed_at: Some("2017-05-22T00:00:00Z".to_string()),
+        };
+
+        let display = result.to_string();
+        assert!(display.starts_with("pushed revision 532 at 2017-05-22T00:00:00Z"));
+        #[cfg(feature = "time")]
+        assert!(display.ends_with(" ago)"));
+
+        let result = PushResult {
+            revision: Revision::from(532),
+            pushed_at: None,
+        };
+        assert_eq!(result.to_string(), "pushed revision 532");
+    }
+
+    #[cfg(feature = "time")]
+    #[test]
+    fn test_push_result_age() {
+        let result = PushResult {
+            revision: Revision::from(2),
+            pushed_at: Some("2017-05-22T00:00:00Z".to_string()),
+        };
+        assert_eq!(
+            result.pushed_at_time().unwrap().unix_timestamp(),
+            1_495_411_200
+        );
+        assert!(result.age().unwrap().as_secs() > 0);
+        assert_eq!(format_age(std::time::Duration::from_secs(3)), "3s");
+        assert_eq!(format_age(std::time::Duration::from_secs(3725)), "1h 2m");
+    }
+
+    #[cfg(feature = "time")]
+    #[test]
+    fn test_timestamps() {
+        let creator = Author {
+            name: "minux".to_owned(),
+            email: "minux@m.x".to_owned(),
+        };
+        let repo = Repository {
+            name: "bar".to_owned(),
+            creator: creator.clone(),
+            head_revision: Revision::from(2),
+            url: None,
+            created_at: Some("2017-05-22T09:00:00+09:00".to_owned()),
+        };
+        assert_eq!(
+            repo.created_at_time().unwrap().unix_timestamp(),
+            1_495_411_200
+        );
+        let commit = Commit {
+            revision: Revision::from(2),
+            author: creator,
+            commit_message: CommitMessage::only_summary("Add a.json"),
+            pushed_at: Some("2017-05-22 00:00:00".to_owned()),
+        };
+        assert_eq!(commit.pushed_at_time(), None);
+    }
+
+    #[test]
+    fn test_watch_file_result_has_same_content() {
+        let result = |revision: i64, content: serde_json::Value| WatchFileResult {
+            revision: Revision::from(revision),
+            entry: Entry {
+                path: "/a.json".to_string(),
+                content: EntryContent::Json(content),
+                revision: Revision::from(revision),
+                url: "/api/v1/projects/foo/repos/bar/contents/a.json".to_string(),
+                modified_at: Some("2017-05-22T00:00:00Z".to_string()),
+            },
+        };
+
+        let first = result(2, serde_json::json!({"a": "b"}));
+        let second = result(3, serde_json::json!({"a": "b"}));
+        let third = result(4, serde_json::json!({"a": "c"}));
+
+        assert_ne!(first, second);
+        assert!(first.has_same_content(&second));
+        assert!(!second.has_same_content(&third));
+        assert_eq!(first.clone(), first);
+        assert_eq!(first.path(), "/a.json");
+        assert_eq!(first.entry_type(), EntryType::Json);
+        assert_eq!(first.modified_at(), Some("2017-05-22T00:00:00Z"));
+    }
+
+    #[test]
+    fn test_binary_content() {
+        let data = [0u8, 159, 146, 150, 255];
+        let change = Change::upsert_binary("/keystore.jks", &data).unwrap();
+        let text = match change.content {
+            ChangeContent::UpsertText(t) => t,
+            _ => panic!("binary content must be stored as text"),
+        };
+
+        let entry = Entry {
+            path: "/keystore.jks".to_string(),
+            content: EntryContent::Text(format!("{}\n", text)),
+            revision: Revision::from(2),
+            url: "/api/v1/projects/foo/repos/bar/contents/keystore.jks".to_string(),
+            modified_at: None,
+        };
+        assert_eq!(entry.binary_content().unwrap(), data);
+
+        let too_large = vec![0u8; MAX_BINARY_CONTENT_SIZE + 1];
+        assert!(Change::upsert_binary("/a.bin", &too_large).is_err());
+
+        let not_base64 = Entry {
+            content: EntryContent::Text("not base64!".to_string()),
+            ..entry
+        };
+        assert!(not_base64.binary_content().is_err());
+    }
+
+    #[test]
+    fn test_commit_detail() {
+        let detail = CommitDetail::markdown("**bold**");
+        assert_eq!(detail, CommitDetail::Markdown("**bold**".to_string()));
+        assert_eq!(detail.text(), "**bold**");
+        assert_eq!(CommitDetail::plaintext("plain").text(), "plain");
+
+        let cm = CommitMessage::with_detail("Add a.json", detail.clone());
+        assert_eq!(cm.detail, Some(detail));
+    }
+
+    #[cfg(feature = "markdown")]
+    #[test]
+    fn test_commit_detail_render_plaintext() {
+        let detail = CommitDetail::markdown(
+            "# Title\n\nSome **bold** and `code`, see [docs](https://example.com).\n\n* a\n* b",
+        );
+        assert_eq!(
+            detail.render_plaintext(),
+            "Title\nSome bold and code, see docs (https://example.com).\n- a\n- b"
+        );
+
+        let detail = CommitDetail::plaintext("**as is**");
+        assert_eq!(detail.render_plaintext(), "**as is**");
+    }
+
+    #[test]
+    fn test_summary() {
+        let entry = Entry {
+            path: "/a.json".to_string(),
+            content: EntryContent::Json(serde_json::json!({"a": "b"})),
+            revision: Revision::from(3),
+            url: "/api/v1/projects/foo/repos/bar/contents/a.json".to_string(),
+            modified_at: None,
+        };
+        assert_eq!(
+            entry.summary().to_string(),
+            r#"/a.json (JSON, revision 3, 9 bytes): {"a":"b"}"#
+        );
+
+        let change = Change {
+            path: "/b.txt".to_string(),
+            content: ChangeContent::UpsertText(format!("line\n{}", "x".repeat(100))),
+        };
+        assert_eq!(
+            change.summary().to_string(),
+            format!(
+                "/b.txt UPSERT_TEXT (105 bytes): line\\n{}...",
+                "x".repeat(27)
+            )
+        );
+
+        let change = Change {
+            path: "/b.txt".to_string(),
+            content: ChangeContent::Rename("/c.txt".to_string()),
+        };
+        assert_eq!(change.summary().to_string(), "/b.txt RENAME -> /c.txt");
+
+        let commit = Commit {
+            revision: Revision::from(3),
+            author: Author {
+                name: "minux".to_string(),
+                email: "minux@m.x".to_string(),
+            },
+            commit_message: CommitMessage::only_summary("Add a.json"),
+            pushed_at: None,
+        };
+        assert_eq!(
+            commit.summary().to_string(),
+            "revision 3 by minux <minux@m.x>: Add a.json"
+        );
+    }
+
+    #[cfg(feature = "redact-debug")]
+    #[test]
+    fn test_redacted_debug() {
+        let change = Change {
+            path: "/secret.json".to_string(),
+            content: ChangeContent::UpsertJson(serde_json::json!({"password": "hunter2"})),
+        };
+        let debug = format!("{:?}", change);
+
+        assert!(!debug.contains("hunter2"), "{}", debug);
+        assert!(debug.contains("/secret.json"), "{}", debug);
+        assert!(
+            debug.contains("UpsertJson(<redacted 22 bytes, fnv1a "),
+            "{}",
+            debug
+        );
+        assert_eq!(
+            format!("{:?}", Redacted("")),
+            "<redacted 0 bytes, fnv1a cbf29ce484222325>"
+        );
+
+        let content = EntryContentRef::Text(Cow::Borrowed("hunter2"));
+        assert!(!format!("{:?}", content).contains("hunter2"));
+        let merged = MergedEntry {
+            revision: Revision::from(2),
+            r#type: EntryType::Json,
+            content: serde_json::json!({"password": "hunter2"}),
+            paths: vec!["/secret.json".to_owned()],
+        };
+        let debug = format!("{:?}", merged);
+        assert!(!debug.contains("hunter2"), "{}", debug);
+        assert!(debug.contains("/secret.json"), "{}", debug);
+    }
+
+    #[test]
+    fn test_entry_ref_borrows_content() {
+        let body = br#"[{
+            "path":"/a.json",
+            "type":"JSON",
+            "revision":2,
+            "url":"/api/v1/projects/foo/repos/bar/contents/a.json",
+            "content":{"a":"b"}
+        }, {
+            "path":"/b.txt",
+            "type":"TEXT",
+            "revision":2,
+            "url":"/api/v1/projects/foo/repos/bar/contents/b.txt",
+            "modifiedAt":"2022-01-01T00:00:00Z",
+            "content":"hello"
+        }, {
+            "path":"/c.txt",
+            "type":"TEXT",
+            "revision":2,
+            "url":"/api/v1/projects/foo/repos/bar/contents/c.txt",
+            "content":"hello\nworld"
+        }, {
+            "path":"/d",
+            "type":"DIRECTORY",
+            "revision":2,
+            "url":"/api/v1/projects/foo/repos/bar/contents/d"
+        }]"#;
+        let raw = RawEntries::new(bytes::Bytes::from_static(body));
+        let entries = raw.entries().unwrap();
+
+        assert_eq!(entries.len(), 4);
+        assert!(matches!(entries[0].path, Cow::Borrowed("/a.json")));
+        assert_eq!(entries[0].json_str(), Some(r#"{"a":"b"}"#));
+        let json: HashMap<&str, &str> = entries[0].json().unwrap();
+        assert_eq!(json["a"], "b");
+
+        assert!(matches!(
+            entries[1].content,
+            EntryContentRef::Text(Cow::Borrowed("hello"))
+        ));
+        assert!(matches!(
+            entries[1].modified_at,
+            Some(Cow::Borrowed("2022-01-01T00:00:00Z"))
+        ));
+        assert_eq!(entries[2].text(), Some("hello\nworld"));
+        assert_eq!(entries[3].entry_type(), EntryType::Directory);
+
+        let entry = entries[0].to_entry().unwrap();
+        assert_eq!(
+            entry.content,
+            EntryContent::Json(serde_json::json!({"a": "b"}))
+        );
+        assert_eq!(entry.revision, Revision::from(2));
+    }
 
     #[test]
     fn test_query_identity() {
@@ -395,4 +2037,239 @@ mod test {
 
         assert!(query.is_none());
     }
+
+    #[test]
+    fn test_query_of_json_path_exprs() {
+        let exprs = vec![
+            JsonPath::root().field("a").index(0),
+            JsonPath::parse("$.b").unwrap(),
+        ];
+        let query = Query::of_json_path_exprs("a.json", exprs).unwrap();
+
+        assert_eq!(query.path, "/a.json");
+        assert_eq!(
+            query.r#type,
+            QueryType::JsonPath(vec!["$.a[0]".to_string(), "$.b".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_change_from_json_tuple() {
+        let changes: Vec<Change> = vec![
+            ("/a.json", serde_json::json!({"a": "b"})),
+            ("/b.json", serde_json::json!([1, 2])),
+        ]
+        .into_iter()
+        .map(Change::from)
+        .collect();
+
+        assert_eq!(
+            changes,
+            vec![
+                Change {
+                    path: "/a.json".to_string(),
+                    content: ChangeContent::UpsertJson(serde_json::json!({"a": "b"})),
+                },
+                Change {
+                    path: "/b.json".to_string(),
+                    content: ChangeContent::UpsertJson(serde_json::json!([1, 2])),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_change_try_from_path() {
+        let change = Change::try_from((Path::new("foo/bar.txt"), "hello")).unwrap();
+
+        assert_eq!(change.path, "/foo/bar.txt");
+        assert_eq!(
+            change.content,
+            ChangeContent::UpsertText("hello".to_string())
+        );
+
+        let change = Change::try_from((Path::new("/./foo.txt"), "hello")).unwrap();
+        assert_eq!(change.path, "/foo.txt");
+
+        assert!(Change::try_from((Path::new("../foo.txt"), "hello")).is_err());
+        assert!(Change::try_from((Path::new(""), "hello")).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_commit_changes() {
+        let server = MockServer::start().await;
+        let resp = ResponseTemplate::new(200).set_body_raw(
+            r#"[{
+                "path":"/a.json",
+                "type":"UPSERT_JSON",
+                "content":{"a":"b"}
+            }]"#,
+            "application/json",
+        );
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/compare"))
+            .and(query_param("from", "2"))
+            .and(query_param("to", "3"))
+            .and(query_param("pathPattern", "/**"))
+            .respond_with(resp)
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = crate::Client::new(&server.uri(), None).await.unwrap();
+        let commit = Commit {
+            revision: Revision::from(3),
+            author: Author {
+                name: "minux".to_string(),
+                email: "minux@m.x".to_string(),
+            },
+            commit_message: CommitMessage::only_summary("Edit a.json"),
+            pushed_at: None,
+        };
+        let paths = commit
+            .changed_paths(&client.repo("foo", "bar"))
+            .await
+            .unwrap();
+
+        drop(server);
+        assert_eq!(paths, vec!["/a.json".to_string()]);
+
+        let initial = Commit {
+            revision: Revision::INIT,
+            ..commit
+        };
+        let client = crate::Client::new("http://localhost:1", None)
+            .await
+            .unwrap();
+        let changes = initial.changes(&client.repo("foo", "bar")).await.unwrap();
+        assert!(changes.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_list_entry_fetch() {
+        let server = MockServer::start().await;
+        let resp = ResponseTemplate::new(200).set_body_raw(
+            r#"{
+                "path":"/b.txt",
+                "type":"TEXT",
+                "revision":2,
+                "url": "/api/v1/projects/foo/repos/bar/contents/b.txt",
+                "content":"hello world~!"
+            }"#,
+            "application/json",
+        );
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/contents/b.txt"))
+            .and(query_param("revision", "2"))
+            .respond_with(resp)
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = crate::Client::new(&server.uri(), None).await.unwrap();
+        let list_entry = ListEntry {
+            path: "/b.txt".to_string(),
+            r#type: EntryType::Text,
+        };
+        let entry = list_entry
+            .fetch(&client.repo("foo", "bar"), Revision::from(2))
+            .await
+            .unwrap();
+
+        drop(server);
+        assert_eq!(entry.path, "/b.txt");
+        assert_eq!(
+            entry.content,
+            EntryContent::Text("hello world~!".to_string())
+        );
+    }
+
+    #[test]
+    fn test_directory_from_entries() {
+        let entry = |path: &str, content: EntryContent| Entry {
+            path: path.to_string(),
+            content,
+            revision: Revision::from(2),
+            url: format!("/api/v1/projects/foo/repos/bar/contents{}", path),
+            modified_at: None,
+        };
+        let entries = vec![
+            entry("/a.json", EntryContent::Json(serde_json::json!({"a": "b"}))),
+            entry("/foo", EntryContent::Directory),
+            entry("/foo/b.txt", EntryContent::Text("b".to_string())),
+            entry("/foo/bar/c.txt", EntryContent::Text("c".to_string())),
+        ];
+
+        let root = Directory::from_entries(entries);
+
+        assert_eq!(root.path, "/");
+        assert_eq!(root.files.len(), 1);
+        assert_eq!(root.files[0].path, "/a.json");
+        assert_eq!(root.directories.len(), 1);
+
+        let foo = root.directory("/foo").unwrap();
+        assert_eq!(foo.files.len(), 1);
+        assert_eq!(foo.files[0].path, "/foo/b.txt");
+
+        let bar = root.directory("/foo/bar").unwrap();
+        assert_eq!(bar.files[0].path, "/foo/bar/c.txt");
+        assert!(bar.directories.is_empty());
+
+        let all: Vec<&str> = root.all_files().iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(all, vec!["/a.json", "/foo/b.txt", "/foo/bar/c.txt"]);
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn test_yaml_entry() {
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Config {
+            name: String,
+            replicas: u32,
+        }
+
+        let entry: Entry = serde_json::from_str(
+            r#"{
+                "path":"/a.yaml",
+                "type":"YAML",
+                "content":{"name":"foo","replicas":3},
+                "revision":2,
+                "url":"/api/v1/projects/foo/repos/bar/contents/a.yaml"
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(entry.entry_type(), EntryType::Yaml);
+        assert_eq!(
+            entry.content.to_yaml().unwrap(),
+            serde_yaml::from_str::<serde_yaml::Value>("name: foo\nreplicas: 3").unwrap()
+        );
+        let typed = entry.into_typed::<Config>().unwrap();
+        assert_eq!(
+            typed.value,
+            Config {
+                name: "foo".to_owned(),
+                replicas: 3
+            }
+        );
+
+        let text = EntryContent::Text("a: [1, 2]".to_owned());
+        assert_eq!(
+            serde_json::to_value(text.to_yaml().unwrap()).unwrap(),
+            serde_json::json!({"a": [1, 2]})
+        );
+
+        let change = Change::upsert_yaml("/a.yaml", &serde_json::json!({"a": 1})).unwrap();
+        assert_eq!(
+            serde_json::to_value(&change).unwrap(),
+            serde_json::json!({"path": "/a.yaml", "type": "UPSERT_YAML", "content": {"a": 1}})
+        );
+        assert_eq!(
+            ChangeContent::upsert_of("/b.yml", "b: 2".to_owned()).unwrap(),
+            ChangeContent::UpsertYaml(serde_yaml::from_str("b: 2").unwrap())
+        );
+        assert_eq!(
+            Query::of_yaml("a.yaml").unwrap().r#type,
+            QueryType::IdentityYaml
+        );
+    }
 }
@@ -0,0 +1,310 @@
+//! OpenTelemetry spans and metrics of the requests of a [`Client`](crate::Client),
+//! enabled by the `otel` feature.
+//!
+//! Every request is a client span, named after its method and the route template of
+//! its path, with the [HTTP semantic attributes] and these ones when they apply:
+//!
+//! | Attribute                 | Value                             |
+//! |---------------------------|-----------------------------------|
+//! | `centraldogma.project`    | Name of the project               |
+//! | `centraldogma.repository` | Name of the repository            |
+//! | `centraldogma.path`       | Path of the file, or path pattern |
+//!
+//! The same attributes are recorded on these metrics:
+//!
+//! | Metric                                 | Instrument      | Unit |
+//! |----------------------------------------|-----------------|------|
+//! | `centraldogma.client.request.duration` | Histogram       | s    |
+//! | `centraldogma.client.pushes`           | Counter         |      |
+//! | `centraldogma.watch.staleness`         | Histogram       | s    |
+//!
+//! The staleness of a watch is recorded whenever it fails to reach the server, as the
+//! time since its last successful request, i.e. for how long changes may have been missed.
+//!
+//! The requests carry the context of their span in the headers of the global text map
+//! propagator, e.g. `traceparent` once [`TraceContextPropagator`] is installed with
+//! [`set_text_map_propagator`](opentelemetry::global::set_text_map_propagator), so that
+//! the traces of the server continue the ones of the client.
+//!
+//! By default, the spans and the metrics go to the global providers of the
+//! `opentelemetry` crate, which must be installed before creating the client.
+//! [`Client::with_opentelemetry`](crate::Client::with_opentelemetry) sends them to
+//! other providers.
+//!
+//! [HTTP semantic attributes]: https://opentelemetry.io/docs/specs/semconv/http/http-spans/
+//! [`TraceContextPropagator`]: https://docs.rs/opentelemetry_sdk/latest/opentelemetry_sdk/propagation/struct.TraceContextPropagator.html
+use std::time::{Duration, Instant};
+
+use opentelemetry::{
+    global::{self, BoxedSpan, BoxedTracer},
+    metrics::{Counter, Histogram, Meter, MeterProvider},
+    propagation::Injector,
+    trace::{Span, SpanKind, Status, TraceContextExt, Tracer, TracerProvider},
+    Context, KeyValue,
+};
+use reqwest::{
+    header::{HeaderMap, HeaderName, HeaderValue},
+    Method,
+};
+
+use crate::{services::path::parse_route, Error};
+
+const NAME: &str = "centraldogma";
+
+/// Instruments of a client.
+pub(crate) struct Telemetry {
+    tracer: BoxedTracer,
+    request_duration: Histogram<f64>,
+    pushes: Counter<u64>,
+    watch_staleness: Histogram<f64>,
+}
+
+impl Telemetry {
+    /// Returns instruments of the global providers.
+    pub(crate) fn global() -> Self {
+        Self::new(&global::meter(NAME), global::tracer(NAME))
+    }
+
+    pub(crate) fn with_providers<M, T>(meter_provider: &M, tracer_provider: &T) -> Self
+    where
+        M: MeterProvider,
+        T: TracerProvider,
+        T::Tracer: Send + Sync + 'static,
+        <T::Tracer as Tracer>::Span: Send + Sync + 'static,
+    {
+        let tracer = BoxedTracer::new(Box::new(tracer_provider.tracer(NAME)));
+        Self::new(&meter_provider.meter(NAME), tracer)
+    }
+
+    fn new(meter: &Meter, tracer: BoxedTracer) -> Self {
+        Telemetry {
+            tracer,
+            request_duration: meter
+                .f64_histogram("centraldogma.client.request.duration")
+                .with_description("Duration of the requests to Central Dogma")
+                .with_unit("s")
+                .build(),
+            pushes: meter
+                .u64_counter("centraldogma.client.pushes")
+                .with_description("Commits pushed to Central Dogma")
+                .build(),
+            watch_staleness: meter
+                .f64_histogram("centraldogma.watch.staleness")
+                .with_description("Time since the last successful request of a failing watch")
+                .with_unit("s")
+                .build(),
+        }
+    }
+
+    /// Starts the span of a request.
+    pub(crate) fn start_request(&self, method: &Method, path: &str) -> RequestTelemetry {
+        let route = parse_route(path);
+        let mut attributes = vec![KeyValue::new("http.request.method", method.to_string())];
+        attributes.extend(attributes_of(path));
+
+        let span = self
+            .tracer
+            .span_builder(format!("{} {}", method, route.template))
+            .with_kind(SpanKind::Client)
+            .with_attributes(
+                attributes
+                    .iter()
+                    .cloned()
+                    .chain([KeyValue::new("url.path", path.to_owned())]),
+            )
+            .start(&self.tracer);
+        let is_push = method == Method::POST && route.template.ends_with("/contents");
+
+        RequestTelemetry {
+            span,
+            attributes,
+            is_push,
+            start: Instant::now(),
+        }
+    }
+
+    /// Records the staleness of the watch of `path`.
+    pub(crate) fn record_watch_staleness(&self, path: &str, staleness: Duration) {
+        self.watch_staleness
+            .record(staleness.as_secs_f64(), &attributes_of(path));
+    }
+}
+
+fn attributes_of(path: &str) -> Vec<KeyValue> {
+    let route = parse_route(path);
+    let mut attributes = vec![KeyValue::new("http.route", route.template)];
+    let names = [
+        ("centraldogma.project", route.project),
+        ("centraldogma.repository", route.repo),
+        ("centraldogma.path", route.path),
+    ];
+    for (key, value) in names {
+        if let Some(value) = value {
+            attributes.push(KeyValue::new(key, value.to_owned()));
+        }
+    }
+
+    attributes
+}
+
+/// A request in flight.
+pub(crate) struct RequestTelemetry {
+    span: BoxedSpan,
+    attributes: Vec<KeyValue>,
+    is_push: bool,
+    start: Instant,
+}
+
+impl RequestTelemetry {
+    /// Adds the context of the span to the headers of `req` with the global propagator.
+    pub(crate) fn inject_context(&self, req: &mut reqwest::Request) {
+        let context = Context::current().with_remote_span_context(self.span.span_context().clone());
+        global::get_text_map_propagator(|propagator| {
+            propagator.inject_context(&context, &mut HeaderInjector(req.headers_mut()));
+        });
+    }
+
+    /// Ends the span with the status of the response, if any, and the result.
+    pub(crate) fn end<T>(
+        mut self,
+        telemetry: &Telemetry,
+        status: Option<u16>,
+        result: &Result<T, Error>,
+    ) {
+        if let Some(status) = status {
+            let status = KeyValue::new("http.response.status_code", i64::from(status));
+            self.span.set_attribute(status.clone());
+            self.attributes.push(status);
+        }
+        if let Err(e) = result {
+            let error_type = KeyValue::new("error.type", e.code().as_str());
+            self.span.set_attribute(error_type.clone());
+            self.span.set_status(Status::error(e.to_string()));
+            self.attributes.push(error_type);
+        } else if self.is_push {
+            telemetry.pushes.add(1, &self.attributes);
+        }
+
+        telemetry
+            .request_duration
+            .record(self.start.elapsed().as_secs_f64(), &self.attributes);
+        self.span.end();
+    }
+}
+
+struct HeaderInjector<'a>(&'a mut HeaderMap);
+
+impl Injector for HeaderInjector<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(key.as_bytes()),
+            HeaderValue::from_str(&value),
+        ) {
+            self.0.insert(name, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use opentelemetry::Value;
+    use opentelemetry_sdk::{
+        metrics::{
+            data::{AggregatedMetrics, MetricData},
+            InMemoryMetricExporter, PeriodicReader, SdkMeterProvider,
+        },
+        propagation::TraceContextPropagator,
+        trace::{InMemorySpanExporter, SdkTracerProvider},
+    };
+    use wiremock::{
+        matchers::{header_exists, method},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use crate::{
+        model::{Change, CommitMessage, Revision},
+        Client, ContentService,
+    };
+
+    #[tokio::test]
+    async fn test_request_telemetry() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(header_exists("traceparent"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_raw(r#"{"revision":2,"pushedAt":"a"}"#, "application/json"),
+            )
+            .mount(&server)
+            .await;
+
+        opentelemetry::global::set_text_map_propagator(TraceContextPropagator::new());
+        let spans = InMemorySpanExporter::default();
+        let tracer_provider = SdkTracerProvider::builder()
+            .with_simple_exporter(spans.clone())
+            .build();
+        let metrics = InMemoryMetricExporter::default();
+        let meter_provider = SdkMeterProvider::builder()
+            .with_reader(PeriodicReader::builder(metrics.clone()).build())
+            .build();
+
+        let client = Client::new(&server.uri(), None)
+            .await
+            .unwrap()
+            .with_opentelemetry(&meter_provider, &tracer_provider);
+        client
+            .repo("foo", "bar")
+            .push(
+                Revision::HEAD,
+                CommitMessage::only_summary("Add a.json"),
+                vec![Change::from(("/a.json", serde_json::json!({})))],
+            )
+            .await
+            .unwrap();
+
+        let spans = spans.get_finished_spans().unwrap();
+        assert_eq!(spans.len(), 1);
+        let span = &spans[0];
+        assert_eq!(
+            span.name,
+            "POST /api/v1/projects/{project}/repos/{repo}/contents"
+        );
+        let attribute = |key: &str| {
+            span.attributes
+                .iter()
+                .find(|kv| kv.key.as_str() == key)
+                .map(|kv| kv.value.clone())
+        };
+        assert_eq!(attribute("centraldogma.project"), Some(Value::from("foo")));
+        assert_eq!(
+            attribute("centraldogma.repository"),
+            Some(Value::from("bar"))
+        );
+        assert_eq!(
+            attribute("http.response.status_code"),
+            Some(Value::I64(200))
+        );
+
+        meter_provider.force_flush().unwrap();
+        let metrics = metrics.get_finished_metrics().unwrap();
+        let recorded: Vec<_> = metrics
+            .iter()
+            .flat_map(|m| m.scope_metrics())
+            .flat_map(|s| s.metrics())
+            .collect();
+        let pushes = recorded
+            .iter()
+            .find(|m| m.name() == "centraldogma.client.pushes")
+            .map(|m| m.data())
+            .unwrap();
+        match pushes {
+            AggregatedMetrics::U64(MetricData::Sum(sum)) => {
+                assert_eq!(sum.data_points().map(|p| p.value()).sum::<u64>(), 1);
+            }
+            _ => panic!("unexpected data of pushes"),
+        }
+        assert!(recorded
+            .iter()
+            .any(|m| m.name() == "centraldogma.client.request.duration"));
+    }
+}
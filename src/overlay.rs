@@ -0,0 +1,387 @@
+//! Layered JSON files merged into one document,
+//! e.g. a base configuration overridden per environment and per region.
+//!
+//! ```no_run
+//! use centraldogma::{overlay::Overlay, Client};
+//! use futures::StreamExt;
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), centraldogma::Error> {
+//! let client = Client::new("http://localhost:36462", None).await?;
+//! let overlay = Overlay::new(client.for_repo("foo", "bar"))
+//!     .layer("/base.json")
+//!     .layer("/env/prod.json")
+//!     .optional_layer("/region/kr.json");
+//!
+//! let merged = overlay.load().await?;
+//!
+//! let mut updates = overlay.watch().await?;
+//! while let Some(merged) = updates.next().await {
+//!     println!("{}", merged);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use std::pin::Pin;
+
+use futures::{Stream, StreamExt};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::{
+    model::{Entry, EntryContent, Query, Revision},
+    services::watch::watch_files_stream_since,
+    CentralDogmaRepository, ContentService, Error,
+};
+
+/// How an array of a layer is merged with the value of the layers below.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ArrayMerge {
+    /// The array replaces the value below.
+    #[default]
+    Replace,
+    /// The elements are appended to the array below.
+    Append,
+    /// The elements are merged with the elements at the same index of the array below.
+    ByIndex,
+}
+
+/// Merges `overlay` into `base`.
+///
+/// The fields of objects are merged recursively, arrays are merged according to `arrays`,
+/// and any other value of `overlay`, including `null`, replaces the value of `base`.
+pub fn deep_merge(base: &mut Value, overlay: Value, arrays: ArrayMerge) {
+    match (base, overlay) {
+        (Value::Object(base), Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) => deep_merge(existing, value, arrays),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (Value::Array(base), Value::Array(overlay)) if arrays != ArrayMerge::Replace => {
+            if arrays == ArrayMerge::Append {
+                base.extend(overlay);
+                return;
+            }
+            for (i, value) in overlay.into_iter().enumerate() {
+                match base.get_mut(i) {
+                    Some(existing) => deep_merge(existing, value, arrays),
+                    None => base.push(value),
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+struct Layer {
+    query: Query,
+    optional: bool,
+}
+
+/// An ordered list of JSON files of a repository, each one overriding the ones before.
+pub struct Overlay {
+    repo: CentralDogmaRepository,
+    layers: Vec<Layer>,
+    arrays: ArrayMerge,
+}
+
+impl Overlay {
+    /// Returns an overlay of the files of `repo`, without any layer.
+    pub fn new(repo: CentralDogmaRepository) -> Self {
+        Overlay {
+            repo,
+            layers: Vec::new(),
+            arrays: ArrayMerge::default(),
+        }
+    }
+
+    /// Adds the file at `path` on top of the previous layers.
+    ///
+    /// Loading fails with [`Error::EntryNotFound`] if the file doesn't exist.
+    pub fn layer(self, path: &str) -> Self {
+        self.push_layer(path, false)
+    }
+
+    /// Adds the file at `path` on top of the previous layers, skipped if it doesn't exist.
+    pub fn optional_layer(self, path: &str) -> Self {
+        self.push_layer(path, true)
+    }
+
+    fn push_layer(mut self, path: &str, optional: bool) -> Self {
+        if let Some(query) = Query::identity(path) {
+            self.layers.push(Layer { query, optional });
+        }
+        self
+    }
+
+    /// Sets how the arrays are merged, [`ArrayMerge::Replace`] by default.
+    pub fn array_merge(mut self, arrays: ArrayMerge) -> Self {
+        self.arrays = arrays;
+        self
+    }
+
+    /// Retrieves the layers and merges them.
+    pub async fn load(&self) -> Result<Value, Error> {
+        let entries = self.fetch().await?;
+
+        merge(&entries, self.arrays)
+    }
+
+    /// Retrieves the layers and merges them, with the latest revision of the layers.
+    pub(crate) async fn load_with_revision(&self) -> Result<(Value, Revision), Error> {
+        let entries = self.fetch().await?;
+        let revision = entries
+            .iter()
+            .flatten()
+            .map(|e| e.revision)
+            .max_by_key(|r| r.as_i64())
+            .unwrap_or(Revision::HEAD);
+
+        Ok((merge(&entries, self.arrays)?, revision))
+    }
+
+    /// Retrieves the layers and deserializes the merged document into `T`.
+    ///
+    /// Fails with [`Error::InvalidConfig`] if the merged document fails to deserialize.
+    pub async fn load_as<T: DeserializeOwned>(&self) -> Result<T, Error> {
+        let merged = self.load().await?;
+
+        serde_json::from_value(merged).map_err(|e| Error::InvalidConfig {
+            path: self.paths(),
+            message: e.to_string(),
+        })
+    }
+
+    /// Returns a stream which outputs the merged document, first the current one,
+    /// then whenever a layer changes.
+    ///
+    /// The layers are watched with a single watch of the repository.
+    /// A change which can't be merged, e.g. a layer which is no longer JSON, is skipped.
+    pub async fn watch(&self) -> Result<Pin<Box<dyn Stream<Item = Value> + Send>>, Error> {
+        if self.layers.is_empty() {
+            return Err(Error::InvalidParams("no layers to watch"));
+        }
+        let entries = self.fetch().await?;
+        let initial = merge(&entries, self.arrays)?;
+
+        // Watches from the oldest layer read, so no change after a read is missed
+        let since = entries
+            .iter()
+            .flatten()
+            .map(|e| e.revision)
+            .min_by_key(|r| r.as_i64());
+        let known = self
+            .layers
+            .iter()
+            .zip(&entries)
+            .filter_map(|(layer, entry)| Some((layer.query.path.clone(), entry.clone()?)))
+            .collect();
+        let queries = self.layers.iter().map(|l| l.query.clone()).collect();
+        let changes = watch_files_stream_since(&self.repo.client(), queries, since, known);
+
+        let paths: Vec<_> = self.layers.iter().map(|l| l.query.path.clone()).collect();
+        let arrays = self.arrays;
+        let updates = changes
+            .scan(entries, move |entries, (path, result)| {
+                for (i, layer) in paths.iter().enumerate() {
+                    if *layer == path {
+                        entries[i] = Some(result.entry.clone());
+                    }
+                }
+                futures::future::ready(Some(merge(entries, arrays)))
+            })
+            .filter_map(|merged| async move {
+                match merged {
+                    Ok(merged) => Some(merged),
+                    Err(e) => {
+                        log::warn!("Skipping a change of the overlay: {}", e);
+                        None
+                    }
+                }
+            });
+
+        Ok(futures::stream::once(async move { initial })
+            .chain(updates)
+            .boxed())
+    }
+
+    /// Retrieves the layers, `None` for the missing optional ones.
+    async fn fetch(&self) -> Result<Vec<Option<Entry>>, Error> {
+        let repo = self.repo.client();
+        let fetches = self.layers.iter().map(|layer| {
+            let repo = &repo;
+            async move {
+                match repo.get_file(Revision::HEAD, &layer.query).await {
+                    Ok(entry) => Ok(Some(entry)),
+                    Err(Error::EntryNotFound(_)) if layer.optional => Ok(None),
+                    Err(e) => Err(e),
+                }
+            }
+        });
+
+        futures::future::try_join_all(fetches).await
+    }
+
+    fn paths(&self) -> String {
+        let paths: Vec<_> = self.layers.iter().map(|l| l.query.path.as_str()).collect();
+        paths.join(", ")
+    }
+}
+
+/// Merges the layers present in `entries`, bottom first.
+fn merge(entries: &[Option<Entry>], arrays: ArrayMerge) -> Result<Value, Error> {
+    let mut merged = Value::Object(Default::default());
+    for entry in entries.iter().flatten() {
+        deep_merge(&mut merged, json_of(entry)?, arrays);
+    }
+
+    Ok(merged)
+}
+
+/// Returns the JSON content of a file, parsing a text file as JSON.
+pub(crate) fn json_of(entry: &Entry) -> Result<Value, Error> {
+    let invalid = |message: String| Error::InvalidConfig {
+        path: entry.path.clone(),
+        message,
+    };
+
+    match &entry.content {
+        EntryContent::Json(json) => Ok(json.clone()),
+        EntryContent::Text(text) => serde_json::from_str(text).map_err(|e| invalid(e.to_string())),
+        #[cfg(feature = "yaml")]
+        EntryContent::Yaml(yaml) => serde_json::to_value(yaml).map_err(|e| invalid(e.to_string())),
+        EntryContent::Directory => Err(invalid("not a file".to_owned())),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde::Deserialize;
+    use serde_json::json;
+    use wiremock::{
+        matchers::{header, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use std::time::Duration;
+
+    use super::*;
+    use crate::Client;
+
+    #[test]
+    fn test_deep_merge() {
+        let base = json!({"a": {"b": 1, "c": [1, {"d": 1}]}, "e": "x"});
+        let overlay = json!({"a": {"c": [2, {"f": 2}, 3]}, "e": null});
+
+        let mut replaced = base.clone();
+        deep_merge(&mut replaced, overlay.clone(), ArrayMerge::Replace);
+        assert_eq!(
+            replaced,
+            json!({"a": {"b": 1, "c": [2, {"f": 2}, 3]}, "e": null})
+        );
+
+        let mut appended = base.clone();
+        deep_merge(&mut appended, overlay.clone(), ArrayMerge::Append);
+        assert_eq!(
+            appended,
+            json!({"a": {"b": 1, "c": [1, {"d": 1}, 2, {"f": 2}, 3]}, "e": null})
+        );
+
+        let mut by_index = base;
+        deep_merge(&mut by_index, overlay, ArrayMerge::ByIndex);
+        assert_eq!(
+            by_index,
+            json!({"a": {"b": 1, "c": [2, {"d": 1, "f": 2}, 3]}, "e": null})
+        );
+    }
+
+    fn entry(path: &str, revision: i64, content: &str) -> String {
+        format!(
+            r#"{{"path":"{}","type":"JSON","content":{},"revision":{},"url":"{}"}}"#,
+            path, content, revision, path
+        )
+    }
+
+    async fn mount_file(server: &MockServer, file: &str, at: &str, revision: i64, content: &str) {
+        Mock::given(method("GET"))
+            .and(path(format!(
+                "/api/v1/projects/foo/repos/bar/contents{}",
+                file
+            )))
+            .and(wiremock::matchers::query_param("revision", at))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_raw(entry(file, revision, content), "application/json"),
+            )
+            .mount(server)
+            .await;
+    }
+
+    #[tokio::test]
+    async fn test_overlay() {
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Settings {
+            timeout: i64,
+            region: String,
+        }
+
+        let server = MockServer::start().await;
+        let base = r#"{"timeout":1,"region":"none"}"#;
+        mount_file(&server, "/base.json", "-1", 2, base).await;
+        mount_file(&server, "/base.json", "3", 3, base).await;
+        mount_file(&server, "/env/prod.json", "-1", 2, r#"{"timeout":5}"#).await;
+        mount_file(&server, "/env/prod.json", "3", 3, r#"{"timeout":7}"#).await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/contents/region/kr.json"))
+            .respond_with(ResponseTemplate::new(404).set_body_raw(
+                r#"{"exception":"com.linecorp.centraldogma.common.EntryNotFoundException","message":"not found"}"#,
+                "application/json",
+            ))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path(
+                "/api/v1/projects/foo/repos/bar/contents/base.json,/env/prod.json,/region/kr.json",
+            ))
+            .and(header("if-none-match", "2"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"revision": 3})))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(304).set_delay(Duration::from_millis(100)))
+            .mount(&server)
+            .await;
+
+        let client = Client::new(&server.uri(), None).await.unwrap();
+        let overlay = Overlay::new(client.for_repo("foo", "bar"))
+            .layer("/base.json")
+            .layer("/env/prod.json")
+            .optional_layer("/region/kr.json");
+
+        let settings: Settings = overlay.load_as().await.unwrap();
+        assert_eq!(
+            settings,
+            Settings {
+                timeout: 5,
+                region: "none".to_owned()
+            }
+        );
+
+        let mut updates = overlay.watch().await.unwrap();
+        assert_eq!(
+            updates.next().await.unwrap(),
+            json!({"timeout": 5, "region": "none"})
+        );
+        let updated = tokio::time::timeout(Duration::from_secs(5), updates.next())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(updated, json!({"timeout": 7, "region": "none"}));
+    }
+}
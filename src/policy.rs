@@ -0,0 +1,306 @@
+//! Policies checked before changes are pushed, e.g. organization-wide guardrails such as
+//! never removing the routing configuration.
+//!
+//! ```no_run
+//! use centraldogma::{model::ChangeContent, policy::PathRule, Client};
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), centraldogma::Error> {
+//! let client = Client::new("http://localhost:36462", None)
+//!     .await?
+//!     .with_push_policy(PathRule::no_removal("/routing.json"))
+//!     .with_push_policy(PathRule::new("json-only", "*.json", |proposed| {
+//!         match proposed.change.content {
+//!             ChangeContent::UpsertText(_) => Err("must be pushed as JSON".to_owned()),
+//!             _ => Ok(()),
+//!         }
+//!     }));
+//! # Ok(())
+//! # }
+//! ```
+use async_trait::async_trait;
+
+use crate::{
+    model::{Change, ChangeContent, Entry, Query, Revision},
+    services::path::matches_pattern,
+    ContentService, Error, RepoClient,
+};
+
+/// A reason a [`PushPolicy`] rejects changes, reported by [`Error::PolicyViolation`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    /// Name of the policy
+    pub policy: String,
+    /// Path of the offending change
+    pub path: String,
+    /// Why the change is rejected
+    pub message: String,
+}
+
+/// A change about to be pushed, with the current content of its file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProposedChange {
+    /// The change, before any [`ContentTransformer`](crate::transform::ContentTransformer)
+    pub change: Change,
+    /// The file at the base revision of the push, `None` if it doesn't exist
+    pub current: Option<Entry>,
+}
+
+/// The changes of a push, checked by every [`PushPolicy`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChangeSet {
+    /// Name of the project
+    pub project: String,
+    /// Name of the repository
+    pub repo: String,
+    /// The revision the changes are based on
+    pub base_revision: Revision,
+    /// The changes matched by the path pattern of the policy, in the order of the push
+    pub changes: Vec<ProposedChange>,
+}
+
+/// A policy checking the changes of every push of a client, installed with
+/// [`Client::with_push_policy()`](crate::Client::with_push_policy).
+///
+/// The policy is only checked when a change matches its [path pattern](Self::path_pattern).
+/// The current content of these files is fetched at the base revision of the push, so a
+/// push fails with the error of the fetch if the server can't be reached.
+#[async_trait]
+pub trait PushPolicy: Send + Sync {
+    /// Returns the name of the policy, reported in violations.
+    fn name(&self) -> &str;
+
+    /// Returns the pattern of the paths of the changes the policy checks, see
+    /// [get_files](trait@crate::ContentService#tymethod.get_files). All by default.
+    fn path_pattern(&self) -> &str {
+        "/**"
+    }
+
+    /// Returns the violations of `changes`, the push being rejected unless empty.
+    async fn check(&self, changes: &ChangeSet) -> Vec<Violation>;
+}
+
+type Check = dyn Fn(&ProposedChange) -> Result<(), String> + Send + Sync;
+
+/// A [`PushPolicy`] checking every change matching a path pattern with a closure.
+pub struct PathRule {
+    name: String,
+    path_pattern: String,
+    check: Box<Check>,
+}
+
+impl PathRule {
+    /// Returns a policy rejecting the changes matching `path_pattern` for which `check`
+    /// returns an error message.
+    pub fn new<F>(name: &str, path_pattern: &str, check: F) -> Self
+    where
+        F: Fn(&ProposedChange) -> Result<(), String> + Send + Sync + 'static,
+    {
+        PathRule {
+            name: name.to_owned(),
+            path_pattern: path_pattern.to_owned(),
+            check: Box::new(check),
+        }
+    }
+
+    /// Returns a policy rejecting the removal and the renaming of the existing files
+    /// matching `path_pattern`.
+    pub fn no_removal(path_pattern: &str) -> Self {
+        PathRule::new("no-removal", path_pattern, |proposed| {
+            match (&proposed.change.content, &proposed.current) {
+                (ChangeContent::Remove, Some(_)) => Err("the file must not be removed".to_owned()),
+                (ChangeContent::Rename(_), Some(_)) => {
+                    Err("the file must not be renamed".to_owned())
+                }
+                _ => Ok(()),
+            }
+        })
+    }
+}
+
+#[async_trait]
+impl PushPolicy for PathRule {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn path_pattern(&self) -> &str {
+        &self.path_pattern
+    }
+
+    async fn check(&self, changes: &ChangeSet) -> Vec<Violation> {
+        changes
+            .changes
+            .iter()
+            .filter_map(|proposed| {
+                (self.check)(proposed).err().map(|message| Violation {
+                    policy: self.name.clone(),
+                    path: proposed.change.path.clone(),
+                    message,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Checks `changes` against the policies of the client of `repo`.
+///
+/// Fails with [`Error::PolicyViolation`] reporting the violations of every policy.
+pub(crate) async fn check(
+    repo: &RepoClient<'_>,
+    base_revision: Revision,
+    changes: &[Change],
+) -> Result<(), Error> {
+    let policies = repo.client.push_policies();
+    let checked = |change: &Change| {
+        policies
+            .iter()
+            .any(|p| matches_pattern(p.path_pattern(), &change.path))
+    };
+    if !changes.iter().any(checked) {
+        return Ok(());
+    }
+
+    let fetches = changes
+        .iter()
+        .filter(|c| checked(c))
+        .map(|change| async move {
+            let query =
+                Query::identity(&change.path).ok_or(Error::InvalidParams("Invalid path"))?;
+            let current = match repo.get_file(base_revision, &query).await {
+                Ok(entry) => Some(entry),
+                Err(Error::EntryNotFound(_)) => None,
+                Err(e) => return Err(e),
+            };
+
+            Ok(ProposedChange {
+                change: change.clone(),
+                current,
+            })
+        });
+    let proposed = futures::future::try_join_all(fetches).await?;
+
+    let mut violations = Vec::new();
+    for policy in policies {
+        let changes = ChangeSet {
+            project: repo.project.to_owned(),
+            repo: repo.repo.to_owned(),
+            base_revision,
+            changes: proposed
+                .iter()
+                .filter(|p| matches_pattern(policy.path_pattern(), &p.change.path))
+                .cloned()
+                .collect(),
+        };
+        if !changes.changes.is_empty() {
+            violations.extend(policy.check(&changes).await);
+        }
+    }
+
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::PolicyViolation(violations))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+    use wiremock::{
+        matchers::{method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+    use crate::{model::CommitMessage, Client, ErrorCode};
+
+    #[tokio::test]
+    async fn test_push_policy() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/contents/routing.json"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "path": "/routing.json",
+                "type": "JSON",
+                "content": {},
+                "revision": 2,
+                "url": "/routing.json"
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(404).set_body_json(json!({
+                "exception": "com.linecorp.centraldogma.common.EntryNotFoundException",
+                "message": "not found"
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/api/v1/projects/foo/repos/bar/contents"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(json!({"revision": 3, "pushedAt": null})),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = Client::new(&server.uri(), None)
+            .await
+            .unwrap()
+            .with_push_policy(PathRule::no_removal("/routing.json"))
+            .with_push_policy(PathRule::new("lowercase", "/**", |proposed| match proposed
+                .change
+                .path
+                .chars()
+                .any(char::is_uppercase)
+            {
+                true => Err("the path must be lowercase".to_owned()),
+                false => Ok(()),
+            }));
+        let repo = client.repo("foo", "bar");
+
+        let err = repo
+            .push(
+                Revision::HEAD,
+                CommitMessage::only_summary("Clean up"),
+                vec![
+                    Change::from(("/A.json", json!({}))),
+                    Change {
+                        path: "/routing.json".to_owned(),
+                        content: ChangeContent::Remove,
+                    },
+                ],
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::PolicyViolation);
+        match err {
+            Error::PolicyViolation(violations) => {
+                let violated: Vec<_> = violations
+                    .iter()
+                    .map(|v| (v.policy.as_str(), v.path.as_str()))
+                    .collect();
+                assert_eq!(
+                    violated,
+                    [("no-removal", "/routing.json"), ("lowercase", "/A.json")]
+                );
+            }
+            e => panic!("unexpected error: {}", e),
+        }
+
+        repo.push(
+            Revision::HEAD,
+            CommitMessage::only_summary("Add a.json"),
+            vec![
+                Change::from(("/a.json", json!({}))),
+                Change {
+                    path: "/b.json".to_owned(),
+                    content: ChangeContent::Remove,
+                },
+            ],
+        )
+        .await
+        .unwrap();
+    }
+}
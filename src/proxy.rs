@@ -0,0 +1,460 @@
+//! A local server answering a subset of the REST API of Central Dogma from a cache, so that
+//! tools which speak the API can be pointed at `localhost` during development and tests.
+//!
+//! The files are fetched through a [`Client`] when first requested, then kept up to date
+//! by watching them.
+//!
+//! ```no_run
+//! use centraldogma::{proxy::LocalProxy, Client};
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), centraldogma::Error> {
+//! let client = Client::new("http://dogma.example.com:36462", None).await?;
+//! let proxy = LocalProxy::new(client).start().await?;
+//!
+//! println!("Serving the API at {}", proxy.url());
+//! # Ok(())
+//! # }
+//! ```
+use std::{
+    collections::{hash_map, HashMap},
+    convert::Infallible,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+
+use futures::StreamExt;
+use hyper::{
+    header::CONTENT_TYPE,
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, Server, StatusCode,
+};
+use serde::Serialize;
+use serde_json::json;
+use tokio::task::JoinHandle;
+
+use crate::{
+    model::{Entry, Query, Revision},
+    Client, ContentService, Error, ErrorCode, ProjectService, RepoService, WatchService,
+};
+
+/// Serves the following endpoints of the v1 API, for `GET` requests only:
+///
+/// * `/monitor/l7check`
+/// * `/api/v1/projects` and `/api/v1/projects/{project}/repos`
+/// * `/api/v1/projects/{project}/repos/{repo}/list/{pattern}`
+/// * `/api/v1/projects/{project}/repos/{repo}/contents/{path}`, with the `jsonpath`
+///   parameters of a JSON file
+///
+/// The contents of a single file at the latest revision are cached and kept up to date by
+/// a watch, for at most [`max_cached_files`](LocalProxy::max_cached_files) files. The other
+/// requests to these endpoints, i.e. the lists, the files matched by a pattern, the files at
+/// another revision and the files beyond the limit, are forwarded to the server every time.
+/// Any other method or endpoint fails with `501 Not Implemented`, without reaching the
+/// server.
+pub struct LocalProxy {
+    client: Client,
+    addr: SocketAddr,
+    max_cached_files: usize,
+}
+
+impl LocalProxy {
+    /// Returns a proxy through `client`, listening on a free port of `127.0.0.1` by default.
+    pub fn new(client: Client) -> Self {
+        LocalProxy {
+            client,
+            addr: SocketAddr::from(([127, 0, 0, 1], 0)),
+            max_cached_files: 1024,
+        }
+    }
+
+    /// Sets the address to listen on.
+    pub fn bind(mut self, addr: SocketAddr) -> Self {
+        self.addr = addr;
+        self
+    }
+
+    /// Sets how many files are cached and watched at most, 1024 by default. Once the limit
+    /// is reached, the other files are forwarded to the server.
+    pub fn max_cached_files(mut self, max_cached_files: usize) -> Self {
+        self.max_cached_files = max_cached_files;
+        self
+    }
+
+    /// Starts serving in the background until the returned handle is dropped.
+    pub async fn start(self) -> Result<ProxyHandle, Error> {
+        let builder = Server::try_bind(&self.addr).map_err(|e| Error::Io {
+            path: self.addr.to_string(),
+            source: std::io::Error::new(std::io::ErrorKind::AddrNotAvailable, e),
+        })?;
+        let state = Arc::new(State {
+            client: self.client,
+            files: Mutex::new(HashMap::new()),
+            max_files: self.max_cached_files,
+        });
+
+        let service_state = state.clone();
+        let server = builder.serve(make_service_fn(move |_| {
+            let state = service_state.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |req| {
+                    let state = state.clone();
+                    async move { Ok::<_, Infallible>(state.handle(req).await) }
+                }))
+            }
+        }));
+        let local_addr = server.local_addr();
+        let task = tokio::spawn(async move {
+            if let Err(e) = server.await {
+                log::warn!("Local proxy stopped: {}", e);
+            }
+        });
+
+        Ok(ProxyHandle {
+            local_addr,
+            state,
+            task,
+        })
+    }
+}
+
+/// The server started by [`LocalProxy::start()`], stopped when dropped.
+pub struct ProxyHandle {
+    local_addr: SocketAddr,
+    state: Arc<State>,
+    task: JoinHandle<()>,
+}
+
+impl ProxyHandle {
+    /// Returns the address the proxy listens on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Returns the base URL of the proxy, e.g. to pass to [`Client::new()`].
+    pub fn url(&self) -> String {
+        format!("http://{}", self.local_addr)
+    }
+
+    /// Returns the number of files in the cache.
+    pub fn cached_files(&self) -> usize {
+        self.state.files.lock().unwrap().len()
+    }
+}
+
+impl Drop for ProxyHandle {
+    fn drop(&mut self) {
+        self.task.abort();
+        for (_, file) in self.state.files.lock().unwrap().drain() {
+            file.watch.abort();
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct FileKey {
+    project: String,
+    repo: String,
+    query: Query,
+}
+
+struct CachedFile {
+    entry: Entry,
+    watch: JoinHandle<()>,
+}
+
+struct State {
+    client: Client,
+    files: Mutex<HashMap<FileKey, CachedFile>>,
+    max_files: usize,
+}
+
+impl State {
+    async fn handle(self: &Arc<Self>, req: Request<Body>) -> Response<Body> {
+        if req.method() != Method::GET {
+            return not_implemented(req.method(), req.uri().path());
+        }
+
+        let path = req.uri().path().to_owned();
+        let params: Vec<(String, String)> = req
+            .uri()
+            .query()
+            .map(|q| form_urlencoded::parse(q.as_bytes()).into_owned().collect())
+            .unwrap_or_default();
+        let param = |name: &str| -> Vec<String> {
+            params
+                .iter()
+                .filter(|(k, _)| k == name)
+                .map(|(_, v)| v.clone())
+                .collect()
+        };
+        let revision = match param("revision").first().map(|r| r.parse::<i64>()) {
+            None => Revision::HEAD,
+            Some(Ok(r)) => Revision::from(r),
+            Some(Err(_)) => return bad_request("invalid revision"),
+        };
+
+        if path == "/monitor/l7check" {
+            return Response::new(Body::empty());
+        }
+        let rest = match path.strip_prefix("/api/v1/projects") {
+            Some(rest) => rest,
+            None => return not_implemented(req.method(), &path),
+        };
+        if rest.is_empty() {
+            return respond(self.client.list_projects().await);
+        }
+
+        let segments: Vec<&str> = rest.splitn(6, '/').collect();
+        match segments.as_slice() {
+            ["", project, "repos"] => respond(self.client.project(project).list_repos().await),
+            ["", project, "repos", repo, "list", pattern] => respond(
+                self.client
+                    .repo(project, repo)
+                    .list_files(revision, &format!("/{}", pattern))
+                    .await,
+            ),
+            ["", project, "repos", repo, "contents", file] => {
+                let file = format!("/{}", file);
+                let json_paths = param("jsonpath");
+                if file.contains('*') {
+                    return respond(
+                        self.client
+                            .repo(project, repo)
+                            .get_files(revision, &file)
+                            .await,
+                    );
+                }
+                let query = if json_paths.is_empty() {
+                    Query::identity(&file)
+                } else {
+                    Query::of_json_path(&file, json_paths)
+                };
+                let query = match query {
+                    Some(query) => query,
+                    None => return bad_request("invalid query"),
+                };
+                if revision != Revision::HEAD {
+                    return respond(
+                        self.client
+                            .repo(project, repo)
+                            .get_file(revision, &query)
+                            .await,
+                    );
+                }
+
+                let key = FileKey {
+                    project: project.to_string(),
+                    repo: repo.to_string(),
+                    query,
+                };
+                respond(self.cached_file(key).await)
+            }
+            _ => not_implemented(req.method(), &path),
+        }
+    }
+
+    /// Returns the cached file of `key`, fetching and starting to watch it on a miss.
+    async fn cached_file(self: &Arc<Self>, key: FileKey) -> Result<Entry, Error> {
+        if let Some(file) = self.files.lock().unwrap().get(&key) {
+            return Ok(file.entry.clone());
+        }
+
+        let entry = self
+            .client
+            .repo(&key.project, &key.repo)
+            .get_file(Revision::HEAD, &key.query)
+            .await?;
+
+        let mut files = self.files.lock().unwrap();
+        if files.len() >= self.max_files {
+            return Ok(entry);
+        }
+        if let hash_map::Entry::Vacant(vacant) = files.entry(key.clone()) {
+            vacant.insert(CachedFile {
+                entry: entry.clone(),
+                watch: tokio::spawn(self.clone().watch(key)),
+            });
+        }
+
+        Ok(entry)
+    }
+
+    /// Replaces the cached file of `key` when it changes, evicting it when the watch ends.
+    async fn watch(self: Arc<Self>, key: FileKey) {
+        let stream = self
+            .client
+            .repo(&key.project, &key.repo)
+            .watch_file_stream(&key.query);
+        if let Ok(mut stream) = stream {
+            while let Some(result) = stream.next().await {
+                if let Some(file) = self.files.lock().unwrap().get_mut(&key) {
+                    file.entry = result.entry;
+                }
+            }
+        }
+
+        self.files.lock().unwrap().remove(&key);
+    }
+}
+
+fn respond<T: Serialize>(result: Result<T, Error>) -> Response<Body> {
+    match result {
+        Ok(body) => json_response(StatusCode::OK, &body),
+        Err(e) => error_response(&e),
+    }
+}
+
+fn json_response<T: Serialize>(status: StatusCode, body: &T) -> Response<Body> {
+    let body = serde_json::to_vec(body).expect("a model serializes to JSON");
+
+    Response::builder()
+        .status(status)
+        .header(CONTENT_TYPE, "application/json")
+        .body(Body::from(body))
+        .expect("a valid response")
+}
+
+/// Maps `err` to the response of the server, with the exception class the client expects.
+fn error_response(err: &Error) -> Response<Body> {
+    let (status, exception) = match err {
+        Error::ErrorResponse {
+            status, exception, ..
+        } => (
+            StatusCode::from_u16(*status).unwrap_or(StatusCode::BAD_GATEWAY),
+            exception.clone(),
+        ),
+        _ => {
+            let (status, class) = match err.code() {
+                ErrorCode::EntryNotFound => (StatusCode::NOT_FOUND, "EntryNotFoundException"),
+                ErrorCode::RepositoryNotFound => {
+                    (StatusCode::NOT_FOUND, "RepositoryNotFoundException")
+                }
+                ErrorCode::TooManyRequests => {
+                    (StatusCode::TOO_MANY_REQUESTS, "TooManyRequestsException")
+                }
+                _ => (StatusCode::BAD_GATEWAY, "CentralDogmaException"),
+            };
+            (
+                status,
+                Some(format!("com.linecorp.centraldogma.common.{}", class)),
+            )
+        }
+    };
+    let message = match err {
+        Error::ErrorResponse { message, .. }
+        | Error::EntryNotFound(message)
+        | Error::RepositoryNotFound(message) => message.clone(),
+        _ => err.to_string(),
+    };
+
+    json_response(status, &json!({"exception": exception, "message": message}))
+}
+
+fn bad_request(message: &str) -> Response<Body> {
+    json_response(
+        StatusCode::BAD_REQUEST,
+        &json!({"exception": "java.lang.IllegalArgumentException", "message": message}),
+    )
+}
+
+fn not_implemented(method: &Method, path: &str) -> Response<Body> {
+    json_response(
+        StatusCode::NOT_IMPLEMENTED,
+        &json!({
+            "exception": "java.lang.UnsupportedOperationException",
+            "message": format!("{} {} is not served by the local proxy", method, path),
+        }),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+    use wiremock::{
+        matchers::{method, path, query_param},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+    use crate::model::EntryContent;
+
+    #[tokio::test]
+    async fn test_local_proxy() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/contents/a.json"))
+            .and(query_param("revision", "-1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "path": "/a.json",
+                "type": "JSON",
+                "content": {"a": 1},
+                "revision": 2,
+                "url": "/a.json"
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/contents/b.json"))
+            .respond_with(ResponseTemplate::new(404).set_body_json(json!({
+                "exception": "com.linecorp.centraldogma.common.EntryNotFoundException",
+                "message": "/b.json"
+            })))
+            .mount(&server)
+            .await;
+
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/contents/c.json"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "path": "/c.json",
+                "type": "JSON",
+                "content": {"c": 1},
+                "revision": 2,
+                "url": "/c.json"
+            })))
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let upstream = Client::new(&server.uri(), None).await.unwrap();
+        let proxy = LocalProxy::new(upstream)
+            .max_cached_files(1)
+            .start()
+            .await
+            .unwrap();
+        let client = Client::new(&proxy.url(), None).await.unwrap();
+        let repo = client.repo("foo", "bar");
+
+        for _ in 0..2 {
+            let entry = repo
+                .get_file(Revision::HEAD, &Query::identity("/a.json").unwrap())
+                .await
+                .unwrap();
+            assert_eq!(entry.content, EntryContent::Json(json!({"a": 1})));
+        }
+        assert_eq!(proxy.cached_files(), 1);
+        for _ in 0..2 {
+            repo.get_file(Revision::HEAD, &Query::identity("/c.json").unwrap())
+                .await
+                .unwrap();
+        }
+        assert_eq!(proxy.cached_files(), 1);
+
+        let err = repo
+            .get_file(Revision::HEAD, &Query::identity("/b.json").unwrap())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::EntryNotFound(m) if m == "/b.json"));
+
+        let err = repo
+            .push(
+                Revision::HEAD,
+                crate::model::CommitMessage::only_summary("Nope"),
+                vec![crate::model::Change::from(("/a.json", json!({})))],
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::ErrorResponse { status: 501, .. }));
+    }
+}
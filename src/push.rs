@@ -0,0 +1,259 @@
+//! A builder of a commit of many files, validated before it is pushed.
+//!
+//! ```no_run
+//! use centraldogma::Client;
+//! use serde_json::json;
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), centraldogma::Error> {
+//! let client = Client::new("http://localhost:36462", None).await?;
+//! let result = client
+//!     .repo("foo", "bar")
+//!     .prepare_push()
+//!     .upsert_json("/a.json", &json!({"a": 1}))
+//!     .upsert_text("/b.txt", "b")
+//!     .remove("/c.txt")
+//!     .summary("Replace c.txt with b.txt")
+//!     .send()
+//!     .await?;
+//!
+//! println!("pushed {}", result.revision);
+//! # Ok(())
+//! # }
+//! ```
+use std::collections::HashSet;
+
+use serde::Serialize;
+
+use crate::{
+    model::{Change, ChangeContent, CommitDetail, CommitMessage, PushResult, Query, Revision},
+    ContentService, Error, RepoClient,
+};
+
+/// Number of files pushed per commit when copying many files, e.g. by a restore or a
+/// migration.
+pub(crate) const FILES_PER_COMMIT: usize = 100;
+
+/// Changes accumulated into one commit, created by [`RepoClient::prepare_push()`].
+///
+/// The changes are validated by [`build()`](Self::build) and [`send()`](Self::send):
+/// there must be at least one, on distinct non-empty paths, and JSON changes must be on
+/// `.json` files. The summary must not be empty.
+pub struct PushBuilder<'a> {
+    repo: RepoClient<'a>,
+    base_revision: Revision,
+    summary: String,
+    detail: Option<CommitDetail>,
+    changes: Vec<Change>,
+    error: Option<Error>,
+}
+
+impl<'a> PushBuilder<'a> {
+    fn change(mut self, path: &str, content: ChangeContent) -> Self {
+        self.changes.push(Change {
+            path: Query::normalize_path(path),
+            content,
+        });
+        self
+    }
+
+    /// Adds or replaces a JSON file with `value` serialized.
+    /// A value which fails to serialize fails [`send()`](Self::send).
+    pub fn upsert_json<T: Serialize + ?Sized>(mut self, path: &str, value: &T) -> Self {
+        match serde_json::to_value(value) {
+            Ok(json) => self.change(path, ChangeContent::UpsertJson(json)),
+            Err(e) => {
+                self.error.get_or_insert(e.into());
+                self
+            }
+        }
+    }
+
+    /// Adds or replaces a text file.
+    pub fn upsert_text(self, path: &str, text: &str) -> Self {
+        self.change(path, ChangeContent::UpsertText(text.to_owned()))
+    }
+
+    /// Removes a file.
+    pub fn remove(self, path: &str) -> Self {
+        self.change(path, ChangeContent::Remove)
+    }
+
+    /// Renames a file to `new_path`.
+    pub fn rename(self, path: &str, new_path: &str) -> Self {
+        self.change(path, ChangeContent::Rename(Query::normalize_path(new_path)))
+    }
+
+    /// Applies a [JSON patch](https://tools.ietf.org/html/rfc6902) to a JSON file.
+    pub fn apply_json_patch(self, path: &str, patch: serde_json::Value) -> Self {
+        self.change(path, ChangeContent::ApplyJsonPatch(patch))
+    }
+
+    /// Applies a unified diff to a text file.
+    pub fn apply_text_patch(self, path: &str, patch: &str) -> Self {
+        self.change(path, ChangeContent::ApplyTextPatch(patch.to_owned()))
+    }
+
+    /// Adds a [`Change`] built elsewhere.
+    pub fn push_change(mut self, change: Change) -> Self {
+        self.changes.push(change);
+        self
+    }
+
+    /// Sets the summary of the commit message.
+    pub fn summary(mut self, summary: &str) -> Self {
+        self.summary = summary.to_owned();
+        self
+    }
+
+    /// Sets the detail of the commit message.
+    pub fn detail(mut self, detail: CommitDetail) -> Self {
+        self.detail = Some(detail);
+        self
+    }
+
+    /// Commits on top of `base_revision` instead of [`Revision::HEAD`],
+    /// failing with [`Error::Conflict`] if the files changed since.
+    pub fn base_revision(mut self, base_revision: Revision) -> Self {
+        self.base_revision = base_revision;
+        self
+    }
+
+    /// Validates the changes, and returns the commit message and the changes to push.
+    pub fn build(self) -> Result<(CommitMessage, Vec<Change>), Error> {
+        if let Some(e) = self.error {
+            return Err(e);
+        }
+        if self.summary.trim().is_empty() {
+            return Err(Error::InvalidParams("commit summary cannot be empty"));
+        }
+        if self.changes.is_empty() {
+            return Err(Error::InvalidParams("no changes to push"));
+        }
+
+        let mut paths = HashSet::new();
+        for change in &self.changes {
+            if change.path.is_empty() || change.path == "/" || change.path.ends_with('/') {
+                return Err(Error::InvalidParams("change path must be a file path"));
+            }
+            if !paths.insert(change.path.as_str()) {
+                return Err(Error::InvalidParams(
+                    "more than one change on the same path",
+                ));
+            }
+            let is_json = matches!(
+                change.content,
+                ChangeContent::UpsertJson(_) | ChangeContent::ApplyJsonPatch(_)
+            );
+            if is_json && !change.path.to_lowercase().ends_with(".json") {
+                return Err(Error::InvalidParams("JSON change path must end with .json"));
+            }
+        }
+
+        let commit_message = CommitMessage {
+            summary: self.summary,
+            detail: self.detail,
+        };
+
+        Ok((commit_message, self.changes))
+    }
+
+    /// Validates the changes, and pushes them in one commit.
+    pub async fn send(self) -> Result<PushResult, Error> {
+        let repo = self.repo.client.repo(self.repo.project, self.repo.repo);
+        let base_revision = self.base_revision;
+        let (commit_message, changes) = self.build()?;
+
+        repo.push(base_revision, commit_message, changes).await
+    }
+}
+
+impl<'a> RepoClient<'a> {
+    /// Returns a [`PushBuilder`] accumulating changes into one commit on top of
+    /// [`Revision::HEAD`].
+    pub fn prepare_push(&self) -> PushBuilder<'a> {
+        PushBuilder {
+            repo: self.client.repo(self.project, self.repo),
+            base_revision: Revision::HEAD,
+            summary: String::new(),
+            detail: None,
+            changes: Vec::new(),
+            error: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+    use wiremock::{
+        matchers::{body_json, method, path, query_param},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+    use crate::Client;
+
+    #[tokio::test]
+    async fn test_prepare_push() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/v1/projects/foo/repos/bar/contents"))
+            .and(query_param("revision", "2"))
+            .and(body_json(json!({
+                "commitMessage": {"summary": "Edit", "detail": "Details", "markup": "PLAINTEXT"},
+                "changes": [
+                    {"path": "/a.json", "type": "UPSERT_JSON", "content": {"a": 1}},
+                    {"path": "/b.txt", "type": "REMOVE"},
+                    {"path": "/c.txt", "type": "RENAME", "content": "/d.txt"}
+                ]
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(
+                r#"{"revision":3,"pushedAt":"2017-05-22T00:00:00Z"}"#,
+                "application/json",
+            ))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = Client::new(&server.uri(), None).await.unwrap();
+        let result = client
+            .repo("foo", "bar")
+            .prepare_push()
+            .upsert_json("a.json", &json!({"a": 1}))
+            .remove("/b.txt")
+            .rename("/c.txt", "d.txt")
+            .summary("Edit")
+            .detail(CommitDetail::plaintext("Details"))
+            .base_revision(Revision::from(2))
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(result.revision, Revision::from(3));
+    }
+
+    #[tokio::test]
+    async fn test_prepare_push_validation() {
+        let client = Client::new("http://localhost:36462", None).await.unwrap();
+        let repo = client.repo("foo", "bar");
+        let message = |builder: PushBuilder<'_>| builder.build().unwrap_err().to_string();
+
+        assert!(message(repo.prepare_push().remove("/a.txt")).contains("summary"));
+        assert!(message(repo.prepare_push().summary("Edit")).contains("no changes"));
+        assert!(message(
+            repo.prepare_push()
+                .summary("Edit")
+                .upsert_text("/a.txt", "a")
+                .remove("a.txt")
+        )
+        .contains("same path"));
+        assert!(message(
+            repo.prepare_push()
+                .summary("Edit")
+                .upsert_json("/a.txt", &json!(1))
+        )
+        .contains(".json"));
+        assert!(message(repo.prepare_push().summary("Edit").remove("/dir/")).contains("file path"));
+    }
+}
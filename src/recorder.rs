@@ -0,0 +1,306 @@
+//! Hooks receiving the measurements of the requests and the watches of a
+//! [`Client`](crate::Client), to report them to any metrics system, e.g. to chart the
+//! watches which fail to reach the server for too long.
+//!
+//! ```no_run
+//! use std::time::Duration;
+//!
+//! use centraldogma::{
+//!     recorder::{MetricsRecorder, WatchBackoffEvent},
+//!     Client,
+//! };
+//!
+//! struct StaleWatchAlert;
+//!
+//! impl MetricsRecorder for StaleWatchAlert {
+//!     fn on_watch_backoff(&self, event: &WatchBackoffEvent) {
+//!         if event.staleness > Duration::from_secs(300) {
+//!             eprintln!("{} is stale for {:?}", event.path, event.staleness);
+//!         }
+//!     }
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), centraldogma::Error> {
+//! let client = Client::new("http://localhost:36462", None)
+//!     .await?
+//!     .with_metrics_recorder(StaleWatchAlert);
+//! # Ok(())
+//! # }
+//! ```
+use std::time::Duration;
+
+use reqwest::Method;
+
+use crate::{services::path::parse_route, ErrorCode};
+
+/// Receives the measurements of a [`Client`](crate::Client), installed with
+/// [`Client::with_metrics_recorder()`](crate::Client::with_metrics_recorder).
+///
+/// The methods are called on the tasks sending the requests, so they should return quickly.
+/// They do nothing by default.
+pub trait MetricsRecorder: Send + Sync {
+    /// Called when a request completes, successfully or not, including every request of a
+    /// watch. A request retried by the [retry config](crate::Client::with_retry) completes once.
+    fn on_request(&self, _event: &RequestEvent) {}
+
+    /// Called when a request is retried by the [retry config](crate::Client::with_retry), before
+    /// waiting for the delay of the retry.
+    fn on_retry(&self, _event: &RetryEvent) {}
+
+    /// Called when a watch receives a response, `modified` being `false` when the revision
+    /// known by the watch is still the latest one.
+    fn on_watch_response(&self, _path: &str, _modified: bool) {}
+
+    /// Called when a watch fails to reach the server, before waiting for the delay of its
+    /// reconnection.
+    fn on_watch_backoff(&self, _event: &WatchBackoffEvent) {}
+}
+
+/// A completed request, passed to [`MetricsRecorder::on_request`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct RequestEvent {
+    /// HTTP method of the request
+    pub method: Method,
+    /// Path of the request, e.g. `/api/v1/projects/foo`
+    pub path: String,
+    /// Path of the request with the names replaced by placeholders,
+    /// e.g. `/api/v1/projects/{project}`
+    pub route: String,
+    /// Status code of the response, `None` if no response was received
+    pub status: Option<u16>,
+    /// Classification of the failure of the request, if it failed
+    pub error: Option<ErrorCode>,
+    /// Time from sending the request to handling its response
+    pub duration: Duration,
+    /// Number of the last attempt of the request, greater than 1 when it was retried
+    pub attempt: usize,
+}
+
+/// A retried request, passed to [`MetricsRecorder::on_retry`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct RetryEvent {
+    /// HTTP method of the request
+    pub method: Method,
+    /// Path of the request
+    pub path: String,
+    /// Path of the request with the names replaced by placeholders
+    pub route: String,
+    /// Number of attempts which failed so far
+    pub failed_count: usize,
+    /// Delay before the next attempt
+    pub delay: Duration,
+}
+
+/// A failed request of a watch, passed to [`MetricsRecorder::on_watch_backoff`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct WatchBackoffEvent {
+    /// Path of the watch request
+    pub path: String,
+    /// Path of the watch request with the names replaced by placeholders
+    pub route: String,
+    /// Number of consecutive failures of the watch
+    pub failed_count: usize,
+    /// Delay before the watch reconnects
+    pub delay: Duration,
+    /// Time since the last successful request of the watch, for how long changes may have
+    /// been missed
+    pub staleness: Duration,
+}
+
+impl RequestEvent {
+    pub(crate) fn new(
+        method: Method,
+        path: String,
+        status: Option<u16>,
+        error: Option<ErrorCode>,
+        duration: Duration,
+        attempt: usize,
+    ) -> Self {
+        RequestEvent {
+            method,
+            route: parse_route(&path).template,
+            path,
+            status,
+            error,
+            duration,
+            attempt,
+        }
+    }
+}
+
+impl RetryEvent {
+    pub(crate) fn new(method: Method, path: String, failed_count: usize, delay: Duration) -> Self {
+        RetryEvent {
+            method,
+            route: parse_route(&path).template,
+            path,
+            failed_count,
+            delay,
+        }
+    }
+}
+
+impl WatchBackoffEvent {
+    pub(crate) fn new(
+        path: String,
+        failed_count: usize,
+        delay: Duration,
+        staleness: Duration,
+    ) -> Self {
+        WatchBackoffEvent {
+            route: parse_route(&path).template,
+            path,
+            failed_count,
+            delay,
+            staleness,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        future::Future,
+        pin::Pin,
+        sync::{Arc, Mutex},
+    };
+
+    use futures::StreamExt;
+    use wiremock::{
+        matchers::{header, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+    use crate::{
+        model::Query, retry::RetryConfig, Client, Clock, ProjectService, RepoService, WatchService,
+    };
+
+    /// Doesn't wait, so that the retries and the backoffs of the watches are instant.
+    struct NoSleep;
+
+    impl Clock for NoSleep {
+        fn sleep(&self, _: Duration) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+            Box::pin(async {})
+        }
+    }
+
+    #[derive(Clone, Default)]
+    struct Recorder {
+        requests: Arc<Mutex<Vec<RequestEvent>>>,
+        retries: Arc<Mutex<Vec<RetryEvent>>>,
+        watch_responses: Arc<Mutex<Vec<bool>>>,
+        watch_backoffs: Arc<Mutex<Vec<WatchBackoffEvent>>>,
+    }
+
+    impl MetricsRecorder for Recorder {
+        fn on_request(&self, event: &RequestEvent) {
+            self.requests.lock().unwrap().push(event.clone());
+        }
+
+        fn on_retry(&self, event: &RetryEvent) {
+            self.retries.lock().unwrap().push(event.clone());
+        }
+
+        fn on_watch_response(&self, _path: &str, modified: bool) {
+            self.watch_responses.lock().unwrap().push(modified);
+        }
+
+        fn on_watch_backoff(&self, event: &WatchBackoffEvent) {
+            self.watch_backoffs.lock().unwrap().push(event.clone());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_request_events() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos"))
+            .respond_with(ResponseTemplate::new(503).insert_header("retry-after", "7"))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw("[]", "application/json"))
+            .mount(&server)
+            .await;
+
+        let recorder = Recorder::default();
+        let client = Client::new(&server.uri(), None)
+            .await
+            .unwrap()
+            .with_retry(RetryConfig::default())
+            .with_clock(NoSleep)
+            .with_metrics_recorder(recorder.clone());
+        client.project("foo").list_repos().await.unwrap();
+        client.list_projects().await.unwrap_err();
+
+        let requests = recorder.requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].route, "/api/v1/projects/{project}/repos");
+        assert_eq!(requests[0].status, Some(200));
+        assert_eq!(requests[0].error, None);
+        assert_eq!(requests[0].attempt, 2);
+        assert_eq!(requests[1].path, "/api/v1/projects");
+        assert_eq!(requests[1].status, Some(404));
+        assert_eq!(requests[1].error, Some(ErrorCode::NotFound));
+        assert_eq!(requests[1].attempt, 1);
+
+        let retries = recorder.retries.lock().unwrap();
+        assert_eq!(retries.len(), 1);
+        assert_eq!(retries[0].method, Method::GET);
+        assert_eq!(retries[0].failed_count, 1);
+        assert!(retries[0].delay >= Duration::from_secs(7));
+    }
+
+    #[tokio::test]
+    async fn test_watch_events() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/contents/a.json"))
+            .and(header("if-none-match", "-1"))
+            .respond_with(ResponseTemplate::new(500))
+            .up_to_n_times(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/contents/a.json"))
+            .and(header("if-none-match", "-1"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(
+                r#"{"revision":3,"entry":{"path":"/a.json","type":"JSON","content":{},"revision":3,"url":"/a.json"}}"#,
+                "application/json",
+            ))
+            .mount(&server)
+            .await;
+
+        let recorder = Recorder::default();
+        let client = Client::new(&server.uri(), None)
+            .await
+            .unwrap()
+            .with_clock(NoSleep)
+            .with_metrics_recorder(recorder.clone());
+        let mut stream = client
+            .repo("foo", "bar")
+            .watch_file_stream(&Query::identity("/a.json").unwrap())
+            .unwrap();
+        stream.next().await.unwrap();
+
+        assert_eq!(*recorder.watch_responses.lock().unwrap(), [true]);
+        let backoffs = recorder.watch_backoffs.lock().unwrap();
+        assert_eq!(backoffs.len(), 2);
+        assert_eq!(
+            backoffs[0].route,
+            "/api/v1/projects/{project}/repos/{repo}/contents/{path}"
+        );
+        assert_eq!(
+            backoffs.iter().map(|b| b.failed_count).collect::<Vec<_>>(),
+            [1, 2]
+        );
+        assert!(backoffs[1].staleness >= backoffs[0].staleness);
+    }
+}
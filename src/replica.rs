@@ -0,0 +1,140 @@
+//! The replicas of a Central Dogma cluster a [`Client`](crate::Client) fails over between.
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use url::{Position, Url};
+
+/// How long a replica which failed to connect is skipped when failing over.
+const DOWN_TIME: Duration = Duration::from_secs(30);
+
+/// The base URLs of the replicas, and the one the requests are sent to.
+///
+/// All the requests go to the same replica until it fails to connect, so that the
+/// watches don't go back and forth between replicas which may lag behind each other.
+#[derive(Debug)]
+pub(crate) struct Replicas {
+    urls: Vec<Url>,
+    current: AtomicUsize,
+    /// When each replica last failed to connect
+    failed_at: Mutex<Vec<Option<Instant>>>,
+}
+
+impl Replicas {
+    pub(crate) fn new(urls: Vec<Url>) -> Self {
+        Replicas {
+            failed_at: Mutex::new(vec![None; urls.len()]),
+            urls,
+            current: AtomicUsize::new(0),
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.urls.len()
+    }
+
+    /// Returns the base URL of the replica the requests are sent to.
+    pub(crate) fn current(&self) -> &Url {
+        &self.urls[self.current.load(Ordering::Relaxed)]
+    }
+
+    /// Marks the replica of `url` down, and returns `url` on the replica to send the
+    /// request to instead: the next one which did not fail recently, or the next one if
+    /// they all did.
+    pub(crate) fn fail_over(&self, url: &Url) -> Url {
+        let failed = self
+            .urls
+            .iter()
+            .position(|replica| replica.origin() == url.origin())
+            .unwrap_or_else(|| self.current.load(Ordering::Relaxed));
+
+        let mut failed_at = self.failed_at.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+        failed_at[failed] = Some(now);
+        let next = (1..self.urls.len())
+            .map(|offset| (failed + offset) % self.urls.len())
+            .find(|&i| failed_at[i].is_none_or(|at| now.duration_since(at) >= DOWN_TIME))
+            .unwrap_or((failed + 1) % self.urls.len());
+        // Another request may have failed over already.
+        let _ = self
+            .current
+            .compare_exchange(failed, next, Ordering::Relaxed, Ordering::Relaxed);
+
+        self.urls[next]
+            .join(&url[Position::BeforePath..])
+            .unwrap_or_else(|_| url.clone())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::net::TcpListener;
+
+    use wiremock::{
+        matchers::{method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+    use crate::{Client, ProjectService};
+
+    /// Returns the URL of a port nothing listens to.
+    fn closed_url() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        format!("http://{}", listener.local_addr().unwrap())
+    }
+
+    #[test]
+    fn test_fail_over() {
+        let urls: Vec<Url> = ["http://a:1", "http://b:2/prefix", "http://c:3"]
+            .iter()
+            .map(|url| Url::parse(url).unwrap())
+            .collect();
+        let replicas = Replicas::new(urls);
+        let url = Url::parse("http://a:1/api/v1/projects?status=removed").unwrap();
+
+        let url = replicas.fail_over(&url);
+        assert_eq!(url.as_str(), "http://b:2/api/v1/projects?status=removed");
+        assert_eq!(replicas.current().as_str(), "http://b:2/prefix");
+        let url = replicas.fail_over(&url);
+        assert_eq!(url.as_str(), "http://c:3/api/v1/projects?status=removed");
+        // `a` and `b` failed recently.
+        let url = replicas.fail_over(&url);
+        assert_eq!(url.as_str(), "http://a:1/api/v1/projects?status=removed");
+        assert_eq!(replicas.current().as_str(), "http://a:1/");
+    }
+
+    #[tokio::test]
+    async fn test_replicas() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw("[]", "application/json"))
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let closed = closed_url();
+        let client = Client::builder(&closed)
+            .replica(&server.uri())
+            .build()
+            .unwrap();
+        assert!(client.list_projects().await.unwrap().is_empty());
+        assert_eq!(client.base_url(), format!("{}/", server.uri()));
+        // Sticks to the replica which answered.
+        assert!(client.list_projects().await.unwrap().is_empty());
+
+        let client = Client::builder(&closed)
+            .replica(&closed_url())
+            .build()
+            .unwrap();
+        assert!(matches!(
+            client.list_projects().await,
+            Err(crate::Error::Connect(..))
+        ));
+    }
+}
@@ -0,0 +1,348 @@
+//! Reports of the commits between two revisions of a repository, e.g. for the release
+//! notes posted by a bot.
+//!
+//! ```no_run
+//! use centraldogma::{model::Revision, report::ReleaseReport, Client};
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), centraldogma::Error> {
+//! let client = Client::new("http://localhost:36462", None).await?;
+//! let report = ReleaseReport::generate(
+//!     &client.repo("foo", "bar"),
+//!     Revision::from(10),
+//!     Revision::HEAD,
+//!     "/**",
+//! )
+//! .await?;
+//!
+//! println!("{}", report.to_markdown());
+//! # Ok(())
+//! # }
+//! ```
+use std::{collections::BTreeMap, fmt::Write};
+
+use crate::{
+    model::{Author, Change, ChangeContent, Commit, Revision},
+    ContentService, Error, RepoClient,
+};
+
+/// A commit and the changes it made to the files of the report.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommitReport {
+    /// The commit
+    pub commit: Commit,
+    /// The changes of the files matched by the path pattern of the report
+    pub changes: Vec<Change>,
+}
+
+/// The commits between two revisions of a repository, with their changes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReleaseReport {
+    /// Name of the project
+    pub project: String,
+    /// Name of the repository
+    pub repo: String,
+    /// The first revision of the range
+    pub from: Revision,
+    /// The last revision of the range
+    pub to: Revision,
+    /// The commits of the range, oldest first
+    pub commits: Vec<CommitReport>,
+}
+
+impl ReleaseReport {
+    /// Retrieves the commits between `from` and `to` which changed the files matched by
+    /// `path_pattern`, and the changes of each commit.
+    pub async fn generate(
+        repo: &RepoClient<'_>,
+        from: Revision,
+        to: Revision,
+        path_pattern: &str,
+    ) -> Result<Self, Error> {
+        let history = repo.get_history(from, to, path_pattern, None).await?;
+        let reports = history.into_iter().map(|commit| async move {
+            let changes = match commit.revision.as_i64() {
+                Some(n) if n > 1 => {
+                    repo.get_diffs(Revision::from(n - 1), commit.revision, path_pattern)
+                        .await?
+                }
+                _ => Vec::new(),
+            };
+            Ok::<_, Error>(CommitReport { commit, changes })
+        });
+
+        let mut commits = futures::future::try_join_all(reports).await?;
+        commits.sort_by_key(|c| c.commit.revision.as_i64());
+
+        Ok(ReleaseReport {
+            project: repo.project.to_owned(),
+            repo: repo.repo.to_owned(),
+            from,
+            to,
+            commits,
+        })
+    }
+
+    /// Returns the commits grouped by author, in the order of their first commit.
+    pub fn by_author(&self) -> Vec<(&Author, Vec<&CommitReport>)> {
+        let mut groups: Vec<(&Author, Vec<&CommitReport>)> = Vec::new();
+        for report in &self.commits {
+            let author = &report.commit.author;
+            match groups.iter_mut().find(|(a, _)| *a == author) {
+                Some((_, commits)) => commits.push(report),
+                None => groups.push((author, vec![report])),
+            }
+        }
+
+        groups
+    }
+
+    /// Returns the commits grouped by the paths they changed, sorted by path.
+    pub fn by_path(&self) -> BTreeMap<&str, Vec<&CommitReport>> {
+        let mut groups: BTreeMap<&str, Vec<&CommitReport>> = BTreeMap::new();
+        for report in &self.commits {
+            for change in &report.changes {
+                let commits = groups.entry(change.path.as_str()).or_default();
+                if !commits.iter().any(|c| std::ptr::eq(*c, report)) {
+                    commits.push(report);
+                }
+            }
+        }
+
+        groups
+    }
+
+    /// Renders the report as markdown: a summary, the commits grouped by author and
+    /// by path, then every commit with its message and changes.
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::new();
+        let _ = self.write_markdown(&mut out);
+        out
+    }
+
+    fn write_markdown(&self, out: &mut String) -> std::fmt::Result {
+        let by_author = self.by_author();
+        let by_path = self.by_path();
+        writeln!(
+            out,
+            "# {}/{}: revisions {} to {}\n",
+            self.project, self.repo, self.from, self.to
+        )?;
+        writeln!(
+            out,
+            "{} commits by {} authors, changing {} files.",
+            self.commits.len(),
+            by_author.len(),
+            by_path.len()
+        )?;
+
+        writeln!(out, "\n## By author")?;
+        for (author, commits) in &by_author {
+            writeln!(out, "\n### {} <{}>\n", author.name, author.email)?;
+            for report in commits {
+                writeln!(out, "- {}", commit_line(&report.commit))?;
+            }
+        }
+
+        writeln!(out, "\n## By path")?;
+        for (path, commits) in &by_path {
+            writeln!(out, "\n### `{}`\n", path)?;
+            for report in commits {
+                writeln!(out, "- {}", commit_line(&report.commit))?;
+            }
+        }
+
+        writeln!(out, "\n## Commits")?;
+        for report in &self.commits {
+            let commit = &report.commit;
+            writeln!(out, "\n### {}\n", commit_line(commit))?;
+            write!(out, "By {} <{}>", commit.author.name, commit.author.email)?;
+            match &commit.pushed_at {
+                Some(pushed_at) => writeln!(out, " at {}", pushed_at)?,
+                None => writeln!(out)?,
+            }
+            if let Some(detail) = &commit.commit_message.detail {
+                if !detail.text().trim().is_empty() {
+                    writeln!(out, "\n{}", detail.text().trim())?;
+                }
+            }
+            if !report.changes.is_empty() {
+                writeln!(out)?;
+            }
+            for change in &report.changes {
+                writeln!(out, "- `{}` {}", change.path, change_kind(&change.content))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn commit_line(commit: &Commit) -> String {
+    format!("r{} {}", commit.revision, commit.commit_message.summary)
+}
+
+fn change_kind(content: &ChangeContent) -> String {
+    match content {
+        ChangeContent::UpsertJson(_) | ChangeContent::UpsertText(_) => "updated".to_owned(),
+        #[cfg(feature = "yaml")]
+        ChangeContent::UpsertYaml(_) => "updated".to_owned(),
+        ChangeContent::Remove => "removed".to_owned(),
+        ChangeContent::Rename(to) => format!("renamed to `{}`", to),
+        ChangeContent::ApplyJsonPatch(_) | ChangeContent::ApplyTextPatch(_) => {
+            "modified".to_owned()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use wiremock::{
+        matchers::{method, path, query_param},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+    use crate::Client;
+
+    async fn mount_diffs(server: &MockServer, from: &str, to: &str, body: &str) {
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/compare"))
+            .and(query_param("from", from))
+            .and(query_param("to", to))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(body, "application/json"))
+            .mount(server)
+            .await;
+    }
+
+    #[tokio::test]
+    async fn test_release_report() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/commits/2"))
+            .and(query_param("to", "4"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(
+                r#"[{
+                    "revision":4,
+                    "author":{"name":"minux", "email":"minux@m.x"},
+                    "commitMessage":{"summary":"Edit a.json"},
+                    "pushedAt":"2024-01-02T00:00:00Z"
+                }, {
+                    "revision":3,
+                    "author":{"name":"ghost", "email":"ghost@g.x"},
+                    "commitMessage":{"summary":"Add b.txt","markup":"MARKDOWN","detail":"For **b**"}
+                }, {
+                    "revision":2,
+                    "author":{"name":"minux", "email":"minux@m.x"},
+                    "commitMessage":{"summary":"Add a.json"}
+                }]"#,
+                "application/json",
+            ))
+            .mount(&server)
+            .await;
+        mount_diffs(
+            &server,
+            "1",
+            "2",
+            r#"[{"path":"/a.json","type":"UPSERT_JSON","content":{"a":1}}]"#,
+        )
+        .await;
+        mount_diffs(
+            &server,
+            "2",
+            "3",
+            r#"[{"path":"/b.txt","type":"UPSERT_TEXT","content":"b"}]"#,
+        )
+        .await;
+        mount_diffs(
+            &server,
+            "3",
+            "4",
+            r#"[{"path":"/a.json","type":"APPLY_JSON_PATCH","content":[]}]"#,
+        )
+        .await;
+
+        let client = Client::new(&server.uri(), None).await.unwrap();
+        let report = ReleaseReport::generate(
+            &client.repo("foo", "bar"),
+            Revision::from(2),
+            Revision::from(4),
+            "/**",
+        )
+        .await
+        .unwrap();
+
+        let revisions: Vec<_> = report
+            .commits
+            .iter()
+            .map(|c| c.commit.revision.as_i64().unwrap())
+            .collect();
+        assert_eq!(revisions, [2, 3, 4]);
+
+        let by_author: Vec<_> = report
+            .by_author()
+            .into_iter()
+            .map(|(author, commits)| (author.name.as_str(), commits.len()))
+            .collect();
+        assert_eq!(by_author, [("minux", 2), ("ghost", 1)]);
+
+        let by_path: Vec<_> = report
+            .by_path()
+            .into_iter()
+            .map(|(path, commits)| (path, commits.len()))
+            .collect();
+        assert_eq!(by_path, [("/a.json", 2), ("/b.txt", 1)]);
+
+        assert_eq!(
+            report.to_markdown(),
+            "# foo/bar: revisions 2 to 4
+
+3 commits by 2 authors, changing 2 files.
+
+## By author
+
+### minux <minux@m.x>
+
+- r2 Add a.json
+- r4 Edit a.json
+
+### ghost <ghost@g.x>
+
+- r3 Add b.txt
+
+## By path
+
+### `/a.json`
+
+- r2 Add a.json
+- r4 Edit a.json
+
+### `/b.txt`
+
+- r3 Add b.txt
+
+## Commits
+
+### r2 Add a.json
+
+By minux <minux@m.x>
+
+- `/a.json` updated
+
+### r3 Add b.txt
+
+By ghost <ghost@g.x>
+
+For **b**
+
+- `/b.txt` updated
+
+### r4 Edit a.json
+
+By minux <minux@m.x> at 2024-01-02T00:00:00Z
+
+- `/a.json` modified
+"
+        );
+    }
+}
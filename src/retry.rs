@@ -0,0 +1,261 @@
+//! Retries of the reads failing transiently, e.g. while a replica of the server restarts.
+//!
+//! ```no_run
+//! use std::time::Duration;
+//!
+//! use centraldogma::{
+//!     retry::{Backoff, RetryConfig},
+//!     Client,
+//! };
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), centraldogma::Error> {
+//! let client = Client::new("http://localhost:36462", None)
+//!     .await?
+//!     .with_retry(RetryConfig {
+//!         max_attempts: 5,
+//!         backoff: Backoff::exponential(Duration::from_millis(200), Duration::from_secs(5)),
+//!         ..RetryConfig::default()
+//!     });
+//! # Ok(())
+//! # }
+//! ```
+use std::{error::Error as _, io, time::Duration};
+
+use reqwest::Response;
+
+use crate::Error;
+
+const JITTER_RATE: f64 = 0.2;
+
+/// A failure of a request which is retried, see [`RetryConfig::retry_on`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetryOn {
+    /// A response with the status code
+    Status(u16),
+    /// A failure to connect to the server, or a connection closed by the server before it
+    /// responded, e.g. reset
+    ConnectionError,
+    /// A request timed out, see [`ClientBuilder::timeout()`](crate::ClientBuilder::timeout)
+    Timeout,
+}
+
+/// The delays between the attempts of a request, growing exponentially up to a maximum,
+/// with up to 20% of jitter.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Backoff {
+    /// The delay before the second attempt
+    pub initial: Duration,
+    /// The maximum delay before an attempt, jitter excluded
+    pub max: Duration,
+    /// The factor the delay is multiplied by after every attempt
+    pub multiplier: f64,
+}
+
+impl Backoff {
+    /// Returns a backoff doubling the delay after every attempt.
+    pub fn exponential(initial: Duration, max: Duration) -> Self {
+        Backoff {
+            initial,
+            max,
+            multiplier: 2.0,
+        }
+    }
+
+    /// Returns the delay after `failed_count` failed attempts.
+    pub(crate) fn delay_for(&self, failed_count: usize) -> Duration {
+        let exponent = failed_count.saturating_sub(1).min(i32::MAX as usize) as i32;
+        let base = (self.initial.as_secs_f64() * self.multiplier.powi(exponent))
+            .min(self.max.as_secs_f64());
+        let jitter = fastrand::f64() * JITTER_RATE * base;
+
+        Duration::from_secs_f64(base + jitter)
+    }
+}
+
+impl Default for Backoff {
+    /// 100 milliseconds, doubled up to 2 seconds.
+    fn default() -> Self {
+        Backoff::exponential(Duration::from_millis(100), Duration::from_secs(2))
+    }
+}
+
+/// How a client retries its `GET` requests, installed with
+/// [`Client::with_retry()`](crate::Client::with_retry).
+///
+/// The other requests are never retried, as they may not be idempotent.
+/// Watches are not retried by it either, as they retry by themselves.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryConfig {
+    /// The maximum number of attempts of a request, the first one included
+    pub max_attempts: usize,
+    /// The delays between the attempts
+    pub backoff: Backoff,
+    /// The failures which are retried
+    pub retry_on: Vec<RetryOn>,
+}
+
+impl Default for RetryConfig {
+    /// 3 attempts with the default [`Backoff`], on the status codes 502, 503 and 504 and on
+    /// connection errors.
+    fn default() -> Self {
+        RetryConfig {
+            max_attempts: 3,
+            backoff: Backoff::default(),
+            retry_on: vec![
+                RetryOn::Status(502),
+                RetryOn::Status(503),
+                RetryOn::Status(504),
+                RetryOn::ConnectionError,
+            ],
+        }
+    }
+}
+
+impl RetryConfig {
+    /// Returns whether the result of an attempt is retried, attempts remaining.
+    pub(crate) fn should_retry(&self, result: &Result<Response, Error>) -> bool {
+        self.retry_on.iter().any(|on| match (on, result) {
+            (RetryOn::Status(status), Ok(resp)) => resp.status().as_u16() == *status,
+            (RetryOn::ConnectionError, Err(e)) => is_connection_error(e),
+            (RetryOn::Timeout, Err(Error::Timeout(..))) => true,
+            _ => false,
+        })
+    }
+}
+
+fn is_connection_error(err: &Error) -> bool {
+    let e = match err {
+        Error::Connect(..) => return true,
+        Error::HttpClient(e) => e,
+        _ => return false,
+    };
+
+    let mut source = e.source();
+    while let Some(cause) = source {
+        if let Some(io_err) = cause.downcast_ref::<io::Error>() {
+            return matches!(
+                io_err.kind(),
+                io::ErrorKind::ConnectionReset
+                    | io::ErrorKind::ConnectionAborted
+                    | io::ErrorKind::BrokenPipe
+                    | io::ErrorKind::UnexpectedEof
+            );
+        }
+        source = cause.source();
+    }
+
+    false
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+    use wiremock::{
+        matchers::{method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::{Client, ProjectService, RepoService};
+
+    fn config() -> RetryConfig {
+        RetryConfig {
+            backoff: Backoff::exponential(Duration::from_millis(1), Duration::from_millis(2)),
+            ..RetryConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_backoff() {
+        let backoff = Backoff::exponential(Duration::from_secs(1), Duration::from_secs(3));
+        let within = |failed_count, secs: f64| {
+            let delay = backoff.delay_for(failed_count).as_secs_f64();
+            delay >= secs && delay <= secs * (1.0 + JITTER_RATE)
+        };
+        assert!(within(1, 1.0));
+        assert!(within(2, 2.0));
+        assert!(within(3, 3.0));
+        assert!(within(10, 3.0));
+    }
+
+    #[tokio::test]
+    async fn test_retry() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(2)
+            .expect(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([])))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = Client::new(&server.uri(), None)
+            .await
+            .unwrap()
+            .with_retry(config());
+        assert!(client.list_projects().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_retry_exhausted() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects"))
+            .respond_with(ResponseTemplate::new(502))
+            .expect(3)
+            .mount(&server)
+            .await;
+        Mock::given(method("DELETE"))
+            .and(path("/api/v1/projects/foo"))
+            .respond_with(ResponseTemplate::new(503))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let attempts = Arc::new(Mutex::new(Vec::new()));
+        let reported = attempts.clone();
+        let client = Client::new(&server.uri(), None)
+            .await
+            .unwrap()
+            .with_retry(config())
+            .with_error_hook(move |ctx, _| reported.lock().unwrap().push(ctx.attempt));
+        let err = client.list_projects().await.unwrap_err();
+        assert!(matches!(err, Error::ErrorResponse { status: 502, .. }));
+        client.remove_project("foo").await.unwrap_err();
+        assert_eq!(*attempts.lock().unwrap(), [3, 1]);
+    }
+
+    #[tokio::test]
+    async fn test_retry_classifier() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects"))
+            .respond_with(ResponseTemplate::new(503))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos"))
+            .respond_with(ResponseTemplate::new(409))
+            .expect(3)
+            .mount(&server)
+            .await;
+
+        let client = Client::new(&server.uri(), None)
+            .await
+            .unwrap()
+            .with_retry(config())
+            .with_retry_classifier(|e| matches!(e, Error::ErrorResponse { status: 409, .. }));
+        client.list_projects().await.unwrap_err();
+        client.project("foo").list_repos().await.unwrap_err();
+    }
+}
@@ -0,0 +1,309 @@
+//! Search of the contents of the files of a repository, like `grep` without exporting it.
+//!
+//! ```no_run
+//! use centraldogma::{model::Revision, search::Needle, Client};
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), centraldogma::Error> {
+//! let client = Client::new("http://localhost:36462", None).await?;
+//! let matches = client
+//!     .repo("foo", "bar")
+//!     .search(Revision::HEAD, "/**", &Needle::JsonKey("timeout".to_owned()))
+//!     .await?;
+//!
+//! for m in matches {
+//!     println!("{}: {}", m.path, m.position);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use futures::{StreamExt, TryStreamExt};
+use serde_json::Value;
+
+use crate::{
+    model::{EntryContent, EntryType, Query, Revision},
+    ContentService, Error, RepoClient,
+};
+
+const CONCURRENCY: usize = 8;
+
+/// What [`RepoClient::search()`] looks for.
+#[derive(Debug, Clone)]
+pub enum Needle {
+    /// A substring of the lines of the text files, or of the keys and string values of the
+    /// JSON files.
+    Text(String),
+    /// A regular expression matching the lines of the text files, or the keys and string
+    /// values of the JSON files.
+    #[cfg(feature = "regex")]
+    Regex(regex::Regex),
+    /// A key of an object of the JSON files.
+    JsonKey(String),
+    /// A value of the JSON files, e.g. `json!(8080)`.
+    JsonValue(Value),
+}
+
+impl Needle {
+    fn matches_str(&self, s: &str) -> Option<usize> {
+        match self {
+            Needle::Text(text) => s.find(text.as_str()),
+            #[cfg(feature = "regex")]
+            Needle::Regex(regex) => regex.find(s).map(|m| m.start()),
+            Needle::JsonKey(_) | Needle::JsonValue(_) => None,
+        }
+    }
+}
+
+/// Where a [`Needle`] was found in a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Position {
+    /// In a line of a text file.
+    Line {
+        /// Number of the line, from 1
+        line: usize,
+        /// Number of the character of the match in the line, from 1
+        column: usize,
+        /// The line
+        text: String,
+    },
+    /// At a value of a JSON file, identified by its
+    /// [JSON pointer](https://tools.ietf.org/html/rfc6901), e.g. `/server/ports/0`.
+    JsonPointer(String),
+}
+
+impl std::fmt::Display for Position {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Position::Line { line, column, text } => write!(f, "{}:{}: {}", line, column, text),
+            Position::JsonPointer(pointer) => write!(f, "{}", pointer),
+        }
+    }
+}
+
+/// A match of a [`Needle`], returned by [`RepoClient::search()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchMatch {
+    /// Path of the file
+    pub path: String,
+    /// Where the needle was found
+    pub position: Position,
+}
+
+impl<'a> RepoClient<'a> {
+    /// Returns the matches of `needle` in the files matched by `path_pattern` at `revision`,
+    /// ordered by path, then by position in the file.
+    ///
+    /// The files are listed, then retrieved and scanned concurrently.
+    pub async fn search(
+        &self,
+        revision: Revision,
+        path_pattern: &str,
+        needle: &Needle,
+    ) -> Result<Vec<SearchMatch>, Error> {
+        let listed = self.list_files(revision, path_pattern).await?;
+        let scanned: Vec<Vec<SearchMatch>> = futures::stream::iter(listed)
+            .filter(|e| futures::future::ready(e.r#type != EntryType::Directory))
+            .filter_map(|e| futures::future::ready(Query::identity(&e.path)))
+            .map(|query| async move {
+                let entry = self.get_file(revision, &query).await?;
+                let positions = match &entry.content {
+                    EntryContent::Text(text) => scan_text(text, needle),
+                    EntryContent::Json(json) => {
+                        let mut positions = Vec::new();
+                        scan_json(json, needle, &mut String::new(), &mut positions);
+                        positions
+                    }
+                    #[cfg(feature = "yaml")]
+                    EntryContent::Yaml(yaml) => {
+                        let mut positions = Vec::new();
+                        if let Ok(json) = serde_json::to_value(yaml) {
+                            scan_json(&json, needle, &mut String::new(), &mut positions);
+                        }
+                        positions
+                    }
+                    EntryContent::Directory => Vec::new(),
+                };
+
+                Ok::<_, Error>(
+                    positions
+                        .into_iter()
+                        .map(|position| SearchMatch {
+                            path: entry.path.clone(),
+                            position,
+                        })
+                        .collect(),
+                )
+            })
+            .buffer_unordered(CONCURRENCY)
+            .try_collect()
+            .await?;
+
+        let mut matches: Vec<_> = scanned.into_iter().flatten().collect();
+        // Stable, so the positions of a file stay in order
+        matches.sort_by(|a, b| a.path.cmp(&b.path));
+
+        Ok(matches)
+    }
+}
+
+fn scan_text(text: &str, needle: &Needle) -> Vec<Position> {
+    text.lines()
+        .enumerate()
+        .filter_map(|(i, line)| {
+            needle.matches_str(line).map(|start| Position::Line {
+                line: i + 1,
+                column: line[..start].chars().count() + 1,
+                text: line.to_owned(),
+            })
+        })
+        .collect()
+}
+
+/// Adds the pointers of the values of `json` matching `needle`, `pointer` being the
+/// pointer of `json`.
+fn scan_json(json: &Value, needle: &Needle, pointer: &mut String, found: &mut Vec<Position>) {
+    let matched = match (needle, json) {
+        (Needle::JsonValue(value), json) => value == json,
+        (Needle::JsonKey(_), _) => false,
+        (needle, Value::String(s)) => needle.matches_str(s).is_some(),
+        _ => false,
+    };
+    if matched {
+        found.push(Position::JsonPointer(pointer.clone()));
+    }
+
+    let len = pointer.len();
+    match json {
+        Value::Object(fields) => {
+            for (key, value) in fields {
+                pointer.push('/');
+                pointer.push_str(&key.replace('~', "~0").replace('/', "~1"));
+                let key_matched = match needle {
+                    Needle::JsonKey(k) => k == key,
+                    Needle::JsonValue(_) => false,
+                    needle => needle.matches_str(key).is_some(),
+                };
+                if key_matched {
+                    found.push(Position::JsonPointer(pointer.clone()));
+                }
+                scan_json(value, needle, pointer, found);
+                pointer.truncate(len);
+            }
+        }
+        Value::Array(values) => {
+            for (i, value) in values.iter().enumerate() {
+                pointer.push('/');
+                pointer.push_str(&i.to_string());
+                scan_json(value, needle, pointer, found);
+                pointer.truncate(len);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+    use wiremock::{
+        matchers::{method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+    use crate::Client;
+
+    fn pointers(json: Value, needle: Needle) -> Vec<String> {
+        let mut found = Vec::new();
+        scan_json(&json, &needle, &mut String::new(), &mut found);
+        found
+            .into_iter()
+            .map(|p| match p {
+                Position::JsonPointer(p) => p,
+                p => panic!("unexpected position: {:?}", p),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_scan_json() {
+        let json = json!({"server": {"port": 8080, "hosts": ["a.example.com", "b"]}, "a/b": 8080});
+        assert_eq!(
+            pointers(json.clone(), Needle::JsonValue(json!(8080))),
+            ["/a~1b", "/server/port"]
+        );
+        assert_eq!(
+            pointers(json.clone(), Needle::JsonKey("port".to_owned())),
+            ["/server/port"]
+        );
+        assert_eq!(
+            pointers(json.clone(), Needle::Text("example".to_owned())),
+            ["/server/hosts/0"]
+        );
+        #[cfg(feature = "regex")]
+        assert_eq!(
+            pointers(json, Needle::Regex(regex::Regex::new("^[ab]").unwrap())),
+            ["/a~1b", "/server/hosts/0", "/server/hosts/1"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_search() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/list/**"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+                {"path": "/b.txt", "type": "TEXT"},
+                {"path": "/a.json", "type": "JSON"},
+                {"path": "/dir", "type": "DIRECTORY"}
+            ])))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/contents/b.txt"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "path": "/b.txt",
+                "type": "TEXT",
+                "content": "host: a\nhost: b.example.com\n",
+                "revision": 2,
+                "url": "/b.txt"
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/contents/a.json"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "path": "/a.json",
+                "type": "JSON",
+                "content": {"host": "c.example.com"},
+                "revision": 2,
+                "url": "/a.json"
+            })))
+            .mount(&server)
+            .await;
+
+        let client = Client::new(&server.uri(), None).await.unwrap();
+        let matches = client
+            .repo("foo", "bar")
+            .search(Revision::HEAD, "/**", &Needle::Text("example".to_owned()))
+            .await
+            .unwrap();
+        assert_eq!(
+            matches,
+            [
+                SearchMatch {
+                    path: "/a.json".to_owned(),
+                    position: Position::JsonPointer("/host".to_owned()),
+                },
+                SearchMatch {
+                    path: "/b.txt".to_owned(),
+                    position: Position::Line {
+                        line: 2,
+                        column: 9,
+                        text: "host: b.example.com".to_owned(),
+                    },
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,262 @@
+//! A declarative selection of files across repositories, optionally projected by JSON path
+//! expressions and merged into one document, executed with as few requests as possible.
+//!
+//! ```no_run
+//! use centraldogma::{overlay::ArrayMerge, select::Selection, Client};
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), centraldogma::Error> {
+//! let client = Client::new("http://localhost:36462", None).await?;
+//! let selected = Selection::new()
+//!     .from("foo", "shared", "/defaults/*.json")
+//!     .from("foo", "bar", "/service/*.json")
+//!     .json_path("$.timeouts")
+//!     .merge(ArrayMerge::Replace)
+//!     .execute(&client)
+//!     .await?;
+//!
+//! println!("{:?}", selected.merged);
+//! # Ok(())
+//! # }
+//! ```
+use futures::{StreamExt, TryStreamExt};
+use serde_json::Value;
+
+use crate::{
+    model::{Entry, EntryType, Query, Revision},
+    overlay::{deep_merge, json_of, ArrayMerge},
+    Client, ContentService, Error,
+};
+
+const DEFAULT_CONCURRENCY: usize = 8;
+
+#[derive(Debug, Clone)]
+struct Source {
+    project: String,
+    repo: String,
+    path_pattern: String,
+}
+
+/// A file selected by a [`Selection`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelectedFile {
+    /// Name of the project
+    pub project: String,
+    /// Name of the repository
+    pub repo: String,
+    /// The file, projected by the JSON path expressions of the selection if any
+    pub entry: Entry,
+}
+
+/// The result of a [`Selection`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Selected {
+    /// The selected files, in the order of the sources, then by path
+    pub files: Vec<SelectedFile>,
+    /// The files merged into one document, if the selection merges them
+    pub merged: Option<Value>,
+}
+
+/// Files matched by path patterns in repositories, optionally projected and merged.
+///
+/// Without JSON path expressions, the files of a source are retrieved by a single request.
+/// With expressions, the files are listed, then the JSON files are retrieved with the
+/// expressions applied by the server, concurrently. The text files are skipped.
+#[derive(Debug, Clone)]
+pub struct Selection {
+    sources: Vec<Source>,
+    json_paths: Vec<String>,
+    revision: Revision,
+    merge: Option<ArrayMerge>,
+    concurrency: usize,
+}
+
+impl Default for Selection {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Selection {
+    /// Returns a selection of no files.
+    pub fn new() -> Self {
+        Selection {
+            sources: Vec::new(),
+            json_paths: Vec::new(),
+            revision: Revision::HEAD,
+            merge: None,
+            concurrency: DEFAULT_CONCURRENCY,
+        }
+    }
+
+    /// Selects the files matched by `path_pattern` in a repository, see
+    /// [get_files](trait@crate::ContentService#tymethod.get_files).
+    pub fn from(mut self, project: &str, repo: &str, path_pattern: &str) -> Self {
+        self.sources.push(Source {
+            project: project.to_owned(),
+            repo: repo.to_owned(),
+            path_pattern: path_pattern.to_owned(),
+        });
+        self
+    }
+
+    /// Projects the JSON files by a JSON path expression, applied after the previous ones.
+    pub fn json_path(mut self, expr: impl Into<String>) -> Self {
+        self.json_paths.push(expr.into());
+        self
+    }
+
+    /// Selects the files at `revision`, [`Revision::HEAD`] by default.
+    pub fn revision(mut self, revision: Revision) -> Self {
+        self.revision = revision;
+        self
+    }
+
+    /// Merges the selected files into one document, each file overriding the ones before,
+    /// see [`deep_merge()`].
+    pub fn merge(mut self, arrays: ArrayMerge) -> Self {
+        self.merge = Some(arrays);
+        self
+    }
+
+    /// Sets how many requests are sent at the same time, 8 by default.
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Retrieves the selected files.
+    ///
+    /// Fails with [`Error::InvalidConfig`] if the selection merges a file which is not JSON.
+    pub async fn execute(&self, client: &Client) -> Result<Selected, Error> {
+        let per_source: Vec<Vec<SelectedFile>> = futures::stream::iter(&self.sources)
+            .map(|source| self.fetch(client, source))
+            .buffered(self.concurrency)
+            .try_collect()
+            .await?;
+        let files: Vec<_> = per_source.into_iter().flatten().collect();
+
+        let merged = match self.merge {
+            Some(arrays) => {
+                let mut merged = Value::Object(Default::default());
+                for file in &files {
+                    deep_merge(&mut merged, json_of(&file.entry)?, arrays);
+                }
+                Some(merged)
+            }
+            None => None,
+        };
+
+        Ok(Selected { files, merged })
+    }
+
+    async fn fetch(&self, client: &Client, source: &Source) -> Result<Vec<SelectedFile>, Error> {
+        let repo = client.repo(&source.project, &source.repo);
+        let selected = |entry| SelectedFile {
+            project: source.project.clone(),
+            repo: source.repo.clone(),
+            entry,
+        };
+
+        let mut entries = if self.json_paths.is_empty() {
+            repo.get_files(self.revision, &source.path_pattern)
+                .await?
+                .into_iter()
+                .filter(|e| e.entry_type() != EntryType::Directory)
+                .collect()
+        } else {
+            let listed = repo.list_files(self.revision, &source.path_pattern).await?;
+            let queries = listed
+                .iter()
+                .filter(|e| e.r#type == EntryType::Json)
+                .filter_map(|e| Query::of_json_path(&e.path, self.json_paths.clone()));
+            futures::stream::iter(queries)
+                .map(|query| {
+                    let repo = &repo;
+                    async move { repo.get_file(self.revision, &query).await }
+                })
+                .buffered(self.concurrency)
+                .try_collect::<Vec<_>>()
+                .await?
+        };
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+
+        Ok(entries.into_iter().map(selected).collect())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+    use wiremock::{
+        matchers::{method, path, query_param},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+
+    fn entry(path: &str, content: Value) -> Value {
+        json!({"path": path, "type": "JSON", "content": content, "revision": 2, "url": path})
+    }
+
+    #[tokio::test]
+    async fn test_selection() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path(
+                "/api/v1/projects/foo/repos/shared/contents/defaults/*.json",
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+                entry("/defaults/b.json", json!({"a": {"x": 2}})),
+                entry("/defaults/a.json", json!({"a": {"x": 1, "y": 1}})),
+            ])))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = Client::new(&server.uri(), None).await.unwrap();
+        let selected = Selection::new()
+            .from("foo", "shared", "/defaults/*.json")
+            .merge(ArrayMerge::Replace)
+            .execute(&client)
+            .await
+            .unwrap();
+        let paths: Vec<_> = selected
+            .files
+            .iter()
+            .map(|f| f.entry.path.as_str())
+            .collect();
+        assert_eq!(paths, ["/defaults/a.json", "/defaults/b.json"]);
+        assert_eq!(selected.merged, Some(json!({"a": {"x": 2, "y": 1}})));
+
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/list/service/**"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+                {"path": "/service/a.json", "type": "JSON"},
+                {"path": "/service/b.txt", "type": "TEXT"}
+            ])))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path(
+                "/api/v1/projects/foo/repos/bar/contents/service/a.json",
+            ))
+            .and(query_param("jsonpath", "$.a"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(entry("/service/a.json", json!({"y": 3}))),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let selected = Selection::new()
+            .from("foo", "bar", "/service/**")
+            .json_path("$.a")
+            .execute(&client)
+            .await
+            .unwrap();
+        assert_eq!(selected.files.len(), 1);
+        assert_eq!(selected.files[0].repo, "bar");
+        assert_eq!(selected.merged, None);
+    }
+}
@@ -1,19 +1,63 @@
 //! Content-related APIs
 use crate::{
-    model::{Change, Commit, CommitMessage, Entry, ListEntry, PushResult, Query, Revision},
-    services::{do_request, path},
-    Error, RepoClient,
+    model::{
+        Change, ChangeContent, Commit, CommitMessage, Directory, Entry, EntryContent,
+        EntryContentRef, EntryMeta, EntryRef, EntryType, ListEntry, MergeQuery, MergedEntry,
+        PushResult, Query, RawEntries, Revision, TypedEntry,
+    },
+    services::{do_raw_request, do_request, execute, path, read_body, status_unwrap},
+    validation, Error, RepoClient,
 };
 
+use std::{borrow::Cow, future::Future, pin::Pin};
+
 use async_trait::async_trait;
-use reqwest::{Body, Method};
-use serde::Serialize;
+use futures::{Stream, StreamExt, TryStreamExt};
+use reqwest::{
+    header::{HeaderValue, ACCEPT, CONTENT_TYPE},
+    Body, Method,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
-struct Push {
-    commit_message: CommitMessage,
-    changes: Vec<Change>,
+pub(crate) struct Push {
+    pub(crate) commit_message: CommitMessage,
+    pub(crate) changes: Vec<Change>,
+}
+
+/// Prefers the content of a file as is to an entry wrapping it.
+const RAW_CONTENT_ACCEPT: &str = "text/plain, application/octet-stream, application/json;q=0.5";
+
+/// The maximum number of commits retrieved by a request of
+/// [get_history_stream](trait@ContentService#method.get_history_stream).
+const HISTORY_PAGE_SIZE: u32 = 100;
+
+/// The next page of a [get_history_stream](trait@ContentService#method.get_history_stream).
+enum HistoryCursor {
+    Start,
+    Next { from: i64, to: i64 },
+    End,
+}
+
+/// Returns the absolute revision of `revision`, normalized by the server if relative.
+async fn absolute_revision<C>(repo: &C, revision: Revision) -> Result<i64, Error>
+where
+    C: ContentService + Sync + ?Sized,
+{
+    let revision = match revision.as_i64() {
+        Some(r) if r > 0 => return Ok(r),
+        _ => repo.normalize_revision(revision).await?,
+    };
+
+    revision
+        .as_i64()
+        .ok_or(Error::InvalidParams("no absolute revision"))
+}
+
+#[derive(Deserialize)]
+struct NormalizedRevision {
+    revision: Revision,
 }
 
 /// Content-related APIs
@@ -37,6 +81,70 @@ pub trait ContentService {
     /// Queries a file at the specified [`Revision`] and path with the specified [`Query`].
     async fn get_file(&self, revision: Revision, query: &Query) -> Result<Entry, Error>;
 
+    /// Converts a relative [`Revision`], e.g. [`Revision::HEAD`], into the absolute revision
+    /// it currently refers to. An absolute revision is returned as it is if it exists.
+    ///
+    /// [`Revision::DEFAULT`] is taken as [`Revision::HEAD`].
+    async fn normalize_revision(&self, revision: Revision) -> Result<Revision, Error>;
+
+    /// Queries a file at the specified [`Revision`] and path with the specified [`Query`],
+    /// and deserializes its content into `T`.
+    ///
+    /// Fails with [`Error::InvalidConfig`] if the content fails to deserialize.
+    async fn get_file_as<T>(
+        &self,
+        revision: Revision,
+        query: &Query,
+    ) -> Result<TypedEntry<T>, Error>
+    where
+        T: DeserializeOwned,
+    {
+        self.get_file(revision, query).await?.into_typed()
+    }
+
+    /// Retrieves the type of the file or the directory at `path` and the specified
+    /// [`Revision`], and the revision and the time of its last change, without its content.
+    ///
+    /// Fails with [`Error::EntryNotFound`] if there is no such entry.
+    async fn get_entry_meta(&self, revision: Revision, path: &str) -> Result<EntryMeta, Error> {
+        let path = Query::normalize_path(path);
+        let entry = self
+            .list_files(revision, &path)
+            .await?
+            .into_iter()
+            .find(|e| e.path == path)
+            .ok_or_else(|| Error::EntryNotFound(path.clone()))?;
+
+        let pattern = match entry.r#type {
+            EntryType::Directory => format!("{}/**", path.trim_end_matches('/')),
+            _ => path.clone(),
+        };
+        let commit = self
+            .get_history(revision, Revision::INIT, &pattern, Some(1))
+            .await?
+            .into_iter()
+            .next()
+            .ok_or_else(|| Error::EntryNotFound(path.clone()))?;
+
+        Ok(EntryMeta {
+            path,
+            r#type: entry.r#type,
+            revision: commit.revision,
+            modified_at: commit.pushed_at,
+        })
+    }
+
+    /// Merges the JSON files of the [`MergeQuery`] at the specified [`Revision`] into one,
+    /// each file overriding the ones before.
+    ///
+    /// Fails with [`Error::EntryNotFound`] if a required file, or every optional one,
+    /// doesn't exist.
+    async fn merge_files(
+        &self,
+        revision: Revision,
+        query: &MergeQuery,
+    ) -> Result<MergedEntry, Error>;
+
     /// Retrieves the files at the specified [`Revision`] matched by the path pattern.
     ///
     /// A path pattern is a variant of glob:
@@ -48,6 +156,73 @@ pub trait ContentService {
     ///   A file will be matched if any pattern matches.
     async fn get_files(&self, revision: Revision, path_pattern: &str) -> Result<Vec<Entry>, Error>;
 
+    /// Retrieves the files at `paths` and the specified [`Revision`] with one request per
+    /// file, sending up to `concurrency` of them at a time, e.g. to fetch a few files
+    /// scattered across a repository which a path pattern would over-fetch.
+    ///
+    /// A relative revision is normalized first, so that all the files are read at the same
+    /// revision. The results are in the order of `paths`, with an error for each file
+    /// which could not be retrieved, e.g. [`Error::EntryNotFound`].
+    async fn get_files_concurrent(
+        &self,
+        revision: Revision,
+        paths: &[&str],
+        concurrency: usize,
+    ) -> Result<Vec<Result<Entry, Error>>, Error> {
+        if concurrency == 0 {
+            return Err(Error::InvalidParams("concurrency must be positive"));
+        }
+        let revision = Revision::from(absolute_revision(self, revision).await?);
+
+        let requests: Vec<_> = paths
+            .iter()
+            .map(|path| async move {
+                let query =
+                    Query::identity(path).ok_or(Error::InvalidParams("path cannot be empty"))?;
+                self.get_file(revision, &query).await
+            })
+            .collect();
+        let results = futures::stream::iter(requests)
+            .buffered(concurrency)
+            .collect()
+            .await;
+
+        Ok(results)
+    }
+
+    /// Retrieves the files at the specified [`Revision`] matched by the path pattern
+    /// without decoding them, so they can be read as borrowed
+    /// [`EntryRef`](crate::model::EntryRef)s.
+    ///
+    /// See [get_files](#tymethod.get_files) for the syntax of the path pattern.
+    async fn get_files_raw(
+        &self,
+        revision: Revision,
+        path_pattern: &str,
+    ) -> Result<RawEntries, Error>;
+
+    /// Retrieves the content of the file at `path` and the specified [`Revision`] as bytes,
+    /// the text of a text file, the JSON text of a JSON file or the document of a YAML file,
+    /// without decoding it into an [`Entry`].
+    ///
+    /// [`RepoClient`] asks for the content as is, sparing the JSON escaping of large text
+    /// files, and extracts it from the entry if the server answers with one.
+    async fn get_file_raw(&self, revision: Revision, path: &str) -> Result<bytes::Bytes, Error>;
+
+    /// Retrieves the files at the specified [`Revision`] matched by the path pattern,
+    /// grouped into a [`Directory`] tree rooted at `/`.
+    ///
+    /// See [get_files](#tymethod.get_files) for the syntax of the path pattern.
+    async fn get_file_tree(
+        &self,
+        revision: Revision,
+        path_pattern: &str,
+    ) -> Result<Directory, Error> {
+        let entries = self.get_files(revision, path_pattern).await?;
+
+        Ok(Directory::from_entries(entries))
+    }
+
     /// Retrieves the history of the repository of the files matched by the given
     /// path pattern between two [`Revision`]s.
     /// Note that this method does not retrieve the diffs but only metadata about the changes.
@@ -61,6 +236,56 @@ pub trait ContentService {
         max_commits: Option<u32>,
     ) -> Result<Vec<Commit>, Error>;
 
+    /// Returns a stream of the history of the repository of the files matched by the given
+    /// path pattern between two [`Revision`]s, like [get_history](#tymethod.get_history)
+    /// without a limit.
+    ///
+    /// The commits are retrieved lazily, 100 at a time. The stream ends
+    /// after the first error.
+    fn get_history_stream<'a>(
+        &'a self,
+        from_rev: Revision,
+        to_rev: Revision,
+        path_pattern: &'a str,
+    ) -> Pin<Box<dyn Stream<Item = Result<Commit, Error>> + Send + 'a>>
+    where
+        Self: Sync,
+    {
+        let pages = futures::stream::try_unfold(HistoryCursor::Start, move |cursor| async move {
+            let (from, to) = match cursor {
+                HistoryCursor::Start => (
+                    absolute_revision(self, from_rev).await?,
+                    absolute_revision(self, to_rev).await?,
+                ),
+                HistoryCursor::Next { from, to } => (from, to),
+                HistoryCursor::End => return Ok::<_, Error>(None),
+            };
+            let commits = self
+                .get_history(
+                    Revision::from(from),
+                    Revision::from(to),
+                    path_pattern,
+                    Some(HISTORY_PAGE_SIZE),
+                )
+                .await?;
+
+            let next = match commits.last().and_then(|c| c.revision.as_i64()) {
+                Some(last) if commits.len() == HISTORY_PAGE_SIZE as usize && last != to => {
+                    let from = if from > to { last - 1 } else { last + 1 };
+                    HistoryCursor::Next { from, to }
+                }
+                _ => HistoryCursor::End,
+            };
+
+            Ok(Some((commits, next)))
+        });
+
+        pages
+            .map_ok(|commits| futures::stream::iter(commits.into_iter().map(Ok)))
+            .try_flatten()
+            .boxed()
+    }
+
     /// Returns the diff of a file between two [`Revision`]s.
     async fn get_diff(
         &self,
@@ -86,6 +311,15 @@ pub trait ContentService {
         path_pattern: &str,
     ) -> Result<Vec<Change>, Error>;
 
+    /// Returns the changes of the files the specified [`Change`]s would make if pushed on top
+    /// of `base_revision`, normalized by the server, e.g. a JSON patch resolved into the
+    /// resulting content, without pushing them.
+    async fn preview_diffs(
+        &self,
+        base_revision: Revision,
+        changes: Vec<Change>,
+    ) -> Result<Vec<Change>, Error>;
+
     /// Pushes the specified [`Change`]s to the repository.
     async fn push(
         &self,
@@ -93,6 +327,96 @@ pub trait ContentService {
         cm: CommitMessage,
         changes: Vec<Change>,
     ) -> Result<PushResult, Error>;
+
+    /// Renames the file at `from` to `to` on top of `base_revision` in one commit.
+    async fn rename_file(
+        &self,
+        base_revision: Revision,
+        from: &str,
+        to: &str,
+        cm: CommitMessage,
+    ) -> Result<PushResult, Error> {
+        let change = Change {
+            path: Query::normalize_path(from),
+            content: ChangeContent::Rename(Query::normalize_path(to)),
+        };
+
+        self.push(base_revision, cm, vec![change]).await
+    }
+
+    /// Copies the file at `from` to `to`, adding or replacing it, on top of `base_revision`
+    /// in one commit.
+    ///
+    /// The file is read at `base_revision`, normalized first if relative, so the push fails
+    /// with a conflict if it changed before the copy is pushed.
+    async fn copy_file(
+        &self,
+        base_revision: Revision,
+        from: &str,
+        to: &str,
+        cm: CommitMessage,
+    ) -> Result<PushResult, Error> {
+        let base_revision = Revision::from(absolute_revision(self, base_revision).await?);
+        let query = Query::identity(from).ok_or(Error::InvalidParams("path cannot be empty"))?;
+        let content = match self.get_file(base_revision, &query).await?.content {
+            EntryContent::Json(json) => ChangeContent::UpsertJson(json),
+            EntryContent::Text(text) => ChangeContent::UpsertText(text),
+            #[cfg(feature = "yaml")]
+            EntryContent::Yaml(yaml) => ChangeContent::UpsertYaml(yaml),
+            EntryContent::Directory => {
+                return Err(Error::InvalidParams("path is a directory, not a file"))
+            }
+        };
+        let change = Change {
+            path: Query::normalize_path(to),
+            content,
+        };
+
+        self.push(base_revision, cm, vec![change]).await
+    }
+
+    /// Pushes the [`Change`]s returned by `changes_fn`, calling it again with the latest
+    /// revision and pushing on top of it when the push conflicts with the changes made
+    /// since, at most `max_retries` times.
+    ///
+    /// `changes_fn` receives the absolute revision the changes are pushed on top of,
+    /// e.g. to read the current content at it. A relative `base_revision` is normalized
+    /// first, so the changes made after `changes_fn` reads the content are detected.
+    async fn push_with_retry<F, Fut>(
+        &self,
+        base_revision: Revision,
+        cm: CommitMessage,
+        mut changes_fn: F,
+        max_retries: u32,
+    ) -> Result<PushResult, Error>
+    where
+        F: FnMut(Revision) -> Fut + Send,
+        Fut: Future<Output = Result<Vec<Change>, Error>> + Send,
+    {
+        let mut base_revision = Revision::from(absolute_revision(self, base_revision).await?);
+        let mut retries = 0;
+        loop {
+            let changes = changes_fn(base_revision).await?;
+            match self.push(base_revision, cm.clone(), changes).await {
+                Err(e) if e.is_conflict() && retries < max_retries => {
+                    retries += 1;
+                    base_revision = match e {
+                        Error::Conflict {
+                            head_revision: Some(head),
+                            ..
+                        } => head,
+                        _ => self.normalize_revision(Revision::HEAD).await?,
+                    };
+                    log::debug!(
+                        "Retrying a conflicting push on top of revision {}: {}",
+                        base_revision,
+                        e
+                    );
+                }
+                result => return result,
+            }
+        }
+    }
 }
 
 #[async_trait]
@@ -114,6 +438,26 @@ impl<'a> ContentService for RepoClient<'a> {
     async fn get_file(&self, revision: Revision, query: &Query) -> Result<Entry, Error> {
         let p = path::content_path(self.project, self.repo, revision, query);
         let req = self.client.new_request(Method::GET, p, None)?;
+        let entry = do_request(self.client, req).await?;
+
+        self.client.after_fetch(entry).await
+    }
+
+    async fn normalize_revision(&self, revision: Revision) -> Result<Revision, Error> {
+        let p = path::repo_revision_path(self.project, self.repo, revision);
+        let req = self.client.new_request(Method::GET, p, None)?;
+        let normalized: NormalizedRevision = do_request(self.client, req).await?;
+
+        Ok(normalized.revision)
+    }
+
+    async fn merge_files(
+        &self,
+        revision: Revision,
+        query: &MergeQuery,
+    ) -> Result<MergedEntry, Error> {
+        let p = path::merge_path(self.project, self.repo, revision, query);
+        let req = self.client.new_request(Method::GET, p, None)?;
 
         do_request(self.client, req).await
     }
@@ -124,8 +468,71 @@ impl<'a> ContentService for RepoClient<'a> {
             path::contents_path(self.project, self.repo, revision, path_pattern),
             None,
         )?;
+        let entries: Vec<Entry> = do_request(self.client, req).await?;
 
-        do_request(self.client, req).await
+        let mut transformed = Vec::with_capacity(entries.len());
+        for entry in entries {
+            transformed.push(self.client.after_fetch(entry).await?);
+        }
+
+        Ok(transformed)
+    }
+
+    async fn get_files_raw(
+        &self,
+        revision: Revision,
+        path_pattern: &str,
+    ) -> Result<RawEntries, Error> {
+        let req = self.client.new_request(
+            Method::GET,
+            path::contents_path(self.project, self.repo, revision, path_pattern),
+            None,
+        )?;
+
+        let body = do_raw_request(self.client, req).await?;
+
+        Ok(RawEntries::new(body))
+    }
+
+    async fn get_file_raw(&self, revision: Revision, path: &str) -> Result<bytes::Bytes, Error> {
+        let query = Query::identity(path).ok_or(Error::InvalidParams("path cannot be empty"))?;
+        let p = path::content_path(self.project, self.repo, revision, &query);
+        let mut req = self.client.new_request(Method::GET, p, None)?;
+        req.headers_mut()
+            .insert(ACCEPT, HeaderValue::from_static(RAW_CONTENT_ACCEPT));
+
+        execute(self.client, req, 1, |resp| async move {
+            let ok_resp = status_unwrap(self.client, resp).await?;
+            let is_entry = ok_resp
+                .headers()
+                .get(CONTENT_TYPE)
+                .and_then(|v| v.to_str().ok())
+                .is_some_and(|v| v.starts_with("application/json"));
+            let body = read_body(self.client, ok_resp).await?;
+            if !is_entry {
+                return Ok(body);
+            }
+
+            let entry: EntryRef<'_> = serde_json::from_slice(&body)?;
+            match &entry.content {
+                EntryContentRef::Json(json) => Ok(body.slice_ref(json.as_bytes())),
+                EntryContentRef::Text(Cow::Borrowed(text)) => Ok(body.slice_ref(text.as_bytes())),
+                EntryContentRef::Text(Cow::Owned(text)) => {
+                    Ok(bytes::Bytes::from(text.clone().into_bytes()))
+                }
+                #[cfg(feature = "yaml")]
+                EntryContentRef::Yaml(json) => {
+                    let yaml: serde_yaml::Value = serde_json::from_str(json)?;
+                    let yaml = serde_yaml::to_string(&yaml)
+                        .map_err(|_| Error::InvalidParams("YAML content cannot be serialized"))?;
+                    Ok(bytes::Bytes::from(yaml.into_bytes()))
+                }
+                EntryContentRef::Directory => {
+                    Err(Error::InvalidParams("path is a directory, not a file"))
+                }
+            }
+        })
+        .await
     }
 
     async fn get_history(
@@ -173,6 +580,28 @@ impl<'a> ContentService for RepoClient<'a> {
         do_request(self.client, req).await
     }
 
+    async fn preview_diffs(
+        &self,
+        base_revision: Revision,
+        changes: Vec<Change>,
+    ) -> Result<Vec<Change>, Error> {
+        if changes.is_empty() {
+            return Err(Error::InvalidParams("no changes to preview"));
+        }
+        validation::validate_changes(&changes)?;
+
+        let mut transformed = Vec::with_capacity(changes.len());
+        for change in changes {
+            transformed.push(self.client.before_push(change).await?);
+        }
+        let body = Body::from(serde_json::to_string(&transformed)?);
+
+        let p = path::contents_preview_path(self.project, self.repo, base_revision);
+        let req = self.client.new_request(Method::POST, p, Some(body))?;
+
+        do_request(self.client, req).await
+    }
+
     async fn push(
         &self,
         base_revision: Revision,
@@ -187,17 +616,35 @@ impl<'a> ContentService for RepoClient<'a> {
         if changes.is_empty() {
             return Err(Error::InvalidParams("no changes to commit"));
         }
+        validation::validate_changes(&changes)?;
+        crate::policy::check(self, base_revision, &changes).await?;
+
+        let mut transformed = Vec::with_capacity(changes.len());
+        for change in changes {
+            transformed.push(self.client.before_push(change).await?);
+        }
 
         let body: String = serde_json::to_string(&Push {
             commit_message: cm,
-            changes,
+            changes: transformed,
         })?;
         let body = Body::from(body);
 
         let p = path::contents_push_path(self.project, self.repo, base_revision);
         let req = self.client.new_request(Method::POST, p, Some(body))?;
 
-        do_request(self.client, req).await
+        match do_request(self.client, req).await {
+            Err(Error::Conflict {
+                base_revision: None,
+                head_revision,
+                message,
+            }) => Err(Error::Conflict {
+                base_revision: Some(base_revision),
+                head_revision,
+                message,
+            }),
+            result => result,
+        }
     }
 }
 
@@ -205,11 +652,12 @@ impl<'a> ContentService for RepoClient<'a> {
 mod test {
     use super::*;
     use crate::{
-        model::{Author, ChangeContent, EntryContent, EntryType, Revision},
+        json_path::JsonPath,
+        model::{Author, ChangeContent, EntryContent, EntryMeta, EntryType, MergeSource, Revision},
         Client,
     };
     use wiremock::{
-        matchers::{body_json, header, method, path, query_param},
+        matchers::{body_json, header, header_exists, method, path, query_param},
         Mock, MockServer, ResponseTemplate,
     };
 
@@ -375,6 +823,164 @@ mod test {
         assert!(matches!(entry.content, EntryContent::Json(js) if js == expected));
     }
 
+    #[tokio::test]
+    async fn test_get_file_as() {
+        #[derive(Debug, PartialEq, serde::Deserialize)]
+        struct Config {
+            a: String,
+        }
+
+        let server = MockServer::start().await;
+        let resp = ResponseTemplate::new(200).set_body_raw(
+            r#"{
+                    "path":"/a.json",
+                    "type":"JSON",
+                    "revision":2,
+                    "url": "/api/v1/projects/foo/repos/bar/contents/a.json",
+                    "content":{"a":"b"}
+                }"#,
+            "application/json",
+        );
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/contents/a.json"))
+            .respond_with(resp)
+            .mount(&server)
+            .await;
+
+        let client = Client::new(&server.uri(), None).await.unwrap();
+        let repo = client.repo("foo", "bar");
+        let query = Query::identity("/a.json").unwrap();
+        let entry = repo
+            .get_file_as::<Config>(Revision::HEAD, &query)
+            .await
+            .unwrap();
+        assert_eq!(entry.value, Config { a: "b".to_owned() });
+        assert_eq!(entry.revision, Revision::from(2));
+        assert_eq!(entry.path, "/a.json");
+
+        let err = repo
+            .get_file_as::<Vec<u32>>(Revision::HEAD, &query)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::InvalidConfig { path, .. } if path == "/a.json"));
+    }
+
+    #[tokio::test]
+    async fn test_get_files_concurrent() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/revision/-1"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_raw(r#"{"revision":4}"#, "application/json"),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        for name in ["a", "c"] {
+            Mock::given(method("GET"))
+                .and(path(format!("/api/v1/projects/foo/repos/bar/contents/{}.json", name)))
+                .and(query_param("revision", "4"))
+                .respond_with(ResponseTemplate::new(200).set_body_raw(
+                    format!(
+                        r#"{{"path":"/{0}.json","type":"JSON","content":{{"name":"{0}"}},"revision":4,"url":"/{0}.json"}}"#,
+                        name
+                    ),
+                    "application/json",
+                ))
+                .mount(&server)
+                .await;
+        }
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/contents/b.json"))
+            .respond_with(ResponseTemplate::new(404).set_body_raw(
+                r#"{"exception":"com.linecorp.centraldogma.common.EntryNotFoundException","message":"/b.json"}"#,
+                "application/json",
+            ))
+            .mount(&server)
+            .await;
+
+        let client = Client::new(&server.uri(), None).await.unwrap();
+        let repo = client.repo("foo", "bar");
+        let results = repo
+            .get_files_concurrent(Revision::HEAD, &["/a.json", "/b.json", "/c.json"], 2)
+            .await
+            .unwrap();
+
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].as_ref().unwrap().path, "/a.json");
+        assert!(matches!(results[1], Err(Error::EntryNotFound(_))));
+        assert_eq!(results[2].as_ref().unwrap().path, "/c.json");
+        assert!(matches!(
+            repo.get_files_concurrent(Revision::HEAD, &["/a.json"], 0)
+                .await,
+            Err(Error::InvalidParams(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_normalize_revision() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/revision/-2"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_raw(r#"{"revision":9}"#, "application/json"),
+            )
+            .mount(&server)
+            .await;
+
+        let client = Client::new(&server.uri(), None).await.unwrap();
+        let revision = client
+            .repo("foo", "bar")
+            .normalize_revision(Revision::from(-2))
+            .await
+            .unwrap();
+
+        assert_eq!(revision, Revision::from(9));
+    }
+
+    #[tokio::test]
+    async fn test_merge_files() {
+        let server = MockServer::start().await;
+        let resp = ResponseTemplate::new(200).set_body_raw(
+            r#"{
+                    "revision":3,
+                    "type":"JSON",
+                    "content":{"a":"c"},
+                    "paths":["/a.json","/b.json"]
+                }"#,
+            "application/json",
+        );
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/merge"))
+            .and(query_param("revision", "3"))
+            .and(query_param("path", "/a.json"))
+            .and(query_param("optional_path", "/b.json"))
+            .and(query_param("jsonpath", "$.a"))
+            .respond_with(resp)
+            .mount(&server)
+            .await;
+
+        let client = Client::new(&server.uri(), None).await.unwrap();
+        let query = MergeQuery::of_json_path(
+            vec![
+                MergeSource::required("a.json").unwrap(),
+                MergeSource::optional("/b.json").unwrap(),
+            ],
+            vec![JsonPath::root().field("a")],
+        )
+        .unwrap();
+        let merged = client
+            .repo("foo", "bar")
+            .merge_files(Revision::from(3), &query)
+            .await
+            .unwrap();
+
+        server.reset().await;
+        assert_eq!(merged.revision, Revision::from(3));
+        assert_eq!(merged.content, serde_json::json!({"a": "c"}));
+        assert_eq!(merged.paths, ["/a.json", "/b.json"]);
+    }
+
     #[tokio::test]
     async fn test_get_file_json_path() {
         let server = MockServer::start().await;
@@ -491,6 +1097,148 @@ mod test {
         }
     }
 
+    #[tokio::test]
+    async fn test_get_files_raw() {
+        let server = MockServer::start().await;
+        let resp = ResponseTemplate::new(200).set_body_raw(
+            r#"[{
+                    "path":"/a.json",
+                    "type":"JSON",
+                    "revision":2,
+                    "url": "/api/v1/projects/foo/repos/bar/contents/a.json",
+                    "content":{"a":"b"}
+                }, {
+                    "path":"/b.txt",
+                    "type":"TEXT",
+                    "revision":2,
+                    "url": "/api/v1/projects/foo/repos/bar/contents/b.txt",
+                    "content":"hello world~!"
+                }]"#,
+            "application/json",
+        );
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/contents/**"))
+            .and(header("Authorization", "Bearer anonymous"))
+            .respond_with(resp)
+            .mount(&server)
+            .await;
+
+        let client = Client::new(&server.uri(), None).await.unwrap();
+        let raw = client
+            .repo("foo", "bar")
+            .get_files_raw(Revision::HEAD, "/**")
+            .await
+            .unwrap();
+
+        server.reset().await;
+        let entries = raw.entries().unwrap();
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].path, "/a.json");
+        assert_eq!(entries[0].json_str(), Some(r#"{"a":"b"}"#));
+        assert_eq!(entries[1].path, "/b.txt");
+        assert_eq!(entries[1].text(), Some("hello world~!"));
+    }
+
+    #[tokio::test]
+    async fn test_get_file_raw() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/contents/big.txt"))
+            .and(header_exists("accept"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw("a\nb\n", "text/plain"))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/contents/a.json"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(
+                r#"{"path":"/a.json","type":"JSON","revision":2,"url":"/a.json","content":{"a": "b"}}"#,
+                "application/json",
+            ))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/contents/b.txt"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(
+                r#"{"path":"/b.txt","type":"TEXT","revision":2,"url":"/b.txt","content":"b\n"}"#,
+                "application/json",
+            ))
+            .mount(&server)
+            .await;
+
+        let client = Client::new(&server.uri(), None).await.unwrap();
+        let repo = client.repo("foo", "bar");
+
+        let raw = repo.get_file_raw(Revision::HEAD, "/big.txt").await.unwrap();
+        assert_eq!(raw, "a\nb\n");
+        let requests = server.received_requests().await.unwrap();
+        let accept: Vec<_> = requests[0].headers[&"accept".into()]
+            .iter()
+            .map(|v| v.as_str())
+            .collect();
+        assert_eq!(accept.join(", "), RAW_CONTENT_ACCEPT);
+        // Extracted from the entry when the server answers with one
+        let raw = repo.get_file_raw(Revision::HEAD, "a.json").await.unwrap();
+        assert_eq!(raw, r#"{"a": "b"}"#);
+        let raw = repo.get_file_raw(Revision::HEAD, "/b.txt").await.unwrap();
+        assert_eq!(raw, "b\n");
+    }
+
+    #[tokio::test]
+    async fn test_get_entry_meta() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/list/a.json"))
+            .and(query_param("revision", "5"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_raw(r#"[{"path":"/a.json","type":"JSON"}]"#, "application/json"),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/list/b.json"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw("[]", "application/json"))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/commits/5"))
+            .and(query_param("to", "1"))
+            .and(query_param("path", "/a.json"))
+            .and(query_param("maxCommits", "1"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(
+                r#"[{
+                    "revision": 3,
+                    "author": {"name": "minux", "email": "minux@m.x"},
+                    "commitMessage": {"summary": "Edit a.json"},
+                    "pushedAt": "2017-05-22T00:00:00Z"
+                }]"#,
+                "application/json",
+            ))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = Client::new(&server.uri(), None).await.unwrap();
+        let repo = client.repo("foo", "bar");
+        let meta = repo
+            .get_entry_meta(Revision::from(5), "a.json")
+            .await
+            .unwrap();
+        assert_eq!(
+            meta,
+            EntryMeta {
+                path: "/a.json".to_owned(),
+                r#type: EntryType::Json,
+                revision: Revision::from(3),
+                modified_at: Some("2017-05-22T00:00:00Z".to_owned()),
+            }
+        );
+
+        let err = repo.get_entry_meta(Revision::HEAD, "/b.json").await;
+        assert!(matches!(err, Err(Error::EntryNotFound(p)) if p == "/b.json"));
+    }
+
     #[tokio::test]
     async fn test_get_history() {
         let server = MockServer::start().await;
@@ -555,6 +1303,55 @@ mod test {
         }
     }
 
+    fn commits(revisions: impl Iterator<Item = i64>) -> serde_json::Value {
+        revisions
+            .map(|r| {
+                serde_json::json!({
+                    "revision": r,
+                    "author": {"name": "minux", "email": "minux@m.x"},
+                    "commitMessage": {"summary": format!("Commit {}", r)}
+                })
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_get_history_stream() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/revision/-1"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_raw(r#"{"revision":250}"#, "application/json"),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        for (from, to) in [(250, 151), (150, 51), (50, 1)] {
+            Mock::given(method("GET"))
+                .and(path(format!(
+                    "/api/v1/projects/foo/repos/bar/commits/{}",
+                    from
+                )))
+                .and(query_param("to", "1"))
+                .and(query_param("maxCommits", "100"))
+                .respond_with(ResponseTemplate::new(200).set_body_json(commits((to..=from).rev())))
+                .expect(1)
+                .mount(&server)
+                .await;
+        }
+
+        let client = Client::new(&server.uri(), None).await.unwrap();
+        let repo = client.repo("foo", "bar");
+        let revisions: Vec<_> = repo
+            .get_history_stream(Revision::HEAD, Revision::INIT, "/**")
+            .map_ok(|c| c.revision.as_i64().unwrap())
+            .try_collect()
+            .await
+            .unwrap();
+
+        assert_eq!(revisions, (1..=250).rev().collect::<Vec<_>>());
+    }
+
     #[tokio::test]
     async fn test_get_diff() {
         let server = MockServer::start().await;
@@ -665,6 +1462,49 @@ mod test {
         }
     }
 
+    #[tokio::test]
+    async fn test_preview_diffs() {
+        let server = MockServer::start().await;
+        let resp = ResponseTemplate::new(200).set_body_raw(
+            r#"[{
+                "path":"/a.json",
+                "type":"UPSERT_JSON",
+                "content":{"a":"c"}
+            }]"#,
+            "application/json",
+        );
+        let changes = vec![Change {
+            path: "/a.json".to_string(),
+            content: ChangeContent::ApplyJsonPatch(serde_json::json!([
+                {"op": "replace", "path": "/a", "value": "c"}
+            ])),
+        }];
+        Mock::given(method("POST"))
+            .and(path("/api/v1/projects/foo/repos/bar/preview"))
+            .and(query_param("revision", "2"))
+            .and(body_json(&changes))
+            .respond_with(resp)
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = Client::new(&server.uri(), None).await.unwrap();
+        let diffs = client
+            .repo("foo", "bar")
+            .preview_diffs(Revision::from(2), changes)
+            .await
+            .unwrap();
+
+        drop(server);
+        assert_eq!(
+            diffs,
+            vec![Change {
+                path: "/a.json".to_string(),
+                content: ChangeContent::UpsertJson(serde_json::json!({"a":"c"})),
+            }]
+        );
+    }
+
     #[tokio::test]
     async fn test_push() {
         let server = MockServer::start().await;
@@ -717,6 +1557,188 @@ mod test {
         assert_eq!(result.unwrap(), expected);
     }
 
+    #[tokio::test]
+    async fn test_rename_and_copy_file() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/revision/-1"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_raw(r#"{"revision":3}"#, "application/json"),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/contents/a.json"))
+            .and(query_param("revision", "3"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(
+                r#"{"path":"/a.json","type":"JSON","content":{"a":"b"},"revision":3,"url":"/a.json"}"#,
+                "application/json",
+            ))
+            .mount(&server)
+            .await;
+        let pushes = [
+            (
+                "-1",
+                "Rename a.json",
+                Change {
+                    path: "/a.json".to_string(),
+                    content: ChangeContent::Rename("/b.json".to_string()),
+                },
+            ),
+            (
+                "3",
+                "Copy a.json",
+                Change {
+                    path: "/c.json".to_string(),
+                    content: ChangeContent::UpsertJson(serde_json::json!({"a":"b"})),
+                },
+            ),
+        ];
+        for (revision, summary, change) in pushes {
+            Mock::given(method("POST"))
+                .and(path("/api/v1/projects/foo/repos/bar/contents"))
+                .and(query_param("revision", revision))
+                .and(body_json(Push {
+                    commit_message: CommitMessage::only_summary(summary),
+                    changes: vec![change],
+                }))
+                .respond_with(
+                    ResponseTemplate::new(200)
+                        .set_body_raw(r#"{"revision":4}"#, "application/json"),
+                )
+                .expect(1)
+                .mount(&server)
+                .await;
+        }
+
+        let client = Client::new(&server.uri(), None).await.unwrap();
+        let repo = client.repo("foo", "bar");
+        let result = repo
+            .rename_file(
+                Revision::HEAD,
+                "a.json",
+                "/b.json",
+                CommitMessage::only_summary("Rename a.json"),
+            )
+            .await
+            .unwrap();
+        assert_eq!(result.revision, Revision::from(4));
+        let result = repo
+            .copy_file(
+                Revision::HEAD,
+                "/a.json",
+                "/c.json",
+                CommitMessage::only_summary("Copy a.json"),
+            )
+            .await
+            .unwrap();
+        assert_eq!(result.revision, Revision::from(4));
+    }
+
+    #[tokio::test]
+    async fn test_push_conflict() {
+        let server = MockServer::start().await;
+        let resp = ResponseTemplate::new(409).set_body_raw(
+            r#"{
+                "exception":"com.linecorp.centraldogma.common.ChangeConflictException",
+                "message":"/a.json has been modified"
+            }"#,
+            "application/json",
+        );
+        Mock::given(method("POST"))
+            .and(path("/api/v1/projects/foo/repos/bar/contents"))
+            .and(query_param("revision", "2"))
+            .respond_with(resp)
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = Client::new(&server.uri(), None).await.unwrap();
+        let changes = vec![Change {
+            path: "/a.json".to_string(),
+            content: ChangeContent::UpsertJson(serde_json::json!({"a":"b"})),
+        }];
+        let err = client
+            .repo("foo", "bar")
+            .push(
+                Revision::from(2),
+                CommitMessage::only_summary("Add a.json"),
+                changes,
+            )
+            .await
+            .unwrap_err();
+
+        match err {
+            Error::Conflict {
+                base_revision,
+                head_revision,
+                message,
+            } => {
+                assert_eq!(base_revision, Some(Revision::from(2)));
+                assert_eq!(head_revision, None);
+                assert_eq!(message, "/a.json has been modified");
+            }
+            e => panic!("unexpected error: {:?}", e),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_push_with_retry() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/v1/projects/foo/repos/bar/contents"))
+            .and(query_param("revision", "2"))
+            .respond_with(ResponseTemplate::new(409).set_body_raw(
+                r#"{
+                    "exception":"com.linecorp.centraldogma.common.ChangeConflictException",
+                    "message":"invalid baseRevision: 2 (expected: 3 or equivalent)"
+                }"#,
+                "application/json",
+            ))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/api/v1/projects/foo/repos/bar/contents"))
+            .and(query_param("revision", "3"))
+            .and(body_json(serde_json::json!({
+                "commitMessage": {"summary": "Increment a"},
+                "changes": [{"path": "/a.json", "type": "UPSERT_JSON", "content": {"a": 3}}]
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(
+                r#"{"revision":4,"pushedAt":"2017-05-22T00:00:00Z"}"#,
+                "application/json",
+            ))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = Client::new(&server.uri(), None).await.unwrap();
+        let repo = client.repo("foo", "bar");
+        let mut bases = Vec::new();
+        let result = repo
+            .push_with_retry(
+                Revision::from(2),
+                CommitMessage::only_summary("Increment a"),
+                |base| {
+                    bases.push(base);
+                    let a = base.as_i64().unwrap();
+                    async move {
+                        Ok(vec![Change::from((
+                            "/a.json",
+                            serde_json::json!({ "a": a }),
+                        ))])
+                    }
+                },
+                3,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(result.revision, Revision::from(4));
+        assert_eq!(bases, vec![Revision::from(2), Revision::from(3)]);
+    }
+
     #[tokio::test]
     async fn test_push_two_files() {
         let server = MockServer::start().await;
@@ -0,0 +1,350 @@
+//! Metadata-related APIs, administrating the members, tokens and permissions of a project
+use crate::{
+    client::{Error, ProjectClient},
+    model::{PerRolePermissions, Permission, ProjectMetadata, ProjectRole, Revision},
+    services::{do_request, path},
+};
+
+use async_trait::async_trait;
+use reqwest::{Body, Method};
+use serde::Serialize;
+use serde_json::json;
+
+#[derive(Serialize)]
+struct IdAndRole<'a> {
+    id: &'a str,
+    role: ProjectRole,
+}
+
+#[derive(Serialize)]
+struct IdAndPermissions<'a> {
+    id: &'a str,
+    permissions: &'a [Permission],
+}
+
+/// Metadata-related APIs, which require the owner role or the administrator privilege
+///
+/// The changes are committed to the `dogma` repository of the project, and return the
+/// [`Revision`] of the commit.
+#[async_trait]
+pub trait MetadataService {
+    /// Retrieves the members, tokens and permissions of the project.
+    async fn get_metadata(&self) -> Result<ProjectMetadata, Error>;
+
+    /// Adds a member to the project.
+    async fn add_member(&self, login: &str, role: ProjectRole) -> Result<Revision, Error>;
+
+    /// Changes the role of a member of the project.
+    async fn update_member_role(&self, login: &str, role: ProjectRole) -> Result<Revision, Error>;
+
+    /// Removes a member from the project, with its permissions on the repositories.
+    async fn remove_member(&self, login: &str) -> Result<Revision, Error>;
+
+    /// Registers an application token to the project.
+    async fn add_token(&self, app_id: &str, role: ProjectRole) -> Result<Revision, Error>;
+
+    /// Changes the role of a token registered to the project.
+    async fn update_token_role(&self, app_id: &str, role: ProjectRole) -> Result<Revision, Error>;
+
+    /// Unregisters a token from the project, with its permissions on the repositories.
+    async fn remove_token(&self, app_id: &str) -> Result<Revision, Error>;
+
+    /// Replaces the permissions on a repository of the members, by their role.
+    async fn update_repo_role_permissions(
+        &self,
+        repo_name: &str,
+        permissions: &PerRolePermissions,
+    ) -> Result<Revision, Error>;
+
+    /// Grants permissions on a repository to a member, overriding the ones of its role.
+    async fn add_member_repo_permissions(
+        &self,
+        repo_name: &str,
+        login: &str,
+        permissions: &[Permission],
+    ) -> Result<Revision, Error>;
+
+    /// Revokes the permissions on a repository granted to a member.
+    async fn remove_member_repo_permissions(
+        &self,
+        repo_name: &str,
+        login: &str,
+    ) -> Result<Revision, Error>;
+
+    /// Grants permissions on a repository to a token, overriding the ones of its role.
+    async fn add_token_repo_permissions(
+        &self,
+        repo_name: &str,
+        app_id: &str,
+        permissions: &[Permission],
+    ) -> Result<Revision, Error>;
+
+    /// Revokes the permissions on a repository granted to a token.
+    async fn remove_token_repo_permissions(
+        &self,
+        repo_name: &str,
+        app_id: &str,
+    ) -> Result<Revision, Error>;
+}
+
+impl<'a> ProjectClient<'a> {
+    async fn send_metadata<T: Serialize>(
+        &self,
+        method: Method,
+        path: String,
+        body: Option<&T>,
+    ) -> Result<Revision, Error> {
+        let body = match body {
+            Some(body) => Some(Body::from(serde_json::to_vec(body)?)),
+            None => None,
+        };
+        let req = self.client.new_request(method, path, body)?;
+
+        do_request(self.client, req).await
+    }
+}
+
+/// Returns a JSON patch replacing the role of a member or a token.
+fn replace_role(role: ProjectRole) -> serde_json::Value {
+    json!([{"op": "replace", "path": "/role", "value": role}])
+}
+
+#[async_trait]
+impl<'a> MetadataService for ProjectClient<'a> {
+    async fn get_metadata(&self) -> Result<ProjectMetadata, Error> {
+        let req = self
+            .client
+            .new_request(Method::GET, path::project_path(self.project), None)?;
+
+        do_request(self.client, req).await
+    }
+
+    async fn add_member(&self, login: &str, role: ProjectRole) -> Result<Revision, Error> {
+        let body = IdAndRole { id: login, role };
+        let p = path::metadata_members_path(self.project);
+
+        self.send_metadata(Method::POST, p, Some(&body)).await
+    }
+
+    async fn update_member_role(&self, login: &str, role: ProjectRole) -> Result<Revision, Error> {
+        let p = path::metadata_member_path(self.project, login);
+
+        self.send_metadata(Method::PATCH, p, Some(&replace_role(role)))
+            .await
+    }
+
+    async fn remove_member(&self, login: &str) -> Result<Revision, Error> {
+        let p = path::metadata_member_path(self.project, login);
+
+        self.send_metadata::<()>(Method::DELETE, p, None).await
+    }
+
+    async fn add_token(&self, app_id: &str, role: ProjectRole) -> Result<Revision, Error> {
+        let body = IdAndRole { id: app_id, role };
+        let p = path::metadata_tokens_path(self.project);
+
+        self.send_metadata(Method::POST, p, Some(&body)).await
+    }
+
+    async fn update_token_role(&self, app_id: &str, role: ProjectRole) -> Result<Revision, Error> {
+        let p = path::metadata_token_path(self.project, app_id);
+
+        self.send_metadata(Method::PATCH, p, Some(&replace_role(role)))
+            .await
+    }
+
+    async fn remove_token(&self, app_id: &str) -> Result<Revision, Error> {
+        let p = path::metadata_token_path(self.project, app_id);
+
+        self.send_metadata::<()>(Method::DELETE, p, None).await
+    }
+
+    async fn update_repo_role_permissions(
+        &self,
+        repo_name: &str,
+        permissions: &PerRolePermissions,
+    ) -> Result<Revision, Error> {
+        let p = path::metadata_repo_perm_path(self.project, repo_name, "role");
+
+        self.send_metadata(Method::POST, p, Some(permissions)).await
+    }
+
+    async fn add_member_repo_permissions(
+        &self,
+        repo_name: &str,
+        login: &str,
+        permissions: &[Permission],
+    ) -> Result<Revision, Error> {
+        let body = IdAndPermissions {
+            id: login,
+            permissions,
+        };
+        let p = path::metadata_repo_perm_path(self.project, repo_name, "users");
+
+        self.send_metadata(Method::POST, p, Some(&body)).await
+    }
+
+    async fn remove_member_repo_permissions(
+        &self,
+        repo_name: &str,
+        login: &str,
+    ) -> Result<Revision, Error> {
+        let p = format!(
+            "{}/{}",
+            path::metadata_repo_perm_path(self.project, repo_name, "users"),
+            login
+        );
+
+        self.send_metadata::<()>(Method::DELETE, p, None).await
+    }
+
+    async fn add_token_repo_permissions(
+        &self,
+        repo_name: &str,
+        app_id: &str,
+        permissions: &[Permission],
+    ) -> Result<Revision, Error> {
+        let body = IdAndPermissions {
+            id: app_id,
+            permissions,
+        };
+        let p = path::metadata_repo_perm_path(self.project, repo_name, "tokens");
+
+        self.send_metadata(Method::POST, p, Some(&body)).await
+    }
+
+    async fn remove_token_repo_permissions(
+        &self,
+        repo_name: &str,
+        app_id: &str,
+    ) -> Result<Revision, Error> {
+        let p = format!(
+            "{}/{}",
+            path::metadata_repo_perm_path(self.project, repo_name, "tokens"),
+            app_id
+        );
+
+        self.send_metadata::<()>(Method::DELETE, p, None).await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Client;
+    use wiremock::{
+        matchers::{body_json, header, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    #[tokio::test]
+    async fn test_get_metadata() {
+        let server = MockServer::start().await;
+        let resp = ResponseTemplate::new(200).set_body_raw(
+            r#"{
+                "name":"foo",
+                "repos":{
+                    "bar":{
+                        "name":"bar",
+                        "perRolePermissions":{"owner":["READ","WRITE"],"member":["READ"],"guest":[]},
+                        "perUserPermissions":{"a@b.c":["READ","WRITE"]},
+                        "perTokenPermissions":{},
+                        "creation":{"user":"a@b.c","timestamp":"2023-01-31T09:00:00Z"}
+                    }
+                },
+                "members":{
+                    "a@b.c":{
+                        "login":"a@b.c",
+                        "role":"OWNER",
+                        "creation":{"user":"a@b.c","timestamp":"2023-01-31T09:00:00Z"}
+                    }
+                },
+                "tokens":{
+                    "app":{
+                        "appId":"app",
+                        "role":"MEMBER",
+                        "creation":{"user":"a@b.c","timestamp":"2023-01-31T09:00:00Z"}
+                    }
+                },
+                "creation":{"user":"a@b.c","timestamp":"2023-01-31T09:00:00Z"}
+            }"#,
+            "application/json",
+        );
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo"))
+            .respond_with(resp)
+            .mount(&server)
+            .await;
+
+        let client = Client::new(&server.uri(), None).await.unwrap();
+        let metadata = client.project("foo").get_metadata().await.unwrap();
+
+        drop(server);
+        assert_eq!(metadata.name, "foo");
+        assert_eq!(metadata.members["a@b.c"].role, ProjectRole::Owner);
+        assert_eq!(metadata.tokens["app"].role, ProjectRole::Member);
+        let repo = &metadata.repos["bar"];
+        assert_eq!(repo.per_role_permissions.member, vec![Permission::Read]);
+        assert_eq!(
+            repo.per_user_permissions["a@b.c"],
+            vec![Permission::Read, Permission::Write]
+        );
+        assert_eq!(repo.removal, None);
+    }
+
+    #[tokio::test]
+    async fn test_members_and_permissions() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/v1/metadata/foo/members"))
+            .and(body_json(json!({"id": "a@b.c", "role": "MEMBER"})))
+            .respond_with(ResponseTemplate::new(200).set_body_raw("2", "application/json"))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("PATCH"))
+            .and(path("/api/v1/metadata/foo/members/a@b.c"))
+            .and(header("Content-Type", "application/json-patch+json"))
+            .and(body_json(
+                json!([{"op": "replace", "path": "/role", "value": "OWNER"}]),
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_raw("3", "application/json"))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/api/v1/metadata/foo/repos/bar/perm/tokens"))
+            .and(body_json(json!({"id": "app", "permissions": ["READ"]})))
+            .respond_with(ResponseTemplate::new(200).set_body_raw("4", "application/json"))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("DELETE"))
+            .and(path("/api/v1/metadata/foo/repos/bar/perm/users/a@b.c"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw("5", "application/json"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = Client::new(&server.uri(), None).await.unwrap();
+        let project = client.project("foo");
+        let revision = project
+            .add_member("a@b.c", ProjectRole::Member)
+            .await
+            .unwrap();
+        assert_eq!(revision, Revision::from(2));
+        project
+            .update_member_role("a@b.c", ProjectRole::Owner)
+            .await
+            .unwrap();
+        project
+            .add_token_repo_permissions("bar", "app", &[Permission::Read])
+            .await
+            .unwrap();
+        let revision = project
+            .remove_member_repo_permissions("bar", "a@b.c")
+            .await
+            .unwrap();
+        assert_eq!(revision, Revision::from(5));
+    }
+}
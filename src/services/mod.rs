@@ -1,41 +1,693 @@
 pub mod content;
-mod path;
+pub mod metadata;
+pub(crate) mod path;
 pub mod project;
 pub mod repository;
 pub mod watch;
 
-use reqwest::Response;
+use std::{
+    backtrace::Backtrace,
+    future::Future,
+    time::{Duration, SystemTime},
+};
+
+use reqwest::{
+    header::{HeaderValue, RETRY_AFTER},
+    Response, StatusCode,
+};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
-use crate::{Client, Error};
+use crate::{client::ErrorContext, model::Revision, recorder::RequestEvent, Client, Error};
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct ErrorMessage {
+    #[serde(default)]
+    exception: Option<String>,
     message: String,
 }
 
 /// convert HTTP Response with status < 200 and > 300 to Error
-async fn status_unwrap(resp: Response) -> Result<Response, Error> {
+async fn status_unwrap(client: &Client, resp: Response) -> Result<Response, Error> {
     match resp.status().as_u16() {
         code if !(200..300).contains(&code) => {
-            let err_body = resp.text().await?;
-            let err_msg: ErrorMessage =
-                serde_json::from_str(&err_body).unwrap_or(ErrorMessage { message: err_body });
+            let retry_after = resp.headers().get(RETRY_AFTER).and_then(parse_retry_after);
+            let err_body = read_body(client, resp).await?;
+            let err_body = String::from_utf8_lossy(&err_body).into_owned();
 
-            Err(Error::ErrorResponse(code, err_msg.message))
+            Err(error_response(code, retry_after, err_body))
         }
         _ => Ok(resp),
     }
 }
 
+/// Maps an error response to a typed [`Error`] using the exception class reported by the server.
+pub(crate) fn error_response(code: u16, retry_after: Option<Duration>, err_body: String) -> Error {
+    let err_msg: Option<ErrorMessage> = serde_json::from_str(&err_body).ok();
+    let (message, exception) = match err_msg {
+        Some(m) => (m.message, m.exception),
+        None => (err_body.clone(), None),
+    };
+    if code == StatusCode::TOO_MANY_REQUESTS.as_u16() {
+        return Error::TooManyRequests {
+            retry_after,
+            message,
+        };
+    }
+
+    let class = exception
+        .as_deref()
+        .and_then(|e| e.rsplit('.').next())
+        .unwrap_or_default();
+    match class {
+        "ChangeConflictException" => Error::Conflict {
+            base_revision: revision_after(&message, "baseRevision: "),
+            head_revision: revision_after(&message, "(expected: "),
+            message,
+        },
+        "EntryNotFoundException" => Error::EntryNotFound(message),
+        "RedundantChangeException" => Error::RedundantChange(message),
+        "RepositoryNotFoundException" => Error::RepositoryNotFound(message),
+        "ProjectExistsException" => Error::ProjectExists(message),
+        _ => Error::ErrorResponse {
+            status: code,
+            message,
+            exception,
+            body: err_body,
+        },
+    }
+}
+
+/// Parses the revision following `prefix` in a server message, such as the
+/// `invalid baseRevision: 3 (expected: 5 or equivalent)` message of a change conflict.
+fn revision_after(message: &str, prefix: &str) -> Option<Revision> {
+    let start = message.find(prefix)? + prefix.len();
+    let digits: String = message[start..]
+        .chars()
+        .take_while(|c| c.is_ascii_digit() || *c == '-')
+        .collect();
+
+    Revision::try_from_i64(digits.parse().ok()?).ok()
+}
+
+/// Parses a `Retry-After` header, given either in seconds or as an HTTP date.
+pub(crate) fn parse_retry_after(value: &HeaderValue) -> Option<Duration> {
+    let value = value.to_str().ok()?.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+
+    let at = httpdate::parse_http_date(value).ok()?;
+    Some(
+        at.duration_since(SystemTime::now())
+            .unwrap_or(Duration::ZERO),
+    )
+}
+
+/// Reads the body of a successful response as JSON.
+async fn json_body<T: DeserializeOwned>(client: &Client, resp: Response) -> Result<T, Error> {
+    let body = read_body(client, resp).await?;
+
+    parse_json(&body, client.is_strict())
+}
+
+/// Reads the body of a response into one buffer, failing as soon as it exceeds the
+/// [maximum response size](Client::with_max_response_size) of the client.
+async fn read_body(client: &Client, resp: Response) -> Result<bytes::Bytes, Error> {
+    read_limited(resp, client.max_response_size()).await
+}
+
+/// Reads the body of a response into one buffer, failing as soon as it exceeds `limit`.
+pub(crate) async fn read_limited(
+    mut resp: Response,
+    limit: Option<usize>,
+) -> Result<bytes::Bytes, Error> {
+    let limit = match limit {
+        Some(limit) => limit,
+        None => return Ok(resp.bytes().await?),
+    };
+    let too_large = || Error::ResponseTooLarge { limit };
+    let capacity = match resp.content_length() {
+        Some(len) if len > limit as u64 => return Err(too_large()),
+        Some(len) => len as usize,
+        None => 0,
+    };
+
+    let mut body = bytes::BytesMut::with_capacity(capacity);
+    while let Some(chunk) = resp.chunk().await? {
+        if body.len() + chunk.len() > limit {
+            return Err(too_large());
+        }
+        body.extend_from_slice(&chunk);
+    }
+
+    Ok(body.freeze())
+}
+
+/// Parses a response body into `T`.
+///
+/// If `strict`, fails if the body contains a field which is not known by `T`,
+/// so incompatibilities with newer servers are not silently ignored.
+fn parse_json<T: DeserializeOwned>(body: &[u8], strict: bool) -> Result<T, Error> {
+    let mut de = serde_json::Deserializer::from_slice(body);
+    let mut unknown_field = None;
+    let result: T = if strict {
+        let mut track = |path: serde_ignored::Path| {
+            unknown_field.get_or_insert_with(|| field_path(&path));
+        };
+        serde_path_to_error::deserialize(serde_ignored::Deserializer::new(&mut de, &mut track))
+    } else {
+        serde_path_to_error::deserialize(&mut de)
+    }
+    .map_err(|e| deserialize_error(e.path().to_string(), body, e.into_inner()))?;
+    de.end()
+        .map_err(|e| deserialize_error(String::new(), body, e))?;
+
+    if let Some(field) = unknown_field {
+        let source = serde::de::Error::custom(format!("unknown field `{}`", field));
+        return Err(deserialize_error(field, body, source));
+    }
+
+    Ok(result)
+}
+
+/// Formats the path of an ignored field like the paths of [`serde_path_to_error`],
+/// e.g. `[0].author.login`.
+fn field_path(path: &serde_ignored::Path) -> String {
+    use serde_ignored::Path;
+
+    match path {
+        Path::Root => String::new(),
+        Path::Seq { parent, index } => format!("{}[{}]", field_path(parent), index),
+        Path::Map { parent, key } => match field_path(parent) {
+            parent if parent.is_empty() => key.to_owned(),
+            parent => format!("{}.{}", parent, key),
+        },
+        Path::Some { parent }
+        | Path::NewtypeStruct { parent }
+        | Path::NewtypeVariant { parent } => field_path(parent),
+    }
+}
+
+/// Maximum number of bytes of the response body kept in [`Error::Deserialize`].
+const MAX_ERROR_BODY_LEN: usize = 1024;
+
+fn deserialize_error(path: String, body: &[u8], source: serde_json::Error) -> Error {
+    let mut body = String::from_utf8_lossy(body).into_owned();
+    if body.len() > MAX_ERROR_BODY_LEN {
+        let mut end = MAX_ERROR_BODY_LEN;
+        while !body.is_char_boundary(end) {
+            end -= 1;
+        }
+        body.truncate(end);
+        body.push_str("...");
+    }
+
+    Error::Deserialize {
+        path,
+        body,
+        source,
+        backtrace: Backtrace::capture(),
+    }
+}
+
+/// Sends a request and handles its response with `handle`,
+/// reporting a failure to the [error hook](Client::with_error_hook) of the client.
+pub(super) async fn execute<T, F, Fut>(
+    client: &Client,
+    req: reqwest::Request,
+    attempt: usize,
+    handle: F,
+) -> Result<T, Error>
+where
+    F: FnOnce(Response) -> Fut,
+    Fut: Future<Output = Result<T, Error>>,
+{
+    let ctx = ErrorContext {
+        method: req.method().clone(),
+        path: req.url().path().to_owned(),
+        attempt,
+    };
+    #[cfg(feature = "otel")]
+    let telemetry = client.telemetry().start_request(&ctx.method, &ctx.path);
+    #[cfg(feature = "otel")]
+    let req = {
+        let mut req = req;
+        telemetry.inject_context(&mut req);
+        req
+    };
+    let start = std::time::Instant::now();
+    let mut status = None;
+
+    let (result, last_attempt) = client.request(req, ctx.attempt).await;
+    let ctx = ErrorContext {
+        attempt: last_attempt,
+        ..ctx
+    };
+    let result = match result {
+        Ok(resp) => {
+            status = Some(resp.status().as_u16());
+            handle(resp).await
+        }
+        Err(e) => Err(e),
+    };
+    if let Err(e) = &result {
+        client.report_error(&ctx, e);
+    }
+    if let Some(recorder) = client.metrics_recorder() {
+        recorder.on_request(&RequestEvent::new(
+            ctx.method.clone(),
+            ctx.path.clone(),
+            status,
+            result.as_ref().err().map(Error::code),
+            start.elapsed(),
+            ctx.attempt,
+        ));
+    }
+    #[cfg(feature = "otel")]
+    telemetry.end(client.telemetry(), status, &result);
+
+    result
+}
+
 pub(super) async fn do_request<T: DeserializeOwned>(
     client: &Client,
     req: reqwest::Request,
 ) -> Result<T, Error> {
-    let resp = client.request(req).await?;
-    let ok_resp = status_unwrap(resp).await?;
-    let result = ok_resp.json().await?;
+    execute(client, req, 1, |resp| async move {
+        let ok_resp = status_unwrap(client, resp).await?;
 
-    Ok(result)
+        json_body(client, ok_resp).await
+    })
+    .await
+}
+
+/// Sends a request whose successful response has no meaningful body.
+pub(super) async fn do_empty_request(client: &Client, req: reqwest::Request) -> Result<(), Error> {
+    execute(client, req, 1, |resp| async move {
+        status_unwrap(client, resp).await?;
+
+        Ok(())
+    })
+    .await
+}
+
+/// Sends a request and returns the undecoded body of a successful response.
+pub(super) async fn do_raw_request(
+    client: &Client,
+    req: reqwest::Request,
+) -> Result<bytes::Bytes, Error> {
+    execute(client, req, 1, |resp| async move {
+        let ok_resp = status_unwrap(client, resp).await?;
+
+        read_body(client, ok_resp).await
+    })
+    .await
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    use crate::{
+        model::{Commit, ProjectMetadata, PushResult},
+        ErrorCode, ProjectService,
+    };
+    use wiremock::{
+        matchers::{method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    #[test]
+    fn test_parse_json() {
+        let result: PushResult = parse_json(br#"{"revision":2,"pushedAt":null}"#, false).unwrap();
+        assert_eq!(result.revision.as_i64(), Some(2));
+
+        let body = br#"[{
+            "revision":1,
+            "author":{"name":"minux", "email":"minux@m.x", "login":"minux"},
+            "commitMessage":{"summary":"Add a.json"}
+        }]"#;
+        assert!(parse_json::<Vec<Commit>>(body, false).is_ok());
+        let err = parse_json::<Vec<Commit>>(body, true).unwrap_err();
+        assert!(err.to_string().contains("[0].author.login"), "{}", err);
+    }
+
+    #[test]
+    fn test_parse_json_strict_known_fields() {
+        let body = br#"{
+            "name":"foo",
+            "repos":{"bar":{
+                "name":"bar",
+                "perRolePermissions":{"owner":["READ","WRITE"],"member":[],"guest":[],
+                    "anonymous":null},
+                "creation":{"user":"minux","timestamp":"2023-01-31T09:00:00Z"},
+                "removal":null
+            }},
+            "creation":{"user":"minux","timestamp":"2023-01-31T09:00:00Z"},
+            "removal":null
+        }"#;
+        let metadata = parse_json::<ProjectMetadata>(body, true).unwrap();
+        assert_eq!(metadata.removal, None);
+        assert_eq!(metadata.repos["bar"].per_role_permissions.anonymous, None);
+
+        let body = br#"[{
+            "revision":1,
+            "author":{"name":"minux", "email":"minux@m.x"},
+            "commitMessage":{"summary":"Add a.json","detail":"Details","markup":"PLAINTEXT"}
+        }]"#;
+        assert!(parse_json::<Vec<Commit>>(body, true).is_ok());
+    }
+
+    #[test]
+    fn test_parse_json_error_context() {
+        let body = br#"[{
+            "revision":1,
+            "author":{"name":"minux", "email":3},
+            "commitMessage":{"summary":"Add a.json"}
+        }]"#;
+        let err = parse_json::<Vec<Commit>>(body, false).unwrap_err();
+
+        match err {
+            Error::Deserialize {
+                path,
+                body: raw,
+                source,
+                ..
+            } => {
+                assert_eq!(path, "[0].author.email");
+                assert_eq!(raw.as_bytes(), &body[..]);
+                assert!(source.is_data());
+            }
+            e => panic!("unexpected error: {:?}", e),
+        }
+
+        let long = format!(r#"{{"revision":"{}"}}"#, "é".repeat(1000));
+        let err = parse_json::<PushResult>(long.as_bytes(), false).unwrap_err();
+        match err {
+            Error::Deserialize { body, .. } => {
+                assert!(body.len() <= MAX_ERROR_BODY_LEN + 3);
+                assert!(body.ends_with("..."));
+            }
+            e => panic!("unexpected error: {:?}", e),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_error_source_chain() {
+        use std::error::Error as _;
+
+        let err = Client::new("not a url", None).await.err().unwrap();
+        assert!(matches!(err, Error::InvalidURL(..)));
+        assert!(err
+            .source()
+            .and_then(|e| e.downcast_ref::<url::ParseError>())
+            .is_some());
+
+        let err = parse_json::<PushResult>(b"{", false).unwrap_err();
+        assert!(err
+            .source()
+            .and_then(|e| e.downcast_ref::<serde_json::Error>())
+            .is_some());
+        assert!(!err.to_string().contains("EOF"), "{}", err);
+    }
+
+    #[test]
+    fn test_error_response() {
+        let body = r#"{
+            "exception":"com.linecorp.centraldogma.common.ChangeConflictException",
+            "message":"/a.json is modified"
+        }"#;
+        let err = error_response(409, None, body.to_string());
+        assert!(matches!(
+            err,
+            Error::Conflict { base_revision: None, head_revision: None, message }
+                if message == "/a.json is modified"
+        ));
+
+        let body = r#"{
+            "exception":"com.linecorp.centraldogma.common.ChangeConflictException",
+            "message":"invalid baseRevision: 3 (expected: 5 or equivalent)"
+        }"#;
+        let err = error_response(409, None, body.to_string());
+        match err {
+            Error::Conflict {
+                base_revision,
+                head_revision,
+                ..
+            } => {
+                assert_eq!(base_revision, Some(Revision::from(3)));
+                assert_eq!(head_revision, Some(Revision::from(5)));
+            }
+            e => panic!("unexpected error: {:?}", e),
+        }
+
+        let body = r#"{
+            "exception":"com.linecorp.centraldogma.common.EntryNotFoundException",
+            "message":"/b.json"
+        }"#;
+        let err = error_response(404, None, body.to_string());
+        assert!(matches!(err, Error::EntryNotFound(m) if m == "/b.json"));
+
+        let body = r#"{"exception":"java.lang.IllegalStateException","message":"oops"}"#;
+        let err = error_response(500, None, body.to_string());
+        match err {
+            Error::ErrorResponse {
+                status,
+                message,
+                exception,
+                body: raw,
+            } => {
+                assert_eq!(status, 500);
+                assert_eq!(message, "oops");
+                assert_eq!(
+                    exception.as_deref(),
+                    Some("java.lang.IllegalStateException")
+                );
+                assert_eq!(raw, body);
+            }
+            e => panic!("unexpected error: {:?}", e),
+        }
+
+        let err = error_response(502, None, "Bad Gateway".to_string());
+        assert!(matches!(
+            err,
+            Error::ErrorResponse { status: 502, message, exception: None, .. } if message == "Bad Gateway"
+        ));
+
+        let err = error_response(429, Some(Duration::from_secs(3)), "slow down".to_string());
+        assert!(matches!(err, Error::TooManyRequests { message, .. } if message == "slow down"));
+    }
+
+    #[test]
+    fn test_error_code() {
+        let body = r#"{
+            "exception":"com.linecorp.centraldogma.common.EntryNotFoundException",
+            "message":"/b.json"
+        }"#;
+        assert_eq!(
+            error_response(404, None, body.to_string()).code(),
+            ErrorCode::EntryNotFound
+        );
+
+        let body = r#"{
+            "exception":"com.linecorp.centraldogma.common.RevisionNotFoundException",
+            "message":"10"
+        }"#;
+        let code = error_response(404, None, body.to_string()).code();
+        assert_eq!(code, ErrorCode::RevisionNotFound);
+        assert_eq!(code.as_str(), "revision_not_found");
+
+        let err = error_response(404, None, body.to_string());
+        assert!(err.is_not_found() && !err.is_conflict());
+        let body = r#"{
+            "exception":"com.linecorp.centraldogma.common.ChangeConflictException",
+            "message":"invalid baseRevision: 3 (expected: 5 or equivalent)"
+        }"#;
+        let err = error_response(409, None, body.to_string());
+        assert!(err.is_conflict() && !err.is_not_found());
+        assert!(error_response(404, None, String::new()).is_not_found());
+        assert!(!error_response(500, None, String::new()).is_not_found());
+
+        let cases = [
+            (401, ErrorCode::Unauthorized),
+            (404, ErrorCode::NotFound),
+            (429, ErrorCode::TooManyRequests),
+            (503, ErrorCode::ServerError),
+            (418, ErrorCode::Unknown),
+        ];
+        for (status, code) in cases.iter() {
+            assert_eq!(error_response(*status, None, String::new()).code(), *code);
+        }
+
+        assert_eq!(
+            Error::InvalidParams("oops").code().to_string(),
+            "invalid_argument"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_max_response_size() {
+        let server = MockServer::start().await;
+        let projects: Vec<_> = (0..10)
+            .map(|i| {
+                format!(
+                    r#"{{"name":"project{}","creator":{{"name":"minux","email":"minux@m.x"}}}}"#,
+                    i
+                )
+            })
+            .collect();
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_raw(format!("[{}]", projects.join(",")), "application/json"),
+            )
+            .mount(&server)
+            .await;
+
+        let client = Client::new(&server.uri(), None).await.unwrap();
+        assert_eq!(
+            client
+                .clone()
+                .with_max_response_size(4096)
+                .list_projects()
+                .await
+                .unwrap()
+                .len(),
+            10
+        );
+        let err = client
+            .with_max_response_size(64)
+            .list_projects()
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::ResponseTooLarge { limit: 64 }));
+        assert_eq!(err.code(), ErrorCode::ResponseTooLarge);
+    }
+
+    #[tokio::test]
+    async fn test_max_error_response_size() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects"))
+            .respond_with(ResponseTemplate::new(500).set_body_string("x".repeat(1024)))
+            .mount(&server)
+            .await;
+
+        let client = Client::new(&server.uri(), None).await.unwrap();
+        let err = client
+            .clone()
+            .with_max_response_size(1024)
+            .list_projects()
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::ErrorResponse { status: 500, .. }));
+        let err = client
+            .with_max_response_size(64)
+            .list_projects()
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::ResponseTooLarge { limit: 64 }));
+    }
+
+    #[tokio::test]
+    async fn test_strict() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(
+                r#"[{"name":"foo","creator":{"name":"minux","email":"minux@m.x"},"new":1}]"#,
+                "application/json",
+            ))
+            .mount(&server)
+            .await;
+
+        let client = Client::new(&server.uri(), None).await.unwrap();
+        assert_eq!(client.list_projects().await.unwrap()[0].name, "foo");
+        let client = Client::builder(&server.uri()).strict(true).build().unwrap();
+        let err = client.list_projects().await.unwrap_err();
+        assert!(err.to_string().contains("[0].new"), "{}", err);
+    }
+
+    #[test]
+    fn test_parse_retry_after() {
+        let secs = HeaderValue::from_static("120");
+        assert_eq!(parse_retry_after(&secs), Some(Duration::from_secs(120)));
+
+        let past = HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT");
+        assert_eq!(parse_retry_after(&past), Some(Duration::ZERO));
+
+        let future = httpdate::fmt_http_date(SystemTime::now() + Duration::from_secs(60));
+        let future = HeaderValue::from_str(&future).unwrap();
+        let wait = parse_retry_after(&future).unwrap();
+        assert!(wait > Duration::from_secs(50) && wait <= Duration::from_secs(60));
+
+        let invalid = HeaderValue::from_static("soon");
+        assert_eq!(parse_retry_after(&invalid), None);
+    }
+
+    #[tokio::test]
+    async fn test_connect_error() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+
+        let client = Client::new(&format!("http://{}", addr), None)
+            .await
+            .unwrap();
+        let err = client.list_projects().await.unwrap_err();
+
+        assert!(matches!(err, Error::Connect(..)), "{:?}", err);
+    }
+
+    #[tokio::test]
+    async fn test_error_hook() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects"))
+            .respond_with(ResponseTemplate::new(500))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let errors = Arc::new(Mutex::new(Vec::new()));
+        let errors_clone = errors.clone();
+        let client = Client::new(&server.uri(), None)
+            .await
+            .unwrap()
+            .with_error_hook(move |ctx, e| {
+                errors_clone.lock().unwrap().push((ctx.clone(), e.code()));
+            });
+        client.list_projects().await.unwrap_err();
+
+        let errors = errors.lock().unwrap();
+        assert_eq!(errors.len(), 1);
+        let (ctx, code) = &errors[0];
+        assert_eq!(ctx.method, reqwest::Method::GET);
+        assert_eq!(ctx.path, "/api/v1/projects");
+        assert_eq!(ctx.attempt, 1);
+        assert_eq!(*code, ErrorCode::ServerError);
+    }
+
+    #[tokio::test]
+    async fn test_too_many_requests() {
+        let server = MockServer::start().await;
+        let resp = ResponseTemplate::new(429)
+            .insert_header("Retry-After", "3")
+            .set_body_raw(r#"{"message":"slow down"}"#, "application/json");
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects"))
+            .respond_with(resp)
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = Client::new(&server.uri(), None).await.unwrap();
+        let err = client.list_projects().await.unwrap_err();
+
+        assert_eq!(err.retry_after(), Some(Duration::from_secs(3)));
+        assert!(matches!(err, Error::TooManyRequests { message, .. } if message == "slow down"));
+    }
 }
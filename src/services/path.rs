@@ -1,6 +1,6 @@
 use std::borrow::Cow;
 
-use crate::model::{Query, QueryType, Revision};
+use crate::model::{MergeQuery, Query, QueryType, Revision};
 
 const PATH_PREFIX: &str = "/api/v1";
 
@@ -8,13 +8,14 @@ mod params {
     pub const REVISION: &str = "revision";
     pub const JSONPATH: &str = "jsonpath";
     pub const PATH: &str = "path";
+    pub const OPTIONAL_PATH: &str = "optional_path";
     pub const PATH_PATTERN: &str = "pathPattern";
     pub const MAX_COMMITS: &str = "maxCommits";
     pub const FROM: &str = "from";
     pub const TO: &str = "to";
 }
 
-fn normalize_path_pattern(path_pattern: &str) -> Cow<str> {
+fn normalize_path_pattern(path_pattern: &str) -> Cow<'_, str> {
     if path_pattern.is_empty() {
         return Cow::Borrowed("/**");
     }
@@ -62,6 +63,20 @@ pub(crate) fn repo_path(project_name: &str, repo_name: &str) -> String {
     )
 }
 
+pub(crate) fn repo_revision_path(
+    project_name: &str,
+    repo_name: &str,
+    revision: Revision,
+) -> String {
+    format!(
+        "{}/projects/{}/repos/{}/revision/{}",
+        PATH_PREFIX,
+        project_name,
+        repo_name,
+        revision.as_i64().unwrap_or(-1)
+    )
+}
+
 pub(crate) fn removed_repo_path(project_name: &str, repo_name: &str) -> String {
     format!(
         "{}/projects/{}/repos/{}/removed",
@@ -69,6 +84,37 @@ pub(crate) fn removed_repo_path(project_name: &str, repo_name: &str) -> String {
     )
 }
 
+pub(crate) fn metadata_members_path(project_name: &str) -> String {
+    format!("{}/metadata/{}/members", PATH_PREFIX, project_name)
+}
+
+pub(crate) fn metadata_member_path(project_name: &str, login: &str) -> String {
+    format!(
+        "{}/metadata/{}/members/{}",
+        PATH_PREFIX, project_name, login
+    )
+}
+
+pub(crate) fn metadata_tokens_path(project_name: &str) -> String {
+    format!("{}/metadata/{}/tokens", PATH_PREFIX, project_name)
+}
+
+pub(crate) fn metadata_token_path(project_name: &str, app_id: &str) -> String {
+    format!(
+        "{}/metadata/{}/tokens/{}",
+        PATH_PREFIX, project_name, app_id
+    )
+}
+
+/// Returns the path of the permissions on a repository of the roles, if `kind` is `role`,
+/// of the members, if `users`, or of the tokens, if `tokens`.
+pub(crate) fn metadata_repo_perm_path(project_name: &str, repo_name: &str, kind: &str) -> String {
+    format!(
+        "{}/metadata/{}/repos/{}/perm/{}",
+        PATH_PREFIX, project_name, repo_name, kind
+    )
+}
+
 pub(crate) fn list_contents_path(
     project_name: &str,
     repo_name: &str,
@@ -137,6 +183,37 @@ pub(crate) fn content_path(
     s.finish()
 }
 
+pub(crate) fn merge_path(
+    project_name: &str,
+    repo_name: &str,
+    revision: Revision,
+    query: &MergeQuery,
+) -> String {
+    let url = format!(
+        "{}/projects/{}/repos/{}/merge?",
+        PATH_PREFIX, project_name, repo_name
+    );
+
+    let len = url.len();
+    let mut s = form_urlencoded::Serializer::for_suffix(url, len);
+    if let Some(v) = revision.as_ref() {
+        add_pair(&mut s, params::REVISION, &v.to_string());
+    }
+    for source in query.sources.iter() {
+        let key = if source.optional {
+            params::OPTIONAL_PATH
+        } else {
+            params::PATH
+        };
+        add_pair(&mut s, key, &source.path);
+    }
+    for expression in query.json_paths.iter() {
+        add_pair(&mut s, params::JSONPATH, expression);
+    }
+
+    s.finish()
+}
+
 pub(crate) fn content_commits_path(
     project_name: &str,
     repo_name: &str,
@@ -247,6 +324,26 @@ pub(crate) fn contents_push_path(
     s.finish()
 }
 
+pub(crate) fn contents_preview_path(
+    project_name: &str,
+    repo_name: &str,
+    base_revision: Revision,
+) -> String {
+    let url = format!(
+        "{}/projects/{}/repos/{}/preview?",
+        PATH_PREFIX, project_name, repo_name
+    );
+
+    let len = url.len();
+    let mut s = form_urlencoded::Serializer::for_suffix(url, len);
+
+    if let Some(v) = base_revision.as_ref() {
+        add_pair(&mut s, params::REVISION, &v.to_string());
+    }
+
+    s.finish()
+}
+
 pub(crate) fn content_watch_path(project_name: &str, repo_name: &str, query: &Query) -> String {
     let url = format!(
         "{}/projects/{}/repos/{}/contents{}?",
@@ -274,6 +371,67 @@ pub(crate) fn repo_watch_path(project_name: &str, repo_name: &str, path_pattern:
     )
 }
 
+/// The parts of a request path, the inverse of the functions of this module.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct Route<'a> {
+    /// The path with the names replaced by placeholders,
+    /// e.g. `/api/v1/projects/{project}/repos/{repo}/contents/{path}`
+    pub(crate) template: String,
+    pub(crate) project: Option<&'a str>,
+    pub(crate) repo: Option<&'a str>,
+    /// The path of the file or the path pattern, if any
+    pub(crate) path: Option<&'a str>,
+}
+
+pub(crate) fn parse_route(path: &str) -> Route<'_> {
+    let mut route = Route {
+        template: PATH_PREFIX.to_owned(),
+        project: None,
+        repo: None,
+        path: None,
+    };
+    let mut rest = path.strip_prefix(PATH_PREFIX).unwrap_or(path);
+    while let Some(stripped) = rest.strip_prefix('/') {
+        let (segment, tail) = stripped.split_at(stripped.find('/').unwrap_or(stripped.len()));
+        route.template.push('/');
+        rest = tail;
+        match segment {
+            "projects" | "repos" => {
+                route.template.push_str(segment);
+                let Some(stripped) = rest.strip_prefix('/') else {
+                    break;
+                };
+                let (name, tail) = stripped.split_at(stripped.find('/').unwrap_or(stripped.len()));
+                if segment == "projects" {
+                    route.project = Some(name);
+                    route.template.push_str("/{project}");
+                } else {
+                    route.repo = Some(name);
+                    route.template.push_str("/{repo}");
+                }
+                rest = tail;
+            }
+            "contents" | "list" | "commits" | "revision" => {
+                route.template.push_str(segment);
+                if !rest.is_empty() && rest != "/" {
+                    route.path = Some(rest);
+                    route
+                        .template
+                        .push_str(if segment == "commits" || segment == "revision" {
+                            "/{revision}"
+                        } else {
+                            "/{path}"
+                        });
+                }
+                break;
+            }
+            _ => route.template.push_str(segment),
+        }
+    }
+
+    route
+}
+
 fn add_pair<'a, T>(s: &mut form_urlencoded::Serializer<'a, T>, key: &str, value: &str)
 where
     T: form_urlencoded::Target,
@@ -283,10 +441,61 @@ where
     }
 }
 
+/// Returns whether `path` matches a path pattern as described in
+/// [get_files](trait@crate::ContentService#tymethod.get_files).
+pub(crate) fn matches_pattern(pattern: &str, path: &str) -> bool {
+    let path: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+
+    pattern.split(',').map(str::trim).any(|p| {
+        let p = if p.starts_with('/') {
+            p.to_owned()
+        } else {
+            format!("/**/{}", p)
+        };
+        let p: Vec<&str> = p.split('/').filter(|s| !s.is_empty()).collect();
+
+        matches_segments(&p, &path)
+    })
+}
+
+fn matches_segments(pattern: &[&str], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((&"**", rest)) => (0..=path.len()).any(|i| matches_segments(rest, &path[i..])),
+        Some((p, rest)) => match path.split_first() {
+            Some((s, path_rest)) => {
+                matches_glob(p.as_bytes(), s.as_bytes()) && matches_segments(rest, path_rest)
+            }
+            None => false,
+        },
+    }
+}
+
+fn matches_glob(pattern: &[u8], name: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some((b'*', rest)) => (0..=name.len()).any(|i| matches_glob(rest, &name[i..])),
+        Some((b'?', rest)) => !name.is_empty() && matches_glob(rest, &name[1..]),
+        Some((c, rest)) => name.first() == Some(c) && matches_glob(rest, &name[1..]),
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
 
+    #[test]
+    fn test_matches_pattern() {
+        assert!(matches_pattern("/**", "/a/b.json"));
+        assert!(matches_pattern("*.json", "/a/b.json"));
+        assert!(matches_pattern("*.json", "/b.json"));
+        assert!(matches_pattern("/a/*.json", "/a/b.json"));
+        assert!(!matches_pattern("/a/*.json", "/a/b/c.json"));
+        assert!(matches_pattern("/*/foo.txt", "/a/foo.txt"));
+        assert!(matches_pattern("*.txt, /a/*.json", "/a/b.json"));
+        assert!(!matches_pattern("*.txt", "/a/b.json"));
+    }
+
     #[test]
     fn test_content_commits_path() {
         let full_arg_path = content_commits_path(
@@ -404,4 +613,43 @@ mod test {
             "/api/v1/projects/foo/repos/bar/compare?path=%2Fa.json&jsonpath=a"
         );
     }
+
+    #[test]
+    fn test_parse_route() {
+        let route = parse_route("/api/v1/projects/foo/repos/bar/contents/a/b.json");
+        assert_eq!(
+            route,
+            Route {
+                template: "/api/v1/projects/{project}/repos/{repo}/contents/{path}".to_owned(),
+                project: Some("foo"),
+                repo: Some("bar"),
+                path: Some("/a/b.json"),
+            }
+        );
+
+        let route = parse_route("/api/v1/projects/foo/repos/bar/contents");
+        assert_eq!(
+            route.template,
+            "/api/v1/projects/{project}/repos/{repo}/contents"
+        );
+        assert_eq!(route.path, None);
+
+        let route = parse_route("/api/v1/projects/foo/repos");
+        assert_eq!(route.template, "/api/v1/projects/{project}/repos");
+        assert_eq!(route.project, Some("foo"));
+        assert_eq!(route.repo, None);
+
+        let path = repo_revision_path("foo", "bar", Revision::HEAD);
+        let route = parse_route(&path);
+        assert_eq!(
+            route.template,
+            "/api/v1/projects/{project}/repos/{repo}/revision/{revision}"
+        );
+
+        let route = parse_route("/api/v1/projects/foo/repos/bar/compare");
+        assert_eq!(
+            route.template,
+            "/api/v1/projects/{project}/repos/{repo}/compare"
+        );
+    }
 }
@@ -2,7 +2,8 @@
 use crate::{
     client::{Client, Error},
     model::Project,
-    services::{path, status_unwrap},
+    services::{do_empty_request, do_request, execute, json_body, path, status_unwrap},
+    validation,
 };
 
 use async_trait::async_trait;
@@ -42,33 +43,24 @@ impl ProjectService for Client {
             name: &'a str,
         }
 
+        validation::validate_project_name(name)?;
         let body: Vec<u8> = serde_json::to_vec(&CreateProject { name })?;
         let body = Body::from(body);
         let req = self.new_request(Method::POST, path::projects_path(), Some(body))?;
 
-        let resp = self.request(req).await?;
-        let ok_resp = status_unwrap(resp).await?;
-        let result = ok_resp.json().await?;
-
-        Ok(result)
+        do_request(self, req).await
     }
 
     async fn remove_project(&self, name: &str) -> Result<(), Error> {
         let req = self.new_request(Method::DELETE, path::project_path(name), None)?;
 
-        let resp = self.request(req).await?;
-        let _ = status_unwrap(resp).await?;
-
-        Ok(())
+        do_empty_request(self, req).await
     }
 
     async fn purge_project(&self, name: &str) -> Result<(), Error> {
         let req = self.new_request(Method::DELETE, path::removed_project_path(name), None)?;
 
-        let resp = self.request(req).await?;
-        let _ = status_unwrap(resp).await?;
-
-        Ok(())
+        do_empty_request(self, req).await
     }
 
     async fn unremove_project(&self, name: &str) -> Result<Project, Error> {
@@ -78,24 +70,22 @@ impl ProjectService for Client {
         let body = Body::from(body);
         let req = self.new_request(Method::PATCH, path::project_path(name), Some(body))?;
 
-        let resp = self.request(req).await?;
-        let ok_resp = status_unwrap(resp).await?;
-        let result = ok_resp.json().await?;
-
-        Ok(result)
+        do_request(self, req).await
     }
 
     async fn list_projects(&self) -> Result<Vec<Project>, Error> {
         let req = self.new_request(Method::GET, path::projects_path(), None)?;
-        let resp = self.request(req).await?;
-        let ok_resp = status_unwrap(resp).await?;
 
-        if let Some(0) = ok_resp.content_length() {
-            return Ok(Vec::new());
-        }
-        let result = ok_resp.json().await?;
+        execute(self, req, 1, |resp| async move {
+            let ok_resp = status_unwrap(self, resp).await?;
 
-        Ok(result)
+            if let Some(0) = ok_resp.content_length() {
+                return Ok(Vec::new());
+            }
+
+            json_body(self, ok_resp).await
+        })
+        .await
     }
 
     async fn list_removed_projects(&self) -> Result<Vec<String>, Error> {
@@ -104,10 +94,8 @@ impl ProjectService for Client {
             name: String,
         }
         let req = self.new_request(Method::GET, path::removed_projects_path(), None)?;
-        let resp = self.request(req).await?;
-        let ok_resp = status_unwrap(resp).await?;
 
-        let result: Vec<RemovedProject> = ok_resp.json().await?;
+        let result: Vec<RemovedProject> = do_request(self, req).await?;
         let result = result.into_iter().map(|p| p.name).collect();
 
         Ok(result)
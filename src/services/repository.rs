@@ -2,7 +2,8 @@
 use crate::{
     client::{Error, ProjectClient},
     model::Repository,
-    services::{path, status_unwrap},
+    services::{do_empty_request, do_request, execute, json_body, path, status_unwrap},
+    validation,
 };
 
 use async_trait::async_trait;
@@ -42,6 +43,7 @@ impl<'a> RepoService for ProjectClient<'a> {
             name: &'a str,
         }
 
+        validation::validate_repo_name(repo_name)?;
         let body = serde_json::to_vec(&CreateRepo { name: repo_name })?;
         let body = Body::from(body);
 
@@ -49,11 +51,7 @@ impl<'a> RepoService for ProjectClient<'a> {
             self.client
                 .new_request(Method::POST, path::repos_path(self.project), Some(body))?;
 
-        let resp = self.client.request(req).await?;
-        let resp_body = status_unwrap(resp).await?.bytes().await?;
-        let result = serde_json::from_slice(&resp_body[..])?;
-
-        Ok(result)
+        do_request(self.client, req).await
     }
 
     async fn remove_repo(&self, repo_name: &str) -> Result<(), Error> {
@@ -63,10 +61,7 @@ impl<'a> RepoService for ProjectClient<'a> {
             None,
         )?;
 
-        let resp = self.client.request(req).await?;
-        let _ = status_unwrap(resp).await?;
-
-        Ok(())
+        do_empty_request(self.client, req).await
     }
 
     async fn purge_repo(&self, repo_name: &str) -> Result<(), Error> {
@@ -76,10 +71,7 @@ impl<'a> RepoService for ProjectClient<'a> {
             None,
         )?;
 
-        let resp = self.client.request(req).await?;
-        let _ = status_unwrap(resp).await?;
-
-        Ok(())
+        do_empty_request(self.client, req).await
     }
 
     async fn unremove_repo(&self, repo_name: &str) -> Result<Repository, Error> {
@@ -93,11 +85,7 @@ impl<'a> RepoService for ProjectClient<'a> {
             Some(body),
         )?;
 
-        let resp = self.client.request(req).await?;
-        let ok_resp = status_unwrap(resp).await?;
-        let result = ok_resp.json().await?;
-
-        Ok(result)
+        do_request(self.client, req).await
     }
 
     async fn list_repos(&self) -> Result<Vec<Repository>, Error> {
@@ -105,11 +93,7 @@ impl<'a> RepoService for ProjectClient<'a> {
             .client
             .new_request(Method::GET, path::repos_path(self.project), None)?;
 
-        let resp = self.client.request(req).await?;
-        let ok_resp = status_unwrap(resp).await?;
-        let result = ok_resp.json().await?;
-
-        Ok(result)
+        do_request(self.client, req).await
     }
 
     async fn list_removed_repos(&self) -> Result<Vec<String>, Error> {
@@ -121,12 +105,15 @@ impl<'a> RepoService for ProjectClient<'a> {
             self.client
                 .new_request(Method::GET, path::removed_repos_path(self.project), None)?;
 
-        let resp = self.client.request(req).await?;
-        let ok_resp = status_unwrap(resp).await?;
-        if ok_resp.status().as_u16() == 204 {
-            return Ok(Vec::new());
-        }
-        let result: Vec<RemovedRepo> = ok_resp.json().await?;
+        let result: Vec<RemovedRepo> = execute(self.client, req, 1, |resp| async move {
+            let ok_resp = status_unwrap(self.client, resp).await?;
+            if ok_resp.status().as_u16() == 204 {
+                return Ok(Vec::new());
+            }
+
+            json_body(self.client, ok_resp).await
+        })
+        .await?;
         let result = result.into_iter().map(|r| r.name).collect();
 
         Ok(result)
@@ -1,35 +1,94 @@
 //! Watch-related APIs
-use std::{pin::Pin, time::Duration};
+use std::{
+    collections::{HashMap, VecDeque},
+    pin::Pin,
+    sync::Arc,
+    time::Duration,
+};
 
 use crate::{
-    model::{Query, Revision, WatchFileResult, WatchRepoResult, Watchable},
-    services::{path, status_unwrap},
-    Client, Error, RepoClient,
+    model::{
+        Commit, Entry, Query, Revision, TypedEntry, WatchFileResult, WatchRepoChanges,
+        WatchRepoResult, Watchable,
+    },
+    recorder::WatchBackoffEvent,
+    services::{execute, json_body, path, status_unwrap},
+    Client, ContentService, Error, RepoClient,
 };
 
-use futures::{Stream, StreamExt};
+use futures::{Stream, StreamExt, TryStreamExt};
 use reqwest::{Method, Request, StatusCode};
+use serde::de::DeserializeOwned;
+use tokio::sync::{mpsc, watch};
 
-const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
+pub(crate) const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
 const DELAY_ON_SUCCESS: Duration = Duration::from_secs(1);
 const JITTER_RATE: f32 = 0.2;
-const MAX_BASE_TIME_MS: usize = 10_000; // 10sec = 10_000millis
+const BACKOFF_STEP: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(10);
+/// How many errors of a [`TypedWatch`] are kept until they are received
+const TYPED_WATCH_ERRORS: usize = 16;
+
+/// The timings of the watches of a client, installed with
+/// [`Client::with_watch_options()`](crate::Client::with_watch_options).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WatchOptions {
+    /// How long the server holds a watch request before answering that nothing changed,
+    /// which should be shorter than the idle timeouts of the proxies in between
+    pub timeout: Duration,
+    /// The delay before watching again after a change or a timeout
+    pub delay_on_success: Duration,
+    /// The delay added after every consecutive failure
+    pub backoff_step: Duration,
+    /// The maximum delay after failures, jitter excluded
+    pub max_backoff: Duration,
+    /// The maximum jitter added to the delays after failures, as a ratio of the delay
+    pub jitter_rate: f32,
+}
 
-async fn request_watch<D: Watchable>(client: &Client, req: Request) -> Result<Option<D>, Error> {
-    let resp = client.request(req).await?;
-    if resp.status() == StatusCode::NOT_MODIFIED {
-        return Ok(None);
+impl Default for WatchOptions {
+    /// A timeout of 60 seconds, 1 second between the watches, and 1 more second after every
+    /// failure up to 10 seconds, with up to 20% of jitter.
+    fn default() -> Self {
+        WatchOptions {
+            timeout: DEFAULT_TIMEOUT,
+            delay_on_success: DELAY_ON_SUCCESS,
+            backoff_step: BACKOFF_STEP,
+            max_backoff: MAX_BACKOFF,
+            jitter_rate: JITTER_RATE,
+        }
     }
-    let ok_resp = status_unwrap(resp).await?;
-    let result = ok_resp.json().await?;
+}
+
+impl WatchOptions {
+    /// Returns the delay after `failed_count` consecutive failures.
+    pub(crate) fn delay_for(&self, failed_count: usize) -> Duration {
+        let failed_count = failed_count.min(u32::MAX as usize) as u32;
+        let base = self
+            .backoff_step
+            .saturating_mul(failed_count)
+            .min(self.max_backoff);
+        let jitter = base.mul_f32(fastrand::f32() * self.jitter_rate.max(0.0));
 
-    Ok(Some(result))
+        base + jitter
+    }
 }
 
-fn delay_time_for(failed_count: usize) -> Duration {
-    let base_time_ms = MAX_BASE_TIME_MS.min(failed_count * 1000);
-    let jitter = (fastrand::f32() * JITTER_RATE * base_time_ms as f32) as u64;
-    Duration::from_millis(base_time_ms as u64 + jitter)
+async fn request_watch<D: Watchable>(
+    client: &Client,
+    req: Request,
+    attempt: usize,
+) -> Result<Option<D>, Error> {
+    execute(client, req, attempt, |resp| async move {
+        if resp.status() == StatusCode::NOT_MODIFIED {
+            return Ok(None);
+        }
+        let ok_resp = status_unwrap(client, resp).await?;
+        let result = json_body(client, ok_resp).await?;
+
+        Ok(Some(result))
+    })
+    .await
 }
 
 struct WatchState {
@@ -38,19 +97,48 @@ struct WatchState {
     last_known_revision: Option<Revision>,
     failed_count: usize,
     success_delay: Option<Duration>,
+    /// Whether the errors which are not retryable end the watch
+    surface_errors: bool,
+    /// Whether the error ending the watch was output
+    ended: bool,
+    last_success: std::time::Instant,
+}
+
+fn watch_stream<D: Watchable>(
+    client: Client,
+    path: String,
+    last_known_revision: Option<Revision>,
+) -> impl Stream<Item = D> + Send {
+    try_watch_stream(client, path, last_known_revision, false)
+        .filter_map(|result| futures::future::ready(result.ok()))
 }
 
-fn watch_stream<D: Watchable>(client: Client, path: String) -> impl Stream<Item = D> + Send {
+/// Returns a stream which outputs the results of a watch, then the error ending it, if any.
+///
+/// Errors which are not [retryable](Client::is_retryable) only end the watch if
+/// `surface_errors` is set, and are retried with a backoff otherwise.
+fn try_watch_stream<D: Watchable>(
+    client: Client,
+    path: String,
+    last_known_revision: Option<Revision>,
+    surface_errors: bool,
+) -> impl Stream<Item = Result<D, Error>> + Send {
     let init_state = WatchState {
         client,
         path,
-        last_known_revision: None,
+        last_known_revision,
         failed_count: 0,
         success_delay: None,
+        surface_errors,
+        ended: false,
+        last_success: std::time::Instant::now(),
     };
     futures::stream::unfold(init_state, |mut state| async move {
+        if state.ended {
+            return None;
+        }
         if let Some(d) = state.success_delay.take() {
-            tokio::time::sleep(d).await;
+            state.client.sleep(d).await;
         }
 
         loop {
@@ -59,15 +147,33 @@ fn watch_stream<D: Watchable>(client: Client, path: String) -> impl Stream<Item
                 &state.path,
                 None,
                 state.last_known_revision,
-                DEFAULT_TIMEOUT,
+                state.client.watch_options().timeout,
             ) {
                 Ok(r) => r,
-                Err(_) => {
-                    return None;
+                Err(e) => {
+                    log::warn!("Failed to build the watch request, stopping watch: {}", e);
+                    state.ended = true;
+                    return Some((Err(e), state));
                 }
             };
 
-            let resp: Result<Option<D>, _> = request_watch(&state.client, req).await;
+            let resp: Result<Option<D>, _> =
+                request_watch(&state.client, req, state.failed_count + 1).await;
+
+            if resp.is_ok() {
+                state.last_success = std::time::Instant::now();
+            }
+            #[cfg(feature = "otel")]
+            if resp.is_err() {
+                state
+                    .client
+                    .telemetry()
+                    .record_watch_staleness(&state.path, state.last_success.elapsed());
+            }
+            if let (Some(recorder), Ok(result)) = (state.client.metrics_recorder(), &resp) {
+                recorder.on_watch_response(&state.path, result.is_some());
+            }
+            let failed = resp.is_err();
 
             // handle response and decide next polling, we don't want to abuse CentralDogma server
             let next_delay = match resp {
@@ -75,28 +181,218 @@ fn watch_stream<D: Watchable>(client: Client, path: String) -> impl Stream<Item
                 Ok(Some(watch_result)) => {
                     state.last_known_revision = Some(watch_result.revision());
                     state.failed_count = 0; // reset fail count
-                    state.success_delay = Some(DELAY_ON_SUCCESS);
+                    state.success_delay = Some(state.client.watch_options().delay_on_success);
 
-                    return Some((watch_result, state));
+                    return Some((Ok(watch_result), state));
                 }
                 Ok(None) => {
                     state.failed_count = 0; // reset fail count
-                    Duration::from_secs(1)
+                    state.client.watch_options().delay_on_success
+                }
+                Err(e) if state.client.classify_retry(&e) == Some(false) => {
+                    log::debug!("Non-retryable error, stopping watch: {}", e);
+                    state.ended = true;
+                    return Some((Err(e), state));
+                }
+                Err(e @ Error::Unsupported(_)) => {
+                    log::warn!("Stopping watch: {}", e);
+                    state.ended = true;
+                    return Some((Err(e), state));
+                }
+                Err(e) if state.surface_errors && !state.client.is_retryable(&e) => {
+                    log::warn!("Non-retryable error, stopping watch: {}", e);
+                    state.ended = true;
+                    return Some((Err(e), state));
+                }
+                Err(Error::Timeout(..)) => state.client.watch_options().delay_on_success,
+                Err(Error::TooManyRequests {
+                    retry_after: Some(retry_after),
+                    ..
+                }) => {
+                    log::debug!("Rate limited, retrying after {:?}", retry_after);
+                    state.failed_count += 1;
+                    retry_after.max(state.client.watch_options().delay_for(state.failed_count))
                 }
-                Err(Error::HttpClient(e)) if e.is_timeout() => Duration::from_secs(1),
                 Err(e) => {
                     log::debug!("Request error: {}", e);
                     state.failed_count += 1;
-                    delay_time_for(state.failed_count)
+                    state.client.watch_options().delay_for(state.failed_count)
                 }
             };
 
+            if let Some(recorder) = state.client.metrics_recorder().filter(|_| failed) {
+                recorder.on_watch_backoff(&WatchBackoffEvent::new(
+                    state.path.clone(),
+                    state.failed_count,
+                    next_delay,
+                    state.last_success.elapsed(),
+                ));
+            }
+
             // Delay
-            tokio::time::sleep(next_delay).await;
+            state.client.sleep(next_delay).await;
         }
     })
 }
 
+/// Returns a stream which outputs the file of `query` when it changes after `revision`.
+pub(crate) fn watch_file_stream_since(
+    repo: &RepoClient<'_>,
+    query: &Query,
+    revision: Revision,
+) -> impl Stream<Item = WatchFileResult> + Send {
+    let p = path::content_watch_path(repo.project, repo.repo, query);
+
+    transformed(
+        repo.client.clone(),
+        watch_stream(repo.client.clone(), p, Some(revision)),
+    )
+}
+
+/// Applies the [`ContentTransformer`](crate::transform::ContentTransformer) of `client`
+/// to the files of `stream`, skipping the ones which fail to transform.
+fn transformed(
+    client: Client,
+    stream: impl Stream<Item = WatchFileResult> + Send,
+) -> impl Stream<Item = WatchFileResult> + Send {
+    try_transformed(client, stream.map(Ok)).filter_map(|result| futures::future::ready(result.ok()))
+}
+
+/// Like [`transformed()`], passing the errors through.
+fn try_transformed(
+    client: Client,
+    stream: impl Stream<Item = Result<WatchFileResult, Error>> + Send,
+) -> impl Stream<Item = Result<WatchFileResult, Error>> + Send {
+    stream.filter_map(move |result| {
+        let client = client.clone();
+        async move {
+            let mut result = match result {
+                Ok(result) => result,
+                Err(e) => return Some(Err(e)),
+            };
+            match client.after_fetch(result.entry).await {
+                Ok(entry) => {
+                    result.entry = entry;
+                    Some(Ok(result))
+                }
+                Err(e) => {
+                    log::warn!("Skipping a change which failed to transform: {}", e);
+                    None
+                }
+            }
+        }
+    })
+}
+
+/// Returns a stream which outputs the revision of every commit changing the files matched
+/// by `path_pattern` after `revision`.
+pub(crate) fn watch_repo_stream_since(
+    repo: &RepoClient<'_>,
+    path_pattern: &str,
+    revision: Revision,
+) -> impl Stream<Item = WatchRepoResult> + Send {
+    let p = path::repo_watch_path(repo.project, repo.repo, path_pattern);
+
+    watch_stream(repo.client.clone(), p, Some(revision))
+}
+
+/// A stream of watch results which outputs the error ending the watch, if any.
+pub type TryWatchStream<T> = Pin<Box<dyn Stream<Item = Result<T, Error>> + Send>>;
+
+/// A stream of the path of a watched query and its [`WatchFileResult`].
+pub type WatchFilesStream = Pin<Box<dyn Stream<Item = (String, WatchFileResult)> + Send>>;
+
+/// A watch of a file deserialized into `T`, returned by
+/// [watch_file_typed](trait@WatchService#method.watch_file_typed).
+pub struct TypedWatch<T> {
+    /// The valid values of the file, the invalid ones skipped
+    pub values: Pin<Box<dyn Stream<Item = TypedEntry<T>> + Send>>,
+    /// Why the skipped values are invalid, as [`Error::InvalidConfig`]s.
+    /// At most 16 errors are kept until they are received, the later ones being only logged.
+    /// Dropping it discards the errors.
+    pub errors: mpsc::Receiver<Error>,
+}
+
+/// A handle stopping a watch stream, created by [`WatchHandle::wrap()`].
+///
+/// Dropping the stream also stops the watch, but the handle can be kept elsewhere, e.g. by
+/// the code shutting down an application while the stream is consumed by a spawned task.
+/// Stopping cancels the pending long-poll request, then the stream ends.
+///
+/// ```no_run
+/// use centraldogma::{model::Query, Client, WatchHandle, WatchService};
+/// use futures::StreamExt;
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), centraldogma::Error> {
+/// let client = Client::new("http://localhost:36462", None).await?;
+/// let stream = client
+///     .repo("foo", "bar")
+///     .watch_file_stream(&Query::identity("/a.json").unwrap())?;
+/// let (mut stream, handle) = WatchHandle::wrap(stream);
+///
+/// let task = tokio::spawn(async move {
+///     while let Some(result) = stream.next().await {
+///         println!("{:?}", result.entry);
+///     }
+/// });
+///
+/// handle.stop();
+/// task.await.unwrap();
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct WatchHandle {
+    stopped: Arc<watch::Sender<bool>>,
+}
+
+impl WatchHandle {
+    /// Returns `stream`, ending when [`stop()`](Self::stop) is called, and the handle
+    /// stopping it. Any stream can be wrapped, e.g. of
+    /// [watch_file_stream](trait@WatchService#tymethod.watch_file_stream) or
+    /// [watch_repo_stream](trait@WatchService#tymethod.watch_repo_stream).
+    pub fn wrap<S>(stream: S) -> (Pin<Box<dyn Stream<Item = S::Item> + Send>>, WatchHandle)
+    where
+        S: Stream + Send + 'static,
+    {
+        let (tx, rx) = watch::channel(false);
+        let stream = futures::stream::unfold(
+            (stream.boxed(), rx),
+            |(mut stream, mut stopped)| async move {
+                tokio::select! {
+                    biased;
+                    _ = until_stopped(&mut stopped) => None,
+                    item = stream.next() => item.map(|item| (item, (stream, stopped))),
+                }
+            },
+        );
+
+        (
+            stream.fuse().boxed(),
+            WatchHandle {
+                stopped: Arc::new(tx),
+            },
+        )
+    }
+
+    /// Stops the watch, cancelling its pending request. The stream ends instead of
+    /// outputting the next result.
+    pub fn stop(&self) {
+        // Fails only if the stream was dropped, which already stopped the watch
+        let _ = self.stopped.send(true);
+    }
+}
+
+/// Completes once the watch is stopped, never if every handle is dropped before.
+async fn until_stopped(stopped: &mut watch::Receiver<bool>) {
+    while !*stopped.borrow() {
+        if stopped.changed().await.is_err() {
+            futures::future::pending::<()>().await;
+        }
+    }
+}
+
 /// Watch-related APIs
 pub trait WatchService {
     /// Returns a stream which output a [`WatchFileResult`] when the result of the
@@ -112,6 +408,125 @@ pub trait WatchService {
         &self,
         path_pattern: &str,
     ) -> Result<Pin<Box<dyn Stream<Item = WatchRepoResult> + Send>>, Error>;
+
+    /// Returns a stream like [watch_file_stream](#tymethod.watch_file_stream) which outputs
+    /// the error ending the watch instead of ending silently.
+    ///
+    /// Errors which may succeed when retried, see [`Client::is_retryable`], are retried with
+    /// a backoff. Other errors, such as an invalid token or a missing repository, are output,
+    /// then the stream ends.
+    fn try_watch_file_stream(
+        &self,
+        query: &Query,
+    ) -> Result<TryWatchStream<WatchFileResult>, Error> {
+        Ok(self.watch_file_stream(query)?.map(Ok).boxed())
+    }
+
+    /// Returns a stream like [watch_repo_stream](#tymethod.watch_repo_stream) which outputs
+    /// the error ending the watch instead of ending silently.
+    ///
+    /// See [try_watch_file_stream](#method.try_watch_file_stream) for the errors which end
+    /// the watch.
+    fn try_watch_repo_stream(
+        &self,
+        path_pattern: &str,
+    ) -> Result<TryWatchStream<WatchRepoResult>, Error> {
+        Ok(self.watch_repo_stream(path_pattern)?.map(Ok).boxed())
+    }
+
+    /// Watches the file of the [`Query`] like
+    /// [watch_file_stream](#tymethod.watch_file_stream), deserializing its content into `T`
+    /// and checking it with `validator`.
+    ///
+    /// The values which fail to deserialize or which the validator rejects are skipped, so
+    /// that the consumers keep the last valid value when an invalid one is pushed, and the
+    /// errors are sent to [`TypedWatch::errors`].
+    ///
+    /// ```no_run
+    /// use centraldogma::{model::Query, Client, WatchService};
+    /// use futures::StreamExt;
+    ///
+    /// #[derive(serde::Deserialize)]
+    /// struct Config {
+    ///     port: u16,
+    /// }
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), centraldogma::Error> {
+    /// let client = Client::new("http://localhost:36462", None).await?;
+    /// let mut watch = client.repo("foo", "bar").watch_file_typed(
+    ///     &Query::identity("/config.json").unwrap(),
+    ///     |config: &Config| match config.port {
+    ///         0 => Err("port must not be 0".to_owned()),
+    ///         _ => Ok(()),
+    ///     },
+    /// )?;
+    /// tokio::spawn(async move {
+    ///     while let Some(error) = watch.errors.recv().await {
+    ///         eprintln!("invalid config: {}", error);
+    ///     }
+    /// });
+    /// while let Some(config) = watch.values.next().await {
+    ///     println!("listening on {}", config.value.port);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn watch_file_typed<T, V>(&self, query: &Query, validator: V) -> Result<TypedWatch<T>, Error>
+    where
+        T: DeserializeOwned + Send + 'static,
+        V: Fn(&T) -> Result<(), String> + Send + 'static,
+    {
+        let (errors_tx, errors) = mpsc::channel(TYPED_WATCH_ERRORS);
+        let values = self
+            .watch_file_stream(query)?
+            .filter_map(move |result| {
+                let valid = result.entry.into_typed::<T>().and_then(|entry| {
+                    match validator(&entry.value) {
+                        Ok(()) => Ok(entry),
+                        Err(message) => Err(Error::InvalidConfig {
+                            path: entry.path,
+                            message,
+                        }),
+                    }
+                });
+                let value = match valid {
+                    Ok(entry) => Some(entry),
+                    Err(e) => {
+                        log::warn!("Skipping an invalid value: {}", e);
+                        let _ = errors_tx.try_send(e);
+                        None
+                    }
+                };
+                futures::future::ready(value)
+            })
+            .boxed();
+
+        Ok(TypedWatch { values, errors })
+    }
+
+    /// Returns a stream which outputs the path of the query and a [`WatchFileResult`] when
+    /// the result of one of the given [`Query`]s becomes available or changes.
+    ///
+    /// [`RepoClient`] watches the files with a single watch of the repository, fetching the
+    /// files of each new revision, instead of one watch per file. The stream ends if the
+    /// files can't be fetched for an error which is not [retryable](Client::is_retryable).
+    fn watch_files_stream(&self, queries: Vec<Query>) -> Result<WatchFilesStream, Error> {
+        if queries.is_empty() {
+            return Err(Error::InvalidParams("no queries to watch"));
+        }
+        let streams = queries
+            .iter()
+            .map(|query| {
+                let path = query.path.clone();
+                Ok(self
+                    .watch_file_stream(query)?
+                    .map(move |result| (path.clone(), result)))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        Ok(futures::stream::select_all(streams).boxed())
+    }
 }
 
 impl<'a> WatchService for RepoClient<'a> {
@@ -121,7 +536,11 @@ impl<'a> WatchService for RepoClient<'a> {
     ) -> Result<Pin<Box<dyn Stream<Item = WatchFileResult> + Send>>, Error> {
         let p = path::content_watch_path(self.project, self.repo, query);
 
-        Ok(watch_stream(self.client.clone(), p).boxed())
+        Ok(transformed(
+            self.client.clone(),
+            watch_stream(self.client.clone(), p, None),
+        )
+        .boxed())
     }
 
     fn watch_repo_stream(
@@ -130,18 +549,368 @@ impl<'a> WatchService for RepoClient<'a> {
     ) -> Result<Pin<Box<dyn Stream<Item = WatchRepoResult> + Send>>, Error> {
         let p = path::repo_watch_path(self.project, self.repo, path_pattern);
 
-        Ok(watch_stream(self.client.clone(), p).boxed())
+        Ok(watch_stream(self.client.clone(), p, None).boxed())
+    }
+
+    fn try_watch_file_stream(
+        &self,
+        query: &Query,
+    ) -> Result<TryWatchStream<WatchFileResult>, Error> {
+        let p = path::content_watch_path(self.project, self.repo, query);
+
+        Ok(try_transformed(
+            self.client.clone(),
+            try_watch_stream(self.client.clone(), p, None, true),
+        )
+        .boxed())
+    }
+
+    fn try_watch_repo_stream(
+        &self,
+        path_pattern: &str,
+    ) -> Result<TryWatchStream<WatchRepoResult>, Error> {
+        let p = path::repo_watch_path(self.project, self.repo, path_pattern);
+
+        Ok(try_watch_stream(self.client.clone(), p, None, true).boxed())
+    }
+
+    fn watch_files_stream(&self, queries: Vec<Query>) -> Result<WatchFilesStream, Error> {
+        if queries.is_empty() {
+            return Err(Error::InvalidParams("no queries to watch"));
+        }
+
+        Ok(watch_files_stream_since(
+            self,
+            queries,
+            None,
+            HashMap::new(),
+        ))
+    }
+}
+
+/// Returns a stream like [watch_files_stream](trait@WatchService#method.watch_files_stream)
+/// with a single watch of `repo` for revisions newer than `revision`, which outputs only the
+/// files whose content differs from the one of `known`, by path.
+pub(crate) fn watch_files_stream_since(
+    repo: &RepoClient<'_>,
+    queries: Vec<Query>,
+    revision: Option<Revision>,
+    known: HashMap<String, Entry>,
+) -> WatchFilesStream {
+    let path_pattern: Vec<_> = queries.iter().map(|q| q.path.as_str()).collect();
+    let p = path::repo_watch_path(repo.project, repo.repo, &path_pattern.join(","));
+    let init_state = FilesWatchState {
+        client: repo.client.clone(),
+        project: repo.project.to_owned(),
+        repo: repo.repo.to_owned(),
+        queries,
+        revisions: watch_stream(repo.client.clone(), p, revision).boxed(),
+        last: known,
+        pending: VecDeque::new(),
+    };
+
+    futures::stream::unfold(init_state, |mut state| async move {
+        loop {
+            if let Some(item) = state.pending.pop_front() {
+                return Some((item, state));
+            }
+
+            let revision = state.revisions.next().await?.revision;
+            let repo = state.client.repo(&state.project, &state.repo);
+            let mut failed_count = 0;
+            let entries = loop {
+                match fetch_files(&repo, &state.queries, revision).await {
+                    Ok(entries) => break entries,
+                    Err(e) if !state.client.is_retryable(&e) => {
+                        log::warn!("Non-retryable error, stopping watch: {}", e);
+                        return None;
+                    }
+                    Err(e) => {
+                        log::debug!("Failed to retrieve the watched files: {}", e);
+                        failed_count += 1;
+                        let delay = state.client.watch_options().delay_for(failed_count);
+                        state.client.sleep(delay).await;
+                    }
+                }
+            };
+            for (path, entry) in entries {
+                if state.last.get(&path).map(|e| &e.content) == Some(&entry.content) {
+                    continue;
+                }
+                state.last.insert(path.clone(), entry.clone());
+                state
+                    .pending
+                    .push_back((path, WatchFileResult { revision, entry }));
+            }
+        }
+    })
+    .boxed()
+}
+
+struct FilesWatchState {
+    client: Client,
+    project: String,
+    repo: String,
+    queries: Vec<Query>,
+    revisions: Pin<Box<dyn Stream<Item = WatchRepoResult> + Send>>,
+    /// The last entry output for the path of each query
+    last: HashMap<String, Entry>,
+    pending: VecDeque<(String, WatchFileResult)>,
+}
+
+/// Returns the entries of `queries` at `revision`, skipping the missing files.
+async fn fetch_files(
+    repo: &RepoClient<'_>,
+    queries: &[Query],
+    revision: Revision,
+) -> Result<Vec<(String, Entry)>, Error> {
+    let mut entries = Vec::with_capacity(queries.len());
+    for query in queries {
+        match repo.get_file(revision, query).await {
+            Ok(entry) => entries.push((query.path.clone(), entry)),
+            Err(Error::EntryNotFound(_)) => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(entries)
+}
+
+struct FeedState {
+    client: Client,
+    project: String,
+    repo: String,
+    path_pattern: String,
+    /// Revision of the last commit output, `None` until the head revision is known
+    last: Option<i64>,
+    /// Revision reported by the watch whose commits are not retrieved yet
+    target: Option<i64>,
+    revisions: Option<Pin<Box<dyn Stream<Item = WatchRepoResult> + Send>>>,
+    pending: VecDeque<Commit>,
+    failed_count: usize,
+}
+
+/// Returns the revision of the latest commit of `repo`.
+async fn head_revision(repo: &RepoClient<'_>) -> Result<i64, Error> {
+    let commits = repo
+        .get_history(Revision::HEAD, Revision::HEAD, "/**", Some(1))
+        .await?;
+
+    commits
+        .first()
+        .and_then(|c| c.revision.as_i64())
+        .ok_or(Error::InvalidParams("no head revision"))
+}
+
+/// Returns the commits from `from` to `to` changing the files matched by `path_pattern`,
+/// oldest first, retrieved page by page so that none is left out by the server limit.
+async fn commits_between(
+    repo: &RepoClient<'_>,
+    path_pattern: &str,
+    from: i64,
+    to: i64,
+) -> Result<Vec<Commit>, Error> {
+    let mut commits: Vec<Commit> = repo
+        .get_history_stream(Revision::from(from), Revision::from(to), path_pattern)
+        .try_collect()
+        .await?;
+    commits.sort_by_key(|c| c.revision.as_i64());
+
+    Ok(commits)
+}
+
+struct ChangesState {
+    client: Client,
+    project: String,
+    repo: String,
+    path_pattern: String,
+    /// Revision of the last changes output, `None` until the head revision is known
+    last: Option<Revision>,
+    revisions: Option<Pin<Box<dyn Stream<Item = WatchRepoResult> + Send>>>,
+    failed_count: usize,
+}
+
+impl<'a> RepoClient<'a> {
+    /// Returns a stream which outputs every commit changing the files matched by
+    /// `path_pattern` after the stream is created, oldest first, like `tail -f` of the
+    /// history of the repository.
+    ///
+    /// The commits are retrieved by
+    /// [get_history_stream](trait@crate::ContentService#method.get_history_stream) when a
+    /// watch reports a new revision. Failed requests are retried with a backoff, so no commit
+    /// is skipped. The stream ends on an error which is not [retryable](Client::is_retryable),
+    /// e.g. if the repository doesn't exist.
+    pub fn commit_feed(&self, path_pattern: &str) -> Pin<Box<dyn Stream<Item = Commit> + Send>> {
+        let init_state = FeedState {
+            client: self.client.clone(),
+            project: self.project.to_owned(),
+            repo: self.repo.to_owned(),
+            path_pattern: path_pattern.to_owned(),
+            last: None,
+            target: None,
+            revisions: None,
+            pending: VecDeque::new(),
+            failed_count: 0,
+        };
+
+        futures::stream::unfold(init_state, |mut state| async move {
+            loop {
+                if let Some(commit) = state.pending.pop_front() {
+                    return Some((commit, state));
+                }
+
+                let client = state.client.clone();
+                let repo = client.repo(&state.project, &state.repo);
+                let result = match (state.last, state.revisions.as_mut()) {
+                    (Some(last), Some(revisions)) => {
+                        // Retries the range whose retrieval failed before watching further
+                        let to = match state.target {
+                            Some(to) => to,
+                            None => match revisions.next().await?.revision.as_i64() {
+                                Some(to) if to > last => to,
+                                _ => continue,
+                            },
+                        };
+                        state.target = Some(to);
+                        commits_between(&repo, &state.path_pattern, last + 1, to)
+                            .await
+                            .map(|commits| {
+                                state.pending.extend(commits);
+                                state.last = Some(to);
+                                state.target = None;
+                            })
+                    }
+                    _ => head_revision(&repo).await.map(|head| {
+                        state.last = Some(head);
+                        state.revisions = Some(
+                            watch_repo_stream_since(
+                                &repo,
+                                &state.path_pattern,
+                                Revision::from(head),
+                            )
+                            .boxed(),
+                        );
+                    }),
+                };
+
+                match result {
+                    Ok(()) => state.failed_count = 0,
+                    Err(e) if !state.client.is_retryable(&e) => {
+                        log::warn!("Non-retryable error, stopping the commit feed: {}", e);
+                        return None;
+                    }
+                    Err(e) => {
+                        log::debug!("Failed to retrieve the commits: {}", e);
+                        state.failed_count += 1;
+                        let delay = state.client.watch_options().delay_for(state.failed_count);
+                        state.client.sleep(delay).await;
+                    }
+                }
+            }
+        })
+        .boxed()
+    }
+
+    /// Returns a stream like [watch_repo_stream](trait@WatchService#tymethod.watch_repo_stream)
+    /// which outputs the changes of the files matched by `path_pattern` along with each new
+    /// revision, sparing a call to
+    /// [get_diffs](trait@crate::ContentService#tymethod.get_diffs) after every notification.
+    ///
+    /// The changes are the diffs from the revision previously output, or from the head
+    /// revision when the stream is created. Failed requests are retried with a backoff, and
+    /// the stream ends on an error which is not [retryable](Client::is_retryable).
+    pub fn watch_repo_changes_stream(
+        &self,
+        path_pattern: &str,
+    ) -> Pin<Box<dyn Stream<Item = WatchRepoChanges> + Send>> {
+        let init_state = ChangesState {
+            client: self.client.clone(),
+            project: self.project.to_owned(),
+            repo: self.repo.to_owned(),
+            path_pattern: path_pattern.to_owned(),
+            last: None,
+            revisions: None,
+            failed_count: 0,
+        };
+
+        futures::stream::unfold(init_state, |mut state| async move {
+            loop {
+                let client = state.client.clone();
+                let repo = client.repo(&state.project, &state.repo);
+                let result = match (state.last, state.revisions.as_mut()) {
+                    (Some(last), Some(revisions)) => {
+                        let revision = revisions.next().await?.revision;
+                        loop {
+                            match repo.get_diffs(last, revision, &state.path_pattern).await {
+                                Ok(changes) => {
+                                    break Ok(Some(WatchRepoChanges { revision, changes }))
+                                }
+                                Err(e) if !client.is_retryable(&e) => break Err(("changes", e)),
+                                Err(e) => {
+                                    log::debug!("Failed to retrieve the changes: {}", e);
+                                    state.failed_count += 1;
+                                    let delay =
+                                        client.watch_options().delay_for(state.failed_count);
+                                    client.sleep(delay).await;
+                                }
+                            }
+                        }
+                    }
+                    _ => repo
+                        .normalize_revision(Revision::HEAD)
+                        .await
+                        .map(|head| {
+                            state.last = Some(head);
+                            state.revisions = Some(
+                                watch_repo_stream_since(&repo, &state.path_pattern, head).boxed(),
+                            );
+                            None
+                        })
+                        .map_err(|e| ("head revision", e)),
+                };
+
+                match result {
+                    Ok(Some(changes)) => {
+                        state.failed_count = 0;
+                        state.last = Some(changes.revision);
+                        return Some((changes, state));
+                    }
+                    Ok(None) => state.failed_count = 0,
+                    Err((_, e)) if !client.is_retryable(&e) => {
+                        log::warn!("Non-retryable error, stopping watch: {}", e);
+                        return None;
+                    }
+                    Err((what, e)) => {
+                        log::debug!("Failed to retrieve the {}: {}", what, e);
+                        state.failed_count += 1;
+                        let delay = client.watch_options().delay_for(state.failed_count);
+                        client.sleep(delay).await;
+                    }
+                }
+            }
+        })
+        .boxed()
     }
 }
 
 #[cfg(test)]
 mod test {
-    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::{
+        future::Future,
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc, Mutex,
+        },
+    };
 
     use super::*;
-    use crate::model::{Entry, EntryContent};
+    use crate::{
+        model::{Entry, EntryContent},
+        Clock,
+    };
+    use serde_json::json;
     use wiremock::{
-        matchers::{header, method, path},
+        matchers::{header, method, path, query_param},
         Mock, MockServer, Respond, ResponseTemplate,
     };
 
@@ -173,6 +942,17 @@ mod test {
         }
     }
 
+    /// Records the sleeps instead of sleeping.
+    #[derive(Clone, Default)]
+    struct RecordingClock(Arc<Mutex<Vec<Duration>>>);
+
+    impl Clock for RecordingClock {
+        fn sleep(&self, duration: Duration) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+            self.0.lock().unwrap().push(duration);
+            Box::pin(async {})
+        }
+    }
+
     #[tokio::test]
     async fn test_watch_file() {
         let server = MockServer::start().await;
@@ -190,7 +970,11 @@ mod test {
             .mount(&server)
             .await;
 
-        let client = Client::new(&server.uri(), None).await.unwrap();
+        let clock = RecordingClock::default();
+        let client = Client::new(&server.uri(), None)
+            .await
+            .unwrap()
+            .with_clock(clock.clone());
         let stream = client
             .repo("foo", "bar")
             .watch_file_stream(&Query::identity("/a.json").unwrap())
@@ -201,6 +985,8 @@ mod test {
         let result = stream.next().await;
 
         server.reset().await;
+        // Polled again after a second on 304 Not Modified
+        assert_eq!(*clock.0.lock().unwrap(), vec![Duration::from_secs(1)]);
         let result = result.unwrap();
         assert_eq!(result.revision, Revision::from(3));
         assert_eq!(
@@ -214,4 +1000,528 @@ mod test {
             }
         );
     }
+
+    #[tokio::test]
+    async fn test_watch_options() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/contents/a.json"))
+            .and(header("prefer", "wait=5"))
+            .respond_with(MockResponse {
+                first_time: AtomicBool::new(true),
+            })
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let options = WatchOptions {
+            timeout: Duration::from_secs(5),
+            delay_on_success: Duration::from_millis(200),
+            ..WatchOptions::default()
+        };
+        let clock = RecordingClock::default();
+        let client = Client::new(&server.uri(), None)
+            .await
+            .unwrap()
+            .with_clock(clock.clone())
+            .with_watch_options(options);
+        let stream = client
+            .repo("foo", "bar")
+            .watch_file_stream(&Query::identity("/a.json").unwrap())
+            .unwrap();
+        tokio::pin!(stream);
+
+        let result = stream.next().await.unwrap();
+        assert_eq!(result.revision, Revision::from(3));
+        assert_eq!(*clock.0.lock().unwrap(), vec![Duration::from_millis(200)]);
+
+        let backoff = WatchOptions {
+            backoff_step: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(250),
+            jitter_rate: 0.0,
+            ..WatchOptions::default()
+        };
+        assert_eq!(backoff.delay_for(1), Duration::from_millis(100));
+        assert_eq!(backoff.delay_for(2), Duration::from_millis(200));
+        assert_eq!(backoff.delay_for(5), Duration::from_millis(250));
+    }
+
+    #[tokio::test]
+    async fn test_watch_file_typed() {
+        #[derive(Debug, serde::Deserialize)]
+        struct Config {
+            port: u16,
+        }
+
+        let server = MockServer::start().await;
+        let contents = [
+            json!({"port": 8080}),
+            json!({"port": "x"}),
+            json!({"port": 0}),
+            json!({"port": 9090}),
+        ];
+        for (known, content) in contents.into_iter().enumerate() {
+            let revision = known + 2;
+            Mock::given(method("GET"))
+                .and(path("/api/v1/projects/foo/repos/bar/contents/a.json"))
+                .and(header("if-none-match", if known == 0 { "-1".to_owned() } else { (revision - 1).to_string() }.as_str()))
+                .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                    "revision": revision,
+                    "entry": {"path": "/a.json", "type": "JSON", "content": content, "revision": revision, "url": "/a.json"}
+                })))
+                .mount(&server)
+                .await;
+        }
+
+        let client = Client::new(&server.uri(), None)
+            .await
+            .unwrap()
+            .with_clock(RecordingClock::default());
+        let mut watch = client
+            .repo("foo", "bar")
+            .watch_file_typed(
+                &Query::identity("/a.json").unwrap(),
+                |config: &Config| match config.port {
+                    0 => Err("port must not be 0".to_owned()),
+                    _ => Ok(()),
+                },
+            )
+            .unwrap();
+
+        let first = watch.values.next().await.unwrap();
+        assert_eq!(
+            (first.revision, first.value.port),
+            (Revision::from(2), 8080)
+        );
+        let second = watch.values.next().await.unwrap();
+        assert_eq!(
+            (second.revision, second.value.port),
+            (Revision::from(5), 9090)
+        );
+        for expected in ["invalid type", "port must not be 0"] {
+            match watch.errors.recv().await.unwrap() {
+                Error::InvalidConfig { path, message } => {
+                    assert_eq!(path, "/a.json");
+                    assert!(message.contains(expected), "{}", message);
+                }
+                e => panic!("unexpected error: {}", e),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_try_watch_surfaces_permanent_error() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/contents/a.json"))
+            .respond_with(ResponseTemplate::new(401).set_body_json(serde_json::json!({
+                "exception": "com.linecorp.centraldogma.common.AuthorizationException",
+                "message": "invalid token"
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = Client::new(&server.uri(), None).await.unwrap();
+        let mut stream = client
+            .repo("foo", "bar")
+            .try_watch_file_stream(&Query::identity("/a.json").unwrap())
+            .unwrap();
+
+        let err = stream.next().await.unwrap().unwrap_err();
+        assert_eq!(err.code(), crate::ErrorCode::Unauthorized);
+        assert!(stream.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_watch_stops_on_non_retryable_error() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/contents/a.json"))
+            .respond_with(ResponseTemplate::new(403))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = Client::new(&server.uri(), None)
+            .await
+            .unwrap()
+            .with_retry_classifier(|e| !matches!(e, Error::ErrorResponse { status: 403, .. }));
+        let stream = client
+            .repo("foo", "bar")
+            .watch_file_stream(&Query::identity("/a.json").unwrap())
+            .unwrap()
+            .take_until(tokio::time::sleep(Duration::from_secs(3)));
+        tokio::pin!(stream);
+
+        let result = stream.next().await;
+
+        assert!(result.is_none());
+        assert!(client.is_retryable(&Error::InvalidParams("retryable?")));
+        assert!(!Client::new(&server.uri(), None)
+            .await
+            .unwrap()
+            .is_retryable(&Error::InvalidParams("retryable?")));
+    }
+
+    #[tokio::test]
+    async fn test_watch_files_stops_on_non_retryable_error() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/contents/a.json"))
+            .and(header("if-none-match", "-1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"revision": 2})))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/contents/a.json"))
+            .and(query_param("revision", "2"))
+            .respond_with(ResponseTemplate::new(400).set_body_json(json!({
+                "exception": "com.linecorp.centraldogma.common.QueryExecutionException",
+                "message": "/a.json"
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = Client::new(&server.uri(), None)
+            .await
+            .unwrap()
+            .with_clock(RecordingClock::default());
+        let query = Query::of_json_path("/a.json", vec!["$.a".to_owned()]).unwrap();
+        let stream = client
+            .repo("foo", "bar")
+            .watch_files_stream(vec![query])
+            .unwrap()
+            .take_until(tokio::time::sleep(Duration::from_secs(3)));
+        tokio::pin!(stream);
+
+        let result = stream.next().await;
+
+        assert!(result.is_none());
+    }
+
+    fn commit(revision: i64) -> serde_json::Value {
+        json!({
+            "revision": revision,
+            "author": {"name": "minux", "email": "minux@m.x"},
+            "commitMessage": {"summary": format!("Commit {}", revision)}
+        })
+    }
+
+    #[tokio::test]
+    async fn test_commit_feed() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/commits/-1"))
+            .and(query_param("maxCommits", "1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([commit(3)])))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/contents/a/**"))
+            .and(header("if-none-match", "3"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"revision": 5})))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/contents/a/**"))
+            .and(header("if-none-match", "5"))
+            .respond_with(ResponseTemplate::new(304).set_delay(Duration::from_millis(100)))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/commits/4"))
+            .and(query_param("to", "5"))
+            .and(query_param("path", "/a/**"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([commit(5), commit(4)])))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = Client::new(&server.uri(), None)
+            .await
+            .unwrap()
+            .with_clock(RecordingClock::default());
+        let feed = client.repo("foo", "bar").commit_feed("/a/**");
+
+        let revisions: Vec<_> = feed.take(2).map(|c| c.revision).collect().await;
+        assert_eq!(revisions, [Revision::from(4), Revision::from(5)]);
+    }
+
+    #[tokio::test]
+    async fn test_commit_feed_retry_and_pages() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/commits/-1"))
+            .and(query_param("maxCommits", "1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([commit(3)])))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/contents/a/**"))
+            .and(header("if-none-match", "3"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"revision": 105})))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/contents/a/**"))
+            .and(header("if-none-match", "105"))
+            .respond_with(ResponseTemplate::new(304).set_delay(Duration::from_millis(100)))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/commits/4"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/commits/4"))
+            .and(query_param("to", "105"))
+            .and(query_param("maxCommits", "100"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json((4..104).map(commit).collect::<Vec<_>>()),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/commits/104"))
+            .and(query_param("to", "105"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(json!([commit(104), commit(105)])),
+            )
+            .mount(&server)
+            .await;
+
+        let clock = RecordingClock::default();
+        let client = Client::new(&server.uri(), None)
+            .await
+            .unwrap()
+            .with_clock(clock.clone());
+        let feed = client.repo("foo", "bar").commit_feed("/a/**");
+
+        let revisions: Vec<_> = feed.take(102).map(|c| c.revision).collect().await;
+        assert_eq!(revisions, (4..106).map(Revision::from).collect::<Vec<_>>());
+        assert_eq!(clock.0.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_commit_feed_ends_on_non_retryable_error() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/commits/-1"))
+            .respond_with(ResponseTemplate::new(404).set_body_json(json!({
+                "exception": "com.linecorp.centraldogma.common.RepositoryNotFoundException",
+                "message": "bar"
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = Client::new(&server.uri(), None)
+            .await
+            .unwrap()
+            .with_clock(RecordingClock::default());
+        let feed = client
+            .repo("foo", "bar")
+            .commit_feed("/a/**")
+            .take_until(tokio::time::sleep(Duration::from_secs(3)));
+        tokio::pin!(feed);
+
+        assert!(feed.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_watch_repo_changes() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/revision/-1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"revision": 3})))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/contents/a/**"))
+            .and(header("if-none-match", "3"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"revision": 5})))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/contents/a/**"))
+            .and(header("if-none-match", "5"))
+            .respond_with(ResponseTemplate::new(304).set_delay(Duration::from_millis(100)))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/compare"))
+            .and(query_param("from", "3"))
+            .and(query_param("to", "5"))
+            .and(query_param("pathPattern", "/a/**"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+                {"path": "/a/b.json", "type": "APPLY_JSON_PATCH", "content": []},
+                {"path": "/a/c.txt", "type": "REMOVE"}
+            ])))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = Client::new(&server.uri(), None)
+            .await
+            .unwrap()
+            .with_clock(RecordingClock::default());
+        let stream = client.repo("foo", "bar").watch_repo_changes_stream("/a/**");
+        tokio::pin!(stream);
+
+        let result = stream.next().await.unwrap();
+        assert_eq!(result.revision, Revision::from(5));
+        assert_eq!(
+            result.paths().collect::<Vec<_>>(),
+            ["/a/b.json", "/a/c.txt"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_watch_repo_changes_ends_on_non_retryable_error() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/revision/-1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"revision": 3})))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/contents/a/**"))
+            .and(header("if-none-match", "3"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"revision": 5})))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/compare"))
+            .respond_with(ResponseTemplate::new(403))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = Client::new(&server.uri(), None)
+            .await
+            .unwrap()
+            .with_clock(RecordingClock::default());
+        let stream = client
+            .repo("foo", "bar")
+            .watch_repo_changes_stream("/a/**")
+            .take_until(tokio::time::sleep(Duration::from_secs(3)));
+        tokio::pin!(stream);
+
+        assert!(stream.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_watch_handle() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/contents/a.json"))
+            .respond_with(ResponseTemplate::new(304).set_delay(Duration::from_secs(60)))
+            .mount(&server)
+            .await;
+
+        let client = Client::new(&server.uri(), None).await.unwrap();
+        let stream = client
+            .repo("foo", "bar")
+            .watch_file_stream(&Query::identity("/a.json").unwrap())
+            .unwrap();
+        let (mut stream, handle) = WatchHandle::wrap(stream);
+
+        let stopper = handle.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            stopper.stop();
+        });
+        let result = tokio::time::timeout(Duration::from_secs(5), stream.next()).await;
+        assert!(matches!(result, Ok(None)));
+        assert_eq!(server.received_requests().await.unwrap().len(), 1);
+
+        // Stopping a dropped or stopped stream is a no-op
+        handle.stop();
+        assert!(stream.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_watch_files() {
+        let server = MockServer::start().await;
+        let watch_path = "/api/v1/projects/foo/repos/bar/contents/a.json,/b.json";
+        for (last, revision) in [("-1", 2), ("2", 3)] {
+            Mock::given(method("GET"))
+                .and(path(watch_path))
+                .and(header("if-none-match", last))
+                .respond_with(
+                    ResponseTemplate::new(200).set_body_json(json!({ "revision": revision })),
+                )
+                .mount(&server)
+                .await;
+        }
+        Mock::given(method("GET"))
+            .and(path(watch_path))
+            .and(header("if-none-match", "3"))
+            .respond_with(ResponseTemplate::new(304).set_delay(Duration::from_millis(100)))
+            .mount(&server)
+            .await;
+        let entry = |name: &str, revision: i64| {
+            json!({
+                "path": format!("/{}", name),
+                "type": "JSON",
+                "content": {"a": 1},
+                "revision": revision,
+                "url": format!("/{}", name)
+            })
+        };
+        for revision in [2, 3] {
+            Mock::given(method("GET"))
+                .and(path("/api/v1/projects/foo/repos/bar/contents/a.json"))
+                .and(query_param("revision", revision.to_string()))
+                .respond_with(ResponseTemplate::new(200).set_body_json(entry("a.json", revision)))
+                .mount(&server)
+                .await;
+        }
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/contents/b.json"))
+            .and(query_param("revision", "2"))
+            .respond_with(ResponseTemplate::new(404).set_body_json(json!({
+                "exception": "com.linecorp.centraldogma.common.EntryNotFoundException",
+                "message": "/b.json"
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/contents/b.json"))
+            .and(query_param("revision", "3"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(entry("b.json", 3)))
+            .mount(&server)
+            .await;
+
+        let client = Client::new(&server.uri(), None)
+            .await
+            .unwrap()
+            .with_clock(RecordingClock::default());
+        let queries = vec![
+            Query::identity("/a.json").unwrap(),
+            Query::identity("/b.json").unwrap(),
+        ];
+        let results: Vec<_> = client
+            .repo("foo", "bar")
+            .watch_files_stream(queries)
+            .unwrap()
+            .take(2)
+            .map(|(path, result)| (path, result.revision))
+            .collect()
+            .await;
+
+        assert_eq!(
+            results,
+            [
+                ("/a.json".to_owned(), Revision::from(2)),
+                ("/b.json".to_owned(), Revision::from(3))
+            ]
+        );
+    }
 }
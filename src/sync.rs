@@ -0,0 +1,394 @@
+//! Mirroring of the files of repositories to a local directory, e.g. by a sidecar
+//! feeding the configuration of processes which don't use this crate.
+//!
+//! ```no_run
+//! use centraldogma::{sync::Syncer, Client};
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), centraldogma::Error> {
+//! let client = Client::new("http://localhost:36462", None).await?;
+//! let handle = Syncer::new(client, "/etc/dogma")
+//!     .target("foo", "bar", "/service/**")
+//!     .start()
+//!     .await?;
+//!
+//! // /etc/dogma/foo/bar/service/... are now kept up to date
+//! for target in handle.status() {
+//!     println!("{}/{} at {:?}", target.project, target.repo, target.revision);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, PoisonError},
+    time::SystemTime,
+};
+
+use futures::StreamExt;
+use tokio::task::JoinHandle;
+
+use crate::{
+    local::{file_content, io, relative_path},
+    model::Revision,
+    services::watch::watch_repo_stream_since,
+    validation::{validate_project_name, validate_repo_name},
+    Client, ContentService, Error,
+};
+
+/// Files of a repository to mirror.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyncTarget {
+    /// Name of the project
+    pub project: String,
+    /// Name of the repository
+    pub repo: String,
+    /// Pattern of the paths of the files, see
+    /// [get_files](trait@crate::ContentService#tymethod.get_files)
+    pub path_pattern: String,
+}
+
+/// The state of a [`SyncTarget`], returned by [`SyncHandle::status()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyncStatus {
+    /// Name of the project
+    pub project: String,
+    /// Name of the repository
+    pub repo: String,
+    /// Pattern of the paths of the files
+    pub path_pattern: String,
+    /// Revision of the files in the directory, if any were written
+    pub revision: Option<Revision>,
+    /// Number of files in the directory
+    pub files: usize,
+    /// When the files were last written
+    pub last_synced: Option<SystemTime>,
+    /// The failure of the last attempt, cleared when an attempt succeeds
+    pub last_error: Option<String>,
+}
+
+/// Mirrors the files matched by [`SyncTarget`]s into a directory.
+///
+/// A file at `/a/b.json` of repository `bar` of project `foo` is written to
+/// `<dir>/foo/bar/a/b.json`, atomically by renaming a temporary file, so readers never
+/// see a partial file. Files which no longer match are removed.
+pub struct Syncer {
+    client: Client,
+    dir: PathBuf,
+    targets: Vec<SyncTarget>,
+}
+
+impl Syncer {
+    /// Returns a syncer into `dir`, without any target.
+    pub fn new(client: Client, dir: impl Into<PathBuf>) -> Self {
+        Syncer {
+            client,
+            dir: dir.into(),
+            targets: Vec::new(),
+        }
+    }
+
+    /// Adds the files matched by `path_pattern` in a repository.
+    pub fn target(mut self, project: &str, repo: &str, path_pattern: &str) -> Self {
+        self.targets.push(SyncTarget {
+            project: project.to_owned(),
+            repo: repo.to_owned(),
+            path_pattern: path_pattern.to_owned(),
+        });
+        self
+    }
+
+    /// Writes all the files, then keeps them up to date in the background until the
+    /// returned handle is dropped.
+    ///
+    /// Fails if the name of the project or of the repository of a target is invalid, so
+    /// that no file is written outside of the directory, or if any target fails to sync
+    /// initially. Later failures are retried with a backoff, and reported by
+    /// [`SyncHandle::status()`].
+    pub async fn start(self) -> Result<SyncHandle, Error> {
+        for target in &self.targets {
+            validate_project_name(&target.project)?;
+            validate_repo_name(&target.repo)?;
+        }
+
+        let mut workers = Vec::with_capacity(self.targets.len());
+        for target in self.targets {
+            let mut worker = Worker {
+                client: self.client.clone(),
+                dir: self.dir.join(&target.project).join(&target.repo),
+                status: Arc::new(Mutex::new(SyncStatus {
+                    project: target.project.clone(),
+                    repo: target.repo.clone(),
+                    path_pattern: target.path_pattern.clone(),
+                    revision: None,
+                    files: 0,
+                    last_synced: None,
+                    last_error: None,
+                })),
+                target,
+                written: BTreeSet::new(),
+            };
+            worker.sync(Revision::HEAD).await?;
+            workers.push(worker);
+        }
+
+        let statuses = workers.iter().map(|w| w.status.clone()).collect();
+        let tasks = workers
+            .into_iter()
+            .map(|worker| tokio::spawn(worker.run()))
+            .collect();
+
+        Ok(SyncHandle { statuses, tasks })
+    }
+}
+
+/// The background sync started by [`Syncer::start()`], stopped when dropped.
+pub struct SyncHandle {
+    statuses: Vec<Arc<Mutex<SyncStatus>>>,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl SyncHandle {
+    /// Returns the state of every target, in the order they were added.
+    pub fn status(&self) -> Vec<SyncStatus> {
+        self.statuses
+            .iter()
+            .map(|s| s.lock().unwrap_or_else(PoisonError::into_inner).clone())
+            .collect()
+    }
+}
+
+impl Drop for SyncHandle {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+struct Worker {
+    client: Client,
+    target: SyncTarget,
+    dir: PathBuf,
+    status: Arc<Mutex<SyncStatus>>,
+    /// Paths of the files written, relative to `dir`
+    written: BTreeSet<PathBuf>,
+}
+
+impl Worker {
+    async fn run(mut self) {
+        let revision = self
+            .status
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .revision;
+        let repo = self.client.repo(&self.target.project, &self.target.repo);
+        let stream = watch_repo_stream_since(
+            &repo,
+            &self.target.path_pattern,
+            revision.unwrap_or(Revision::HEAD),
+        );
+        futures::pin_mut!(stream);
+
+        while let Some(result) = stream.next().await {
+            let mut failed_count = 0;
+            while let Err(e) = self.sync(result.revision).await {
+                failed_count += 1;
+                log::debug!("Failed to sync {:?}: {}", self.target, e);
+                let delay = self.client.watch_options().delay_for(failed_count);
+                self.client.sleep(delay).await;
+            }
+        }
+    }
+
+    async fn sync(&mut self, revision: Revision) -> Result<(), Error> {
+        let result = self.write_files(revision).await;
+
+        let mut status = self.status.lock().unwrap_or_else(PoisonError::into_inner);
+        match &result {
+            Ok(revision) => {
+                status.revision = *revision;
+                status.files = self.written.len();
+                status.last_synced = Some(SystemTime::now());
+                status.last_error = None;
+            }
+            Err(e) => status.last_error = Some(e.to_string()),
+        }
+
+        result.map(|_| ())
+    }
+
+    /// Writes the files at `revision` and removes the other ones,
+    /// returning the revision of the files.
+    async fn write_files(&mut self, revision: Revision) -> Result<Option<Revision>, Error> {
+        let entries = self
+            .client
+            .repo(&self.target.project, &self.target.repo)
+            .get_files(revision, &self.target.path_pattern)
+            .await?;
+
+        let mut written = BTreeSet::new();
+        for entry in &entries {
+            let Some(content) = file_content(entry)? else {
+                continue;
+            };
+            let relative = relative_path(&entry.path)?;
+            write_atomically(&self.dir.join(&relative), &content).await?;
+            written.insert(relative);
+        }
+        for removed in self.written.difference(&written) {
+            let path = self.dir.join(removed);
+            match tokio::fs::remove_file(&path).await {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(io(&path, e)),
+                _ => {}
+            }
+        }
+        self.written = written;
+
+        let requested = Some(revision).filter(|r| r.as_i64().is_some_and(|r| r > 0));
+        Ok(entries
+            .iter()
+            .map(|e| e.revision)
+            .max_by_key(|r| r.as_i64())
+            .or(requested))
+    }
+}
+
+async fn write_atomically(path: &Path, content: &[u8]) -> Result<(), Error> {
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent)
+            .await
+            .map_err(|e| io(parent, e))?;
+    }
+
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(format!(".tmp-{:x}", fastrand::u64(..)));
+    let temp = PathBuf::from(temp);
+    tokio::fs::write(&temp, content)
+        .await
+        .map_err(|e| io(&temp, e))?;
+    if let Err(e) = tokio::fs::rename(&temp, path).await {
+        let _ = tokio::fs::remove_file(&temp).await;
+        return Err(io(path, e));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use wiremock::{
+        matchers::{header, method, path, query_param},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+
+    fn files(revision: i64, files: &[(&str, &str)]) -> String {
+        let entries: Vec<_> = files
+            .iter()
+            .map(|(path, content)| {
+                format!(
+                    r#"{{"path":"{}","type":"TEXT","content":"{}","revision":{},"url":"{}"}}"#,
+                    path, content, revision, path
+                )
+            })
+            .collect();
+        format!("[{}]", entries.join(","))
+    }
+
+    #[tokio::test]
+    async fn test_sync() {
+        let server = MockServer::start().await;
+        let contents = "/api/v1/projects/foo/repos/bar/contents/**";
+        Mock::given(method("GET"))
+            .and(path(contents))
+            .and(query_param("revision", "-1"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(
+                files(2, &[("/a.txt", "a"), ("/b/c.txt", "c")]),
+                "application/json",
+            ))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path(contents))
+            .and(header("if-none-match", "2"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_raw(r#"{"revision":3}"#, "application/json"),
+            )
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path(contents))
+            .and(query_param("revision", "3"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_raw(files(3, &[("/b/c.txt", "d")]), "application/json"),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(304).set_delay(Duration::from_millis(100)))
+            .mount(&server)
+            .await;
+
+        let dir = std::env::temp_dir().join(format!("centraldogma-sync-{}", fastrand::u64(..)));
+        let client = Client::new(&server.uri(), None).await.unwrap();
+        let handle = Syncer::new(client, &dir)
+            .target("foo", "bar", "/**")
+            .start()
+            .await
+            .unwrap();
+
+        let repo_dir = dir.join("foo").join("bar");
+        assert_eq!(
+            std::fs::read_to_string(repo_dir.join("a.txt")).unwrap(),
+            "a"
+        );
+        assert_eq!(
+            std::fs::read_to_string(repo_dir.join("b/c.txt")).unwrap(),
+            "c"
+        );
+
+        for _ in 0..50 {
+            if handle.status()[0].revision == Some(Revision::from(3)) {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        let status = &handle.status()[0];
+        assert_eq!(status.revision, Some(Revision::from(3)));
+        assert_eq!(status.files, 1);
+        assert_eq!(status.last_error, None);
+        assert!(!repo_dir.join("a.txt").exists());
+        assert_eq!(
+            std::fs::read_to_string(repo_dir.join("b/c.txt")).unwrap(),
+            "d"
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_invalid_target_names() {
+        let server = MockServer::start().await;
+        let client = Client::new(&server.uri(), None).await.unwrap();
+        let dir = std::env::temp_dir().join(format!("centraldogma-sync-{}", fastrand::u64(..)));
+
+        for (project, repo) in [("..", "bar"), ("foo", "/etc")] {
+            let err = Syncer::new(client.clone(), &dir)
+                .target(project, repo, "/**")
+                .start()
+                .await
+                .err()
+                .unwrap();
+            assert!(matches!(err, Error::InvalidParams(_)));
+        }
+        assert!(server.received_requests().await.unwrap().is_empty());
+        assert!(!dir.exists());
+    }
+}
@@ -0,0 +1,104 @@
+//! A [`Clock`] which doesn't wait.
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use crate::Clock;
+
+/// A [`Clock`] whose sleeps complete at once, recording their durations,
+/// so the delays of a watch can be checked without waiting for them.
+///
+/// ```
+/// use centraldogma::{test_util::MockClock, Client};
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let clock = MockClock::new();
+/// let client = Client::new("http://localhost:36462", None)
+///     .await
+///     .unwrap()
+///     .with_clock(clock.clone());
+/// // Watch with `client`, then check `clock.sleeps()`.
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MockClock {
+    sleeps: Arc<Mutex<Vec<Duration>>>,
+}
+
+impl MockClock {
+    /// Returns a clock which hasn't slept yet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the durations of the sleeps so far, oldest first.
+    pub fn sleeps(&self) -> Vec<Duration> {
+        self.sleeps.lock().unwrap().clone()
+    }
+
+    /// Returns the total duration of the sleeps so far.
+    pub fn elapsed(&self) -> Duration {
+        self.sleeps.lock().unwrap().iter().sum()
+    }
+}
+
+impl Clock for MockClock {
+    fn sleep(&self, duration: Duration) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+        self.sleeps.lock().unwrap().push(duration);
+        // Yield so that a loop of sleeps can't starve other tasks.
+        Box::pin(tokio::task::yield_now())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{model::Query, Client, WatchService};
+    use futures::StreamExt;
+    use wiremock::{matchers::method, Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn test_mock_clock() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(500))
+            .up_to_n_times(3)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(
+                r#"{"revision":3,"entry":{"path":"/a.json","type":"JSON","content":1,
+                    "revision":3,"url":"/a.json"}}"#,
+                "application/json",
+            ))
+            .mount(&server)
+            .await;
+
+        let clock = MockClock::new();
+        let client = Client::new(&server.uri(), None)
+            .await
+            .unwrap()
+            .with_clock(clock.clone());
+        let mut stream = client
+            .repo("foo", "bar")
+            .watch_file_stream(&Query::identity("/a.json").unwrap())
+            .unwrap();
+
+        assert!(stream.next().await.is_some());
+        let sleeps = clock.sleeps();
+        assert_eq!(sleeps.len(), 3);
+        for (i, sleep) in sleeps.iter().enumerate() {
+            let base = Duration::from_secs(i as u64 + 1);
+            assert!(
+                *sleep >= base && *sleep <= base.mul_f32(1.2),
+                "{:?}",
+                sleeps
+            );
+        }
+        assert_eq!(clock.elapsed(), sleeps.iter().sum::<Duration>());
+    }
+}
@@ -0,0 +1,139 @@
+//! A Central Dogma server in a container for end-to-end tests,
+//! enabled by the `testcontainers` feature.
+use std::time::{Duration, Instant};
+
+use testcontainers::{
+    core::{IntoContainerPort, WaitFor},
+    runners::AsyncRunner,
+    ContainerAsync, GenericImage, ImageExt,
+};
+
+use crate::Client;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Path of the health check endpoint of the server.
+const HEALTH_CHECK_PATH: &str = "/monitor/l7check";
+
+/// Configuration of a Central Dogma server container.
+///
+/// ```no_run
+/// use centraldogma::{test_util::CentralDogmaContainer, ProjectService};
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+/// let server = CentralDogmaContainer::new().tag("0.64.0").start().await?;
+/// let projects = server.client().list_projects().await?;
+/// // The container is stopped when `server` is dropped.
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct CentralDogmaContainer {
+    image: String,
+    tag: String,
+    port: u16,
+    startup_timeout: Duration,
+}
+
+impl Default for CentralDogmaContainer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CentralDogmaContainer {
+    /// Returns the configuration of a container of `line/centraldogma:latest`
+    /// serving on port 36462.
+    pub fn new() -> Self {
+        CentralDogmaContainer {
+            image: "line/centraldogma".to_owned(),
+            tag: "latest".to_owned(),
+            port: 36462,
+            startup_timeout: Duration::from_secs(120),
+        }
+    }
+
+    /// Sets the name of the image.
+    pub fn image(mut self, image: &str) -> Self {
+        self.image = image.to_owned();
+        self
+    }
+
+    /// Sets the tag of the image.
+    pub fn tag(mut self, tag: &str) -> Self {
+        self.tag = tag.to_owned();
+        self
+    }
+
+    /// Sets the port the server listens on inside the container.
+    pub fn port(mut self, port: u16) -> Self {
+        self.port = port;
+        self
+    }
+
+    /// Sets how long to wait for the server to become ready.
+    pub fn startup_timeout(mut self, timeout: Duration) -> Self {
+        self.startup_timeout = timeout;
+        self
+    }
+
+    /// Starts the container and waits until the server is ready to serve requests.
+    pub async fn start(self) -> Result<RunningCentralDogma, BoxError> {
+        let container = GenericImage::new(self.image, self.tag)
+            .with_exposed_port(self.port.tcp())
+            .with_wait_for(WaitFor::Nothing)
+            .with_startup_timeout(self.startup_timeout)
+            .start()
+            .await?;
+        let host = container.get_host().await?;
+        let port = container.get_host_port_ipv4(self.port).await?;
+        let base_url = format!("http://{}:{}", host, port);
+
+        wait_until_ready(&base_url, self.startup_timeout).await?;
+        let client = Client::new(&base_url, None).await?;
+
+        Ok(RunningCentralDogma {
+            _container: container,
+            base_url,
+            client,
+        })
+    }
+}
+
+async fn wait_until_ready(base_url: &str, timeout: Duration) -> Result<(), BoxError> {
+    let http_client = reqwest::Client::new();
+    let url = format!("{}{}", base_url, HEALTH_CHECK_PATH);
+    let deadline = Instant::now() + timeout;
+
+    loop {
+        match http_client.get(&url).send().await {
+            Ok(resp) if resp.status().is_success() => return Ok(()),
+            Ok(resp) => log::debug!("Central Dogma is not ready: {}", resp.status()),
+            Err(e) => log::debug!("Central Dogma is not ready: {}", e),
+        }
+        if Instant::now() >= deadline {
+            return Err(format!("Central Dogma was not ready within {:?}", timeout).into());
+        }
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+}
+
+/// A running Central Dogma server container, stopped when dropped.
+pub struct RunningCentralDogma {
+    _container: ContainerAsync<GenericImage>,
+    base_url: String,
+    client: Client,
+}
+
+impl RunningCentralDogma {
+    /// Returns the base URL of the server, e.g. `http://localhost:49153`.
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Returns an anonymous client of the server.
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+}
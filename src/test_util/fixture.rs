@@ -0,0 +1,312 @@
+//! Builders of model values for tests, filling in the fields a test does not care about.
+use serde_json::Value;
+
+use crate::model::{
+    Author, Commit, CommitDetail, CommitMessage, Entry, EntryContent, Project, Query, Repository,
+    Revision, WatchFileResult,
+};
+
+const PROJECT: &str = "test";
+const REPO: &str = "test";
+
+pub(super) fn default_author() -> Author {
+    Author {
+        name: "mock".to_owned(),
+        email: "mock@localhost".to_owned(),
+    }
+}
+
+fn content_url(project: &str, repo: &str, path: &str) -> String {
+    format!(
+        "/api/v1/projects/{}/repos/{}/contents{}",
+        project, repo, path
+    )
+}
+
+/// Builder of an [`Entry`] at revision 1.
+///
+/// ```
+/// use centraldogma::{model::Revision, test_util::EntryFixture};
+/// use serde_json::json;
+///
+/// let entry = EntryFixture::json("/a.json", json!({"a": 1})).revision(3).build();
+/// assert_eq!(entry.revision, Revision::from(3));
+/// ```
+#[derive(Debug, Clone)]
+pub struct EntryFixture {
+    entry: Entry,
+}
+
+impl EntryFixture {
+    fn new(path: &str, content: EntryContent) -> Self {
+        let path = Query::normalize_path(path);
+        EntryFixture {
+            entry: Entry {
+                url: content_url(PROJECT, REPO, &path),
+                path,
+                content,
+                revision: Revision::INIT,
+                modified_at: None,
+            },
+        }
+    }
+
+    /// Returns a builder of a JSON file.
+    pub fn json(path: &str, content: Value) -> Self {
+        Self::new(path, EntryContent::Json(content))
+    }
+
+    /// Returns a builder of a text file.
+    pub fn text(path: &str, content: &str) -> Self {
+        Self::new(path, EntryContent::Text(content.to_owned()))
+    }
+
+    /// Returns a builder of a directory.
+    pub fn directory(path: &str) -> Self {
+        Self::new(path, EntryContent::Directory)
+    }
+
+    /// Sets the revision of the entry.
+    pub fn revision(mut self, revision: i64) -> Self {
+        self.entry.revision = Revision::from(revision);
+        self
+    }
+
+    /// Sets the project and repository the url of the entry points to.
+    pub fn repo(mut self, project: &str, repo: &str) -> Self {
+        self.entry.url = content_url(project, repo, &self.entry.path);
+        self
+    }
+
+    /// Sets when the entry was last modified.
+    pub fn modified_at(mut self, modified_at: &str) -> Self {
+        self.entry.modified_at = Some(modified_at.to_owned());
+        self
+    }
+
+    pub fn build(self) -> Entry {
+        self.entry
+    }
+
+    /// Returns a [`WatchFileResult`] of the entry, as output by a watch on the file.
+    pub fn watch_result(self) -> WatchFileResult {
+        WatchFileResult {
+            revision: self.entry.revision,
+            entry: self.entry,
+        }
+    }
+}
+
+impl From<EntryFixture> for Entry {
+    fn from(fixture: EntryFixture) -> Self {
+        fixture.build()
+    }
+}
+
+/// Builder of a [`Commit`], authored by `mock <mock@localhost>` with the summary `Update`.
+///
+/// ```
+/// use centraldogma::test_util::CommitFixture;
+///
+/// let commit = CommitFixture::new(2).summary("Add a.json").build();
+/// assert_eq!(commit.commit_message.summary, "Add a.json");
+/// ```
+#[derive(Debug, Clone)]
+pub struct CommitFixture {
+    commit: Commit,
+}
+
+impl CommitFixture {
+    /// Returns a builder of the commit of the specified revision.
+    pub fn new(revision: i64) -> Self {
+        CommitFixture {
+            commit: Commit {
+                revision: Revision::from(revision),
+                author: default_author(),
+                commit_message: CommitMessage::only_summary("Update"),
+                pushed_at: None,
+            },
+        }
+    }
+
+    /// Sets the author of the commit.
+    pub fn author(mut self, name: &str, email: &str) -> Self {
+        self.commit.author = Author {
+            name: name.to_owned(),
+            email: email.to_owned(),
+        };
+        self
+    }
+
+    /// Sets the summary of the commit message.
+    pub fn summary(mut self, summary: &str) -> Self {
+        self.commit.commit_message.summary = summary.to_owned();
+        self
+    }
+
+    /// Sets the detail of the commit message.
+    pub fn detail(mut self, detail: CommitDetail) -> Self {
+        self.commit.commit_message.detail = Some(detail);
+        self
+    }
+
+    /// Sets when the commit was pushed.
+    pub fn pushed_at(mut self, pushed_at: &str) -> Self {
+        self.commit.pushed_at = Some(pushed_at.to_owned());
+        self
+    }
+
+    pub fn build(self) -> Commit {
+        self.commit
+    }
+}
+
+impl From<CommitFixture> for Commit {
+    fn from(fixture: CommitFixture) -> Self {
+        fixture.build()
+    }
+}
+
+/// Builder of a [`Project`], created by `mock <mock@localhost>`.
+#[derive(Debug, Clone)]
+pub struct ProjectFixture {
+    project: Project,
+}
+
+impl ProjectFixture {
+    /// Returns a builder of the project with the specified name.
+    pub fn new(name: &str) -> Self {
+        ProjectFixture {
+            project: Project {
+                name: name.to_owned(),
+                creator: default_author(),
+                url: Some(format!("/api/v1/projects/{}", name)),
+                created_at: None,
+            },
+        }
+    }
+
+    /// Sets the creator of the project.
+    pub fn creator(mut self, name: &str, email: &str) -> Self {
+        self.project.creator = Author {
+            name: name.to_owned(),
+            email: email.to_owned(),
+        };
+        self
+    }
+
+    /// Sets when the project was created.
+    pub fn created_at(mut self, created_at: &str) -> Self {
+        self.project.created_at = Some(created_at.to_owned());
+        self
+    }
+
+    pub fn build(self) -> Project {
+        self.project
+    }
+}
+
+impl From<ProjectFixture> for Project {
+    fn from(fixture: ProjectFixture) -> Self {
+        fixture.build()
+    }
+}
+
+/// Builder of a [`Repository`] at revision 1, created by `mock <mock@localhost>`.
+#[derive(Debug, Clone)]
+pub struct RepositoryFixture {
+    repository: Repository,
+}
+
+impl RepositoryFixture {
+    /// Returns a builder of the repository with the specified project and name.
+    pub fn new(project: &str, name: &str) -> Self {
+        RepositoryFixture {
+            repository: Repository {
+                name: name.to_owned(),
+                creator: default_author(),
+                head_revision: Revision::INIT,
+                url: Some(format!("/api/v1/projects/{}/repos/{}", project, name)),
+                created_at: None,
+            },
+        }
+    }
+
+    /// Sets the head revision of the repository.
+    pub fn head_revision(mut self, revision: i64) -> Self {
+        self.repository.head_revision = Revision::from(revision);
+        self
+    }
+
+    /// Sets the creator of the repository.
+    pub fn creator(mut self, name: &str, email: &str) -> Self {
+        self.repository.creator = Author {
+            name: name.to_owned(),
+            email: email.to_owned(),
+        };
+        self
+    }
+
+    /// Sets when the repository was created.
+    pub fn created_at(mut self, created_at: &str) -> Self {
+        self.repository.created_at = Some(created_at.to_owned());
+        self
+    }
+
+    pub fn build(self) -> Repository {
+        self.repository
+    }
+}
+
+impl From<RepositoryFixture> for Repository {
+    fn from(fixture: RepositoryFixture) -> Self {
+        fixture.build()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_entry_fixture() {
+        let entry = EntryFixture::json("a.json", json!({"a": 1}))
+            .revision(3)
+            .repo("foo", "bar")
+            .build();
+
+        assert_eq!(
+            entry,
+            Entry {
+                path: "/a.json".to_owned(),
+                content: EntryContent::Json(json!({"a": 1})),
+                revision: Revision::from(3),
+                url: "/api/v1/projects/foo/repos/bar/contents/a.json".to_owned(),
+                modified_at: None,
+            }
+        );
+
+        let result = EntryFixture::text("/b.txt", "hello")
+            .revision(2)
+            .watch_result();
+        assert_eq!(result.revision, Revision::from(2));
+        assert_eq!(result.entry.content, EntryContent::Text("hello".to_owned()));
+    }
+
+    #[test]
+    fn test_commit_fixture() {
+        let commit: Commit = CommitFixture::new(2)
+            .author("minux", "minux@m.x")
+            .summary("Add a.json")
+            .detail(CommitDetail::plaintext("details"))
+            .into();
+
+        assert_eq!(commit.revision, Revision::from(2));
+        assert_eq!(commit.author.name, "minux");
+        assert_eq!(
+            commit.commit_message,
+            CommitMessage::with_detail("Add a.json", CommitDetail::plaintext("details"))
+        );
+    }
+}
@@ -0,0 +1,299 @@
+//! [wiremock] matchers of the requests sent by [`Client`](crate::Client), enabled by the
+//! `wiremock` feature.
+//!
+//! ```no_run
+//! use centraldogma::{
+//!     model::{Query, Revision},
+//!     test_util::matchers,
+//!     Client, ContentService,
+//! };
+//! use wiremock::{matchers::method, Mock, MockServer, ResponseTemplate};
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let server = MockServer::start().await;
+//! let query = Query::identity("/a.json").unwrap();
+//! Mock::given(method("GET"))
+//!     .and(matchers::file_path("foo", "bar", Revision::HEAD, &query))
+//!     .and(matchers::authorization(None))
+//!     .respond_with(ResponseTemplate::new(200).set_body_raw(
+//!         r#"{"path":"/a.json","type":"JSON","content":{},"revision":2,"url":"/a.json"}"#,
+//!         "application/json",
+//!     ))
+//!     .mount(&server)
+//!     .await;
+//!
+//! let client = Client::new(&server.uri(), None).await.unwrap();
+//! let entry = client.repo("foo", "bar").get_file(Revision::HEAD, &query).await.unwrap();
+//! # }
+//! ```
+use std::time::Duration;
+
+use reqwest::Url;
+use wiremock::{
+    matchers::{body_json, header},
+    Match, Request,
+};
+
+use crate::{
+    model::{Change, CommitMessage, Query, Revision},
+    services::{content::Push, path},
+};
+
+struct PathAndQuery {
+    path: String,
+    query: Vec<(String, String)>,
+}
+
+impl PathAndQuery {
+    fn new(path_and_query: &str) -> Self {
+        let url = Url::parse("http://localhost")
+            .and_then(|base| base.join(path_and_query))
+            .expect("request paths are valid");
+
+        PathAndQuery {
+            path: url.path().to_owned(),
+            query: url.query_pairs().into_owned().collect(),
+        }
+    }
+}
+
+impl Match for PathAndQuery {
+    fn matches(&self, request: &Request) -> bool {
+        request.url.path() == self.path
+            && request.url.query_pairs().into_owned().collect::<Vec<_>>() == self.query
+    }
+}
+
+struct All(Vec<Box<dyn Match>>);
+
+impl Match for All {
+    fn matches(&self, request: &Request) -> bool {
+        self.0.iter().all(|m| m.matches(request))
+    }
+}
+
+/// Matches requests authorized with `token`, or anonymous requests if `None`.
+pub fn authorization(token: Option<&str>) -> impl Match {
+    header(
+        "Authorization",
+        format!("Bearer {}", token.unwrap_or("anonymous")).as_str(),
+    )
+}
+
+/// Matches the headers of a watch request, which waits up to `timeout` for a revision newer
+/// than `last_known_revision`, or for any revision if `None`, e.g. with the
+/// [timeout of the watches](crate::WatchOptions::timeout) of the client.
+pub fn watch_headers(last_known_revision: Option<Revision>, timeout: Duration) -> impl Match {
+    let revision = last_known_revision.unwrap_or(Revision::HEAD);
+
+    let mut matchers: Vec<Box<dyn Match>> = vec![Box::new(header(
+        "if-none-match",
+        revision.to_string().as_str(),
+    ))];
+    if timeout.as_secs() != 0 {
+        matchers.push(Box::new(header(
+            "prefer",
+            format!("wait={}", timeout.as_secs()).as_str(),
+        )));
+    }
+    All(matchers)
+}
+
+/// Matches the path and the query string of a request getting the file of `query`.
+pub fn file_path(project: &str, repo: &str, revision: Revision, query: &Query) -> impl Match {
+    PathAndQuery::new(&path::content_path(project, repo, revision, query))
+}
+
+/// Matches the path and the query string of a request watching the file of `query`.
+pub fn watch_file_path(project: &str, repo: &str, query: &Query) -> impl Match {
+    PathAndQuery::new(&path::content_watch_path(project, repo, query))
+}
+
+/// Matches the path of a request watching the repository with `path_pattern`.
+pub fn watch_repo_path(project: &str, repo: &str, path_pattern: &str) -> impl Match {
+    PathAndQuery::new(&path::repo_watch_path(project, repo, path_pattern))
+}
+
+/// Matches the path and the query string of a request pushing to `base_revision`.
+pub fn push_path(project: &str, repo: &str, base_revision: Revision) -> impl Match {
+    PathAndQuery::new(&path::contents_push_path(project, repo, base_revision))
+}
+
+/// Matches the body of a request pushing `changes` with `commit_message`.
+pub fn push_body(commit_message: CommitMessage, changes: Vec<Change>) -> impl Match {
+    let push = Push {
+        commit_message,
+        changes,
+    };
+    body_json(serde_json::to_value(push).expect("push bodies are serializable"))
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+
+    /// Whether `request`, written out literally, is matched by `matcher`.
+    async fn matches(matcher: impl Match + 'static, request: reqwest::RequestBuilder) -> bool {
+        let server = MockServer::start().await;
+        Mock::given(matcher)
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+
+        let request = request.build().unwrap();
+        let mut url = Url::parse(&server.uri()).unwrap();
+        url.set_path(request.url().path());
+        url.set_query(request.url().query());
+        let mut literal = reqwest::Request::new(request.method().clone(), url);
+        *literal.headers_mut() = request.headers().clone();
+        *literal.body_mut() = request
+            .body()
+            .and_then(|b| b.as_bytes())
+            .map(|b| b.to_vec().into());
+
+        let response = reqwest::Client::new().execute(literal).await.unwrap();
+        response.status() == 200
+    }
+
+    fn get(path_and_query: &str) -> reqwest::RequestBuilder {
+        reqwest::Client::new().get(format!("http://localhost{}", path_and_query))
+    }
+
+    #[tokio::test]
+    async fn test_authorization() {
+        let request = || get("/api/v1/projects").header("Authorization", "Bearer anonymous");
+
+        assert!(matches(authorization(None), request()).await);
+        assert!(!matches(authorization(Some("secret")), request()).await);
+        assert!(
+            matches(
+                authorization(Some("secret")),
+                get("/api/v1/projects").header("Authorization", "Bearer secret")
+            )
+            .await
+        );
+    }
+
+    #[tokio::test]
+    async fn test_watch_headers() {
+        let request = || {
+            get("/api/v1/projects/foo/repos/bar/contents/a.json")
+                .header("if-none-match", "-1")
+                .header("prefer", "wait=60")
+        };
+
+        assert!(matches(watch_headers(None, Duration::from_secs(60)), request()).await);
+        assert!(!matches(watch_headers(None, Duration::from_secs(5)), request()).await);
+        assert!(
+            !matches(
+                watch_headers(Some(Revision::from(3)), Duration::from_secs(60)),
+                request()
+            )
+            .await
+        );
+        assert!(
+            matches(
+                watch_headers(Some(Revision::from(3)), Duration::from_secs(5)),
+                get("/api/v1/projects/foo/repos/bar/contents/a.json")
+                    .header("if-none-match", "3")
+                    .header("prefer", "wait=5")
+            )
+            .await
+        );
+    }
+
+    #[tokio::test]
+    async fn test_file_path() {
+        let query = Query::of_json_path("/a.json", vec!["$.a".to_owned()]).unwrap();
+        let request =
+            || get("/api/v1/projects/foo/repos/bar/contents/a.json?revision=5&jsonpath=%24.a");
+
+        assert!(
+            matches(
+                file_path("foo", "bar", Revision::from(5), &query),
+                request()
+            )
+            .await
+        );
+        assert!(
+            !matches(
+                file_path("foo", "bar", Revision::from(4), &query),
+                request()
+            )
+            .await
+        );
+        assert!(
+            !matches(
+                file_path(
+                    "foo",
+                    "bar",
+                    Revision::from(5),
+                    &Query::identity("/a.json").unwrap()
+                ),
+                request()
+            )
+            .await
+        );
+    }
+
+    #[tokio::test]
+    async fn test_watch_paths() {
+        let query = Query::of_json_path("/a.json", vec!["$.a".to_owned()]).unwrap();
+
+        assert!(
+            matches(
+                watch_file_path("foo", "bar", &query),
+                get("/api/v1/projects/foo/repos/bar/contents/a.json?jsonpath=%24.a")
+            )
+            .await
+        );
+        assert!(
+            !matches(
+                watch_file_path("foo", "bar", &query),
+                get("/api/v1/projects/foo/repos/bar/contents/a.json")
+            )
+            .await
+        );
+        assert!(
+            matches(
+                watch_repo_path("foo", "bar", "/a/**"),
+                get("/api/v1/projects/foo/repos/bar/contents/a/**")
+            )
+            .await
+        );
+    }
+
+    #[tokio::test]
+    async fn test_push() {
+        let request = || {
+            reqwest::Client::new()
+                .post("http://localhost/api/v1/projects/foo/repos/bar/contents?revision=-1")
+                .json(&json!({
+                    "commitMessage": {"summary": "Add a.json"},
+                    "changes": [{"path": "/a.json", "type": "UPSERT_JSON", "content": {"a": 1}}]
+                }))
+        };
+        let changes = || vec![Change::from(("/a.json", json!({"a": 1})))];
+
+        assert!(matches(push_path("foo", "bar", Revision::HEAD), request()).await);
+        assert!(!matches(push_path("foo", "bar", Revision::from(2)), request()).await);
+        assert!(
+            matches(
+                push_body(CommitMessage::only_summary("Add a.json"), changes()),
+                request()
+            )
+            .await
+        );
+        assert!(
+            !matches(
+                push_body(CommitMessage::only_summary("Add b.json"), changes()),
+                request()
+            )
+            .await
+        );
+    }
+}
@@ -0,0 +1,1172 @@
+//! An in-memory implementation of the service traits.
+use std::{
+    collections::BTreeMap,
+    pin::Pin,
+    sync::{Arc, Mutex},
+};
+
+use async_trait::async_trait;
+use futures::{Stream, StreamExt};
+use tokio::sync::watch;
+
+use crate::{
+    model::{
+        Author, Change, ChangeContent, Commit, CommitMessage, Entry, EntryContent, EntryType,
+        ListEntry, MergeQuery, MergedEntry, Project, PushResult, Query, QueryType, RawEntries,
+        Repository, Revision, WatchFileResult, WatchRepoResult,
+    },
+    overlay::{deep_merge, ArrayMerge},
+    services::{error_response, path::matches_pattern},
+    test_util::fixture::default_author,
+    ContentService, Error, ProjectService, RepoService, WatchService,
+};
+
+type Snapshot = BTreeMap<String, EntryContent>;
+
+struct RepoState {
+    repository: Repository,
+    /// Commits and the paths they changed, the commit of revision `n` at index `n - 1`.
+    commits: Vec<(Commit, Vec<String>)>,
+    /// Files at each revision, the files of revision `n` at index `n - 1`.
+    snapshots: Vec<Snapshot>,
+}
+
+impl RepoState {
+    fn head(&self) -> i64 {
+        self.snapshots.len() as i64
+    }
+
+    fn normalize(&self, revision: Revision) -> Result<i64, Error> {
+        let head = self.head();
+        let normalized = match revision.as_i64() {
+            None => head,
+            Some(n) if n < 0 => head + 1 + n,
+            Some(n) => n,
+        };
+        if normalized < 1 || normalized > head {
+            return Err(server_error(
+                404,
+                "RevisionNotFoundException",
+                &format!("revision {} not found", revision),
+            ));
+        }
+
+        Ok(normalized)
+    }
+
+    fn snapshot(&self, revision: i64) -> &Snapshot {
+        &self.snapshots[revision as usize - 1]
+    }
+}
+
+struct ProjectState {
+    project: Project,
+    repos: BTreeMap<String, RepoState>,
+    removed_repos: BTreeMap<String, RepoState>,
+}
+
+#[derive(Default)]
+struct State {
+    projects: BTreeMap<String, ProjectState>,
+    removed_projects: BTreeMap<String, ProjectState>,
+}
+
+/// An in-memory Central Dogma, implementing the service traits of this crate
+/// without a server.
+///
+/// [`MockCentralDogma`] implements [`ProjectService`], [`MockCentralDogma::project`]
+/// returns a [`MockProject`] implementing [`RepoService`] and [`MockCentralDogma::repo`]
+/// returns a [`MockRepo`] implementing [`ContentService`] and [`WatchService`],
+/// mirroring [`Client`](crate::Client), [`ProjectClient`](crate::ProjectClient) and
+/// [`RepoClient`](crate::RepoClient).
+///
+/// Each push creates a new [`Revision`], starting from the revision 1 created with the
+/// repository. Revisions are resolved and conflicts are detected like the server does,
+/// with these limitations:
+///   * JSON path queries and `APPLY_JSON_PATCH`/`APPLY_TEXT_PATCH` changes are not supported.
+///   * Diffs are returned as upserts of the new content rather than patches.
+///   * Watch streams output the current value first, then every change.
+///
+/// ```
+/// use centraldogma::{
+///     model::{Change, CommitMessage, Query, Revision},
+///     test_util::MockCentralDogma,
+///     ContentService, ProjectService, RepoService,
+/// };
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), centraldogma::Error> {
+/// let dogma = MockCentralDogma::new();
+/// dogma.create_project("foo").await?;
+/// dogma.project("foo").create_repo("bar").await?;
+///
+/// let repo = dogma.repo("foo", "bar");
+/// let change = Change::from(("/a.json", serde_json::json!({"a": 1})));
+/// let result = repo
+///     .push(Revision::HEAD, CommitMessage::only_summary("Add a.json"), vec![change])
+///     .await?;
+/// assert_eq!(result.revision, Revision::from(2));
+///
+/// let entry = repo
+///     .get_file(Revision::HEAD, &Query::identity("/a.json").unwrap())
+///     .await?;
+/// assert_eq!(entry.revision, Revision::from(2));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct MockCentralDogma {
+    state: Arc<Mutex<State>>,
+    changes: Arc<watch::Sender<u64>>,
+    author: Author,
+}
+
+impl Default for MockCentralDogma {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MockCentralDogma {
+    /// Returns an empty [`MockCentralDogma`].
+    pub fn new() -> Self {
+        let (changes, _) = watch::channel(0);
+
+        MockCentralDogma {
+            state: Arc::new(Mutex::new(State::default())),
+            changes: Arc::new(changes),
+            author: default_author(),
+        }
+    }
+
+    /// Sets the author of the projects, repositories and commits created from now on.
+    pub fn with_author(mut self, author: Author) -> Self {
+        self.author = author;
+        self
+    }
+
+    /// Returns a view of the specified project, implementing [`RepoService`].
+    pub fn project(&self, project_name: &str) -> MockProject {
+        MockProject {
+            dogma: self.clone(),
+            project: project_name.to_owned(),
+        }
+    }
+
+    /// Returns a view of the specified repository,
+    /// implementing [`ContentService`] and [`WatchService`].
+    pub fn repo(&self, project_name: &str, repo_name: &str) -> MockRepo {
+        MockRepo {
+            dogma: self.clone(),
+            project: project_name.to_owned(),
+            repo: repo_name.to_owned(),
+        }
+    }
+
+    fn notify(&self) {
+        self.changes.send_modify(|n| *n += 1);
+    }
+
+    fn with_project<T>(
+        &self,
+        project: &str,
+        f: impl FnOnce(&mut ProjectState) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        let mut state = self.state.lock().unwrap();
+        let project_state = state.projects.get_mut(project).ok_or_else(|| {
+            server_error(
+                404,
+                "ProjectNotFoundException",
+                &format!("project {} not found", project),
+            )
+        })?;
+
+        f(project_state)
+    }
+
+    fn with_repo<T>(
+        &self,
+        project: &str,
+        repo: &str,
+        f: impl FnOnce(&mut RepoState) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        self.with_project(project, |p| {
+            let repo_state = p
+                .repos
+                .get_mut(repo)
+                .ok_or_else(|| Error::RepositoryNotFound(format!("{}/{}", project, repo)))?;
+
+            f(repo_state)
+        })
+    }
+}
+
+/// Builds the error the client returns for an error response from the server.
+fn server_error(status: u16, exception: &str, message: &str) -> Error {
+    let body = serde_json::json!({
+        "exception": format!("com.linecorp.centraldogma.common.{}", exception),
+        "message": message,
+    });
+
+    error_response(status, None, body.to_string())
+}
+
+fn entry_type(content: &EntryContent) -> EntryType {
+    match content {
+        EntryContent::Json(_) => EntryType::Json,
+        EntryContent::Text(_) => EntryType::Text,
+        #[cfg(feature = "yaml")]
+        EntryContent::Yaml(_) => EntryType::Yaml,
+        EntryContent::Directory => EntryType::Directory,
+    }
+}
+
+/// Applies `changes` to `files`, returning the paths they changed.
+fn apply(files: &mut Snapshot, changes: Vec<Change>) -> Result<Vec<String>, Error> {
+    let mut changed = Vec::new();
+    for change in changes {
+        match change.content {
+            ChangeContent::UpsertJson(json) => {
+                files.insert(change.path.clone(), EntryContent::Json(json));
+            }
+            ChangeContent::UpsertText(text) => {
+                files.insert(change.path.clone(), EntryContent::Text(text));
+            }
+            #[cfg(feature = "yaml")]
+            ChangeContent::UpsertYaml(yaml) => {
+                files.insert(change.path.clone(), EntryContent::Yaml(yaml));
+            }
+            ChangeContent::Remove => {
+                files
+                    .remove(&change.path)
+                    .ok_or_else(|| Error::EntryNotFound(change.path.clone()))?;
+            }
+            ChangeContent::Rename(to) => {
+                let content = files
+                    .remove(&change.path)
+                    .ok_or_else(|| Error::EntryNotFound(change.path.clone()))?;
+                files.insert(to.clone(), content);
+                changed.push(to);
+            }
+            ChangeContent::ApplyJsonPatch(_) | ChangeContent::ApplyTextPatch(_) => {
+                return Err(Error::InvalidParams(
+                    "patches are not supported by MockCentralDogma",
+                ))
+            }
+        }
+        changed.push(change.path);
+    }
+
+    Ok(changed)
+}
+
+fn upsert(content: &EntryContent) -> ChangeContent {
+    match content {
+        EntryContent::Json(json) => ChangeContent::UpsertJson(json.clone()),
+        EntryContent::Text(text) => ChangeContent::UpsertText(text.clone()),
+        #[cfg(feature = "yaml")]
+        EntryContent::Yaml(yaml) => ChangeContent::UpsertYaml(yaml.clone()),
+        EntryContent::Directory => ChangeContent::Remove,
+    }
+}
+
+#[async_trait]
+impl ProjectService for MockCentralDogma {
+    async fn create_project(&self, name: &str) -> Result<Project, Error> {
+        let mut state = self.state.lock().unwrap();
+        if state.projects.contains_key(name) || state.removed_projects.contains_key(name) {
+            return Err(Error::ProjectExists(format!("project {} exists", name)));
+        }
+
+        let project = Project {
+            name: name.to_owned(),
+            creator: self.author.clone(),
+            url: Some(format!("/api/v1/projects/{}", name)),
+            created_at: None,
+        };
+        state.projects.insert(
+            name.to_owned(),
+            ProjectState {
+                project: project.clone(),
+                repos: BTreeMap::new(),
+                removed_repos: BTreeMap::new(),
+            },
+        );
+
+        Ok(project)
+    }
+
+    async fn remove_project(&self, name: &str) -> Result<(), Error> {
+        let mut state = self.state.lock().unwrap();
+        let project = state.projects.remove(name).ok_or_else(|| {
+            server_error(
+                404,
+                "ProjectNotFoundException",
+                &format!("project {} not found", name),
+            )
+        })?;
+        state.removed_projects.insert(name.to_owned(), project);
+
+        Ok(())
+    }
+
+    async fn purge_project(&self, name: &str) -> Result<(), Error> {
+        let mut state = self.state.lock().unwrap();
+        state.removed_projects.remove(name).ok_or_else(|| {
+            server_error(
+                404,
+                "ProjectNotFoundException",
+                &format!("removed project {} not found", name),
+            )
+        })?;
+
+        Ok(())
+    }
+
+    async fn unremove_project(&self, name: &str) -> Result<Project, Error> {
+        let mut state = self.state.lock().unwrap();
+        let project = state.removed_projects.remove(name).ok_or_else(|| {
+            server_error(
+                404,
+                "ProjectNotFoundException",
+                &format!("removed project {} not found", name),
+            )
+        })?;
+        let result = project.project.clone();
+        state.projects.insert(name.to_owned(), project);
+
+        Ok(result)
+    }
+
+    async fn list_projects(&self) -> Result<Vec<Project>, Error> {
+        let state = self.state.lock().unwrap();
+
+        Ok(state.projects.values().map(|p| p.project.clone()).collect())
+    }
+
+    async fn list_removed_projects(&self) -> Result<Vec<String>, Error> {
+        let state = self.state.lock().unwrap();
+
+        Ok(state.removed_projects.keys().cloned().collect())
+    }
+}
+
+/// A project of a [`MockCentralDogma`], implementing [`RepoService`].
+/// Created by [`MockCentralDogma::project`].
+#[derive(Clone)]
+pub struct MockProject {
+    dogma: MockCentralDogma,
+    project: String,
+}
+
+#[async_trait]
+impl RepoService for MockProject {
+    async fn create_repo(&self, repo_name: &str) -> Result<Repository, Error> {
+        let author = self.dogma.author.clone();
+        self.dogma.with_project(&self.project, |p| {
+            if p.repos.contains_key(repo_name) || p.removed_repos.contains_key(repo_name) {
+                return Err(server_error(
+                    409,
+                    "RepositoryExistsException",
+                    &format!("repository {}/{} exists", self.project, repo_name),
+                ));
+            }
+
+            let repository = Repository {
+                name: repo_name.to_owned(),
+                creator: author.clone(),
+                head_revision: Revision::INIT,
+                url: Some(format!(
+                    "/api/v1/projects/{}/repos/{}",
+                    self.project, repo_name
+                )),
+                created_at: None,
+            };
+            let init = Commit {
+                revision: Revision::INIT,
+                author,
+                commit_message: CommitMessage::only_summary("Create a new repository"),
+                pushed_at: None,
+            };
+            p.repos.insert(
+                repo_name.to_owned(),
+                RepoState {
+                    repository: repository.clone(),
+                    commits: vec![(init, Vec::new())],
+                    snapshots: vec![Snapshot::new()],
+                },
+            );
+
+            Ok(repository)
+        })
+    }
+
+    async fn remove_repo(&self, repo_name: &str) -> Result<(), Error> {
+        self.dogma.with_project(&self.project, |p| {
+            let repo = p
+                .repos
+                .remove(repo_name)
+                .ok_or_else(|| Error::RepositoryNotFound(repo_name.to_owned()))?;
+            p.removed_repos.insert(repo_name.to_owned(), repo);
+
+            Ok(())
+        })
+    }
+
+    async fn purge_repo(&self, repo_name: &str) -> Result<(), Error> {
+        self.dogma.with_project(&self.project, |p| {
+            p.removed_repos
+                .remove(repo_name)
+                .ok_or_else(|| Error::RepositoryNotFound(repo_name.to_owned()))?;
+
+            Ok(())
+        })
+    }
+
+    async fn unremove_repo(&self, repo_name: &str) -> Result<Repository, Error> {
+        self.dogma.with_project(&self.project, |p| {
+            let repo = p
+                .removed_repos
+                .remove(repo_name)
+                .ok_or_else(|| Error::RepositoryNotFound(repo_name.to_owned()))?;
+            let result = repo.repository.clone();
+            p.repos.insert(repo_name.to_owned(), repo);
+
+            Ok(result)
+        })
+    }
+
+    async fn list_repos(&self) -> Result<Vec<Repository>, Error> {
+        self.dogma.with_project(&self.project, |p| {
+            Ok(p.repos.values().map(|r| r.repository.clone()).collect())
+        })
+    }
+
+    async fn list_removed_repos(&self) -> Result<Vec<String>, Error> {
+        self.dogma.with_project(&self.project, |p| {
+            Ok(p.removed_repos.keys().cloned().collect())
+        })
+    }
+}
+
+/// A repository of a [`MockCentralDogma`], implementing [`ContentService`] and
+/// [`WatchService`].
+/// Created by [`MockCentralDogma::repo`].
+#[derive(Clone)]
+pub struct MockRepo {
+    dogma: MockCentralDogma,
+    project: String,
+    repo: String,
+}
+
+impl MockRepo {
+    fn with_repo<T>(&self, f: impl FnOnce(&mut RepoState) -> Result<T, Error>) -> Result<T, Error> {
+        self.dogma.with_repo(&self.project, &self.repo, f)
+    }
+
+    fn entry(&self, path: &str, content: &EntryContent, revision: i64) -> Entry {
+        Entry {
+            path: path.to_owned(),
+            content: content.clone(),
+            revision: Revision::from(revision),
+            url: format!(
+                "/api/v1/projects/{}/repos/{}/contents{}",
+                self.project, self.repo, path
+            ),
+            modified_at: None,
+        }
+    }
+
+    fn query_file(&self, state: &RepoState, revision: i64, query: &Query) -> Result<Entry, Error> {
+        let content = state
+            .snapshot(revision)
+            .get(&query.path)
+            .ok_or_else(|| Error::EntryNotFound(query.path.clone()))?;
+        match (&query.r#type, content) {
+            (QueryType::Identity, _)
+            | (QueryType::IdentityJson, EntryContent::Json(_))
+            | (QueryType::IdentityText, EntryContent::Text(_)) => {}
+            #[cfg(feature = "yaml")]
+            (QueryType::IdentityYaml, EntryContent::Yaml(_)) => {}
+            (QueryType::JsonPath(_), _) => {
+                return Err(Error::InvalidParams(
+                    "JSON path queries are not supported by MockCentralDogma",
+                ))
+            }
+            _ => {
+                return Err(server_error(
+                    400,
+                    "QueryExecutionException",
+                    &format!("{} does not match the query type", query.path),
+                ))
+            }
+        }
+
+        Ok(self.entry(&query.path, content, revision))
+    }
+
+    fn diff(from: Option<&EntryContent>, to: Option<&EntryContent>) -> Option<ChangeContent> {
+        match (from, to) {
+            (Some(from), Some(to)) if from == to => None,
+            (_, Some(to)) => Some(upsert(to)),
+            (Some(_), None) => Some(ChangeContent::Remove),
+            (None, None) => None,
+        }
+    }
+
+    /// Returns the changes from `before` to `after` of the files matched by `path_pattern`,
+    /// ordered by path.
+    fn diffs(before: &Snapshot, after: &Snapshot, path_pattern: &str) -> Vec<Change> {
+        let mut paths: Vec<&String> = before.keys().chain(after.keys()).collect();
+        paths.sort();
+        paths.dedup();
+
+        paths
+            .into_iter()
+            .filter(|path| matches_pattern(path_pattern, path))
+            .filter_map(|path| {
+                Self::diff(before.get(path), after.get(path)).map(|content| Change {
+                    path: path.clone(),
+                    content,
+                })
+            })
+            .collect()
+    }
+
+    /// Returns the revision of the latest commit after `after` which changed a file matched by
+    /// `path_pattern`, if any.
+    fn latest_matching_commit(&self, after: Option<i64>, path_pattern: &str) -> Option<i64> {
+        self.with_repo(|r| {
+            let after = after.unwrap_or(0);
+            Ok(r.commits
+                .iter()
+                .rev()
+                .take_while(|(c, _)| c.revision.as_i64().unwrap_or(0) > after)
+                .find(|(_, paths)| {
+                    after == 0 || paths.iter().any(|p| matches_pattern(path_pattern, p))
+                })
+                .and_then(|(c, _)| c.revision.as_i64()))
+        })
+        .ok()
+        .flatten()
+    }
+}
+
+#[async_trait]
+impl ContentService for MockRepo {
+    async fn list_files(
+        &self,
+        revision: Revision,
+        path_pattern: &str,
+    ) -> Result<Vec<ListEntry>, Error> {
+        self.with_repo(|r| {
+            let revision = r.normalize(revision)?;
+
+            Ok(r.snapshot(revision)
+                .iter()
+                .filter(|(path, _)| matches_pattern(path_pattern, path))
+                .map(|(path, content)| ListEntry {
+                    path: path.clone(),
+                    r#type: entry_type(content),
+                })
+                .collect())
+        })
+    }
+
+    async fn get_file(&self, revision: Revision, query: &Query) -> Result<Entry, Error> {
+        self.with_repo(|r| {
+            let revision = r.normalize(revision)?;
+
+            self.query_file(r, revision, query)
+        })
+    }
+
+    async fn normalize_revision(&self, revision: Revision) -> Result<Revision, Error> {
+        self.with_repo(|r| r.normalize(revision).map(Revision::from))
+    }
+
+    async fn merge_files(
+        &self,
+        revision: Revision,
+        query: &MergeQuery,
+    ) -> Result<MergedEntry, Error> {
+        if !query.json_paths.is_empty() {
+            return Err(Error::InvalidParams(
+                "JSON path queries are not supported by MockCentralDogma",
+            ));
+        }
+
+        self.with_repo(|r| {
+            let revision = r.normalize(revision)?;
+            let snapshot = r.snapshot(revision);
+
+            let mut content = serde_json::Value::Object(Default::default());
+            let mut paths = Vec::new();
+            for source in &query.sources {
+                match snapshot.get(&source.path) {
+                    Some(EntryContent::Json(json)) => {
+                        deep_merge(&mut content, json.clone(), ArrayMerge::Replace);
+                        paths.push(source.path.clone());
+                    }
+                    Some(_) => {
+                        return Err(server_error(
+                            400,
+                            "QueryExecutionException",
+                            &format!("{} is not a JSON file", source.path),
+                        ))
+                    }
+                    None if source.optional => {}
+                    None => return Err(Error::EntryNotFound(source.path.clone())),
+                }
+            }
+            if paths.is_empty() {
+                let missing: Vec<_> = query.sources.iter().map(|s| s.path.as_str()).collect();
+                return Err(Error::EntryNotFound(missing.join(",")));
+            }
+
+            Ok(MergedEntry {
+                revision: Revision::from(revision),
+                r#type: EntryType::Json,
+                content,
+                paths,
+            })
+        })
+    }
+
+    async fn get_files(&self, revision: Revision, path_pattern: &str) -> Result<Vec<Entry>, Error> {
+        self.with_repo(|r| {
+            let revision = r.normalize(revision)?;
+
+            Ok(r.snapshot(revision)
+                .iter()
+                .filter(|(path, _)| matches_pattern(path_pattern, path))
+                .map(|(path, content)| self.entry(path, content, revision))
+                .collect())
+        })
+    }
+
+    async fn get_files_raw(
+        &self,
+        revision: Revision,
+        path_pattern: &str,
+    ) -> Result<RawEntries, Error> {
+        let entries = self.get_files(revision, path_pattern).await?;
+        let body = serde_json::to_vec(&entries)?;
+
+        Ok(RawEntries::new(body.into()))
+    }
+
+    async fn get_file_raw(&self, revision: Revision, path: &str) -> Result<bytes::Bytes, Error> {
+        let query = Query::identity(path).ok_or(Error::InvalidParams("path cannot be empty"))?;
+        match self.get_file(revision, &query).await?.content {
+            EntryContent::Json(json) => Ok(serde_json::to_vec(&json)?.into()),
+            EntryContent::Text(text) => Ok(text.into()),
+            #[cfg(feature = "yaml")]
+            EntryContent::Yaml(yaml) => serde_yaml::to_string(&yaml)
+                .map(Into::into)
+                .map_err(|_| Error::InvalidParams("YAML content cannot be serialized")),
+            EntryContent::Directory => Err(Error::InvalidParams("path is a directory, not a file")),
+        }
+    }
+
+    async fn get_history(
+        &self,
+        from_rev: Revision,
+        to_rev: Revision,
+        path: &str,
+        max_commits: Option<u32>,
+    ) -> Result<Vec<Commit>, Error> {
+        self.with_repo(|r| {
+            let from = r.normalize(from_rev)?;
+            let to = r.normalize(to_rev)?;
+            let (low, high) = (from.min(to), from.max(to));
+            let mut commits: Vec<Commit> = r.commits[low as usize - 1..high as usize]
+                .iter()
+                .filter(|(c, paths)| {
+                    c.revision == Revision::INIT || paths.iter().any(|p| matches_pattern(path, p))
+                })
+                .map(|(c, _)| c.clone())
+                .collect();
+            if from > to {
+                commits.reverse();
+            }
+            if let Some(max) = max_commits {
+                commits.truncate(max as usize);
+            }
+
+            Ok(commits)
+        })
+    }
+
+    async fn get_diff(
+        &self,
+        from_rev: Revision,
+        to_rev: Revision,
+        query: &Query,
+    ) -> Result<Change, Error> {
+        self.with_repo(|r| {
+            let from = r.normalize(from_rev)?;
+            let to = r.normalize(to_rev)?;
+            let before = r.snapshot(from).get(&query.path);
+            let after = r.snapshot(to).get(&query.path);
+            let content = match (before, after) {
+                (None, None) => return Err(Error::EntryNotFound(query.path.clone())),
+                (Some(EntryContent::Json(_)), Some(EntryContent::Json(_))) if before == after => {
+                    ChangeContent::ApplyJsonPatch(serde_json::json!([]))
+                }
+                _ if before == after => ChangeContent::ApplyTextPatch(String::new()),
+                _ => Self::diff(before, after).unwrap_or(ChangeContent::Remove),
+            };
+
+            Ok(Change {
+                path: query.path.clone(),
+                content,
+            })
+        })
+    }
+
+    async fn get_diffs(
+        &self,
+        from_rev: Revision,
+        to_rev: Revision,
+        path_pattern: &str,
+    ) -> Result<Vec<Change>, Error> {
+        self.with_repo(|r| {
+            let before = r.snapshot(r.normalize(from_rev)?);
+            let after = r.snapshot(r.normalize(to_rev)?);
+
+            Ok(Self::diffs(before, after, path_pattern))
+        })
+    }
+
+    async fn preview_diffs(
+        &self,
+        base_revision: Revision,
+        changes: Vec<Change>,
+    ) -> Result<Vec<Change>, Error> {
+        if changes.is_empty() {
+            return Err(Error::InvalidParams("no changes to preview"));
+        }
+
+        self.with_repo(|r| {
+            let before = r.snapshot(r.normalize(base_revision)?);
+            let mut after = before.clone();
+            apply(&mut after, changes)?;
+
+            Ok(Self::diffs(before, &after, "/**"))
+        })
+    }
+
+    async fn push(
+        &self,
+        base_revision: Revision,
+        cm: CommitMessage,
+        changes: Vec<Change>,
+    ) -> Result<PushResult, Error> {
+        if cm.summary.is_empty() {
+            return Err(Error::InvalidParams(
+                "summary of commit_message cannot be empty",
+            ));
+        }
+        if changes.is_empty() {
+            return Err(Error::InvalidParams("no changes to commit"));
+        }
+
+        let author = self.dogma.author.clone();
+        let result = self.with_repo(|r| {
+            let base = r.normalize(base_revision)?;
+            let head = r.head();
+            let mut files = r.snapshot(head).clone();
+            let changed = apply(&mut files, changes)?;
+
+            let (base_files, head_files) = (r.snapshot(base), r.snapshot(head));
+            if let Some(path) = changed
+                .iter()
+                .find(|p| base_files.get(*p) != head_files.get(*p))
+            {
+                return Err(Error::Conflict {
+                    base_revision: Some(base_revision),
+                    head_revision: Some(Revision::from(head)),
+                    message: format!("{} has been modified since revision {}", path, base),
+                });
+            }
+            if &files == head_files {
+                return Err(Error::RedundantChange(
+                    "changes did not change anything".to_owned(),
+                ));
+            }
+
+            let revision = Revision::from(head + 1);
+            let commit = Commit {
+                revision,
+                author,
+                commit_message: cm,
+                pushed_at: None,
+            };
+            r.commits.push((commit, changed));
+            r.snapshots.push(files);
+            r.repository.head_revision = revision;
+
+            Ok(PushResult {
+                revision,
+                pushed_at: None,
+            })
+        })?;
+        self.dogma.notify();
+
+        Ok(result)
+    }
+}
+
+impl WatchService for MockRepo {
+    fn watch_file_stream(
+        &self,
+        query: &Query,
+    ) -> Result<Pin<Box<dyn Stream<Item = WatchFileResult> + Send>>, Error> {
+        let rx = self.dogma.changes.subscribe();
+        let init = (self.clone(), query.clone(), None::<Entry>, rx);
+
+        let stream = futures::stream::unfold(init, |(repo, query, mut last, mut rx)| async move {
+            loop {
+                rx.borrow_and_update();
+                match repo.get_file(Revision::HEAD, &query).await {
+                    Ok(entry) if !matches!(&last, Some(l) if l.content == entry.content) => {
+                        let result = WatchFileResult {
+                            revision: entry.revision,
+                            entry: entry.clone(),
+                        };
+                        return Some((result, (repo, query, Some(entry), rx)));
+                    }
+                    Ok(_) => {}
+                    // Output the file again once it is recreated.
+                    Err(_) => last = None,
+                }
+                if rx.changed().await.is_err() {
+                    return None;
+                }
+            }
+        });
+
+        Ok(stream.boxed())
+    }
+
+    fn watch_repo_stream(
+        &self,
+        path_pattern: &str,
+    ) -> Result<Pin<Box<dyn Stream<Item = WatchRepoResult> + Send>>, Error> {
+        let rx = self.dogma.changes.subscribe();
+        let init = (self.clone(), path_pattern.to_owned(), None::<i64>, rx);
+
+        let stream = futures::stream::unfold(init, |(repo, pattern, last, mut rx)| async move {
+            loop {
+                rx.borrow_and_update();
+                if let Some(revision) = repo.latest_matching_commit(last, &pattern) {
+                    let result = WatchRepoResult {
+                        revision: Revision::from(revision),
+                    };
+                    return Some((result, (repo, pattern, Some(revision), rx)));
+                }
+                if rx.changed().await.is_err() {
+                    return None;
+                }
+            }
+        });
+
+        Ok(stream.boxed())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::model::MergeSource;
+    use serde_json::json;
+    use std::time::Duration;
+
+    async fn repo_with_files() -> MockRepo {
+        let dogma = MockCentralDogma::new();
+        dogma.create_project("foo").await.unwrap();
+        dogma.project("foo").create_repo("bar").await.unwrap();
+
+        let repo = dogma.repo("foo", "bar");
+        let changes = vec![
+            Change::from(("/a.json", json!({"a": 1}))),
+            Change::from(("/b/c.json", json!({"c": 1}))),
+        ];
+        repo.push(Revision::HEAD, CommitMessage::only_summary("Add"), changes)
+            .await
+            .unwrap();
+
+        repo
+    }
+
+    #[tokio::test]
+    async fn test_projects_and_repos() {
+        let dogma = MockCentralDogma::new();
+        dogma.create_project("foo").await.unwrap();
+        assert!(matches!(
+            dogma.create_project("foo").await,
+            Err(Error::ProjectExists(_))
+        ));
+
+        let project = dogma.project("foo");
+        let repo = project.create_repo("bar").await.unwrap();
+        assert_eq!(repo.head_revision, Revision::INIT);
+        assert_eq!(
+            project.create_repo("bar").await.unwrap_err().code(),
+            crate::ErrorCode::RepositoryExists
+        );
+
+        project.remove_repo("bar").await.unwrap();
+        assert_eq!(project.list_removed_repos().await.unwrap(), vec!["bar"]);
+        project.unremove_repo("bar").await.unwrap();
+        assert_eq!(project.list_repos().await.unwrap().len(), 1);
+
+        dogma.remove_project("foo").await.unwrap();
+        assert!(dogma.list_projects().await.unwrap().is_empty());
+        assert_eq!(
+            dogma.project("foo").list_repos().await.unwrap_err().code(),
+            crate::ErrorCode::ProjectNotFound
+        );
+        dogma.purge_project("foo").await.unwrap();
+        assert!(dogma.list_removed_projects().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_push_and_get() {
+        let repo = repo_with_files().await;
+        let query = Query::identity("/a.json").unwrap();
+
+        let change = Change::from(("/a.json", json!({"a": 2})));
+        let result = repo
+            .push(
+                Revision::HEAD,
+                CommitMessage::only_summary("Update"),
+                vec![change],
+            )
+            .await
+            .unwrap();
+        assert_eq!(result.revision, Revision::from(3));
+
+        let entry = repo.get_file(Revision::HEAD, &query).await.unwrap();
+        assert_eq!(entry.content, EntryContent::Json(json!({"a": 2})));
+        assert_eq!(entry.revision, Revision::from(3));
+        let entry = repo.get_file(Revision::from(-2), &query).await.unwrap();
+        assert_eq!(entry.content, EntryContent::Json(json!({"a": 1})));
+        let revision = repo.normalize_revision(Revision::from(-2)).await.unwrap();
+        assert_eq!(revision, Revision::from(2));
+
+        let err = repo.get_file(Revision::from(4), &query).await.unwrap_err();
+        assert_eq!(err.code(), crate::ErrorCode::RevisionNotFound);
+        let err = repo.get_file(Revision::INIT, &query).await.unwrap_err();
+        assert!(matches!(err, Error::EntryNotFound(_)));
+
+        let files = repo.get_files(Revision::HEAD, "/b/*.json").await.unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].path, "/b/c.json");
+        let raw = repo.get_files_raw(Revision::HEAD, "/**").await.unwrap();
+        assert_eq!(raw.entries().unwrap().len(), 2);
+
+        let merge = MergeQuery::of_json(vec![
+            MergeSource::required("/a.json").unwrap(),
+            MergeSource::optional("/missing.json").unwrap(),
+            MergeSource::required("/b/c.json").unwrap(),
+        ])
+        .unwrap();
+        let merged = repo.merge_files(Revision::HEAD, &merge).await.unwrap();
+        assert_eq!(merged.content, json!({"a": 2, "c": 1}));
+        assert_eq!(merged.paths, ["/a.json", "/b/c.json"]);
+        let merge =
+            MergeQuery::of_json(vec![MergeSource::required("/missing.json").unwrap()]).unwrap();
+        let err = repo.merge_files(Revision::HEAD, &merge).await.unwrap_err();
+        assert!(matches!(err, Error::EntryNotFound(_)));
+
+        let diffs = repo
+            .preview_diffs(
+                Revision::HEAD,
+                vec![
+                    Change::from(("/a.json", json!({"a": 2}))),
+                    Change::from(("/d.json", json!({"d": 1}))),
+                ],
+            )
+            .await
+            .unwrap();
+        assert_eq!(diffs, vec![Change::from(("/d.json", json!({"d": 1})))]);
+        assert_eq!(
+            repo.get_files(Revision::HEAD, "/**").await.unwrap().len(),
+            2
+        );
+    }
+
+    #[cfg(feature = "yaml")]
+    #[tokio::test]
+    async fn test_yaml_query() {
+        let repo = repo_with_files().await;
+        let change = Change::upsert_yaml("/c.yaml", &json!({"c": 1})).unwrap();
+        repo.push(
+            Revision::HEAD,
+            CommitMessage::only_summary("Add"),
+            vec![change],
+        )
+        .await
+        .unwrap();
+
+        let query = Query::of_yaml("/c.yaml").unwrap();
+        let entry = repo.get_file(Revision::HEAD, &query).await.unwrap();
+        assert!(matches!(entry.content, EntryContent::Yaml(_)));
+        let err = repo
+            .get_file(Revision::HEAD, &Query::of_yaml("/a.json").unwrap())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::ErrorResponse { status: 400, .. }));
+    }
+
+    #[tokio::test]
+    async fn test_push_conflict_and_redundant() {
+        let repo = repo_with_files().await;
+
+        let change = Change::from(("/a.json", json!({"a": 2})));
+        repo.push(
+            Revision::from(2),
+            CommitMessage::only_summary("Update"),
+            vec![change.clone()],
+        )
+        .await
+        .unwrap();
+
+        let err = repo
+            .push(
+                Revision::from(2),
+                CommitMessage::only_summary("Update again"),
+                vec![Change::from(("/a.json", json!({"a": 3})))],
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::Conflict { head_revision: Some(h), .. } if h == Revision::from(3)
+        ));
+
+        // Changes to other files are merged.
+        repo.push(
+            Revision::from(2),
+            CommitMessage::only_summary("Add d.json"),
+            vec![Change::from(("/d.json", json!({})))],
+        )
+        .await
+        .unwrap();
+
+        let err = repo
+            .push(
+                Revision::HEAD,
+                CommitMessage::only_summary("Update"),
+                vec![change],
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::RedundantChange(_)));
+    }
+
+    #[tokio::test]
+    async fn test_history_and_diffs() {
+        let repo = repo_with_files().await;
+        repo.push(
+            Revision::HEAD,
+            CommitMessage::only_summary("Remove a.json"),
+            vec![Change {
+                path: "/a.json".to_owned(),
+                content: ChangeContent::Remove,
+            }],
+        )
+        .await
+        .unwrap();
+
+        let history = repo
+            .get_history(Revision::HEAD, Revision::INIT, "/a.json", None)
+            .await
+            .unwrap();
+        let revisions: Vec<_> = history.iter().map(|c| c.revision).collect();
+        assert_eq!(
+            revisions,
+            vec![Revision::from(3), Revision::from(2), Revision::INIT]
+        );
+
+        let diffs = repo
+            .get_diffs(Revision::INIT, Revision::HEAD, "/**")
+            .await
+            .unwrap();
+        assert_eq!(diffs, vec![Change::from(("/b/c.json", json!({"c": 1})))]);
+
+        let diff = repo
+            .get_diff(
+                Revision::from(2),
+                Revision::HEAD,
+                &Query::identity("/a.json").unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(diff.content, ChangeContent::Remove);
+    }
+
+    #[tokio::test]
+    async fn test_watch_file() {
+        let repo = repo_with_files().await;
+        let stream = repo
+            .watch_file_stream(&Query::identity("/a.json").unwrap())
+            .unwrap()
+            .take_until(tokio::time::sleep(Duration::from_secs(3)));
+        tokio::pin!(stream);
+
+        let first = stream.next().await.unwrap();
+        assert_eq!(first.revision, Revision::from(2));
+
+        // Changes to other files are not output.
+        repo.push(
+            Revision::HEAD,
+            CommitMessage::only_summary("Add d.json"),
+            vec![Change::from(("/d.json", json!({})))],
+        )
+        .await
+        .unwrap();
+        repo.push(
+            Revision::HEAD,
+            CommitMessage::only_summary("Update a.json"),
+            vec![Change::from(("/a.json", json!({"a": 2})))],
+        )
+        .await
+        .unwrap();
+
+        let second = stream.next().await.unwrap();
+        assert_eq!(second.revision, Revision::from(4));
+        assert_eq!(second.entry.content, EntryContent::Json(json!({"a": 2})));
+    }
+
+    #[tokio::test]
+    async fn test_watch_repo() {
+        let repo = repo_with_files().await;
+        let stream = repo
+            .watch_repo_stream("/b/**")
+            .unwrap()
+            .take_until(tokio::time::sleep(Duration::from_secs(3)));
+        tokio::pin!(stream);
+
+        assert_eq!(stream.next().await.unwrap().revision, Revision::from(2));
+
+        repo.push(
+            Revision::HEAD,
+            CommitMessage::only_summary("Update a.json"),
+            vec![Change::from(("/a.json", json!({"a": 2})))],
+        )
+        .await
+        .unwrap();
+        repo.push(
+            Revision::HEAD,
+            CommitMessage::only_summary("Update c.json"),
+            vec![Change::from(("/b/c.json", json!({"c": 2})))],
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(stream.next().await.unwrap().revision, Revision::from(4));
+    }
+}
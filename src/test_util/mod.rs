@@ -0,0 +1,34 @@
+//! Utilities for testing code which uses this crate, enabled by the `test-util` feature.
+//!
+//! The matchers are also built for the tests of this crate, which mock the server with them.
+#[cfg(feature = "test-util")]
+mod clock;
+#[cfg(feature = "testcontainers")]
+mod container;
+#[cfg(feature = "test-util")]
+mod fixture;
+#[cfg(any(test, feature = "wiremock"))]
+pub mod matchers;
+#[cfg(feature = "test-util")]
+mod mock;
+#[cfg(feature = "test-util")]
+mod record;
+#[cfg(feature = "test-util")]
+mod snapshot;
+#[cfg(feature = "test-util")]
+mod watch;
+
+#[cfg(feature = "test-util")]
+pub use clock::MockClock;
+#[cfg(feature = "testcontainers")]
+pub use container::{CentralDogmaContainer, RunningCentralDogma};
+#[cfg(feature = "test-util")]
+pub use fixture::{CommitFixture, EntryFixture, ProjectFixture, RepositoryFixture};
+#[cfg(feature = "test-util")]
+pub use mock::{MockCentralDogma, MockProject, MockRepo};
+#[cfg(feature = "test-util")]
+pub use record::{Cassette, Interaction, RecordedRequest, RecordedResponse};
+#[cfg(feature = "test-util")]
+pub use snapshot::{assert_golden, compare_golden, to_golden, GoldenError, UPDATE_GOLDEN_ENV};
+#[cfg(feature = "test-util")]
+pub use watch::{FakeWatch, FakeWatchSource};
@@ -0,0 +1,268 @@
+//! Recording and replaying of the HTTP interactions of a [`Client`](crate::Client).
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::Error;
+
+/// A request as stored in a [`Cassette`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordedRequest {
+    /// HTTP method, e.g. `GET`
+    pub method: String,
+    /// Path and query of the request, e.g. `/api/v1/projects?status=removed`
+    pub path: String,
+    /// Body of the request, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+}
+
+/// A response as stored in a [`Cassette`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordedResponse {
+    /// HTTP status code
+    pub status: u16,
+    /// Response headers, excluding the ones describing the transfer such as `content-length`
+    pub headers: Vec<(String, String)>,
+    /// Body of the response
+    pub body: String,
+}
+
+/// A request and the response the server returned for it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Interaction {
+    pub request: RecordedRequest,
+    pub response: RecordedResponse,
+}
+
+enum Mode {
+    Record(PathBuf),
+    Replay,
+}
+
+/// A file of recorded HTTP interactions, installed on a client with
+/// [`Client::with_cassette`](crate::Client::with_cassette).
+///
+/// In record mode, the client talks to the server and every request with its response is
+/// written to the file. In replay mode, the client never touches the network: each request is
+/// answered with the first unused recorded response for the same method, path, query and body,
+/// so a test recorded once against a live server can run hermetically afterwards.
+///
+/// ```no_run
+/// use centraldogma::{test_util::Cassette, Client, ProjectService};
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let cassette = if std::env::var("RECORD").is_ok() {
+///     Cassette::record("tests/cassettes/projects.json")
+/// } else {
+///     Cassette::replay("tests/cassettes/projects.json")?
+/// };
+/// let client = Client::new("http://localhost:36462", None)
+///     .await?
+///     .with_cassette(cassette);
+/// let projects = client.list_projects().await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct Cassette {
+    mode: Mode,
+    interactions: Mutex<Vec<(Interaction, bool)>>,
+}
+
+impl Cassette {
+    /// Returns a cassette recording to the file at `path`, replacing its content.
+    pub fn record<P: AsRef<Path>>(path: P) -> Self {
+        Cassette {
+            mode: Mode::Record(path.as_ref().to_owned()),
+            interactions: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Returns a cassette replaying the interactions recorded in the file at `path`.
+    pub fn replay<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let json = fs::read(path)?;
+        let interactions: Vec<Interaction> = serde_json::from_slice(&json)?;
+
+        Ok(Self::from_interactions(interactions))
+    }
+
+    /// Returns a cassette replaying the specified interactions.
+    pub fn from_interactions(interactions: Vec<Interaction>) -> Self {
+        Cassette {
+            mode: Mode::Replay,
+            interactions: Mutex::new(interactions.into_iter().map(|i| (i, false)).collect()),
+        }
+    }
+
+    /// Returns the interactions recorded or loaded so far.
+    pub fn interactions(&self) -> Vec<Interaction> {
+        let interactions = self.interactions.lock().unwrap();
+
+        interactions.iter().map(|(i, _)| i.clone()).collect()
+    }
+
+    pub(crate) async fn execute(
+        &self,
+        http_client: &reqwest::Client,
+        req: reqwest::Request,
+    ) -> Result<reqwest::Response, Error> {
+        let request = RecordedRequest {
+            method: req.method().to_string(),
+            path: match req.url().query() {
+                Some(q) => format!("{}?{}", req.url().path(), q),
+                None => req.url().path().to_owned(),
+            },
+            body: req
+                .body()
+                .and_then(|b| b.as_bytes())
+                .map(|b| String::from_utf8_lossy(b).into_owned()),
+        };
+
+        match &self.mode {
+            Mode::Replay => {
+                let mut interactions = self.interactions.lock().unwrap();
+                let (interaction, used) = interactions
+                    .iter_mut()
+                    .find(|(i, used)| !*used && i.request == request)
+                    .ok_or(Error::InvalidParams(
+                        "no recorded response matches the request",
+                    ))?;
+                *used = true;
+
+                Ok(to_response(&interaction.response))
+            }
+            Mode::Record(path) => {
+                let resp = http_client.execute(req).await?;
+                let status = resp.status().as_u16();
+                let headers = resp
+                    .headers()
+                    .iter()
+                    .filter(|(name, _)| {
+                        !matches!(
+                            name.as_str(),
+                            "content-length" | "transfer-encoding" | "connection" | "date"
+                        )
+                    })
+                    .filter_map(|(name, value)| {
+                        Some((name.to_string(), value.to_str().ok()?.to_owned()))
+                    })
+                    .collect();
+                let body = resp.text().await?;
+                let response = RecordedResponse {
+                    status,
+                    headers,
+                    body,
+                };
+
+                let mut interactions = self.interactions.lock().unwrap();
+                interactions.push((
+                    Interaction {
+                        request,
+                        response: response.clone(),
+                    },
+                    true,
+                ));
+                let all: Vec<&Interaction> = interactions.iter().map(|(i, _)| i).collect();
+                if let Err(e) = serde_json::to_vec_pretty(&all)
+                    .map_err(io::Error::from)
+                    .and_then(|json| fs::write(path, json))
+                {
+                    log::warn!("Failed to write cassette {}: {}", path.display(), e);
+                }
+
+                Ok(to_response(&response))
+            }
+        }
+    }
+}
+
+fn to_response(recorded: &RecordedResponse) -> reqwest::Response {
+    let mut builder = http::Response::builder().status(recorded.status);
+    for (name, value) in &recorded.headers {
+        builder = builder.header(name.as_str(), value.as_str());
+    }
+    let resp = builder
+        .body(recorded.body.clone())
+        .unwrap_or_else(|_| http::Response::new(recorded.body.clone()));
+
+    reqwest::Response::from(resp)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Client, ErrorCode, ProjectService};
+    use wiremock::{
+        matchers::{method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    #[tokio::test]
+    async fn test_record_and_replay() {
+        let server = MockServer::start().await;
+        let resp = ResponseTemplate::new(200).set_body_raw(
+            r#"[{"name":"foo", "creator":{"name":"minux", "email":"minux@m.x"}}]"#,
+            "application/json",
+        );
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects"))
+            .respond_with(resp)
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let file = std::env::temp_dir().join(format!("cassette-{}.json", std::process::id()));
+        let client = Client::new(&server.uri(), None)
+            .await
+            .unwrap()
+            .with_cassette(Cassette::record(&file));
+        let recorded = client.list_projects().await.unwrap();
+        drop(server);
+
+        let client = Client::new("http://localhost:1", None)
+            .await
+            .unwrap()
+            .with_cassette(Cassette::replay(&file).unwrap());
+        let replayed = client.list_projects().await.unwrap();
+        fs::remove_file(&file).unwrap();
+
+        assert_eq!(replayed, recorded);
+        assert_eq!(replayed[0].name, "foo");
+
+        // Each recorded response is served once.
+        let err = client.list_projects().await.unwrap_err();
+        assert_eq!(err.code(), ErrorCode::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn test_replay_error_response() {
+        let cassette = Cassette::from_interactions(vec![Interaction {
+            request: RecordedRequest {
+                method: "DELETE".to_owned(),
+                path: "/api/v1/projects/foo".to_owned(),
+                body: None,
+            },
+            response: RecordedResponse {
+                status: 404,
+                headers: vec![("content-type".to_owned(), "application/json".to_owned())],
+                body: r#"{
+                    "exception":"com.linecorp.centraldogma.common.ProjectNotFoundException",
+                    "message":"foo"
+                }"#
+                .to_owned(),
+            },
+        }]);
+        let client = Client::new("http://localhost:1", None)
+            .await
+            .unwrap()
+            .with_cassette(cassette);
+
+        let err = client.remove_project("foo").await.unwrap_err();
+        assert_eq!(err.code(), ErrorCode::ProjectNotFound);
+    }
+}
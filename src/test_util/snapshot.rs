@@ -0,0 +1,172 @@
+//! Golden files of the JSON representation of the models.
+use std::{fmt::Debug, fs, io, path::Path};
+
+use serde::{de::DeserializeOwned, Serialize};
+use thiserror::Error;
+
+/// Environment variable which makes [`assert_golden`] rewrite the golden files.
+pub const UPDATE_GOLDEN_ENV: &str = "UPDATE_GOLDEN";
+
+/// How a value differs from its golden file.
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum GoldenError {
+    /// The golden file could not be read
+    #[error("Failed to read golden file")]
+    Io(#[from] io::Error),
+
+    /// The value serializes differently than the golden file
+    #[error("Serialization changed, expected:\n{expected}\nactual:\n{actual}")]
+    Changed {
+        /// The content of the golden file
+        expected: String,
+        /// The serialization of the value
+        actual: String,
+    },
+
+    /// The golden file no longer deserializes into the model
+    #[error("Golden file no longer deserializes")]
+    Incompatible(#[source] serde_json::Error),
+
+    /// The golden file deserializes into another value
+    #[error("Golden file deserializes into {deserialized}, not the value")]
+    Lossy {
+        /// The value deserialized from the golden file, in `Debug` format
+        deserialized: String,
+    },
+}
+
+/// Returns the golden representation of `value`, pretty printed JSON with sorted keys.
+pub fn to_golden<T: Serialize>(value: &T) -> String {
+    let json = serde_json::to_value(value).expect("models are serializable");
+    let mut golden = serde_json::to_string_pretty(&json).expect("JSON values are serializable");
+    golden.push('\n');
+    golden
+}
+
+/// Checks that `value` serializes as the golden file at `path`, and that the golden file
+/// deserializes back into `value`. Works with any model, such as [`Entry`],
+/// [`Commit`], [`Change`] and the watch results.
+///
+/// [`Entry`]: crate::model::Entry
+/// [`Commit`]: crate::model::Commit
+/// [`Change`]: crate::model::Change
+pub fn compare_golden<T>(path: impl AsRef<Path>, value: &T) -> Result<(), GoldenError>
+where
+    T: Serialize + DeserializeOwned + PartialEq + Debug,
+{
+    let expected = fs::read_to_string(path)?;
+
+    let deserialized: T = serde_json::from_str(&expected).map_err(GoldenError::Incompatible)?;
+    if &deserialized != value {
+        return Err(GoldenError::Lossy {
+            deserialized: format!("{:?}", deserialized),
+        });
+    }
+
+    let actual = to_golden(value);
+    if actual != expected {
+        return Err(GoldenError::Changed { expected, actual });
+    }
+
+    Ok(())
+}
+
+/// Panics unless [`compare_golden`] succeeds.
+///
+/// Writes the golden file instead if it doesn't exist,
+/// or if the `UPDATE_GOLDEN` environment variable is set.
+///
+/// ```no_run
+/// use centraldogma::test_util::{assert_golden, EntryFixture};
+/// use serde_json::json;
+///
+/// let entry = EntryFixture::json("/a.json", json!({"a": 1})).build();
+/// assert_golden("tests/golden/entry.json", &entry);
+/// ```
+#[track_caller]
+pub fn assert_golden<T>(path: impl AsRef<Path>, value: &T)
+where
+    T: Serialize + DeserializeOwned + PartialEq + Debug,
+{
+    let path = path.as_ref();
+    if std::env::var_os(UPDATE_GOLDEN_ENV).is_some() || !path.exists() {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).expect("failed to create golden file directory");
+        }
+        fs::write(path, to_golden(value)).expect("failed to write golden file");
+        return;
+    }
+
+    if let Err(e) = compare_golden(path, value) {
+        panic!(
+            "{} does not match: {}\nSet {} to update it.",
+            path.display(),
+            e,
+            UPDATE_GOLDEN_ENV
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        model::{Change, Revision, WatchRepoResult},
+        test_util::{CommitFixture, EntryFixture},
+    };
+    use serde_json::json;
+
+    fn golden_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!(
+            "centraldogma-golden-{}-{}",
+            fastrand::u64(..),
+            name
+        ))
+    }
+
+    #[test]
+    fn test_to_golden() {
+        let change = Change::from(("/a.json", json!({"b": 1, "a": 2})));
+
+        assert_eq!(
+            to_golden(&change),
+            "{\n  \"content\": {\n    \"a\": 2,\n    \"b\": 1\n  },\n  \
+             \"path\": \"/a.json\",\n  \"type\": \"UPSERT_JSON\"\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_golden() {
+        let path = golden_path("entry.json");
+        let entry = EntryFixture::json("/a.json", json!({"a": 1})).build();
+
+        // Written on the first run
+        assert_golden(&path, &entry);
+        compare_golden(&path, &entry).unwrap();
+        compare_golden(&path, &CommitFixture::new(2).build()).unwrap_err();
+
+        let other = EntryFixture::json("/a.json", json!({"a": 2})).build();
+        assert!(matches!(
+            compare_golden(&path, &other),
+            Err(GoldenError::Lossy { .. })
+        ));
+
+        fs::write(&path, "{\"revision\": 1}").unwrap();
+        assert!(matches!(
+            compare_golden(
+                &path,
+                &WatchRepoResult {
+                    revision: Revision::from(1)
+                }
+            ),
+            Err(GoldenError::Changed { .. })
+        ));
+
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(
+            compare_golden(&path, &entry),
+            Err(GoldenError::Io(_))
+        ));
+    }
+}
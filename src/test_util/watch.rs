@@ -0,0 +1,277 @@
+//! A [`WatchService`] whose streams output what the test sends them.
+use std::{
+    collections::HashMap,
+    pin::Pin,
+    sync::{Arc, Mutex},
+};
+
+use futures::{Stream, StreamExt};
+use tokio::sync::mpsc;
+
+use crate::{
+    client::RetryClassifier,
+    model::{Query, WatchFileResult, WatchRepoResult},
+    Error, WatchService,
+};
+
+enum Event<D> {
+    Value(D),
+    NotModified,
+    Error { stop: bool },
+}
+
+struct Channel<D> {
+    subscribers: Vec<mpsc::UnboundedSender<Event<D>>>,
+    /// Events sent while nobody watches, delivered to the first stream.
+    pending: Vec<Event<D>>,
+    closed: bool,
+}
+
+impl<D> Default for Channel<D> {
+    fn default() -> Self {
+        Channel {
+            subscribers: Vec::new(),
+            pending: Vec::new(),
+            closed: false,
+        }
+    }
+}
+
+/// Controls the watch streams of a file or a path pattern of a [`FakeWatchSource`].
+///
+/// Every value sent is output by all the streams watching it. Values sent before a stream is
+/// created are output by the first stream created.
+pub struct FakeWatch<D> {
+    channel: Arc<Mutex<Channel<D>>>,
+    classifier: Option<Arc<RetryClassifier>>,
+}
+
+impl<D> Clone for FakeWatch<D> {
+    fn clone(&self) -> Self {
+        FakeWatch {
+            channel: self.channel.clone(),
+            classifier: self.classifier.clone(),
+        }
+    }
+}
+
+impl<D: Clone + Send + 'static> FakeWatch<D> {
+    /// Outputs `value` from the streams.
+    pub fn push(&self, value: D) {
+        self.send(|| Event::Value(value.clone()));
+    }
+
+    /// Answers a long poll with `304 Not Modified`, which the streams don't output.
+    pub fn not_modified(&self) {
+        self.send(|| Event::NotModified);
+    }
+
+    /// Fails a long poll with `err`. As with a [`Client`](crate::Client), the streams end
+    /// if the [retry classifier](FakeWatchSource::with_retry_classifier) rejects `err`,
+    /// and keep watching otherwise.
+    pub fn fail(&self, err: Error) {
+        let stop = self.classifier.as_ref().map(|f| !f(&err)).unwrap_or(false);
+        log::debug!("Fake watch error: {}", err);
+        self.send(|| Event::Error { stop });
+    }
+
+    /// Ends the streams, including the ones created later.
+    pub fn close(&self) {
+        let mut channel = self.channel.lock().unwrap();
+        channel.closed = true;
+        channel.subscribers.clear();
+    }
+
+    /// Returns a stream which outputs the values sent to this.
+    pub fn stream(&self) -> Pin<Box<dyn Stream<Item = D> + Send>> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        {
+            let mut channel = self.channel.lock().unwrap();
+            for event in channel.pending.drain(..) {
+                let _ = tx.send(event);
+            }
+            if !channel.closed {
+                channel.subscribers.push(tx);
+            }
+        }
+
+        futures::stream::unfold(rx, |mut rx| async move {
+            loop {
+                match rx.recv().await? {
+                    Event::Value(value) => return Some((value, rx)),
+                    Event::NotModified => {}
+                    Event::Error { stop: true } => return None,
+                    Event::Error { stop: false } => {}
+                }
+            }
+        })
+        .boxed()
+    }
+
+    fn send(&self, event: impl Fn() -> Event<D>) {
+        let mut channel = self.channel.lock().unwrap();
+        if channel.closed {
+            return;
+        }
+        channel.subscribers.retain(|tx| tx.send(event()).is_ok());
+        if channel.subscribers.is_empty() {
+            channel.pending.push(event());
+        }
+    }
+}
+
+#[derive(Default)]
+struct State {
+    files: HashMap<String, Arc<Mutex<Channel<WatchFileResult>>>>,
+    repos: HashMap<String, Arc<Mutex<Channel<WatchRepoResult>>>>,
+}
+
+/// A [`WatchService`] whose streams output only what is sent through [`FakeWatch`]es,
+/// for testing code which reacts to changes without a server, timers or HTTP.
+///
+/// ```
+/// use centraldogma::{model::Query, test_util::{EntryFixture, FakeWatchSource}, WatchService};
+/// use futures::StreamExt;
+/// use serde_json::json;
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let source = FakeWatchSource::new();
+/// let watch = source.file("/a.json");
+///
+/// let mut stream = source
+///     .watch_file_stream(&Query::identity("/a.json").unwrap())
+///     .unwrap();
+/// watch.not_modified();
+/// watch.push(EntryFixture::json("/a.json", json!({"a": 1})).revision(2).watch_result());
+///
+/// assert_eq!(stream.next().await.unwrap().revision.as_i64(), Some(2));
+/// # }
+/// ```
+#[derive(Clone, Default)]
+pub struct FakeWatchSource {
+    state: Arc<Mutex<State>>,
+    classifier: Option<Arc<RetryClassifier>>,
+}
+
+impl FakeWatchSource {
+    /// Returns a source without any watch.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Decides which [failures](FakeWatch::fail) end the streams, as
+    /// [`Client::with_retry_classifier`](crate::Client::with_retry_classifier) does.
+    /// Without it the streams keep watching on every failure.
+    pub fn with_retry_classifier<F>(mut self, classifier: F) -> Self
+    where
+        F: Fn(&Error) -> bool + Send + Sync + 'static,
+    {
+        self.classifier = Some(Arc::new(classifier));
+        self
+    }
+
+    /// Returns the [`FakeWatch`] of the streams watching the file at `path`, whatever
+    /// their query type.
+    pub fn file(&self, path: &str) -> FakeWatch<WatchFileResult> {
+        let path = Query::normalize_path(path);
+        let mut state = self.state.lock().unwrap();
+        FakeWatch {
+            channel: state.files.entry(path).or_default().clone(),
+            classifier: self.classifier.clone(),
+        }
+    }
+
+    /// Returns the [`FakeWatch`] of the streams watching the repository with `path_pattern`.
+    pub fn repo(&self, path_pattern: &str) -> FakeWatch<WatchRepoResult> {
+        let mut state = self.state.lock().unwrap();
+        FakeWatch {
+            channel: state
+                .repos
+                .entry(path_pattern.to_owned())
+                .or_default()
+                .clone(),
+            classifier: self.classifier.clone(),
+        }
+    }
+}
+
+impl WatchService for FakeWatchSource {
+    fn watch_file_stream(
+        &self,
+        query: &Query,
+    ) -> Result<Pin<Box<dyn Stream<Item = WatchFileResult> + Send>>, Error> {
+        Ok(self.file(&query.path).stream())
+    }
+
+    fn watch_repo_stream(
+        &self,
+        path_pattern: &str,
+    ) -> Result<Pin<Box<dyn Stream<Item = WatchRepoResult> + Send>>, Error> {
+        Ok(self.repo(path_pattern).stream())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{model::Revision, test_util::EntryFixture};
+    use serde_json::json;
+
+    fn result(revision: i64) -> WatchFileResult {
+        EntryFixture::json("/a.json", json!({ "a": revision }))
+            .revision(revision)
+            .watch_result()
+    }
+
+    #[tokio::test]
+    async fn test_fake_watch_file() {
+        let source = FakeWatchSource::new();
+        let watch = source.file("a.json");
+
+        // Sent before watching
+        watch.push(result(2));
+        let mut first = source
+            .watch_file_stream(&Query::identity("/a.json").unwrap())
+            .unwrap();
+        let mut second = source
+            .watch_file_stream(&Query::of_json_path("/a.json", vec!["$.a".to_owned()]).unwrap())
+            .unwrap();
+
+        watch.not_modified();
+        watch.fail(Error::InvalidParams("fail"));
+        watch.push(result(3));
+        watch.close();
+
+        assert_eq!(first.next().await.unwrap().revision, Revision::from(2));
+        assert_eq!(first.next().await.unwrap().revision, Revision::from(3));
+        assert!(first.next().await.is_none());
+        assert_eq!(second.next().await.unwrap().revision, Revision::from(3));
+        assert!(second.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_fake_watch_stops_on_non_retryable_error() {
+        let source =
+            FakeWatchSource::new().with_retry_classifier(|e| !matches!(e, Error::InvalidParams(_)));
+        let watch = source.repo("/**");
+        let mut stream = source.watch_repo_stream("/**").unwrap();
+
+        watch.fail(Error::ErrorResponse {
+            status: 500,
+            message: "retryable".to_owned(),
+            exception: None,
+            body: String::new(),
+        });
+        watch.push(WatchRepoResult {
+            revision: Revision::from(2),
+        });
+        watch.fail(Error::InvalidParams("not retryable"));
+        watch.push(WatchRepoResult {
+            revision: Revision::from(3),
+        });
+
+        assert_eq!(stream.next().await.unwrap().revision, Revision::from(2));
+        assert!(stream.next().await.is_none());
+    }
+}
@@ -0,0 +1,133 @@
+//! A tower [`Layer`] giving the requests of a service the current value of a
+//! [`CentralDogmaConfig`], enabled by the `tower` feature.
+//!
+//! The value is inserted as an `Arc<T>` into the extensions of every request,
+//! e.g. for an axum handler to extract it with `Extension<Arc<T>>`:
+//!
+//! ```ignore
+//! use std::sync::Arc;
+//!
+//! use axum::{routing::get, Extension, Router};
+//! use centraldogma::{model::Query, tower::ConfigLayer, CentralDogmaConfig};
+//!
+//! let limits: CentralDogmaConfig<Limits> =
+//!     CentralDogmaConfig::builder(Query::identity("/limits.json").unwrap())
+//!         .start(&repo)
+//!         .await?;
+//!
+//! let app = Router::new()
+//!     .route("/", get(|Extension(limits): Extension<Arc<Limits>>| async move {
+//!         limits.max_connections.to_string()
+//!     }))
+//!     .layer(ConfigLayer::new(limits));
+//! ```
+use std::{
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::CentralDogmaConfig;
+
+/// A [`Layer`] inserting the current value of a [`CentralDogmaConfig`] into the
+/// extensions of the requests.
+pub struct ConfigLayer<T> {
+    config: Arc<CentralDogmaConfig<T>>,
+}
+
+impl<T> ConfigLayer<T> {
+    /// Returns a layer of `config`.
+    pub fn new(config: CentralDogmaConfig<T>) -> Self {
+        Self::shared(Arc::new(config))
+    }
+
+    /// Returns a layer of `config`, shared with other users of it.
+    pub fn shared(config: Arc<CentralDogmaConfig<T>>) -> Self {
+        ConfigLayer { config }
+    }
+}
+
+impl<T> Clone for ConfigLayer<T> {
+    fn clone(&self) -> Self {
+        ConfigLayer {
+            config: self.config.clone(),
+        }
+    }
+}
+
+impl<S, T> Layer<S> for ConfigLayer<T> {
+    type Service = ConfigService<S, T>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ConfigService {
+            inner,
+            config: self.config.clone(),
+        }
+    }
+}
+
+/// The service of a [`ConfigLayer`].
+pub struct ConfigService<S, T> {
+    inner: S,
+    config: Arc<CentralDogmaConfig<T>>,
+}
+
+impl<S: Clone, T> Clone for ConfigService<S, T> {
+    fn clone(&self) -> Self {
+        ConfigService {
+            inner: self.inner.clone(),
+            config: self.config.clone(),
+        }
+    }
+}
+
+impl<S, T, B> Service<http1::Request<B>> for ConfigService<S, T>
+where
+    S: Service<http1::Request<B>>,
+    T: Send + Sync + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: http1::Request<B>) -> Self::Future {
+        req.extensions_mut().insert(self.config.load());
+        self.inner.call(req)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::convert::Infallible;
+
+    use tower::{service_fn, ServiceExt};
+
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Limits {
+        max: i64,
+    }
+
+    #[tokio::test]
+    async fn test_config_layer() {
+        let config = CentralDogmaConfig::fixed(Limits { max: 3 });
+        let service =
+            ConfigLayer::new(config).layer(service_fn(|req: http1::Request<()>| async move {
+                Ok::<_, Infallible>(req.extensions().get::<Arc<Limits>>().cloned())
+            }));
+
+        let limits = service
+            .oneshot(http1::Request::new(()))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(*limits, Limits { max: 3 });
+    }
+}
@@ -0,0 +1,167 @@
+//! [`tracing`] spans of the requests of a [`Client`](crate::Client), enabled by the
+//! `tracing` feature.
+//!
+//! Every attempt of a request, the retries included, is an `INFO` span named
+//! `centraldogma.request`, a child of the current span of the caller, with these fields
+//! when they apply:
+//!
+//! | Field                       | Value                                             |
+//! |-----------------------------|---------------------------------------------------|
+//! | `http.request.method`       | Method of the request                             |
+//! | `http.route`                | Route template of the path                        |
+//! | `centraldogma.project`      | Name of the project                               |
+//! | `centraldogma.repository`   | Name of the repository                            |
+//! | `centraldogma.attempt`      | Number of the attempt, starting at 1              |
+//! | `http.response.status_code` | Status of the response                            |
+//! | `error.type`                | [`ErrorCode`](crate::ErrorCode) of the failure    |
+//!
+//! The route template replaces the names in the path by placeholders, e.g.
+//! `/api/v1/projects/{project}/repos/{repo}/contents/{path}`, so that the spans of the
+//! same operation group together.
+//!
+//! The trace context is propagated to the server with the `otel` feature, which adds the
+//! headers of the global text map propagator of the `opentelemetry` crate to the requests,
+//! e.g. `traceparent` with the W3C trace context propagator, see [`otel`](crate::otel).
+use reqwest::Method;
+use tracing::{field, Span};
+
+use crate::{services::path::parse_route, Error};
+
+/// Returns the span of the `attempt`th attempt of a request.
+pub(crate) fn request_span(method: &Method, path: &str, attempt: usize) -> Span {
+    let route = parse_route(path);
+
+    tracing::info_span!(
+        "centraldogma.request",
+        http.request.method = %method,
+        http.route = %route.template,
+        centraldogma.project = route.project,
+        centraldogma.repository = route.repo,
+        centraldogma.attempt = attempt,
+        http.response.status_code = field::Empty,
+        "error.type" = field::Empty,
+    )
+}
+
+/// Records the status of the response, or the failure, of an attempt.
+pub(crate) fn record_result(span: &Span, result: &Result<reqwest::Response, Error>) {
+    match result {
+        Ok(resp) => {
+            span.record("http.response.status_code", resp.status().as_u16());
+        }
+        Err(e) => {
+            span.record("error.type", e.code().as_str());
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        collections::HashMap,
+        fmt,
+        sync::{Arc, Mutex},
+    };
+
+    use tracing::{
+        field::{Field, Visit},
+        span, Event, Metadata, Subscriber,
+    };
+    use wiremock::{matchers::method, Mock, MockServer, ResponseTemplate};
+
+    use crate::{
+        model::{Query, Revision},
+        retry::RetryConfig,
+        Client, ContentService,
+    };
+
+    #[derive(Default)]
+    struct Fields(HashMap<String, String>);
+
+    /// Collects the fields of the spans.
+    #[derive(Clone, Default)]
+    struct Recorder {
+        spans: Arc<Mutex<Vec<Fields>>>,
+    }
+
+    impl Visit for Fields {
+        fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+            self.0
+                .insert(field.name().to_owned(), format!("{:?}", value));
+        }
+
+        fn record_str(&mut self, field: &Field, value: &str) {
+            self.0.insert(field.name().to_owned(), value.to_owned());
+        }
+    }
+
+    impl Subscriber for Recorder {
+        fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+            metadata.target().starts_with("centraldogma")
+        }
+
+        fn new_span(&self, span: &span::Attributes<'_>) -> span::Id {
+            let mut fields = Fields::default();
+            span.record(&mut fields);
+            let mut spans = self.spans.lock().unwrap();
+            spans.push(fields);
+            span::Id::from_u64(spans.len() as u64)
+        }
+
+        fn record(&self, span: &span::Id, values: &span::Record<'_>) {
+            let mut spans = self.spans.lock().unwrap();
+            values.record(&mut spans[span.into_u64() as usize - 1]);
+        }
+
+        fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+
+        fn event(&self, _: &Event<'_>) {}
+
+        fn enter(&self, _: &span::Id) {}
+
+        fn exit(&self, _: &span::Id) {}
+    }
+
+    #[tokio::test]
+    async fn test_request_span() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(
+                r#"{"path":"/a.json","type":"JSON","content":{},"revision":2,"url":"/a.json"}"#,
+                "application/json",
+            ))
+            .mount(&server)
+            .await;
+
+        let recorder = Recorder::default();
+        let _guard = tracing::subscriber::set_default(recorder.clone());
+        let client = Client::new(&server.uri(), None)
+            .await
+            .unwrap()
+            .with_retry(RetryConfig::default());
+        client
+            .repo("foo", "bar")
+            .get_file(Revision::HEAD, &Query::identity("/a.json").unwrap())
+            .await
+            .unwrap();
+
+        let spans = recorder.spans.lock().unwrap();
+        assert_eq!(spans.len(), 2);
+        for (attempt, (Fields(span), status)) in spans.iter().zip(["503", "200"]).enumerate() {
+            assert_eq!(span["http.request.method"], "GET");
+            assert_eq!(
+                span["http.route"],
+                "/api/v1/projects/{project}/repos/{repo}/contents/{path}"
+            );
+            assert_eq!(span["centraldogma.project"], "foo");
+            assert_eq!(span["centraldogma.repository"], "bar");
+            assert_eq!(span["centraldogma.attempt"], (attempt + 1).to_string());
+            assert_eq!(span["http.response.status_code"], status);
+        }
+    }
+}
@@ -0,0 +1,213 @@
+//! A hook transforming the contents of the files between the server and the application,
+//! e.g. to decrypt the secrets which are stored encrypted in a repository.
+//!
+//! ```no_run
+//! use async_trait::async_trait;
+//! use centraldogma::{model::Entry, transform::ContentTransformer, Client, Error};
+//!
+//! struct Decrypter;
+//!
+//! #[async_trait]
+//! impl ContentTransformer for Decrypter {
+//!     async fn after_fetch(&self, entry: Entry) -> Result<Entry, Error> {
+//!         // Replace the sealed values of `entry.content`, e.g. with a KMS
+//!         Ok(entry)
+//!     }
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), centraldogma::Error> {
+//! let client = Client::new("http://localhost:36462", None)
+//!     .await?
+//!     .with_content_transformer(Decrypter);
+//! # Ok(())
+//! # }
+//! ```
+use async_trait::async_trait;
+
+use crate::{
+    model::{Change, Entry},
+    Error,
+};
+
+/// Transforms the files fetched from and the changes pushed to the server,
+/// installed with [`Client::with_content_transformer()`](crate::Client::with_content_transformer).
+///
+/// The fetched files are transformed by
+/// [get_file](trait@crate::ContentService#tymethod.get_file),
+/// [get_files](trait@crate::ContentService#tymethod.get_files) and the file watches,
+/// including the ones of [`CentralDogmaConfig`](crate::CentralDogmaConfig) and
+/// [`DogmaConfig`](crate::DogmaConfig), while
+/// [get_files_raw](trait@crate::ContentService#tymethod.get_files_raw) returns the files
+/// as they are stored. The changes are transformed by
+/// [push](trait@crate::ContentService#tymethod.push).
+///
+/// A file which fails to transform fails the operation, and is skipped by the watches.
+/// [`Error::Transform`] is intended for these failures.
+#[async_trait]
+pub trait ContentTransformer: Send + Sync {
+    /// Transforms a file fetched from the server. Returns it as it is by default.
+    async fn after_fetch(&self, entry: Entry) -> Result<Entry, Error> {
+        Ok(entry)
+    }
+
+    /// Transforms a change before it's pushed to the server. Returns it as it is by default.
+    async fn before_push(&self, change: Change) -> Result<Change, Error> {
+        Ok(change)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use futures::StreamExt;
+    use serde_json::{json, Value};
+    use wiremock::{
+        matchers::{body_json, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+    use crate::{
+        model::{ChangeContent, CommitMessage, EntryContent, Query, Revision},
+        Client, ContentService, ErrorCode, WatchService,
+    };
+
+    /// Seals the strings by reversing them, `sealed:cba` being the sealed `abc`.
+    struct Sealer;
+
+    fn map_strings(value: &mut Value, f: &dyn Fn(&str) -> Option<String>) {
+        match value {
+            Value::String(s) => {
+                if let Some(mapped) = f(s) {
+                    *s = mapped;
+                }
+            }
+            Value::Array(values) => values.iter_mut().for_each(|v| map_strings(v, f)),
+            Value::Object(values) => values.values_mut().for_each(|v| map_strings(v, f)),
+            _ => {}
+        }
+    }
+
+    #[async_trait]
+    impl ContentTransformer for Sealer {
+        async fn after_fetch(&self, mut entry: Entry) -> Result<Entry, Error> {
+            if let EntryContent::Json(json) = &mut entry.content {
+                if json.get("broken").is_some() {
+                    return Err(Error::Transform {
+                        path: entry.path.clone(),
+                        message: "broken".to_owned(),
+                    });
+                }
+                map_strings(json, &|s| {
+                    s.strip_prefix("sealed:").map(|s| s.chars().rev().collect())
+                });
+            }
+            Ok(entry)
+        }
+
+        async fn before_push(&self, mut change: Change) -> Result<Change, Error> {
+            if let ChangeContent::UpsertJson(json) = &mut change.content {
+                map_strings(json, &|s| {
+                    Some(format!("sealed:{}", s.chars().rev().collect::<String>()))
+                });
+            }
+            Ok(change)
+        }
+    }
+
+    fn entry(revision: i64, content: Value) -> Value {
+        json!({
+            "path": "/a.json",
+            "type": "JSON",
+            "content": content,
+            "revision": revision,
+            "url": "/a.json"
+        })
+    }
+
+    #[tokio::test]
+    async fn test_content_transformer() {
+        let server = MockServer::start().await;
+        let file = "/api/v1/projects/foo/repos/bar/contents/a.json";
+        Mock::given(method("GET"))
+            .and(path(file))
+            .and(wiremock::matchers::query_param("revision", "-1"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(entry(2, json!({"password": "sealed:terces"}))),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path(file))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "revision": 3,
+                "entry": entry(3, json!({"password": "sealed:wen"})),
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/api/v1/projects/foo/repos/bar/contents"))
+            .and(body_json(json!({
+                "commitMessage": {"summary": "Rotate"},
+                "changes": [{
+                    "path": "/a.json",
+                    "type": "UPSERT_JSON",
+                    "content": {"password": "sealed:wen"}
+                }]
+            })))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(json!({"revision": 3, "pushedAt": null})),
+            )
+            .mount(&server)
+            .await;
+
+        let client = Client::new(&server.uri(), None)
+            .await
+            .unwrap()
+            .with_content_transformer(Sealer);
+        let repo = client.repo("foo", "bar");
+        let query = Query::identity("/a.json").unwrap();
+
+        let entry = repo.get_file(Revision::HEAD, &query).await.unwrap();
+        assert_eq!(
+            entry.content,
+            EntryContent::Json(json!({"password": "secret"}))
+        );
+
+        repo.push(
+            Revision::HEAD,
+            CommitMessage::only_summary("Rotate"),
+            vec![Change::from(("/a.json", json!({"password": "new"})))],
+        )
+        .await
+        .unwrap();
+
+        let mut watch = repo.watch_file_stream(&query).unwrap();
+        let result = watch.next().await.unwrap();
+        assert_eq!(
+            result.entry.content,
+            EntryContent::Json(json!({"password": "new"}))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_transform_error() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(entry(2, json!({"broken": 1}))))
+            .mount(&server)
+            .await;
+
+        let client = Client::new(&server.uri(), None)
+            .await
+            .unwrap()
+            .with_content_transformer(Sealer);
+        let err = client
+            .repo("foo", "bar")
+            .get_file(Revision::HEAD, &Query::identity("/a.json").unwrap())
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::Transform);
+    }
+}
@@ -0,0 +1,175 @@
+//! Checks of the names of projects and repositories, and of the paths of files, against the
+//! rules of the server, so invalid ones fail with the violated rule before any request is
+//! sent instead of an opaque `400 Bad Request`.
+//!
+//! They are checked when projects and repositories are created and when changes are pushed.
+//!
+//! ```
+//! use centraldogma::validation::{validate_file_path, validate_project_name};
+//!
+//! assert!(validate_project_name("my-project").is_ok());
+//! assert!(validate_project_name("my project").is_err());
+//! assert!(validate_file_path("/a/b.json").is_ok());
+//! assert!(validate_file_path("a/b.json").is_err());
+//! ```
+use crate::{
+    model::{Change, ChangeContent},
+    Error,
+};
+
+/// Checks that `name` is a valid project name: letters, digits, `-`, `+`, `_` and `.`,
+/// starting and ending with a letter or a digit.
+pub fn validate_project_name(name: &str) -> Result<(), Error> {
+    validate_name(name).map_err(|violation| {
+        Error::InvalidParams(match violation {
+            NameViolation::Empty => "project name cannot be empty",
+            NameViolation::Edge => "project name must start and end with a letter or a digit",
+            NameViolation::Char => {
+                "project name may only contain letters, digits, `-`, `+`, `_` and `.`"
+            }
+        })
+    })
+}
+
+/// Checks that `name` is a valid repository name, with the rules of
+/// [`validate_project_name()`].
+pub fn validate_repo_name(name: &str) -> Result<(), Error> {
+    validate_name(name).map_err(|violation| {
+        Error::InvalidParams(match violation {
+            NameViolation::Empty => "repository name cannot be empty",
+            NameViolation::Edge => "repository name must start and end with a letter or a digit",
+            NameViolation::Char => {
+                "repository name may only contain letters, digits, `-`, `+`, `_` and `.`"
+            }
+        })
+    })
+}
+
+/// Checks that `path` is a valid file path: an absolute path whose segments are made of
+/// letters, digits, `-`, `_` and `.`, and don't start or end with `.`.
+pub fn validate_file_path(path: &str) -> Result<(), Error> {
+    let Some(relative) = path.strip_prefix('/') else {
+        return Err(Error::InvalidParams("file path must start with `/`"));
+    };
+    for segment in relative.split('/') {
+        if segment.is_empty() {
+            return Err(Error::InvalidParams(
+                "file path cannot have an empty segment or end with `/`",
+            ));
+        }
+        if !segment.bytes().all(|b| is_path_char(b) || b == b'.') {
+            return Err(Error::InvalidParams(
+                "file path may only contain letters, digits, `-`, `_`, `.` and `/`",
+            ));
+        }
+        if segment.starts_with('.') || segment.ends_with('.') {
+            return Err(Error::InvalidParams(
+                "file path segments cannot start or end with `.`",
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks the paths of `changes`, including the targets of the renames.
+pub(crate) fn validate_changes(changes: &[Change]) -> Result<(), Error> {
+    for change in changes {
+        validate_file_path(&change.path)?;
+        if let ChangeContent::Rename(to) = &change.content {
+            validate_file_path(to)?;
+        }
+    }
+
+    Ok(())
+}
+
+enum NameViolation {
+    Empty,
+    Edge,
+    Char,
+}
+
+fn validate_name(name: &str) -> Result<(), NameViolation> {
+    let bytes = name.as_bytes();
+    let (Some(first), Some(last)) = (bytes.first(), bytes.last()) else {
+        return Err(NameViolation::Empty);
+    };
+    if !bytes
+        .iter()
+        .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'+' | b'_' | b'.'))
+    {
+        return Err(NameViolation::Char);
+    }
+    if !first.is_ascii_alphanumeric() || !last.is_ascii_alphanumeric() {
+        return Err(NameViolation::Edge);
+    }
+
+    Ok(())
+}
+
+fn is_path_char(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'-' || b == b'_'
+}
+
+#[cfg(test)]
+mod test {
+    use wiremock::{matchers::any, Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+    use crate::{
+        model::{CommitMessage, Revision},
+        Client, ContentService, ProjectService, RepoService,
+    };
+
+    fn message(result: Result<(), Error>) -> String {
+        result.unwrap_err().to_string()
+    }
+
+    #[test]
+    fn test_validate_names() {
+        for name in ["foo", "Foo1", "a", "foo-bar_baz+qux.1"] {
+            assert!(validate_project_name(name).is_ok(), "{}", name);
+            assert!(validate_repo_name(name).is_ok(), "{}", name);
+        }
+        assert!(message(validate_project_name("")).contains("empty"));
+        assert!(message(validate_project_name("Test Project")).contains("may only contain"));
+        assert!(message(validate_repo_name("-foo")).contains("start and end"));
+        assert!(message(validate_repo_name("foo.")).contains("repository name"));
+    }
+
+    #[test]
+    fn test_validate_file_path() {
+        for path in ["/a.json", "/foo/bar-baz_1.txt", "/a.b.c"] {
+            assert!(validate_file_path(path).is_ok(), "{}", path);
+        }
+        assert!(message(validate_file_path("/.a/b")).contains("`.`"));
+        assert!(message(validate_file_path("a.json")).contains("start with `/`"));
+        assert!(message(validate_file_path("/a//b.json")).contains("empty segment"));
+        assert!(message(validate_file_path("/a/")).contains("empty segment"));
+        assert!(message(validate_file_path("/a b.json")).contains("may only contain"));
+        assert!(message(validate_file_path("/a/../b.json")).contains("`.`"));
+    }
+
+    #[tokio::test]
+    async fn test_validated_before_sending() {
+        let server = MockServer::start().await;
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(400))
+            .expect(0)
+            .mount(&server)
+            .await;
+
+        let client = Client::new(&server.uri(), None).await.unwrap();
+        let err = client.create_project("Test Project").await.unwrap_err();
+        assert!(matches!(err, Error::InvalidParams(_)));
+        let err = client.project("foo").create_repo("bar/baz").await;
+        assert!(matches!(err, Err(Error::InvalidParams(_))));
+        let changes = vec![Change::from(("/a b.json", serde_json::json!({})))];
+        let err = client
+            .repo("foo", "bar")
+            .push(Revision::HEAD, CommitMessage::only_summary("Add"), changes)
+            .await;
+        assert!(matches!(err, Err(Error::InvalidParams(_))));
+    }
+}
@@ -0,0 +1,265 @@
+//! Retrieval of the configuration a service needs before it starts serving, all at once.
+//!
+//! ```no_run
+//! use centraldogma::{warmup::WarmUpRequest, Client};
+//! use serde::Deserialize;
+//!
+//! #[derive(Deserialize)]
+//! struct Limits {
+//!     max_connections: u32,
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), centraldogma::Error> {
+//! let client = Client::new("http://localhost:36462", None).await?;
+//! let repo = client.for_repo("foo", "bar");
+//! let warm = client
+//!     .warm_up(vec![
+//!         WarmUpRequest::get("app", repo.clone(), "/app.json"),
+//!         WarmUpRequest::merge("limits", repo.clone(), &["/limits.json", "/prod/limits.json"]),
+//!         WarmUpRequest::get("flags", repo, "/flags.json").optional(),
+//!     ])
+//!     .await?;
+//!
+//! let limits: Limits = warm.get("limits")?;
+//! # Ok(())
+//! # }
+//! ```
+use std::collections::HashMap;
+
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::{
+    model::{Query, Revision},
+    overlay::{json_of, ArrayMerge, Overlay},
+    CentralDogmaRepository, Client, ContentService, Error,
+};
+
+#[derive(Clone)]
+enum Kind {
+    Get(String),
+    Merge(Vec<String>, ArrayMerge),
+}
+
+/// A file, or files merged into one document, to retrieve by [`Client::warm_up()`].
+#[derive(Clone)]
+pub struct WarmUpRequest {
+    key: String,
+    repo: CentralDogmaRepository,
+    kind: Kind,
+    optional: bool,
+}
+
+impl WarmUpRequest {
+    /// Retrieves the JSON file at `path` of `repo`, under `key`.
+    pub fn get(key: &str, repo: CentralDogmaRepository, path: &str) -> Self {
+        WarmUpRequest {
+            key: key.to_owned(),
+            repo,
+            kind: Kind::Get(path.to_owned()),
+            optional: false,
+        }
+    }
+
+    /// Retrieves the JSON files at `paths` of `repo`, each one overriding the ones before,
+    /// and merges them under `key`, see [`Overlay`].
+    pub fn merge(key: &str, repo: CentralDogmaRepository, paths: &[&str]) -> Self {
+        WarmUpRequest {
+            key: key.to_owned(),
+            repo,
+            kind: Kind::Merge(
+                paths.iter().map(|p| p.to_string()).collect(),
+                ArrayMerge::default(),
+            ),
+            optional: false,
+        }
+    }
+
+    /// Sets how the arrays of a merge are merged, [`ArrayMerge::Replace`] by default.
+    pub fn array_merge(mut self, arrays: ArrayMerge) -> Self {
+        if let Kind::Merge(_, merge) = &mut self.kind {
+            *merge = arrays;
+        }
+        self
+    }
+
+    /// Doesn't fail the warm-up if a file of this request doesn't exist.
+    pub fn optional(mut self) -> Self {
+        self.optional = true;
+        self
+    }
+
+    /// Returns the document and the revision it was read at.
+    async fn execute(&self) -> Result<(Value, Revision), Error> {
+        match &self.kind {
+            Kind::Get(path) => {
+                let query = Query::identity(path).ok_or(Error::InvalidParams("Invalid path"))?;
+                let entry = self.repo.client().get_file(Revision::HEAD, &query).await?;
+
+                Ok((json_of(&entry)?, entry.revision))
+            }
+            Kind::Merge(paths, arrays) => {
+                let overlay = paths
+                    .iter()
+                    .fold(Overlay::new(self.repo.clone()), |o, p| o.layer(p))
+                    .array_merge(*arrays);
+
+                overlay.load_with_revision().await
+            }
+        }
+    }
+}
+
+/// The documents retrieved by [`Client::warm_up()`], by the keys of their requests.
+#[derive(Debug, Clone, Default)]
+pub struct WarmUp {
+    values: HashMap<String, Value>,
+    revisions: HashMap<(String, String), Revision>,
+}
+
+impl WarmUp {
+    /// Returns the document of `key`, `None` for a missing optional one.
+    pub fn value(&self, key: &str) -> Option<&Value> {
+        self.values.get(key)
+    }
+
+    /// Deserializes the document of `key` into `T`.
+    ///
+    /// Fails with [`Error::EntryNotFound`] for a missing optional document, and with
+    /// [`Error::InvalidConfig`] if it fails to deserialize.
+    pub fn get<T: DeserializeOwned>(&self, key: &str) -> Result<T, Error> {
+        let value = self
+            .values
+            .get(key)
+            .ok_or_else(|| Error::EntryNotFound(key.to_owned()))?;
+
+        T::deserialize(value).map_err(|e| Error::InvalidConfig {
+            path: key.to_owned(),
+            message: e.to_string(),
+        })
+    }
+
+    /// Returns the latest revision of a repository the documents were read at, e.g. to
+    /// start watching from it.
+    pub fn revision(&self, project: &str, repo: &str) -> Option<Revision> {
+        self.revisions
+            .get(&(project.to_owned(), repo.to_owned()))
+            .copied()
+    }
+}
+
+impl Client {
+    /// Executes `requests` concurrently, e.g. in the `main` function of a service.
+    ///
+    /// Fails with [`Error::WarmUp`] reporting every failed request, if any request fails
+    /// other than an optional one whose file doesn't exist.
+    pub async fn warm_up(&self, requests: Vec<WarmUpRequest>) -> Result<WarmUp, Error> {
+        let results = futures::future::join_all(requests.iter().map(|r| r.execute())).await;
+
+        let mut warm = WarmUp::default();
+        let mut failures = Vec::new();
+        for (request, result) in requests.iter().zip(results) {
+            match result {
+                Ok((value, revision)) => {
+                    let key = (
+                        request.repo.project_name().to_owned(),
+                        request.repo.repo_name().to_owned(),
+                    );
+                    let latest = warm.revisions.entry(key).or_insert(revision);
+                    if revision.as_i64() > latest.as_i64() {
+                        *latest = revision;
+                    }
+                    warm.values.insert(request.key.clone(), value);
+                }
+                Err(Error::EntryNotFound(_)) if request.optional => {}
+                Err(e) => failures.push((request.key.clone(), e)),
+            }
+        }
+
+        if failures.is_empty() {
+            Ok(warm)
+        } else {
+            Err(Error::WarmUp { failures })
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+    use wiremock::{
+        matchers::{method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+    use crate::ErrorCode;
+
+    async fn mock_file(server: &MockServer, file: &str, revision: i64, content: Value) {
+        Mock::given(method("GET"))
+            .and(path(format!(
+                "/api/v1/projects/foo/repos/bar/contents{}",
+                file
+            )))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "path": file,
+                "type": "JSON",
+                "content": content,
+                "revision": revision,
+                "url": file
+            })))
+            .expect(1)
+            .mount(server)
+            .await;
+    }
+
+    #[tokio::test]
+    async fn test_warm_up() {
+        let server = MockServer::start().await;
+        mock_file(&server, "/app.json", 3, json!({"name": "app"})).await;
+        mock_file(&server, "/base.json", 2, json!({"a": 1, "b": 1})).await;
+        mock_file(&server, "/prod.json", 4, json!({"b": 2})).await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(404).set_body_json(json!({
+                "exception": "com.linecorp.centraldogma.common.EntryNotFoundException",
+                "message": "not found"
+            })))
+            .mount(&server)
+            .await;
+
+        let client = Client::new(&server.uri(), None).await.unwrap();
+        let repo = client.for_repo("foo", "bar");
+        let warm = client
+            .warm_up(vec![
+                WarmUpRequest::get("app", repo.clone(), "/app.json"),
+                WarmUpRequest::merge("merged", repo.clone(), &["/base.json", "/prod.json"]),
+                WarmUpRequest::get("flags", repo.clone(), "/flags.json").optional(),
+            ])
+            .await
+            .unwrap();
+        assert_eq!(warm.value("app"), Some(&json!({"name": "app"})));
+        assert_eq!(
+            warm.get::<Value>("merged").unwrap(),
+            json!({"a": 1, "b": 2})
+        );
+        assert_eq!(warm.value("flags"), None);
+        assert_eq!(warm.revision("foo", "bar"), Some(Revision::from(4)));
+
+        let err = client
+            .warm_up(vec![
+                WarmUpRequest::get("a", repo.clone(), "/a.json"),
+                WarmUpRequest::get("b", repo, "/b.json"),
+            ])
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::EntryNotFound);
+        match err {
+            Error::WarmUp { failures } => {
+                let keys: Vec<_> = failures.iter().map(|(k, _)| k.as_str()).collect();
+                assert_eq!(keys, ["a", "b"]);
+            }
+            e => panic!("unexpected error: {}", e),
+        }
+    }
+}
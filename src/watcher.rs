@@ -0,0 +1,184 @@
+//! Watchers keeping the latest value of a file, like the `Watcher` of the Java client.
+//!
+//! ```no_run
+//! use centraldogma::{model::Query, Client};
+//! use serde::Deserialize;
+//!
+//! #[derive(Deserialize)]
+//! struct Limits {
+//!     max_connections: u32,
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), centraldogma::Error> {
+//! let client = Client::new("http://localhost:36462", None).await?;
+//! let repo = client.for_repo("foo", "bar");
+//! let watcher = repo
+//!     .file(Query::identity("/limits.json").unwrap())
+//!     .watcher::<Limits>()?;
+//!
+//! if let Some((revision, limits)) = watcher.await_initial_value().await {
+//!     println!("{}: {}", revision, limits.max_connections);
+//! }
+//!
+//! let mut changes = watcher.subscribe();
+//! while changes.changed().await.is_ok() {
+//!     if let Some((revision, _)) = &*changes.borrow() {
+//!         println!("updated to {}", revision);
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use std::sync::Arc;
+
+use futures::{Stream, StreamExt};
+use serde::de::DeserializeOwned;
+use tokio::{sync::watch, task::JoinHandle};
+
+use crate::model::{Revision, WatchFileResult};
+
+/// The latest value of a [`FileWatcher`] and the revision of the file it was read from,
+/// `None` until the file is first read.
+pub type Latest<T> = Option<(Revision, Arc<T>)>;
+
+/// A file deserialized into `T`, watched by a background task, created by
+/// [`FileRequest::watcher()`](crate::fluent::FileRequest::watcher).
+///
+/// A new content which fails to deserialize is ignored, keeping the last value.
+/// The background watch stops when this is dropped.
+pub struct FileWatcher<T> {
+    latest: watch::Receiver<Latest<T>>,
+    task: JoinHandle<()>,
+}
+
+impl<T> FileWatcher<T>
+where
+    T: DeserializeOwned + Send + Sync + 'static,
+{
+    /// Returns a watcher deserializing the files output by `stream`.
+    pub(crate) fn start<S>(stream: S) -> Self
+    where
+        S: Stream<Item = WatchFileResult> + Send + 'static,
+    {
+        let (sender, latest) = watch::channel(None);
+        let task = tokio::spawn(async move {
+            futures::pin_mut!(stream);
+            while let Some(result) = stream.next().await {
+                match result.entry.into_typed::<T>() {
+                    Ok(entry) => {
+                        if sender
+                            .send(Some((result.revision, Arc::new(entry.value))))
+                            .is_err()
+                        {
+                            return;
+                        }
+                    }
+                    Err(e) => log::warn!("Ignoring the new content: {}", e),
+                }
+            }
+        });
+
+        FileWatcher { latest, task }
+    }
+}
+
+impl<T> FileWatcher<T> {
+    /// Returns the latest value, `None` if the file hasn't been read yet.
+    pub fn latest(&self) -> Latest<T> {
+        self.latest.borrow().clone()
+    }
+
+    /// Waits until the file is first read, and returns its value.
+    ///
+    /// Returns `None` if the watch ends before, e.g. when the
+    /// [retry classifier](crate::Client::with_retry_classifier) of the client gives up.
+    pub async fn await_initial_value(&self) -> Option<(Revision, Arc<T>)> {
+        let mut latest = self.latest.clone();
+        loop {
+            if let Some(value) = &*latest.borrow_and_update() {
+                return Some(value.clone());
+            }
+            latest.changed().await.ok()?;
+        }
+    }
+
+    /// Returns a receiver notified whenever the value changes.
+    ///
+    /// Its [`changed()`](watch::Receiver::changed) fails once the watch ends.
+    pub fn subscribe(&self) -> watch::Receiver<Latest<T>> {
+        self.latest.clone()
+    }
+}
+
+impl<T> Drop for FileWatcher<T> {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde::Deserialize;
+    use wiremock::{
+        matchers::{header, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+    use crate::{model::Query, Client};
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Limits {
+        max: i64,
+    }
+
+    fn entry(revision: i64, max: i64) -> String {
+        format!(
+            r#"{{"revision":{},"entry":{{"path":"/a.json","type":"JSON","content":{{"max":{}}},"revision":{},"url":"/a.json"}}}}"#,
+            revision, max, revision
+        )
+    }
+
+    #[tokio::test]
+    async fn test_file_watcher() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/contents/a.json"))
+            .and(header("if-none-match", "-1"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(entry(2, 1), "application/json"))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/contents/a.json"))
+            .and(header("if-none-match", "2"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(entry(3, 5), "application/json"))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/projects/foo/repos/bar/contents/a.json"))
+            .and(header("if-none-match", "3"))
+            .respond_with(
+                ResponseTemplate::new(304).set_delay(std::time::Duration::from_millis(100)),
+            )
+            .mount(&server)
+            .await;
+
+        let client = Client::new(&server.uri(), None).await.unwrap();
+        let repo = client.for_repo("foo", "bar");
+        let watcher = repo
+            .file(Query::identity("/a.json").unwrap())
+            .watcher::<Limits>()
+            .unwrap();
+
+        let (revision, value) = watcher.await_initial_value().await.unwrap();
+        assert!(revision == Revision::from(2) || revision == Revision::from(3));
+        assert!(value.max == 1 || value.max == 5);
+
+        let mut changes = watcher.subscribe();
+        while watcher.latest().map(|(r, _)| r) != Some(Revision::from(3)) {
+            changes.changed().await.unwrap();
+        }
+        assert_eq!(*watcher.latest().unwrap().1, Limits { max: 5 });
+    }
+}
@@ -0,0 +1,18 @@
+#![cfg(feature = "conformance")]
+#[macro_use]
+mod utils;
+
+use centraldogma::conformance::{self, Capability};
+
+#[tokio::test]
+async fn test_conformance() {
+    let server = utils::start_server().await.unwrap();
+
+    let report = conformance::run(&server.client).await;
+
+    assert!(report.is_compatible(), "{}", report);
+    assert!(report.supports(Capability::Projects));
+    assert!(report.supports(Capability::Repositories));
+    assert!(report.supports(Capability::Content));
+    assert!(report.supports(Capability::Watch));
+}
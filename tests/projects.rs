@@ -1,12 +1,15 @@
+#[macro_use]
+mod utils;
+
 use cd::ProjectService;
 use centraldogma as cd;
 
 #[cfg(test)]
 #[tokio::test]
+#[allow(clippy::redundant_pattern_matching)]
 async fn test_projects() {
-    let client = cd::Client::new("http://localhost:36462", None)
-        .await
-        .unwrap();
+    let server = utils::start_server().await.unwrap();
+    let client = &server.client;
     let projects = client
         .list_projects()
         .await
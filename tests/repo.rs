@@ -8,6 +8,7 @@ use futures::future::{Future, FutureExt};
 use std::pin::Pin;
 
 struct TestContext {
+    _server: utils::TestServer,
     client: cd::Client,
     project: cd::model::Project,
 }
@@ -26,9 +27,8 @@ where
 }
 
 async fn setup() -> Result<TestContext> {
-    let client = cd::Client::new("http://localhost:36462", None)
-        .await
-        .context("Failed to create client")?;
+    let server = utils::start_server().await?;
+    let client = server.client.clone();
     let projects = client
         .list_projects()
         .await
@@ -41,7 +41,11 @@ async fn setup() -> Result<TestContext> {
         .await
         .context("Failed to create new project")?;
 
-    Ok(TestContext { client, project })
+    Ok(TestContext {
+        _server: server,
+        client,
+        project,
+    })
 }
 
 async fn teardown(ctx: TestContext) -> Result<()> {
@@ -8,3 +8,53 @@ macro_rules! here {
         )
     };
 }
+
+use anyhow::{Context, Result};
+use centraldogma as cd;
+
+/// Address of the server started by `docker-compose up`.
+#[cfg(not(feature = "testcontainers"))]
+const DEFAULT_URL: &str = "http://localhost:36462";
+
+/// A Central Dogma server the end-to-end tests run against.
+///
+/// The server at `CENTRAL_DOGMA_URL` is used when the variable is set. Otherwise, with the
+/// `testcontainers` feature a fresh server is started in a container, which is stopped when
+/// this is dropped, and without it the server at `http://localhost:36462` is used.
+pub struct TestServer {
+    pub client: cd::Client,
+    #[cfg(feature = "testcontainers")]
+    _container: Option<cd::test_util::RunningCentralDogma>,
+}
+
+pub async fn start_server() -> Result<TestServer> {
+    if let Ok(url) = std::env::var("CENTRAL_DOGMA_URL") {
+        return connect(&url).await;
+    }
+
+    #[cfg(feature = "testcontainers")]
+    {
+        let container = cd::test_util::CentralDogmaContainer::new()
+            .start()
+            .await
+            .map_err(|e| anyhow::anyhow!(e))
+            .context("Failed to start Central Dogma container")?;
+        Ok(TestServer {
+            client: container.client().clone(),
+            _container: Some(container),
+        })
+    }
+    #[cfg(not(feature = "testcontainers"))]
+    connect(DEFAULT_URL).await
+}
+
+async fn connect(url: &str) -> Result<TestServer> {
+    let client = cd::Client::new(url, None)
+        .await
+        .context("Failed to create client")?;
+    Ok(TestServer {
+        client,
+        #[cfg(feature = "testcontainers")]
+        _container: None,
+    })
+}
@@ -17,6 +17,7 @@ use futures::{
 use serde_json::json;
 
 struct TestContext {
+    _server: utils::TestServer,
     client: cd::Client,
     project: cd::model::Project,
     repo: cd::model::Repository,
@@ -36,9 +37,8 @@ where
 }
 
 async fn setup() -> Result<TestContext> {
-    let client = cd::Client::new("http://localhost:36462", None)
-        .await
-        .context("Failed to create client")?;
+    let server = utils::start_server().await?;
+    let client = server.client.clone();
     let projects = client
         .list_projects()
         .await
@@ -59,6 +59,7 @@ async fn setup() -> Result<TestContext> {
         .context("Failed to create new repository")?;
 
     Ok(TestContext {
+        _server: server,
         client,
         project,
         repo,